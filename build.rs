@@ -0,0 +1,5 @@
+fn main() {
+    // Node loads the addon into its own process, so napi symbols resolve at load time.
+    #[cfg(feature = "node")]
+    napi_build::setup();
+}
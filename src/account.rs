@@ -0,0 +1,49 @@
+use crate::client::Client;
+use crate::error::Result;
+use crate::json::Value;
+
+impl Client {
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_DeleteUser.html
+    pub fn delete_user(&self, access_token: &str) -> Result<()> {
+        let body = Value::object([("AccessToken", Value::from(access_token))]);
+        self.call("DeleteUser", body)?;
+        Ok(())
+    }
+
+    // Invalidates every refresh token issued to the user; access and id tokens stay valid until
+    // they expire.
+    pub fn global_sign_out(&self, access_token: &str) -> Result<()> {
+        let body = Value::object([("AccessToken", Value::from(access_token))]);
+        self.idempotent_call("GlobalSignOut", body)?;
+        self.hooks().sign_out();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_delete_user_and_global_sign_out() {
+        let transport = MockTransport::new();
+        transport.respond(200, "{}");
+        transport.respond(200, "{}");
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+
+        client.global_sign_out("access").unwrap();
+        let request = transport.last_request();
+        assert_eq!(request.body, r#"{"AccessToken":"access"}"#);
+        assert!(request.headers.contains(&(
+            "X-Amz-Target".to_owned(),
+            "AWSCognitoIdentityProviderService.GlobalSignOut".to_owned()
+        )));
+
+        client.delete_user("access").unwrap();
+        assert!(transport.last_request().headers.contains(&(
+            "X-Amz-Target".to_owned(),
+            "AWSCognitoIdentityProviderService.DeleteUser".to_owned()
+        )));
+    }
+}
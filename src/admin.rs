@@ -0,0 +1,251 @@
+// Provisioning users from a backend, test fixture or internal tool. Like every Admin*
+// operation these need AWS credentials on the client.
+use crate::attributes::UserAttribute;
+use crate::client::Client;
+use crate::error::{CognitoSrpError, Result};
+use crate::json::Value;
+use crate::registration::DeliveryMedium;
+use crate::secret::SecretString;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MessageAction {
+    // Create the user without sending the invitation.
+    Suppress,
+    // Send the invitation again to a user that already exists, with a new temporary password.
+    Resend,
+}
+
+impl MessageAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            MessageAction::Suppress => "SUPPRESS",
+            MessageAction::Resend => "RESEND",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CreateUserOptions {
+    pub attributes: Vec<UserAttribute>,
+    // None lets Cognito generate one, following the pool's password policy.
+    pub temporary_password: Option<SecretString>,
+    // None sends the invitation as the pool is configured to.
+    pub message_action: Option<MessageAction>,
+    // Empty means SMS only, Cognito's default.
+    pub delivery_mediums: Vec<DeliveryMedium>,
+    // Move an email or phone alias over from another user who already has it.
+    pub force_alias_creation: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UserStatus {
+    Unconfirmed,
+    Confirmed,
+    ForceChangePassword,
+    ResetRequired,
+    ExternalProvider,
+    Other(String),
+}
+
+impl UserStatus {
+    fn parse(status: &str) -> Self {
+        match status {
+            "UNCONFIRMED" => UserStatus::Unconfirmed,
+            "CONFIRMED" => UserStatus::Confirmed,
+            "FORCE_CHANGE_PASSWORD" => UserStatus::ForceChangePassword,
+            "RESET_REQUIRED" => UserStatus::ResetRequired,
+            "EXTERNAL_PROVIDER" => UserStatus::ExternalProvider,
+            other => UserStatus::Other(other.to_owned()),
+        }
+    }
+}
+
+// The user as AdminCreateUser returns it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CreatedUser {
+    pub username: String,
+    pub attributes: Vec<UserAttribute>,
+    pub status: UserStatus,
+    pub enabled: bool,
+    pub created_at: Option<SystemTime>,
+}
+
+impl CreatedUser {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+            .map(|attribute| attribute.value.as_str())
+    }
+
+    fn from_json(user: &Value) -> Result<Self> {
+        let attributes = match user.get("Attributes") {
+            Some(Value::Array(items)) => items
+                .iter()
+                .filter_map(|item| {
+                    Some(UserAttribute {
+                        name: item.str_field("Name")?,
+                        value: item.str_field("Value").unwrap_or_default(),
+                    })
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        Ok(Self {
+            username: user.str_field("Username").ok_or_else(|| {
+                CognitoSrpError::InvalidResponse("AdminCreateUser returned no Username".to_owned())
+            })?,
+            attributes,
+            status: UserStatus::parse(&user.str_field("UserStatus").unwrap_or_default()),
+            enabled: user.get("Enabled").and_then(Value::as_bool).unwrap_or(true),
+            // Epoch seconds, possibly fractional.
+            created_at: match user.get("UserCreateDate") {
+                Some(Value::Number(secs)) if *secs >= 0.0 => {
+                    Some(UNIX_EPOCH + Duration::from_secs(*secs as u64))
+                }
+                _ => None,
+            },
+        })
+    }
+}
+
+impl Client {
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_AdminCreateUser.html
+    // The user starts in FORCE_CHANGE_PASSWORD: their first sign-in answers NEW_PASSWORD_REQUIRED,
+    // unless admin_set_user_password makes the password permanent first.
+    pub fn admin_create_user(
+        &self,
+        username: &str,
+        options: &CreateUserOptions,
+    ) -> Result<CreatedUser> {
+        let username = self.normalize_username(username);
+        let mut body = Value::object([("Username", Value::from(username.as_str()))]);
+        if !options.attributes.is_empty() {
+            body.insert(
+                "UserAttributes",
+                Value::Array(
+                    options
+                        .attributes
+                        .iter()
+                        .map(UserAttribute::to_json)
+                        .collect(),
+                ),
+            );
+        }
+        if let Some(password) = &options.temporary_password {
+            body.insert("TemporaryPassword", Value::from(password.expose_secret()));
+        }
+        if let Some(action) = options.message_action {
+            body.insert("MessageAction", Value::from(action.as_str()));
+        }
+        if !options.delivery_mediums.is_empty() {
+            let mediums = options.delivery_mediums.iter().map(|medium| {
+                Value::from(match medium {
+                    DeliveryMedium::Sms => "SMS",
+                    DeliveryMedium::Email => "EMAIL",
+                })
+            });
+            body.insert("DesiredDeliveryMediums", Value::Array(mediums.collect()));
+        }
+        if options.force_alias_creation {
+            body.insert("ForceAliasCreation", Value::from(true));
+        }
+        let response = self.admin_call("AdminCreateUser", body)?;
+        let user = response.get("User").ok_or_else(|| {
+            CognitoSrpError::InvalidResponse("AdminCreateUser returned no User".to_owned())
+        })?;
+        CreatedUser::from_json(user)
+    }
+
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_AdminSetUserPassword.html
+    // `permanent` confirms the user with this password (status CONFIRMED); otherwise it is a new
+    // temporary password and the user is back in FORCE_CHANGE_PASSWORD.
+    pub fn admin_set_user_password(
+        &self,
+        username: &str,
+        password: &str,
+        permanent: bool,
+    ) -> Result<()> {
+        self.check_password(password)?;
+        let body = Value::object([
+            (
+                "Username",
+                Value::from(self.normalize_username(username).as_str()),
+            ),
+            ("Password", Value::from(password)),
+            ("Permanent", Value::from(permanent)),
+        ]);
+        self.admin_call("AdminSetUserPassword", body)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::credentials::AwsCredentials;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_admin_create_user() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"User":{"Username":"bob","Enabled":true,"UserStatus":"FORCE_CHANGE_PASSWORD",
+                "UserCreateDate":1759827903.123,
+                "Attributes":[{"Name":"sub","Value":"sub-bob"},{"Name":"email","Value":"bob@example.com"}]}}"#,
+        );
+        let client = Client::new("us-east-1_pool", "client", transport.clone())
+            .unwrap()
+            .with_credentials(AwsCredentials::new("AKID", "secret"));
+
+        let user = client
+            .admin_create_user(
+                "bob",
+                &CreateUserOptions {
+                    attributes: vec![UserAttribute::email("bob@example.com")],
+                    temporary_password: Some("Temp0rary!".into()),
+                    message_action: Some(MessageAction::Suppress),
+                    ..CreateUserOptions::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(user.status, UserStatus::ForceChangePassword);
+        assert_eq!(user.attribute("sub"), Some("sub-bob"));
+        assert_eq!(
+            user.created_at,
+            Some(UNIX_EPOCH + Duration::from_secs(1_759_827_903))
+        );
+        assert_eq!(
+            transport.last_request().body,
+            r#"{"MessageAction":"SUPPRESS","TemporaryPassword":"Temp0rary!","UserAttributes":[{"Name":"email","Value":"bob@example.com"}],"UserPoolId":"us-east-1_pool","Username":"bob"}"#
+        );
+    }
+
+    #[test]
+    fn test_admin_set_user_password() {
+        let transport = MockTransport::new();
+        transport.respond(200, "{}");
+        let client = Client::new("us-east-1_pool", "client", transport.clone())
+            .unwrap()
+            .with_credentials(AwsCredentials::new("AKID", "secret"));
+
+        client
+            .admin_set_user_password("bob", "P@ssw0rd!", true)
+            .unwrap();
+        let request = transport.last_request();
+        assert_eq!(
+            request.body,
+            r#"{"Password":"P@ssw0rd!","Permanent":true,"UserPoolId":"us-east-1_pool","Username":"bob"}"#
+        );
+        assert!(request.headers.contains(&(
+            "X-Amz-Target".to_owned(),
+            "AWSCognitoIdentityProviderService.AdminSetUserPassword".to_owned()
+        )));
+    }
+}
@@ -0,0 +1,163 @@
+use crate::error::{CognitoSrpError, Result, ServiceError, ServiceErrorKind};
+use crate::transport::{HttpRequest, HttpResponse, Transport};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+// The cognito-idp API one operation at a time: `operation` is the action name ("InitiateAuth",
+// "RespondToAuthChallenge", ...) and `request`/the response are its JSON bodies. A Client
+// funnels every user pool call through this after filling in ClientId, SECRET_HASH, metadata
+// and context data, so swapping it (see `Client::with_api`) takes the network out of a flow
+// without changing the code that drives it. Failures are ServiceErrors, as Cognito returns them.
+pub trait CognitoApi: Send + Sync {
+    fn call(&self, operation: &str, request: &str) -> Result<String>;
+}
+
+impl<T: CognitoApi + ?Sized> CognitoApi for Arc<T> {
+    fn call(&self, operation: &str, request: &str) -> Result<String> {
+        (**self).call(operation, request)
+    }
+}
+
+// One request the mock saw.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiCall {
+    pub operation: String,
+    pub request: String,
+}
+
+// A scripted CognitoApi for application tests: queue responses per operation, run the flow,
+// then assert on the recorded calls. An operation with nothing queued fails the call.
+#[derive(Default)]
+pub struct MockCognitoApi {
+    responses: Mutex<BTreeMap<String, VecDeque<Result<String>>>>,
+    calls: Mutex<Vec<ApiCall>>,
+}
+
+impl MockCognitoApi {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    // Queues a successful response body for the next `operation` call.
+    pub fn respond(&self, operation: &str, response: &str) -> &Self {
+        self.push(operation, Ok(response.to_owned()))
+    }
+
+    // Queues a Cognito exception, e.g. NotAuthorized for a wrong password.
+    pub fn fail(&self, operation: &str, kind: ServiceErrorKind, message: &str) -> &Self {
+        let err = ServiceError {
+            operation: operation.to_owned(),
+            status: if kind == ServiceErrorKind::InternalError {
+                500
+            } else {
+                400
+            },
+            kind,
+            message: message.to_owned(),
+        };
+        self.push(operation, Err(err.into()))
+    }
+
+    fn push(&self, operation: &str, response: Result<String>) -> &Self {
+        self.responses
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(operation.to_owned())
+            .or_default()
+            .push_back(response);
+        self
+    }
+
+    pub fn calls(&self) -> Vec<ApiCall> {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    // The operations called so far, in order.
+    pub fn operations(&self) -> Vec<String> {
+        self.calls()
+            .into_iter()
+            .map(|call| call.operation)
+            .collect()
+    }
+
+    // The body of the most recent `operation` call.
+    pub fn last_request(&self, operation: &str) -> Option<String> {
+        self.calls()
+            .into_iter()
+            .rev()
+            .find(|call| call.operation == operation)
+            .map(|call| call.request)
+    }
+}
+
+impl CognitoApi for MockCognitoApi {
+    fn call(&self, operation: &str, request: &str) -> Result<String> {
+        self.calls
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(ApiCall {
+                operation: operation.to_owned(),
+                request: request.to_owned(),
+            });
+        self.responses
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_mut(operation)
+            .and_then(VecDeque::pop_front)
+            .unwrap_or_else(|| {
+                Err(CognitoSrpError::Transport(
+                    format!("no scripted response for {}", operation).into(),
+                ))
+            })
+    }
+}
+
+// The transport behind `Client::from_api`: the user pool is mocked, so anything that would still
+// go over HTTP (JWKS, identity pools, STS) fails instead of reaching the network.
+pub(crate) struct Offline;
+
+impl Transport for Offline {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        Err(CognitoSrpError::Transport(
+            format!("no transport for {}", request.url).into(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Client;
+    use crate::json;
+
+    #[test]
+    fn test_mock_api() {
+        let api = MockCognitoApi::new();
+        api.respond("SignUp", r#"{"UserConfirmed":true,"UserSub":"1234-abcd"}"#)
+            .fail(
+                "ConfirmSignUp",
+                ServiceErrorKind::CodeMismatch,
+                "Invalid code.",
+            );
+        let client = Client::from_api("us-east-1_pool", "client", api.clone())
+            .unwrap()
+            .with_client_secret("secret");
+
+        let result = client.sign_up("bob", "hunter2", &[]).unwrap();
+        assert!(result.user_confirmed);
+        let err = client.confirm_sign_up("bob", "000000").unwrap_err();
+        assert_eq!(err.service_kind(), Some(&ServiceErrorKind::CodeMismatch));
+        assert!(client.resend_confirmation_code("bob").is_err());
+        assert_eq!(
+            api.operations(),
+            ["SignUp", "ConfirmSignUp", "ResendConfirmationCode"]
+        );
+
+        let request = json::parse(&api.last_request("SignUp").unwrap()).unwrap();
+        assert_eq!(
+            request.str_field("SecretHash").unwrap(),
+            "P1WGx30spzkjVO6gbksCMo0r3Ro1xm4a6xXZTTrvrD0="
+        );
+        assert!(crate::jwt::Verifier::fetch(&client).is_err());
+    }
+}
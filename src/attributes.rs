@@ -0,0 +1,163 @@
+use crate::client::Client;
+use crate::error::Result;
+use crate::json::Value;
+use crate::registration::CodeDeliveryDetails;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UserAttribute {
+    pub name: String,
+    pub value: String,
+}
+
+impl UserAttribute {
+    pub fn new(name: &str, value: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        }
+    }
+
+    pub fn email(value: &str) -> Self {
+        Self::new("email", value)
+    }
+
+    pub fn phone_number(value: &str) -> Self {
+        Self::new("phone_number", value)
+    }
+
+    pub(crate) fn to_json(&self) -> Value {
+        Value::object([
+            ("Name", Value::from(self.name.as_str())),
+            ("Value", Value::from(self.value.as_str())),
+        ])
+    }
+}
+
+impl Client {
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_UpdateUserAttributes.html
+    // Changing email or phone_number sends a code for each; finish with verify_user_attribute.
+    pub fn update_user_attributes(
+        &self,
+        access_token: &str,
+        attributes: &[UserAttribute],
+    ) -> Result<Vec<CodeDeliveryDetails>> {
+        let body = Value::object([
+            ("AccessToken", Value::from(access_token)),
+            (
+                "UserAttributes",
+                Value::Array(attributes.iter().map(UserAttribute::to_json).collect()),
+            ),
+        ]);
+        let response = self.call("UpdateUserAttributes", body)?;
+        match response.get("CodeDeliveryDetailsList") {
+            Some(Value::Array(items)) => items.iter().map(CodeDeliveryDetails::from_json).collect(),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_GetUserAttributeVerificationCode.html
+    // Sends a (new) code for `attribute_name`, e.g. "email" or "phone_number", when the one from
+    // update_user_attributes was lost or has expired.
+    pub fn get_user_attribute_verification_code(
+        &self,
+        access_token: &str,
+        attribute_name: &str,
+    ) -> Result<Option<CodeDeliveryDetails>> {
+        let body = Value::object([
+            ("AccessToken", Value::from(access_token)),
+            ("AttributeName", Value::from(attribute_name)),
+        ]);
+        let response = self.call("GetUserAttributeVerificationCode", body)?;
+        CodeDeliveryDetails::from_response(&response)
+    }
+
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_VerifyUserAttribute.html
+    // Confirms the code, marking email_verified or phone_number_verified.
+    pub fn verify_user_attribute(
+        &self,
+        access_token: &str,
+        attribute_name: &str,
+        code: &str,
+    ) -> Result<()> {
+        let body = Value::object([
+            ("AccessToken", Value::from(access_token)),
+            ("AttributeName", Value::from(attribute_name)),
+            ("Code", Value::from(code)),
+        ]);
+        self.call("VerifyUserAttribute", body)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registration::DeliveryMedium;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_update_and_verify() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"CodeDeliveryDetailsList":[{"AttributeName":"email","DeliveryMedium":"EMAIL","Destination":"n***@e***"}]}"#,
+        );
+        transport.respond(200, "{}");
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+
+        let details = client
+            .update_user_attributes(
+                "access",
+                &[
+                    UserAttribute::email("new@example.com"),
+                    UserAttribute::new("custom:tier", "gold"),
+                ],
+            )
+            .unwrap();
+        assert_eq!(details.len(), 1);
+        assert_eq!(details[0].delivery_medium, Some(DeliveryMedium::Email));
+        assert_eq!(
+            transport
+                .last_body()
+                .get("UserAttributes")
+                .unwrap()
+                .to_string(),
+            r#"[{"Name":"email","Value":"new@example.com"},{"Name":"custom:tier","Value":"gold"}]"#
+        );
+
+        client
+            .verify_user_attribute("access", "email", "123456")
+            .unwrap();
+        assert_eq!(
+            transport.last_body().to_string(),
+            r#"{"AccessToken":"access","AttributeName":"email","Code":"123456"}"#
+        );
+    }
+
+    #[test]
+    fn test_get_user_attribute_verification_code() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"CodeDeliveryDetails":{"AttributeName":"phone_number","DeliveryMedium":"SMS","Destination":"+*******0100"}}"#,
+        );
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+
+        let details = client
+            .get_user_attribute_verification_code("access", "phone_number")
+            .unwrap()
+            .unwrap();
+        assert_eq!(details.delivery_medium, Some(DeliveryMedium::Sms));
+        assert_eq!(details.destination.as_deref(), Some("+*******0100"));
+        let request = transport.last_request();
+        assert_eq!(
+            request.body,
+            r#"{"AccessToken":"access","AttributeName":"phone_number"}"#
+        );
+        assert!(request.headers.contains(&(
+            "X-Amz-Target".to_owned(),
+            "AWSCognitoIdentityProviderService.GetUserAttributeVerificationCode".to_owned()
+        )));
+    }
+}
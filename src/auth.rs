@@ -0,0 +1,989 @@
+use crate::client::Client;
+use crate::device::RememberedDevice;
+use crate::error::{CognitoSrpError, Result, ServiceErrorKind};
+use crate::json::{self, Value};
+use crate::metrics;
+use crate::srp::{AwsSrp, SrpSession};
+use crate::time::cognito_timestamp;
+use crate::tokens::TokenSet;
+use crate::user::MfaType;
+use crate::wire::{AuthResponse, InitiateAuthRequest, RespondToAuthChallengeRequest};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChallengeName {
+    SmsMfa,
+    SoftwareTokenMfa,
+    // A one-time code sent by email, on pools with email MFA; answer with respond_to_mfa.
+    EmailOtp,
+    // A one-time code by SMS, as a USER_AUTH first factor; also answered with respond_to_mfa.
+    SmsOtp,
+    NewPasswordRequired,
+    CustomChallenge,
+    // MFA is required but the user has none yet; see Client::begin_mfa_setup.
+    MfaSetup,
+    // The user has both SMS and TOTP enabled; see Challenge::mfa_choices.
+    SelectMfaType,
+    // A passkey assertion, from the USER_AUTH flow; see Client::authenticate_with_passkey.
+    WebAuthn,
+    // USER_AUTH asking for a first factor; see Challenge::available_challenges.
+    SelectChallenge,
+    Other(String),
+}
+
+impl ChallengeName {
+    pub(crate) fn parse(name: &str) -> Self {
+        match name {
+            "SMS_MFA" => ChallengeName::SmsMfa,
+            "SOFTWARE_TOKEN_MFA" => ChallengeName::SoftwareTokenMfa,
+            "EMAIL_OTP" => ChallengeName::EmailOtp,
+            "SMS_OTP" => ChallengeName::SmsOtp,
+            "NEW_PASSWORD_REQUIRED" => ChallengeName::NewPasswordRequired,
+            "CUSTOM_CHALLENGE" => ChallengeName::CustomChallenge,
+            "MFA_SETUP" => ChallengeName::MfaSetup,
+            "SELECT_MFA_TYPE" => ChallengeName::SelectMfaType,
+            "WEB_AUTHN" => ChallengeName::WebAuthn,
+            "SELECT_CHALLENGE" => ChallengeName::SelectChallenge,
+            other => ChallengeName::Other(other.to_owned()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            ChallengeName::SmsMfa => "SMS_MFA",
+            ChallengeName::SoftwareTokenMfa => "SOFTWARE_TOKEN_MFA",
+            ChallengeName::EmailOtp => "EMAIL_OTP",
+            ChallengeName::SmsOtp => "SMS_OTP",
+            ChallengeName::NewPasswordRequired => "NEW_PASSWORD_REQUIRED",
+            ChallengeName::CustomChallenge => "CUSTOM_CHALLENGE",
+            ChallengeName::MfaSetup => "MFA_SETUP",
+            ChallengeName::SelectMfaType => "SELECT_MFA_TYPE",
+            ChallengeName::WebAuthn => "WEB_AUTHN",
+            ChallengeName::SelectChallenge => "SELECT_CHALLENGE",
+            ChallengeName::Other(name) => name,
+        }
+    }
+}
+
+// As the Cognito name, e.g. "SOFTWARE_TOKEN_MFA".
+#[cfg(feature = "serde")]
+impl serde::Serialize for ChallengeName {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ChallengeName {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <String as serde::Deserialize>::deserialize(deserializer).map(|name| Self::parse(&name))
+    }
+}
+
+// A challenge Cognito wants answered before it issues tokens.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Challenge {
+    pub name: ChallengeName,
+    pub session: Option<String>,
+    pub parameters: BTreeMap<String, String>,
+    // The username Cognito knows the user by, which is what every response must carry.
+    pub username: String,
+}
+
+impl Challenge {
+    // For SELECT_MFA_TYPE, the methods on offer, from MFAS_CAN_CHOOSE (a JSON array in a
+    // string). Empty for any other challenge.
+    pub fn mfa_choices(&self) -> Vec<MfaType> {
+        let parsed = self
+            .parameters
+            .get("MFAS_CAN_CHOOSE")
+            .map(|names| json::parse(names));
+        let Some(Ok(Value::Array(names))) = parsed else {
+            return Vec::new();
+        };
+        names
+            .iter()
+            .filter_map(|name| name.as_str().and_then(MfaType::parse))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Authentication {
+    Tokens(TokenSet),
+    Challenge(Challenge),
+}
+
+impl Client {
+    pub(crate) fn authentication_from(
+        &self,
+        admin: bool,
+        response: AuthResponse,
+        username: &str,
+    ) -> Result<Authentication> {
+        if let Some(result) = &response.authentication_result {
+            let tokens = TokenSet::from_authentication_result(result, None, self.now());
+            if let Some(metadata) = &result.new_device_metadata {
+                // Failing here would throw away good tokens; the device just isn't remembered.
+                self.confirm_device(username, metadata, &tokens.access_token)
+                    .ok();
+            }
+            self.hooks().tokens_obtained(&tokens);
+            return Ok(Authentication::Tokens(tokens));
+        }
+        let name = response.challenge_name.ok_or_else(|| {
+            CognitoSrpError::InvalidResponse(
+                "response has neither tokens nor a challenge".to_owned(),
+            )
+        })?;
+        let mut parameters = response.challenge_parameters;
+        // Kept with the challenge, the way SELECT_MFA_TYPE carries MFAS_CAN_CHOOSE.
+        if !response.available_challenges.is_empty() {
+            let names = response
+                .available_challenges
+                .iter()
+                .map(|name| Value::from(name.as_str()))
+                .collect();
+            parameters.insert(
+                "AVAILABLE_CHALLENGES".to_owned(),
+                Value::Array(names).to_string(),
+            );
+        }
+        let username = parameters
+            .get("USERNAME")
+            .cloned()
+            .unwrap_or_else(|| username.to_owned());
+        if name.as_str() == "DEVICE_SRP_AUTH" {
+            if let Some(device) = self.remembered_device(&username)? {
+                let session = response.session.as_deref();
+                return self.device_srp_auth(admin, session, &username, &device);
+            }
+        }
+        let challenge = Challenge {
+            name,
+            session: response.session,
+            username,
+            parameters,
+        };
+        self.hooks().challenge_received(&challenge);
+        Ok(Authentication::Challenge(challenge))
+    }
+
+    // The public operation, authorised by the ClientId, or its IAM-signed Admin* twin.
+    pub(crate) fn auth_call(&self, admin: bool, operation: &str, body: Value) -> Result<Value> {
+        if admin {
+            self.admin_call(&format!("Admin{}", operation), body)
+        } else {
+            self.call(operation, body)
+        }
+    }
+
+    fn respond(
+        &self,
+        admin: bool,
+        challenge_name: &str,
+        session: Option<&str>,
+        username: &str,
+        responses: &[(&str, &str)],
+    ) -> Result<Authentication> {
+        let response = self.respond_call(admin, challenge_name, session, username, responses)?;
+        self.authentication_from(admin, response, username)
+    }
+
+    pub(crate) fn respond_call(
+        &self,
+        admin: bool,
+        challenge_name: &str,
+        session: Option<&str>,
+        username: &str,
+        responses: &[(&str, &str)],
+    ) -> Result<AuthResponse> {
+        let mut request = RespondToAuthChallengeRequest::new(
+            self.client_id(),
+            ChallengeName::parse(challenge_name),
+            session,
+        )
+        .with_response("USERNAME", username);
+        for &(name, value) in responses {
+            request = request.with_response(name, value);
+        }
+        if let Some(hash) = self.secret_hash(username) {
+            request = request.with_response("SECRET_HASH", &hash);
+        }
+        self.respond_to_auth_challenge_call(admin, &request)
+    }
+
+    // DEVICE_SRP_AUTH and DEVICE_PASSWORD_VERIFIER: a second SRP exchange that proves the
+    // remembered device, with its own ephemeral and the device password.
+    fn device_srp_auth(
+        &self,
+        admin: bool,
+        session: Option<&str>,
+        username: &str,
+        device: &RememberedDevice,
+    ) -> Result<Authentication> {
+        let srp = self.random(|rng| AwsSrp::for_device(&device.device_group_key, rng));
+        let response = self.respond_call(
+            admin,
+            "DEVICE_SRP_AUTH",
+            session,
+            username,
+            &[("DEVICE_KEY", &device.device_key), ("SRP_A", &srp.srp_a())],
+        )?;
+        response.expect_challenge("DEVICE_PASSWORD_VERIFIER")?;
+        let parameters = &response.challenge_parameters;
+        let param = |name: &str| {
+            parameters.get(name).map(String::as_str).ok_or_else(|| {
+                CognitoSrpError::InvalidResponse(format!(
+                    "DEVICE_PASSWORD_VERIFIER challenge is missing {}",
+                    name
+                ))
+            })
+        };
+        let timestamp = cognito_timestamp(self.now());
+        let secret_block = param("SECRET_BLOCK")?;
+        let signature = srp.password_claim_signature(
+            &device.device_key,
+            device.device_password.expose_secret(),
+            param("SALT")?,
+            param("SRP_B")?,
+            secret_block,
+            &timestamp,
+        )?;
+        self.respond(
+            admin,
+            "DEVICE_PASSWORD_VERIFIER",
+            response.session.as_deref(),
+            username,
+            &[
+                ("DEVICE_KEY", &device.device_key),
+                ("TIMESTAMP", &timestamp),
+                ("PASSWORD_CLAIM_SECRET_BLOCK", secret_block),
+                ("PASSWORD_CLAIM_SIGNATURE", &signature),
+            ],
+        )
+    }
+
+    // # https://docs.aws.amazon.com/cognito/latest/developerguide/amazon-cognito-user-pools-authentication-flow.html#Using-SRP-password-verification-in-custom-authentication-flow
+    // USER_SRP_AUTH followed by the PASSWORD_VERIFIER answer; the password never leaves the process.
+    pub fn authenticate(&self, username: &str, password: &str) -> Result<Authentication> {
+        // A retry restarts the whole exchange so no SRP ephemeral is ever sent twice.
+        let username = &self.normalize_username(username);
+        self.measure(metrics::AUTHENTICATE, || {
+            self.retry_policy()
+                .run(|| self.authenticate_once(false, username, password))
+        })
+    }
+
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_AdminInitiateAuth.html
+    // authenticate for backends signing users in on their behalf: the same SRP exchange through
+    // AdminInitiateAuth and AdminRespondToAuthChallenge, signed with the client's AWS credentials.
+    pub fn admin_authenticate(&self, username: &str, password: &str) -> Result<Authentication> {
+        let username = &self.normalize_username(username);
+        self.measure(metrics::ADMIN_AUTHENTICATE, || {
+            self.retry_policy()
+                .run(|| self.authenticate_once(true, username, password))
+        })
+    }
+
+    // ADMIN_USER_PASSWORD_AUTH: the password goes to Cognito as is, over TLS from the backend.
+    // Needs the flow enabled on the app client; prefer admin_authenticate where it isn't.
+    pub fn admin_authenticate_with_password(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<Authentication> {
+        let username = &self.normalize_username(username);
+        let mut request = InitiateAuthRequest::new("ADMIN_USER_PASSWORD_AUTH", self.client_id())
+            .with_parameter("USERNAME", username)
+            .with_parameter("PASSWORD", password);
+        if let Some(hash) = self.secret_hash(username) {
+            request = request.with_parameter("SECRET_HASH", &hash);
+        }
+        let response = self.initiate_auth_call(true, &request)?;
+        self.authentication_from(true, response, username)
+    }
+
+    fn authenticate_once(
+        &self,
+        admin: bool,
+        username: &str,
+        password: &str,
+    ) -> Result<Authentication> {
+        let (srp, response) = self.initiate_srp(admin, username)?;
+        self.answer_password_verifier(admin, srp, &response, password)
+    }
+
+    // InitiateAuth with a fresh SRP_A; returns the ephemeral with the PASSWORD_VERIFIER response.
+    pub(crate) fn initiate_srp(
+        &self,
+        admin: bool,
+        username: &str,
+    ) -> Result<(SrpSession, AuthResponse)> {
+        let srp = self.random(|rng| SrpSession::new(self.pool_id(), rng))?;
+        let mut request = InitiateAuthRequest::new("USER_SRP_AUTH", self.client_id())
+            .with_parameter("USERNAME", username)
+            .with_parameter("SRP_A", &srp.srp_a());
+        if let Some(hash) = self.secret_hash(username) {
+            request = request.with_parameter("SECRET_HASH", &hash);
+        }
+        let response = self.initiate_auth_call(admin, &request)?;
+        response.expect_challenge("PASSWORD_VERIFIER")?;
+        Ok((srp, response))
+    }
+
+    // Takes the ephemeral by value: each one answers a single PASSWORD_VERIFIER.
+    pub(crate) fn answer_password_verifier(
+        &self,
+        admin: bool,
+        srp: SrpSession,
+        response: &AuthResponse,
+        password: &str,
+    ) -> Result<Authentication> {
+        let mut responses = srp.process_challenge(
+            &response.challenge_parameters,
+            password,
+            &cognito_timestamp(self.now()),
+        )?;
+        let internal_username = responses.remove("USERNAME").unwrap_or_default();
+        // Offering a remembered device is what makes Cognito ask for DEVICE_SRP_AUTH.
+        let device = self.remembered_device(&internal_username)?;
+        if let Some(device) = &device {
+            responses.insert("DEVICE_KEY".to_owned(), device.device_key.clone());
+        }
+        let responses: Vec<(&str, &str)> = responses
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        let result = self.respond(
+            admin,
+            "PASSWORD_VERIFIER",
+            response.session.as_deref(),
+            &internal_username,
+            &responses,
+        );
+        // Forgotten on Cognito's side (ForgetDevice, or an admin reset); the next sign-in goes
+        // through MFA and remembers the device afresh.
+        if let (Some(_), Err(err)) = (&device, &result) {
+            if err.service_kind() == Some(&ServiceErrorKind::ResourceNotFound) {
+                self.forget_remembered_device(&internal_username);
+            }
+        }
+        result
+    }
+
+    // Answers any challenge; USERNAME and SECRET_HASH are filled in automatically.
+    pub fn respond_to_challenge(
+        &self,
+        challenge: &Challenge,
+        responses: &[(&str, &str)],
+    ) -> Result<Authentication> {
+        self.respond(
+            false,
+            challenge.name.as_str(),
+            challenge.session.as_deref(),
+            &challenge.username,
+            responses,
+        )
+    }
+
+    pub fn respond_to_mfa(&self, challenge: &Challenge, code: &str) -> Result<Authentication> {
+        self.respond_to_challenge(challenge, &[(mfa_code_key(challenge)?, code)])
+    }
+
+    pub fn respond_to_new_password(
+        &self,
+        challenge: &Challenge,
+        new_password: &str,
+    ) -> Result<Authentication> {
+        check_new_password_required(challenge)?;
+        self.check_password(new_password)?;
+        self.respond_to_challenge(challenge, &[("NEW_PASSWORD", new_password)])
+    }
+
+    // Answers SELECT_MFA_TYPE; Cognito follows up with the chosen method's own MFA challenge.
+    pub fn respond_to_select_mfa_type(
+        &self,
+        challenge: &Challenge,
+        choice: MfaType,
+    ) -> Result<Authentication> {
+        check_mfa_choice(challenge, choice)?;
+        self.respond_to_challenge(challenge, &[("ANSWER", choice.as_str())])
+    }
+
+    // The Admin* counterparts, for challenges from admin_authenticate and
+    // admin_authenticate_with_password.
+    pub fn admin_respond_to_challenge(
+        &self,
+        challenge: &Challenge,
+        responses: &[(&str, &str)],
+    ) -> Result<Authentication> {
+        self.respond(
+            true,
+            challenge.name.as_str(),
+            challenge.session.as_deref(),
+            &challenge.username,
+            responses,
+        )
+    }
+
+    pub fn admin_respond_to_mfa(
+        &self,
+        challenge: &Challenge,
+        code: &str,
+    ) -> Result<Authentication> {
+        self.admin_respond_to_challenge(challenge, &[(mfa_code_key(challenge)?, code)])
+    }
+
+    pub fn admin_respond_to_new_password(
+        &self,
+        challenge: &Challenge,
+        new_password: &str,
+    ) -> Result<Authentication> {
+        check_new_password_required(challenge)?;
+        self.check_password(new_password)?;
+        self.admin_respond_to_challenge(challenge, &[("NEW_PASSWORD", new_password)])
+    }
+
+    pub fn admin_respond_to_select_mfa_type(
+        &self,
+        challenge: &Challenge,
+        choice: MfaType,
+    ) -> Result<Authentication> {
+        check_mfa_choice(challenge, choice)?;
+        self.admin_respond_to_challenge(challenge, &[("ANSWER", choice.as_str())])
+    }
+}
+
+fn mfa_code_key(challenge: &Challenge) -> Result<&'static str> {
+    match challenge.name {
+        ChallengeName::SmsMfa => Ok("SMS_MFA_CODE"),
+        ChallengeName::SoftwareTokenMfa => Ok("SOFTWARE_TOKEN_MFA_CODE"),
+        ChallengeName::EmailOtp => Ok("EMAIL_OTP_CODE"),
+        ChallengeName::SmsOtp => Ok("SMS_OTP_CODE"),
+        _ => Err(CognitoSrpError::InvalidArgument(format!(
+            "{} is not an MFA challenge",
+            challenge.name.as_str()
+        ))),
+    }
+}
+
+fn check_mfa_choice(challenge: &Challenge, choice: MfaType) -> Result<()> {
+    if challenge.name != ChallengeName::SelectMfaType {
+        return Err(CognitoSrpError::InvalidArgument(format!(
+            "{} is not SELECT_MFA_TYPE",
+            challenge.name.as_str()
+        )));
+    }
+    let choices = challenge.mfa_choices();
+    // Without MFAS_CAN_CHOOSE there is nothing to check against; Cognito will say.
+    if !choices.is_empty() && !choices.contains(&choice) {
+        return Err(CognitoSrpError::InvalidArgument(format!(
+            "{} is not one of the offered MFA methods",
+            choice.as_str()
+        )));
+    }
+    Ok(())
+}
+
+fn check_new_password_required(challenge: &Challenge) -> Result<()> {
+    if challenge.name != ChallengeName::NewPasswordRequired {
+        return Err(CognitoSrpError::InvalidArgument(format!(
+            "{} is not NEW_PASSWORD_REQUIRED",
+            challenge.name.as_str()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use crate::context_data::UserContextData;
+    use crate::credentials::AwsCredentials;
+    use crate::device::{DeviceStore, FileDeviceStore};
+    use crate::retry::RetryPolicy;
+    use crate::srp::server::{PasswordVerifier, ServerSession};
+    use crate::transport::mock::MockTransport;
+    use crate::username::UsernameNormalization;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    const PASSWORD_VERIFIER: &str = r#"{"ChallengeName":"PASSWORD_VERIFIER","ChallengeParameters":{
+        "SALT":"ab12cd34ef56","SECRET_BLOCK":"b3BhcXVlIHNlY3JldCBibG9jayBmcm9tIGNvZ25pdG8=",
+        "SRP_B":"82f7e185b83288814ad86154a1bfa657f70d27ec43f507a618d89eb5ac9f90e9",
+        "USERNAME":"bob-internal","USER_ID_FOR_SRP":"bob-internal"}}"#;
+
+    #[test]
+    fn test_authenticate() {
+        let transport = MockTransport::new();
+        transport.respond(200, PASSWORD_VERIFIER);
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a","IdToken":"i","RefreshToken":"r","ExpiresIn":3600}}"#,
+        );
+        let now = UNIX_EPOCH + Duration::from_secs(1_759_827_903);
+        let client = Client::new("us-east-1_Pool123", "client", transport.clone())
+            .unwrap()
+            .with_client_secret("secret")
+            .with_clock(FixedClock::new(now));
+
+        match client.authenticate("bob@example.com", "P@ssw0rd!").unwrap() {
+            Authentication::Tokens(tokens) => {
+                assert_eq!(tokens.refresh_token, Some("r".into()));
+                assert_eq!(tokens.expires_at, now + Duration::from_secs(3600));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let requests = transport.requests.lock().unwrap();
+        let initiate = crate::json::parse(&requests[0].body).unwrap();
+        let parameters = initiate.get("AuthParameters").unwrap();
+        assert_eq!(initiate.str_field("AuthFlow").unwrap(), "USER_SRP_AUTH");
+        assert_eq!(parameters.str_field("USERNAME").unwrap(), "bob@example.com");
+        assert!(parameters.str_field("SRP_A").unwrap().len() > 700);
+
+        let respond = crate::json::parse(&requests[1].body).unwrap();
+        let responses = respond.get("ChallengeResponses").unwrap();
+        assert_eq!(
+            respond.str_field("ChallengeName").unwrap(),
+            "PASSWORD_VERIFIER"
+        );
+        assert_eq!(responses.str_field("USERNAME").unwrap(), "bob-internal");
+        assert_eq!(
+            responses.str_field("TIMESTAMP").unwrap(),
+            "Tue Oct 7 09:05:03 UTC 2025"
+        );
+        assert_eq!(
+            responses
+                .str_field("PASSWORD_CLAIM_SIGNATURE")
+                .unwrap()
+                .len(),
+            44
+        );
+        assert_eq!(
+            responses.str_field("SECRET_HASH").unwrap(),
+            client.secret_hash("bob-internal").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_remembered_device() {
+        let dir = std::env::temp_dir().join(format!("cogneato-test-{}", rand::random::<u64>()));
+        let store = FileDeviceStore::new(&dir);
+        let transport = MockTransport::new();
+        let client = Client::new("us-east-1_Pool123", "client", transport.clone())
+            .unwrap()
+            .with_device_store(store.clone());
+
+        // First sign-in: Cognito issues device keys, which get confirmed and remembered.
+        transport.respond(200, PASSWORD_VERIFIER);
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a","IdToken":"i","ExpiresIn":3600,
+                "NewDeviceMetadata":{"DeviceKey":"us-east-1_dev1","DeviceGroupKey":"-grp1"}}}"#,
+        );
+        transport.respond(200, r#"{"UserConfirmationNecessary":true}"#);
+        transport.respond(200, "{}");
+        client.authenticate("bob", "P@ssw0rd!").unwrap();
+        let device = store
+            .load("us-east-1_Pool123", "bob-internal")
+            .unwrap()
+            .unwrap();
+        assert_eq!(device.device_key, "us-east-1_dev1");
+        let (confirm, remember) = {
+            let requests = transport.requests.lock().unwrap();
+            (
+                crate::json::parse(&requests[2].body).unwrap(),
+                requests[3].body.clone(),
+            )
+        };
+        assert_eq!(
+            remember,
+            r#"{"AccessToken":"a","DeviceKey":"us-east-1_dev1","DeviceRememberedStatus":"remembered"}"#
+        );
+        let config = confirm.get("DeviceSecretVerifierConfig").unwrap();
+        let verifier = PasswordVerifier::for_device(
+            "-grp1",
+            "us-east-1_dev1",
+            &config.str_field("Salt").unwrap(),
+            &config.str_field("PasswordVerifier").unwrap(),
+        );
+        let server = ServerSession::new(&verifier, &mut rand::thread_rng());
+
+        // Second sign-in: the device answers DEVICE_SRP_AUTH instead of the user answering MFA.
+        transport.respond(200, PASSWORD_VERIFIER);
+        transport.respond(
+            200,
+            r#"{"ChallengeName":"DEVICE_SRP_AUTH","Session":"s1","ChallengeParameters":{"USERNAME":"bob-internal"}}"#,
+        );
+        transport.respond(
+            200,
+            &format!(
+                r#"{{"ChallengeName":"DEVICE_PASSWORD_VERIFIER","Session":"s2","ChallengeParameters":{{
+                    "USERNAME":"bob-internal","SRP_B":"{}","SALT":"{}","SECRET_BLOCK":"{}"}}}}"#,
+                server.srp_b(),
+                server.salt(),
+                server.secret_block()
+            ),
+        );
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a2","IdToken":"i2","ExpiresIn":3600}}"#,
+        );
+        let Authentication::Tokens(tokens) = client.authenticate("bob", "P@ssw0rd!").unwrap()
+        else {
+            panic!("expected tokens");
+        };
+        assert_eq!(tokens.access_token, "a2");
+
+        let requests = transport.requests.lock().unwrap();
+        let responses = |i: usize| {
+            let body = crate::json::parse(&requests[i].body).unwrap();
+            body.get("ChallengeResponses").unwrap().clone()
+        };
+        assert_eq!(
+            responses(5).str_field("DEVICE_KEY").unwrap(),
+            "us-east-1_dev1"
+        );
+        let device_srp = responses(6);
+        assert_eq!(device_srp.str_field("USERNAME").unwrap(), "bob-internal");
+        let answer = responses(7);
+        assert_eq!(answer.str_field("DEVICE_KEY").unwrap(), "us-east-1_dev1");
+        server
+            .verify(
+                &device_srp.str_field("SRP_A").unwrap(),
+                &answer.str_field("TIMESTAMP").unwrap(),
+                &answer.str_field("PASSWORD_CLAIM_SIGNATURE").unwrap(),
+            )
+            .unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_authenticate_with_alias() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            &PASSWORD_VERIFIER.replace(
+                r#""USERNAME":"bob-internal""#,
+                r#""USERNAME":"bob@example.com""#,
+            ),
+        );
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a","IdToken":"i","ExpiresIn":3600}}"#,
+        );
+        let client = Client::new("us-east-1_Pool123", "client", transport.clone())
+            .unwrap()
+            .with_client_secret("secret")
+            .with_username_normalization(UsernameNormalization::case_insensitive());
+
+        client
+            .authenticate("  Bob@Example.com\n", "P@ssw0rd!")
+            .unwrap();
+        let requests = transport.requests.lock().unwrap();
+        let initiate = crate::json::parse(&requests[0].body).unwrap();
+        let parameters = initiate.get("AuthParameters").unwrap();
+        assert_eq!(parameters.str_field("USERNAME").unwrap(), "bob@example.com");
+        assert_eq!(
+            parameters.str_field("SECRET_HASH").unwrap(),
+            client.secret_hash("bob@example.com").unwrap()
+        );
+
+        let respond = crate::json::parse(&requests[1].body).unwrap();
+        let responses = respond.get("ChallengeResponses").unwrap();
+        assert_eq!(responses.str_field("USERNAME").unwrap(), "bob-internal");
+        assert_eq!(
+            responses.str_field("SECRET_HASH").unwrap(),
+            client.secret_hash("bob-internal").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_mfa_challenge() {
+        let transport = MockTransport::new();
+        transport.respond(200, PASSWORD_VERIFIER);
+        transport.respond(
+            200,
+            r#"{"ChallengeName":"SOFTWARE_TOKEN_MFA","Session":"s1","ChallengeParameters":{}}"#,
+        );
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a","IdToken":"i","ExpiresIn":3600}}"#,
+        );
+        let client = Client::new("us-east-1_Pool123", "client", transport.clone())
+            .unwrap()
+            .with_client_metadata(&[("source", "kiosk")])
+            .with_user_context_data(UserContextData::new().with_ip_address("203.0.113.7"))
+            .with_analytics_endpoint_id("pinpoint-1");
+
+        let challenge = match client.authenticate("bob", "P@ssw0rd!").unwrap() {
+            Authentication::Challenge(challenge) => challenge,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(challenge.name, ChallengeName::SoftwareTokenMfa);
+        assert_eq!(challenge.username, "bob-internal");
+        assert!(client.respond_to_new_password(&challenge, "x").is_err());
+
+        let outcome = client.respond_to_mfa(&challenge, "123456").unwrap();
+        assert!(matches!(outcome, Authentication::Tokens(_)));
+        let body = transport.last_body();
+        assert_eq!(body.str_field("Session").unwrap(), "s1");
+        assert_eq!(
+            body.get("ChallengeResponses").unwrap().to_string(),
+            r#"{"SOFTWARE_TOKEN_MFA_CODE":"123456","USERNAME":"bob-internal"}"#
+        );
+        // The metadata rides along on the initiate call and every continuation.
+        for request in transport.requests.lock().unwrap().iter() {
+            assert!(request
+                .body
+                .contains(r#""ClientMetadata":{"source":"kiosk"}"#));
+            assert!(request
+                .body
+                .contains(r#""UserContextData":{"IpAddress":"203.0.113.7"}"#));
+            assert!(request
+                .body
+                .contains(r#""AnalyticsMetadata":{"AnalyticsEndpointId":"pinpoint-1"}"#));
+        }
+    }
+
+    #[test]
+    fn test_email_otp() {
+        let transport = MockTransport::new();
+        transport.respond(200, PASSWORD_VERIFIER);
+        transport.respond(
+            200,
+            r#"{"ChallengeName":"EMAIL_OTP","Session":"s1","ChallengeParameters":{
+                "CODE_DELIVERY_DELIVERY_MEDIUM":"EMAIL","CODE_DELIVERY_DESTINATION":"b***@e***"}}"#,
+        );
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a","IdToken":"i","ExpiresIn":3600}}"#,
+        );
+        let client = Client::new("us-east-1_Pool123", "client", transport.clone()).unwrap();
+
+        let challenge = match client.authenticate("bob", "P@ssw0rd!").unwrap() {
+            Authentication::Challenge(challenge) => challenge,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(challenge.name, ChallengeName::EmailOtp);
+        assert_eq!(
+            ChallengeName::parse(challenge.name.as_str()),
+            challenge.name
+        );
+        let outcome = client.respond_to_mfa(&challenge, "12345678").unwrap();
+        assert!(matches!(outcome, Authentication::Tokens(_)));
+        assert_eq!(
+            transport
+                .last_body()
+                .get("ChallengeResponses")
+                .unwrap()
+                .to_string(),
+            r#"{"EMAIL_OTP_CODE":"12345678","USERNAME":"bob-internal"}"#
+        );
+    }
+
+    #[test]
+    fn test_select_mfa_type() {
+        let transport = MockTransport::new();
+        transport.respond(200, PASSWORD_VERIFIER);
+        transport.respond(
+            200,
+            r#"{"ChallengeName":"SELECT_MFA_TYPE","Session":"s1","ChallengeParameters":{
+                "MFAS_CAN_CHOOSE":"[\"SMS_MFA\",\"SOFTWARE_TOKEN_MFA\"]"}}"#,
+        );
+        transport.respond(
+            200,
+            r#"{"ChallengeName":"SOFTWARE_TOKEN_MFA","Session":"s2","ChallengeParameters":{}}"#,
+        );
+        let client = Client::new("us-east-1_Pool123", "client", transport.clone()).unwrap();
+
+        let challenge = match client.authenticate("bob", "P@ssw0rd!").unwrap() {
+            Authentication::Challenge(challenge) => challenge,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(challenge.name, ChallengeName::SelectMfaType);
+        assert_eq!(
+            challenge.mfa_choices(),
+            [MfaType::Sms, MfaType::SoftwareToken]
+        );
+        let next = match client
+            .respond_to_select_mfa_type(&challenge, MfaType::SoftwareToken)
+            .unwrap()
+        {
+            Authentication::Challenge(next) => next,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(next.name, ChallengeName::SoftwareTokenMfa);
+        let body = transport.last_body();
+        assert_eq!(body.str_field("ChallengeName").unwrap(), "SELECT_MFA_TYPE");
+        assert_eq!(
+            body.get("ChallengeResponses").unwrap().to_string(),
+            r#"{"ANSWER":"SOFTWARE_TOKEN_MFA","USERNAME":"bob-internal"}"#
+        );
+
+        let sms_only = Challenge {
+            parameters: [("MFAS_CAN_CHOOSE".to_owned(), r#"["SMS_MFA"]"#.to_owned())].into(),
+            ..challenge
+        };
+        assert!(client
+            .respond_to_select_mfa_type(&sms_only, MfaType::SoftwareToken)
+            .is_err());
+        assert!(client
+            .respond_to_select_mfa_type(&next, MfaType::Sms)
+            .is_err());
+        assert_eq!(transport.requests.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_retry_uses_fresh_ephemeral() {
+        let transport = MockTransport::new();
+        transport.respond(200, PASSWORD_VERIFIER);
+        transport.respond(400, r#"{"__type":"TooManyRequestsException","message":""}"#);
+        transport.respond(200, PASSWORD_VERIFIER);
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a","IdToken":"i","ExpiresIn":3600}}"#,
+        );
+        let client = Client::new("us-east-1_Pool123", "client", transport.clone())
+            .unwrap()
+            .with_retry_policy(RetryPolicy {
+                base_delay: std::time::Duration::ZERO,
+                ..RetryPolicy::default()
+            });
+
+        assert!(matches!(
+            client.authenticate("bob", "P@ssw0rd!").unwrap(),
+            Authentication::Tokens(_)
+        ));
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests.len(), 4);
+        let srp_a = |i: usize| {
+            crate::json::parse(&requests[i].body)
+                .unwrap()
+                .get("AuthParameters")
+                .unwrap()
+                .str_field("SRP_A")
+                .unwrap()
+        };
+        assert_ne!(srp_a(0), srp_a(2));
+    }
+
+    #[test]
+    fn test_seeded_rng_is_repeatable() {
+        use rand::SeedableRng;
+
+        let srp_a = |seed: u64| {
+            let transport = MockTransport::new();
+            transport.respond(200, r#"{"ChallengeName":"CUSTOM_CHALLENGE"}"#);
+            let client = Client::new("us-east-1_Pool123", "client", transport.clone())
+                .unwrap()
+                .with_rng(rand_chacha::ChaCha20Rng::seed_from_u64(seed));
+            assert!(client.authenticate("bob", "P@ssw0rd!").is_err());
+            let body = transport.last_body();
+            body.get("AuthParameters")
+                .unwrap()
+                .str_field("SRP_A")
+                .unwrap()
+        };
+        assert_eq!(srp_a(7), srp_a(7));
+        assert_ne!(srp_a(7), srp_a(8));
+    }
+
+    #[test]
+    fn test_admin_authenticate() {
+        let transport = MockTransport::new();
+        transport.respond(200, PASSWORD_VERIFIER);
+        transport.respond(
+            200,
+            r#"{"ChallengeName":"SMS_MFA","Session":"s1","ChallengeParameters":{}}"#,
+        );
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a","IdToken":"i","ExpiresIn":3600}}"#,
+        );
+        let client = Client::new("us-east-1_Pool123", "client", transport.clone())
+            .unwrap()
+            .with_credentials(AwsCredentials::new("AKID", "secret"));
+
+        let challenge = match client.admin_authenticate("bob", "P@ssw0rd!").unwrap() {
+            Authentication::Challenge(challenge) => challenge,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(challenge.name, ChallengeName::SmsMfa);
+        let outcome = client.admin_respond_to_mfa(&challenge, "123456").unwrap();
+        assert!(matches!(outcome, Authentication::Tokens(_)));
+
+        let requests = transport.requests.lock().unwrap();
+        for (request, operation) in requests.iter().zip([
+            "AdminInitiateAuth",
+            "AdminRespondToAuthChallenge",
+            "AdminRespondToAuthChallenge",
+        ]) {
+            assert!(request.headers.contains(&(
+                "X-Amz-Target".to_owned(),
+                format!("AWSCognitoIdentityProviderService.{}", operation)
+            )));
+            assert!(request
+                .headers
+                .iter()
+                .any(|(name, _)| name == "Authorization"));
+            let body = crate::json::parse(&request.body).unwrap();
+            assert_eq!(body.str_field("UserPoolId").unwrap(), "us-east-1_Pool123");
+        }
+        let initiate = crate::json::parse(&requests[0].body).unwrap();
+        assert_eq!(initiate.str_field("AuthFlow").unwrap(), "USER_SRP_AUTH");
+        let verifier = crate::json::parse(&requests[1].body).unwrap();
+        assert_eq!(
+            verifier.str_field("ChallengeName").unwrap(),
+            "PASSWORD_VERIFIER"
+        );
+    }
+
+    #[test]
+    fn test_admin_authenticate_with_password() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"ChallengeName":"NEW_PASSWORD_REQUIRED","Session":"s1","ChallengeParameters":{"USER_ID_FOR_SRP":"bob"}}"#,
+        );
+        let client = Client::new("us-east-1_Pool123", "client", transport.clone())
+            .unwrap()
+            .with_client_secret("secret");
+        // Without credentials nothing is sent.
+        assert!(client
+            .admin_authenticate_with_password("bob", "pw")
+            .is_err());
+        assert!(transport.requests.lock().unwrap().is_empty());
+
+        let client = client.with_credentials(AwsCredentials::new("AKID", "secret"));
+        let challenge = match client
+            .admin_authenticate_with_password("bob", "pw")
+            .unwrap()
+        {
+            Authentication::Challenge(challenge) => challenge,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(challenge.name, ChallengeName::NewPasswordRequired);
+        let body = transport.last_body();
+        let parameters = body.get("AuthParameters").unwrap();
+        assert_eq!(
+            body.str_field("AuthFlow").unwrap(),
+            "ADMIN_USER_PASSWORD_AUTH"
+        );
+        assert_eq!(parameters.str_field("PASSWORD").unwrap(), "pw");
+        assert!(parameters.str_field("SECRET_HASH").is_some());
+        assert!(client.admin_respond_to_mfa(&challenge, "123456").is_err());
+    }
+
+    #[test]
+    fn test_unexpected_initiate_response() {
+        let transport = MockTransport::new();
+        transport.respond(200, r#"{"ChallengeName":"CUSTOM_CHALLENGE"}"#);
+        let client = Client::new("us-east-1_Pool123", "client", transport).unwrap();
+        assert!(client.authenticate("bob", "pw").is_err());
+    }
+}
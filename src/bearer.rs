@@ -0,0 +1,183 @@
+// Outgoing half of token auth: puts a current token from a TokenProvider on requests to
+// Cognito-protected APIs (API Gateway, AppSync, anything behind CognitoAuthLayer).
+use crate::error::Result;
+use crate::jwt::TokenUse;
+use crate::provider::TokenProvider;
+use std::sync::Arc;
+
+// Which token goes out and how the Authorization header carries it.
+#[derive(Clone)]
+pub struct BearerAuth {
+    provider: Arc<dyn TokenProvider>,
+    token_use: TokenUse,
+    bare: bool,
+}
+
+impl BearerAuth {
+    // Sends the access token as "Bearer <token>".
+    pub fn new(provider: impl TokenProvider + 'static) -> Self {
+        Self {
+            provider: Arc::new(provider),
+            token_use: TokenUse::Access,
+            bare: false,
+        }
+    }
+
+    // API Gateway's Cognito authorizers and AppSync's user pool auth want the ID token.
+    pub fn with_token_use(mut self, token_use: TokenUse) -> Self {
+        self.token_use = token_use;
+        self
+    }
+
+    // The token alone, without the "Bearer " scheme, as AppSync expects it.
+    pub fn with_bare_token(mut self) -> Self {
+        self.bare = true;
+        self
+    }
+
+    // The Authorization value for the current token. May refresh, so it can block on I/O.
+    pub fn header_value(&self) -> Result<String> {
+        let token = match self.token_use {
+            TokenUse::Access => self.provider.access_token()?,
+            TokenUse::Id => self.provider.id_token()?,
+        };
+        Ok(if self.bare {
+            token
+        } else {
+            format!("Bearer {}", token)
+        })
+    }
+
+    // After a 401: drop the cached tokens so the next header_value renews them.
+    pub fn invalidate(&self) {
+        self.provider.invalidate();
+    }
+}
+
+// # https://docs.rs/reqwest-middleware/
+// Attaches the header and, when the API answers 401, refreshes and retries once. Requests with
+// streaming bodies can't be replayed, so their 401 is returned as is.
+#[cfg(feature = "reqwest")]
+#[async_trait::async_trait]
+impl reqwest_middleware::Middleware for BearerAuth {
+    async fn handle(
+        &self,
+        request: reqwest::Request,
+        extensions: &mut http::Extensions,
+        next: reqwest_middleware::Next<'_>,
+    ) -> reqwest_middleware::Result<reqwest::Response> {
+        let retry = request.try_clone();
+        let response = next
+            .clone()
+            .run(self.authorize(request).await?, extensions)
+            .await?;
+        match retry {
+            Some(retry) if response.status() == reqwest::StatusCode::UNAUTHORIZED => {
+                self.invalidate();
+                next.run(self.authorize(retry).await?, extensions).await
+            }
+            _ => Ok(response),
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl BearerAuth {
+    // header_value off the async executor's threads, since a refresh blocks on the transport.
+    async fn header_value_async(&self) -> Result<String> {
+        let auth = self.clone();
+        tokio::task::spawn_blocking(move || auth.header_value())
+            .await
+            .map_err(|_| crate::error::CognitoSrpError::TaskPanicked)?
+    }
+
+    async fn authorize(
+        &self,
+        mut request: reqwest::Request,
+    ) -> reqwest_middleware::Result<reqwest::Request> {
+        let value = self
+            .header_value_async()
+            .await
+            .map_err(|err| reqwest_middleware::Error::Middleware(err.into()))?;
+        let value = reqwest::header::HeaderValue::from_str(&value)
+            .map_err(|err| reqwest_middleware::Error::Middleware(err.into()))?;
+        request
+            .headers_mut()
+            .insert(reqwest::header::AUTHORIZATION, value);
+        Ok(request)
+    }
+}
+
+// # https://docs.rs/tonic/latest/tonic/service/trait.Interceptor.html
+// `GreeterClient::with_interceptor(channel, auth)`. Interceptors are synchronous, so a due
+// refresh runs inline on the calling task; the provider's lead time keeps that rare.
+#[cfg(feature = "tonic")]
+impl tonic::service::Interceptor for BearerAuth {
+    fn call(
+        &mut self,
+        mut request: tonic::Request<()>,
+    ) -> std::result::Result<tonic::Request<()>, tonic::Status> {
+        let value = self
+            .header_value()
+            .map_err(|err| tonic::Status::unauthenticated(err.to_string()))?;
+        let value = value
+            .parse()
+            .map_err(|_| tonic::Status::unauthenticated("token is not valid metadata"))?;
+        request.metadata_mut().insert("authorization", value);
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokens::TokenSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::SystemTime;
+
+    #[derive(Default)]
+    struct Counting(AtomicUsize);
+
+    impl TokenProvider for Counting {
+        fn tokens(&self) -> Result<TokenSet> {
+            let n = self.0.load(Ordering::SeqCst);
+            Ok(TokenSet {
+                access_token: format!("a{}", n),
+                id_token: format!("i{}", n),
+                refresh_token: None,
+                token_type: "Bearer".to_owned(),
+                expires_at: SystemTime::now(),
+            })
+        }
+
+        fn invalidate(&self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_header_value() {
+        let auth = BearerAuth::new(Counting::default());
+        assert_eq!(auth.header_value().unwrap(), "Bearer a0");
+        auth.invalidate();
+        assert_eq!(auth.header_value().unwrap(), "Bearer a1");
+
+        let auth = BearerAuth::new(Counting::default())
+            .with_token_use(TokenUse::Id)
+            .with_bare_token();
+        assert_eq!(auth.header_value().unwrap(), "i0");
+    }
+
+    #[test]
+    #[cfg(feature = "tonic")]
+    fn test_interceptor() {
+        use tonic::service::Interceptor;
+
+        let mut auth = BearerAuth::new(Counting::default());
+        let request = auth.call(tonic::Request::new(())).unwrap();
+        assert_eq!(
+            request.metadata().get("authorization").unwrap(),
+            "Bearer a0"
+        );
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    std::process::exit(cog_neat_oh::cli::main());
+}
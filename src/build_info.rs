@@ -0,0 +1,63 @@
+// What actually got compiled in, for deployments that need to prove the minimal build is minimal.
+
+// `deps-min` promises a RustCrypto-only build with no C/asm crypto; fail loudly if anything in
+// the dependency graph turns ring back on instead of silently shipping it.
+#[cfg(all(feature = "deps-min", feature = "ring"))]
+compile_error!(
+    "cog-neat-oh: `deps-min` cannot be combined with the `ring` feature; \
+     depend on the crate with `default-features = false`"
+);
+
+// Nor anything that brings an async runtime, an HTTP stack or a language binding into the graph:
+// those are most of the size a Lambda cold start pays for.
+#[cfg(all(
+    feature = "deps-min",
+    any(
+        feature = "async",
+        feature = "aws-sdk",
+        feature = "reqwest",
+        feature = "tower",
+        feature = "axum",
+        feature = "tonic",
+        feature = "python",
+        feature = "node",
+    )
+))]
+compile_error!(
+    "cog-neat-oh: `deps-min` cannot be combined with `async`, `aws-sdk`, `reqwest`, `tower`, \
+     `axum`, `tonic`, `python` or `node`; they pull in tokio, hyper, pyo3 or napi"
+);
+
+#[cfg(not(any(feature = "ring", feature = "backend-rustcrypto")))]
+compile_error!("cog-neat-oh: no crypto backend; enable `backend-ring` or `backend-rustcrypto`");
+
+pub const CRYPTO_BACKEND: &str = if cfg!(feature = "ring") {
+    "ring"
+} else {
+    "rustcrypto"
+};
+
+pub const CLIENT: bool = cfg!(feature = "client");
+
+// jwt::Verifier is built; without CLIENT this is the verify-only build.
+pub const VERIFY: bool = cfg!(any(feature = "client", feature = "verify"));
+
+// False on no_std builds, where only `srp` and `srp6a` are available.
+pub const STD: bool = cfg!(feature = "std");
+
+pub const DEPS_MIN: bool = cfg!(feature = "deps-min");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info() {
+        if DEPS_MIN {
+            assert_eq!(CRYPTO_BACKEND, "rustcrypto");
+        }
+        assert_eq!(CLIENT, cfg!(feature = "client"));
+        const { assert!(STD || !CLIENT) };
+        const { assert!(VERIFY || !CLIENT) };
+    }
+}
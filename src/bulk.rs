@@ -0,0 +1,405 @@
+use crate::auth::Authentication;
+use crate::client::Client;
+#[cfg(feature = "reqwest")]
+use crate::curl::TransportConfig;
+use crate::error::{CognitoSrpError, Result};
+use crate::json::Value;
+#[cfg(feature = "reqwest")]
+use crate::pooled::PooledTransport;
+use crate::registration::SignUpResult;
+use crate::secret::SecretString;
+use crate::transport::Transport;
+use std::fmt;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct NewUser {
+    pub username: String,
+    pub password: String,
+    pub attributes: Vec<(String, String)>,
+}
+
+impl fmt::Debug for NewUser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NewUser")
+            .field("username", &self.username)
+            .field("password", &"***")
+            .field("attributes", &self.attributes)
+            .finish()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BulkOptions {
+    pub concurrency: usize,
+    pub max_attempts: u32,
+    pub retry_delay: Duration,
+    // AdminConfirmSignUp each new user; needs AWS credentials on the client.
+    pub confirm: bool,
+}
+
+impl Default for BulkOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            max_attempts: 3,
+            retry_delay: Duration::from_millis(200),
+            confirm: true,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SignUpReport {
+    pub username: String,
+    pub attempts: u32,
+    pub outcome: Result<SignUpResult>,
+}
+
+// Retries errors that `retry` accepts, up to options.max_attempts in all.
+fn with_retries<T>(
+    options: &BulkOptions,
+    attempts: &mut u32,
+    retry: fn(&CognitoSrpError) -> bool,
+    mut op: impl FnMut() -> Result<T>,
+) -> Result<T> {
+    let mut delay = options.retry_delay;
+    loop {
+        *attempts += 1;
+        match op() {
+            Err(err) if retry(&err) && *attempts < options.max_attempts => {
+                thread::sleep(delay);
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn sign_up_one(client: &Client, user: &NewUser, options: &BulkOptions) -> SignUpReport {
+    let mut attempts = 0;
+    let outcome = (|| {
+        let attributes: Vec<(&str, &str)> = user
+            .attributes
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        // SignUp isn't idempotent: after a 5xx or a dropped connection the user may well exist,
+        // and a retry would report UsernameExists for a sign-up that worked. Only a throttle is
+        // sure to have been turned away.
+        let mut result =
+            with_retries(options, &mut attempts, CognitoSrpError::is_throttle, || {
+                client.sign_up(&user.username, &user.password, &attributes)
+            })?;
+        if options.confirm && !result.user_confirmed {
+            with_retries(
+                options,
+                &mut attempts,
+                CognitoSrpError::is_retryable,
+                || {
+                    let body = Value::object([("Username", Value::from(user.username.as_str()))]);
+                    client.admin_call("AdminConfirmSignUp", body)
+                },
+            )?;
+            result.user_confirmed = true;
+        }
+        Ok(result)
+    })();
+    SignUpReport {
+        username: user.username.clone(),
+        attempts,
+        outcome,
+    }
+}
+
+// Runs `f` for every item on `concurrency` worker threads and returns the results in input
+// order. Workers share `client`, whose state is all behind Arcs, so nothing is rebuilt per item.
+fn run_all<T, R, I>(concurrency: usize, items: I, f: impl Fn(T) -> R + Sync) -> Vec<R>
+where
+    I: IntoIterator<Item = T>,
+    I::IntoIter: Send,
+    R: Send,
+{
+    run_all_with(concurrency, items, || (), |_, item| f(item))
+}
+
+// run_all with per-worker state, made by `init` once on each worker thread.
+fn run_all_with<S, T, R, I>(
+    concurrency: usize,
+    items: I,
+    init: impl Fn() -> S + Sync,
+    f: impl Fn(&S, T) -> R + Sync,
+) -> Vec<R>
+where
+    I: IntoIterator<Item = T>,
+    I::IntoIter: Send,
+    R: Send,
+{
+    let queue = Mutex::new(items.into_iter().enumerate());
+    let results = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..concurrency.max(1) {
+            scope.spawn(|| {
+                let state = init();
+                loop {
+                    let next = queue.lock().unwrap().next();
+                    let Some((index, item)) = next else { break };
+                    let result = f(&state, item);
+                    results.lock().unwrap().push((index, result));
+                }
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+// Runs SignUp (+ AdminConfirmSignUp) for every user on `options.concurrency` worker threads.
+// Reports come back in input order; a failed record never stops the rest of the batch.
+pub fn sign_up_all<I>(client: &Client, users: I, options: &BulkOptions) -> Vec<SignUpReport>
+where
+    I: IntoIterator<Item = NewUser>,
+    I::IntoIter: Send,
+{
+    run_all(options.concurrency, users, |user| {
+        sign_up_one(client, &user, options)
+    })
+}
+
+#[derive(Debug)]
+pub struct AuthReport {
+    pub username: String,
+    pub attempts: u32,
+    // Wall time for the user's whole exchange, retries and their delays included.
+    pub elapsed: Duration,
+    pub outcome: Result<Authentication>,
+}
+
+// SRP-authenticates every (username, password) pair on `options.concurrency` worker threads,
+// for load-testing a pool or checking a migration; `options.confirm` is not used. Reports come
+// back in input order. With the `reqwest` feature the workers share one PooledTransport (proxy
+// settings from the environment) in place of the client's, so each keeps its connection from
+// user to user; if that can't be built, or without the feature, they send through the client's
+// transport, and CurlTransport's process and TLS handshake per request skew the timings.
+pub fn authenticate_all<I>(client: &Client, users: I, options: &BulkOptions) -> Vec<AuthReport>
+where
+    I: IntoIterator<Item = (String, SecretString)>,
+    I::IntoIter: Send,
+{
+    #[cfg(feature = "reqwest")]
+    if let Ok(transport) = PooledTransport::new(TransportConfig::from_env()) {
+        return authenticate_all_with(client, users, options, || transport.clone());
+    }
+    run_all(options.concurrency, users, |(username, password)| {
+        authenticate_one(client, username, &password, options)
+    })
+}
+
+// authenticate_all with a transport per worker: each thread calls `transport` once and sends all
+// its users' requests through the result, e.g. a clone of one PooledTransport, or a separate
+// FakeCognito-style transport per worker in tests.
+pub fn authenticate_all_with<I, T>(
+    client: &Client,
+    users: I,
+    options: &BulkOptions,
+    transport: impl Fn() -> T + Sync,
+) -> Vec<AuthReport>
+where
+    I: IntoIterator<Item = (String, SecretString)>,
+    I::IntoIter: Send,
+    T: Transport + 'static,
+{
+    run_all_with(
+        options.concurrency,
+        users,
+        || client.clone().with_transport(transport()),
+        |client, (username, password)| authenticate_one(client, username, &password, options),
+    )
+}
+
+fn authenticate_one(
+    client: &Client,
+    username: String,
+    password: &SecretString,
+    options: &BulkOptions,
+) -> AuthReport {
+    let mut attempts = 0;
+    let start = Instant::now();
+    let outcome = with_retries(
+        options,
+        &mut attempts,
+        CognitoSrpError::is_retryable,
+        || client.authenticate(&username, password.expose_secret()),
+    );
+    AuthReport {
+        username,
+        attempts,
+        elapsed: start.elapsed(),
+        outcome,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::credentials::AwsCredentials;
+    use crate::error::ServiceErrorKind;
+    use crate::transport::mock::MockTransport;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn user(name: &str) -> NewUser {
+        NewUser {
+            username: name.to_owned(),
+            password: "Passw0rd!".to_owned(),
+            attributes: vec![("email".to_owned(), format!("{}@example.com", name))],
+        }
+    }
+
+    #[test]
+    fn test_sign_up_all() {
+        let transport = MockTransport::new();
+        transport.respond(200, r#"{"UserConfirmed":false,"UserSub":"sub-a"}"#);
+        transport.respond(
+            400,
+            r#"{"__type":"TooManyRequestsException","message":"slow down"}"#,
+        );
+        transport.respond(200, "{}");
+        transport.respond(
+            400,
+            r#"{"__type":"UsernameExistsException","message":"User already exists"}"#,
+        );
+        transport.respond(500, r#"{"__type":"InternalErrorException","message":""}"#);
+        let client = Client::new("us-east-1_pool", "client", transport.clone())
+            .unwrap()
+            .with_credentials(AwsCredentials::new("AKID", "secret"));
+        let options = BulkOptions {
+            concurrency: 1,
+            retry_delay: Duration::from_millis(1),
+            ..BulkOptions::default()
+        };
+
+        let reports = sign_up_all(&client, vec![user("a"), user("b"), user("c")], &options);
+        assert_eq!(reports.len(), 3);
+        assert!(!format!("{:?}", user("a")).contains("Passw0rd!"));
+        assert_eq!(reports[0].username, "a");
+        assert_eq!(reports[0].attempts, 3);
+        let result = reports[0].outcome.as_ref().unwrap();
+        assert!(result.user_confirmed);
+        assert_eq!(result.user_sub, "sub-a");
+
+        assert_eq!(reports[1].username, "b");
+        assert_eq!(reports[1].attempts, 1);
+        assert!(reports[1].outcome.is_err());
+        // The SignUp may have landed, so it isn't sent again.
+        assert_eq!(reports[2].attempts, 1);
+        assert!(reports[2].outcome.as_ref().unwrap_err().is_retryable());
+        assert_eq!(transport.requests.lock().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_concurrent_reports_keep_input_order() {
+        let transport = MockTransport::new();
+        for i in 0..20 {
+            transport.respond(
+                200,
+                &format!(r#"{{"UserConfirmed":true,"UserSub":"{}"}}"#, i),
+            );
+        }
+        let client = Client::new("us-east-1_pool", "client", transport).unwrap();
+        let users: Vec<NewUser> = (0..20).map(|i| user(&i.to_string())).collect();
+
+        let reports = sign_up_all(&client, users, &BulkOptions::default());
+        let names: Vec<String> = reports.iter().map(|r| r.username.clone()).collect();
+        let expected: Vec<String> = (0..20).map(|i| i.to_string()).collect();
+        assert_eq!(names, expected);
+        assert!(reports.iter().all(|r| r.outcome.is_ok()));
+    }
+
+    // With `reqwest`, authenticate_all sends through its own PooledTransport, not the mock.
+    #[cfg(not(feature = "reqwest"))]
+    #[test]
+    fn test_authenticate_all() {
+        let transport = MockTransport::new();
+        transport.respond(
+            400,
+            r#"{"__type":"NotAuthorizedException","message":"Incorrect username or password."}"#,
+        );
+        transport.respond(400, r#"{"__type":"InternalErrorException","message":""}"#);
+        transport.respond(400, r#"{"__type":"InternalErrorException","message":""}"#);
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let options = BulkOptions {
+            concurrency: 1,
+            max_attempts: 2,
+            retry_delay: Duration::from_millis(1),
+            ..BulkOptions::default()
+        };
+
+        let users = [("a", "wrong"), ("b", "pw")]
+            .map(|(name, password)| (name.to_owned(), SecretString::from(password)));
+        let reports = authenticate_all(&client, users, &options);
+        assert_eq!(reports[0].username, "a");
+        assert_eq!(reports[0].attempts, 1);
+        let err = reports[0].outcome.as_ref().unwrap_err();
+        assert_eq!(err.service_kind(), Some(&ServiceErrorKind::NotAuthorized));
+        assert_eq!(reports[1].username, "b");
+        assert_eq!(reports[1].attempts, 2);
+        assert!(reports[1].outcome.is_err());
+        assert_eq!(transport.requests.lock().unwrap().len(), 3);
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn test_authenticate_all_reuses_connections() {
+        let server = crate::pooled::tests::KeepAliveServer::start(
+            "400 Bad Request",
+            r#"{"__type":"UserNotFoundException","message":"User does not exist."}"#,
+        );
+        let client = Client::new("us-east-1_pool", "client", MockTransport::new())
+            .unwrap()
+            .with_endpoint(&server.url);
+        let options = BulkOptions {
+            concurrency: 2,
+            ..BulkOptions::default()
+        };
+
+        let users = (0..4).map(|i| (i.to_string(), SecretString::from("pw")));
+        let reports = authenticate_all(&client, users, &options);
+        assert_eq!(reports.len(), 4);
+        let err = reports[0].outcome.as_ref().unwrap_err();
+        assert_eq!(err.service_kind(), Some(&ServiceErrorKind::UserNotFound));
+        assert_eq!(server.requests.load(Ordering::SeqCst), 4);
+        // At most one connection per worker, however many users.
+        assert!(server.connections.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[test]
+    fn test_transport_per_worker() {
+        let transport = MockTransport::new();
+        for _ in 0..6 {
+            transport.respond(
+                400,
+                r#"{"__type":"UserNotFoundException","message":"User does not exist."}"#,
+            );
+        }
+        let client = Client::new("us-east-1_pool", "client", MockTransport::new()).unwrap();
+        let made = AtomicUsize::new(0);
+        let options = BulkOptions {
+            concurrency: 3,
+            ..BulkOptions::default()
+        };
+
+        let users = (0..6).map(|i| (i.to_string(), SecretString::from("pw")));
+        let reports = authenticate_all_with(&client, users, &options, || {
+            made.fetch_add(1, Ordering::SeqCst);
+            transport.clone()
+        });
+        assert_eq!(reports.len(), 6);
+        assert!(reports.iter().all(|r| r.outcome.is_err()));
+        // One per worker, not one per user; the client's own transport is never used.
+        assert_eq!(made.load(Ordering::SeqCst), 3);
+        assert_eq!(transport.requests.lock().unwrap().len(), 6);
+    }
+}
@@ -0,0 +1,46 @@
+// Aborting a sign-in (or any other multi-step flow) from outside it, e.g. a UI's Cancel button.
+// Limits go on a clone of the client used for that one flow:
+//
+//     let token = CancelToken::new();
+//     let flow = client.clone().with_timeout(Duration::from_secs(30));
+//     let flow = flow.with_cancel_token(token.clone());
+//     let step = Step::start(&flow, "bob")?;
+//
+// token.cancel(), from any thread, stops the flow at its next request.
+//
+// Both are checked before each request, so a request already in flight finishes (or hits the
+// transport's own timeout) first. The resulting CognitoSrpError::Interrupted carries the Cognito
+// Session the flow had reached, if any; see flow::Step::resume.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_token() {
+        let token = CancelToken::new();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+        token.cancel();
+        assert!(clone.is_cancelled());
+    }
+}
@@ -0,0 +1,868 @@
+// The `cogneato` command: a thin layer over the library for ops scripts and shells.
+use crate::auth::{Authentication, ChallengeName};
+use crate::client::Client;
+use crate::curl::CurlTransport;
+use crate::error::{CognitoSrpError, Result};
+use crate::json::Value;
+use crate::jwt::Claims;
+use crate::profile::{ProfileConfig, TokenStoreKind};
+use crate::provider::{RefreshingTokenProvider, TokenProvider};
+use crate::secret::SecretString;
+use crate::store::{FileTokenStore, TokenKey, TokenStore};
+use crate::time::iso8601;
+use crate::tokens::TokenSet;
+use crate::transport::Transport;
+use crate::user::MfaType;
+use std::collections::BTreeMap;
+use std::env;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+const USAGE: &str = "\
+usage: cogneato <command> [options]
+
+commands:
+  auth      sign in with SRP, cache the tokens and print them
+  refresh   trade the cached refresh token for new tokens
+  whoami    show who the cached ID token belongs to and when it expires
+  credentials
+            trade the cached ID token for AWS credentials from an identity pool
+
+options:
+  --profile <name>      take unset options from a profile in ~/.config/cogneato/config.toml
+  --pool-id <id>        user pool, e.g. us-east-1_AbCdEfGhI
+  --client-id <id>      app client id
+  --username <name>
+  --endpoint <url>      cognito-idp endpoint override, e.g. a local emulator
+  --output <format>     json (the default), env (export lines for eval), dotenv or text;
+                        whoami defaults to text
+  --identity-pool-id <id>
+                        identity pool for credentials, e.g. us-east-1:0123abcd-...
+  --credential-process  print credentials as an AWS CLI credential_process command must
+  --no-cache            don't read or write ~/.cache/cogneato
+
+Secrets are never taken as arguments: the password comes from COGNEATO_PASSWORD or a prompt,
+the app client secret (if any) from COGNEATO_CLIENT_SECRET.
+";
+
+const OPTIONS: &[&str] = &[
+    "profile",
+    "pool-id",
+    "client-id",
+    "username",
+    "endpoint",
+    "output",
+    "identity-pool-id",
+];
+const FLAGS: &[&str] = &["no-cache", "credential-process", "help"];
+
+#[derive(Debug, PartialEq, Eq)]
+struct Args {
+    command: String,
+    options: BTreeMap<String, String>,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Result<Self> {
+        let usage = |message: String| {
+            CognitoSrpError::InvalidArgument(format!("{}\n\n{}", message, USAGE.trim_end()))
+        };
+        let mut args = args.iter();
+        let mut command = None;
+        let mut options = BTreeMap::new();
+        while let Some(arg) = args.next() {
+            let Some(option) = arg.strip_prefix("--") else {
+                if command.is_some() {
+                    return Err(usage(format!("unexpected argument {}", arg)));
+                }
+                command = Some(arg.clone());
+                continue;
+            };
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_owned())),
+                None => (option, None),
+            };
+            let value = if FLAGS.contains(&name) {
+                value.unwrap_or_default()
+            } else if OPTIONS.contains(&name) {
+                match value.or_else(|| args.next().cloned()) {
+                    Some(value) => value,
+                    None => return Err(usage(format!("--{} needs a value", name))),
+                }
+            } else {
+                return Err(usage(format!("unknown option --{}", name)));
+            };
+            options.insert(name.to_owned(), value);
+        }
+        Ok(Self {
+            command: command.unwrap_or_else(|| "help".to_owned()),
+            options,
+        })
+    }
+
+    // Options given on the command line win over the profile's.
+    fn with_profile(mut self, config: &ProfileConfig) -> Result<(Self, Option<TokenStoreKind>)> {
+        let Some(profile) = config.profile(self.options.get("profile").map(String::as_str))? else {
+            return Ok((self, None));
+        };
+        for (name, value) in [
+            ("pool-id", &profile.pool_id),
+            ("client-id", &profile.client_id),
+            ("username", &profile.username),
+            ("endpoint", &profile.endpoint),
+        ] {
+            if let Some(value) = value {
+                self.options
+                    .entry(name.to_owned())
+                    .or_insert_with(|| value.clone());
+            }
+        }
+        Ok((self, Some(profile.token_store)))
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    fn required(&self, name: &str) -> Result<&str> {
+        self.options
+            .get(name)
+            .map(String::as_str)
+            .ok_or_else(|| CognitoSrpError::InvalidArgument(format!("--{} is required", name)))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Json,
+    Env,
+    Dotenv,
+    Text,
+}
+
+// What a command prints: PascalCase names as in Cognito's own JSON, in display order.
+type Fields = Vec<(&'static str, Value)>;
+
+// "AccessKeyId" -> ["Access", "Key", "Id"].
+fn words(name: &str) -> Vec<&str> {
+    let starts: Vec<usize> = name
+        .char_indices()
+        .filter(|(_, c)| c.is_ascii_uppercase())
+        .map(|(i, _)| i)
+        .chain([name.len()])
+        .collect();
+    starts.windows(2).map(|w| &name[w[0]..w[1]]).collect()
+}
+
+fn plain(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(plain).collect::<Vec<_>>().join(","),
+        other => other.to_string(),
+    }
+}
+
+// Single quotes keep everything literal except the quote itself.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn dotenv_quote(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+impl Format {
+    fn parse(name: &str) -> Result<Self> {
+        match name {
+            "json" => Ok(Format::Json),
+            "env" => Ok(Format::Env),
+            "dotenv" => Ok(Format::Dotenv),
+            "text" => Ok(Format::Text),
+            other => Err(CognitoSrpError::InvalidArgument(format!(
+                "unknown output format {}; expected json, env, dotenv or text",
+                other
+            ))),
+        }
+    }
+
+    // Variable names for env and dotenv are `prefix` plus the field name in SCREAMING_SNAKE_CASE.
+    fn render(self, fields: &Fields, prefix: &str) -> String {
+        let var = |name: &str| {
+            let words: Vec<String> = words(name).iter().map(|w| w.to_ascii_uppercase()).collect();
+            format!("{}{}", prefix, words.join("_"))
+        };
+        let lines: Vec<String> = match self {
+            Format::Json => return Value::object(fields.iter().cloned()).to_string(),
+            Format::Env => fields
+                .iter()
+                .map(|(name, value)| format!("export {}={}", var(name), shell_quote(&plain(value))))
+                .collect(),
+            Format::Dotenv => fields
+                .iter()
+                .map(|(name, value)| format!("{}={}", var(name), dotenv_quote(&plain(value))))
+                .collect(),
+            Format::Text => {
+                let label = |name: &str| words(name).join(" ").to_ascii_lowercase();
+                let width = fields
+                    .iter()
+                    .map(|(name, _)| label(name).len())
+                    .max()
+                    .unwrap_or(0);
+                fields
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = match value {
+                            Value::Array(_) => plain(value).replace(',', ", "),
+                            value => plain(value),
+                        };
+                        format!(
+                            "{:width$}  {}",
+                            format!("{}:", label(name)),
+                            value,
+                            width = width + 1
+                        )
+                    })
+                    .collect()
+            }
+        };
+        lines.join("\n")
+    }
+}
+
+type Prompt<'a> = &'a mut dyn FnMut(&str, bool) -> Result<String>;
+
+// Everything a command touches outside its arguments, so tests can stand in for all of it.
+struct Context<'a> {
+    transport: Arc<dyn Transport>,
+    profiles: ProfileConfig,
+    store: Option<Arc<dyn TokenStore>>,
+    client_secret: Option<SecretString>,
+    password: Option<SecretString>,
+    prompt: Prompt<'a>,
+    out: &'a mut dyn Write,
+}
+
+impl Context<'_> {
+    fn client(&self, args: &Args) -> Result<Client> {
+        let mut client = Client::new(
+            args.required("pool-id")?,
+            args.required("client-id")?,
+            self.transport.clone(),
+        )?;
+        if let Some(endpoint) = args.options.get("endpoint") {
+            client = client.with_endpoint(endpoint);
+        }
+        if let Some(secret) = &self.client_secret {
+            client = client.with_client_secret(secret.expose_secret());
+        }
+        Ok(client)
+    }
+
+    fn store(&self, args: &Args) -> Option<&Arc<dyn TokenStore>> {
+        self.store.as_ref().filter(|_| !args.has("no-cache"))
+    }
+
+    fn required_store(&self, args: &Args) -> Result<&Arc<dyn TokenStore>> {
+        self.store(args)
+            .ok_or_else(|| CognitoSrpError::Configuration("the token cache is disabled".to_owned()))
+    }
+
+    fn cached(&self, args: &Args, client: &Client, username: &str) -> Result<TokenSet> {
+        let store = self.required_store(args)?;
+        store
+            .load(&TokenKey::new(client, username))?
+            .ok_or_else(|| {
+                CognitoSrpError::SignedOut(format!(
+                    "no cached tokens for {}; run cogneato auth first",
+                    username
+                ))
+            })
+    }
+
+    fn print(&mut self, args: &Args, fields: &Fields, default: Format, prefix: &str) -> Result<()> {
+        let format = match args.options.get("output") {
+            Some(name) => Format::parse(name)?,
+            None => default,
+        };
+        writeln!(self.out, "{}", format.render(fields, prefix))?;
+        Ok(())
+    }
+}
+
+fn token_fields(tokens: &TokenSet) -> Fields {
+    let mut fields = vec![
+        ("AccessToken", Value::from(tokens.access_token.as_str())),
+        ("IdToken", Value::from(tokens.id_token.as_str())),
+    ];
+    if let Some(refresh_token) = &tokens.refresh_token {
+        fields.push(("RefreshToken", Value::from(refresh_token.expose_secret())));
+    }
+    fields.push(("TokenType", Value::from(tokens.token_type.as_str())));
+    fields.push(("ExpiresAt", Value::from(iso8601(tokens.expires_at))));
+    fields
+}
+
+fn print_tokens(context: &mut Context<'_>, args: &Args, tokens: &TokenSet) -> Result<()> {
+    context.print(args, &token_fields(tokens), Format::Json, "COGNEATO_")
+}
+
+fn auth(context: &mut Context<'_>, args: &Args) -> Result<()> {
+    let client = context.client(args)?;
+    let username = args.required("username")?;
+    let password = match context.password.take() {
+        Some(password) => password,
+        None => SecretString::new((context.prompt)("Password: ", true)?),
+    };
+    let mut authentication = client.authenticate(username, password.expose_secret())?;
+    let tokens = loop {
+        let challenge = match authentication {
+            Authentication::Tokens(tokens) => break tokens,
+            Authentication::Challenge(challenge) => challenge,
+        };
+        authentication = match challenge.name {
+            ChallengeName::SmsMfa
+            | ChallengeName::SoftwareTokenMfa
+            | ChallengeName::EmailOtp
+            | ChallengeName::SmsOtp => {
+                let code = (context.prompt)("MFA code: ", false)?;
+                client.respond_to_mfa(&challenge, code.trim())?
+            }
+            ChallengeName::NewPasswordRequired => {
+                let new_password = (context.prompt)("New password: ", true)?;
+                client.respond_to_new_password(&challenge, &new_password)?
+            }
+            ChallengeName::SelectMfaType => {
+                let choices: Vec<&str> = challenge
+                    .mfa_choices()
+                    .iter()
+                    .map(MfaType::as_str)
+                    .collect();
+                let label = format!("MFA method ({}): ", choices.join(", "));
+                let answer = (context.prompt)(&label, false)?;
+                let choice = MfaType::parse(answer.trim()).ok_or_else(|| {
+                    CognitoSrpError::InvalidArgument(format!(
+                        "{} is not an MFA method",
+                        answer.trim()
+                    ))
+                })?;
+                client.respond_to_select_mfa_type(&challenge, choice)?
+            }
+            ChallengeName::MfaSetup => {
+                let setup = client.begin_mfa_setup(&challenge)?;
+                let label = format!(
+                    "Add this key to an authenticator app: {}\nCode: ",
+                    setup.secret_code().expose_secret()
+                );
+                let code = (context.prompt)(&label, false)?;
+                client.complete_mfa_setup(&setup, code.trim(), None)?
+            }
+            _ => {
+                return Err(CognitoSrpError::ChallengeMismatch {
+                    expected: "MFA or NEW_PASSWORD_REQUIRED".to_owned(),
+                    actual: Some(challenge.name.as_str().to_owned()),
+                })
+            }
+        };
+    };
+    if let Some(store) = context.store(args) {
+        store.save(&TokenKey::new(&client, username), &tokens)?;
+    }
+    print_tokens(context, args, &tokens)
+}
+
+fn refresh(context: &mut Context<'_>, args: &Args) -> Result<()> {
+    let client = context.client(args)?;
+    let username = args.required("username")?;
+    let cached = context.cached(args, &client, username)?;
+    let refresh_token = cached.refresh_token.ok_or_else(|| {
+        CognitoSrpError::SignedOut("the cached tokens have no refresh token".to_owned())
+    })?;
+    let tokens = client.refresh_tokens(username, refresh_token.expose_secret())?;
+    if let Some(store) = context.store(args) {
+        store.save(&TokenKey::new(&client, username), &tokens)?;
+    }
+    print_tokens(context, args, &tokens)
+}
+
+// Reads the cached ID token without verifying it: it is the user's own, straight from Cognito.
+fn whoami(context: &mut Context<'_>, args: &Args) -> Result<()> {
+    let client = context.client(args)?;
+    let tokens = context.cached(args, &client, args.required("username")?)?;
+    let claims = Claims::decode_unverified(&tokens.id_token)?;
+    let expires_at = claims.expires_at();
+    let mut fields = vec![
+        (
+            "Username",
+            Value::from(claims.username.as_deref().unwrap_or_default()),
+        ),
+        ("Sub", Value::from(claims.sub.as_str())),
+    ];
+    if let Some(email) = &claims.email {
+        fields.push(("Email", Value::from(email.as_str())));
+    }
+    let groups = claims
+        .groups
+        .iter()
+        .map(|g| Value::from(g.as_str()))
+        .collect();
+    fields.push(("Groups", Value::Array(groups)));
+    fields.push(("ExpiresAt", Value::from(iso8601(expires_at))));
+    fields.push(("Expired", Value::from(expires_at <= SystemTime::now())));
+    context.print(args, &fields, Format::Text, "COGNEATO_")
+}
+
+// Uses the cached ID token, refreshing it first if it is close to expiry, and signs in again
+// when COGNEATO_PASSWORD is set and the refresh token is gone. Nothing prompts, so this works as
+// an AWS CLI credential_process.
+fn credentials(context: &mut Context<'_>, args: &Args) -> Result<()> {
+    let client = context.client(args)?;
+    let username = args.required("username")?;
+    let identity_pool_id = args.required("identity-pool-id")?;
+    let mut provider = RefreshingTokenProvider::from_store(
+        client.clone(),
+        username,
+        context.required_store(args)?.clone(),
+    );
+    if let Some(password) = &context.password {
+        provider = provider.with_password(password.expose_secret());
+    }
+    let id_token = provider.id_token()?;
+    let credentials = client
+        .identity_credentials(identity_pool_id, &id_token)?
+        .credentials;
+    if args.has("credential-process") {
+        writeln!(context.out, "{}", credentials.to_credential_process_json())?;
+        return Ok(());
+    }
+    let mut fields = vec![
+        (
+            "AccessKeyId",
+            Value::from(credentials.access_key_id.as_str()),
+        ),
+        (
+            "SecretAccessKey",
+            Value::from(credentials.secret_access_key.as_str()),
+        ),
+    ];
+    if let Some(session_token) = &credentials.session_token {
+        fields.push(("SessionToken", Value::from(session_token.as_str())));
+    }
+    // Named so env output gives AWS_CREDENTIAL_EXPIRATION, which the AWS SDKs read.
+    if let Some(expiration) = credentials.expiration {
+        fields.push(("CredentialExpiration", Value::from(iso8601(expiration))));
+    }
+    context.print(args, &fields, Format::Json, "AWS_")
+}
+
+fn run(context: &mut Context<'_>, args: &[String]) -> Result<()> {
+    let args = Args::parse(args)?;
+    if args.has("help") || args.command == "help" {
+        write!(context.out, "{}", USAGE)?;
+        return Ok(());
+    }
+    let (args, token_store) = args.with_profile(&context.profiles)?;
+    match token_store {
+        None | Some(TokenStoreKind::File) => {}
+        Some(TokenStoreKind::None) => context.store = None,
+        #[cfg(feature = "keyring")]
+        Some(TokenStoreKind::Keyring) => {
+            context.store = Some(Arc::new(crate::keyring::KeyringTokenStore::new()?));
+        }
+        #[cfg(not(feature = "keyring"))]
+        Some(TokenStoreKind::Keyring) => {
+            return Err(CognitoSrpError::Configuration(
+                "token_store = \"keyring\" needs cogneato built with the keyring feature"
+                    .to_owned(),
+            ))
+        }
+    }
+    match args.command.as_str() {
+        "auth" => auth(context, &args),
+        "refresh" => refresh(context, &args),
+        "whoami" => whoami(context, &args),
+        "credentials" => credentials(context, &args),
+        command => Err(CognitoSrpError::InvalidArgument(format!(
+            "unknown command {}\n\n{}",
+            command,
+            USAGE.trim_end()
+        ))),
+    }
+}
+
+// Reads a line from the terminal, with echo off for secrets when stdin is a terminal.
+fn prompt(label: &str, secret: bool) -> Result<String> {
+    eprint!("{}", label);
+    io::stderr().flush()?;
+    let stty = |arg: &str| {
+        Command::new("stty")
+            .arg(arg)
+            .stdin(Stdio::inherit())
+            .status()
+            .is_ok_and(|status| status.success())
+    };
+    let hidden = secret && io::stdin().is_terminal() && stty("-echo");
+    let mut line = String::new();
+    let read = io::stdin().lock().read_line(&mut line);
+    if hidden {
+        stty("echo");
+        eprintln!();
+    }
+    read?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_owned())
+}
+
+// Entry point for the binary; returns the process exit code.
+pub fn main() -> i32 {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let var = |name: &str| env::var(name).ok().filter(|v| !v.is_empty());
+    let mut stdout = io::stdout().lock();
+    let mut prompt = prompt;
+    let profiles = match ProfileConfig::load_default() {
+        Ok(profiles) => profiles,
+        Err(err) => {
+            eprintln!("cogneato: {}", err);
+            return 1;
+        }
+    };
+    let mut context = Context {
+        transport: Arc::new(CurlTransport::default()),
+        profiles,
+        store: FileTokenStore::in_cache_dir()
+            .ok()
+            .map(|store| Arc::new(store) as Arc<dyn TokenStore>),
+        client_secret: var("COGNEATO_CLIENT_SECRET").map(SecretString::new),
+        password: var("COGNEATO_PASSWORD").map(SecretString::new),
+        prompt: &mut prompt,
+        out: &mut stdout,
+    };
+    match run(&mut context, &args) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("cogneato: {}", err);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let parsed = Args::parse(&args(&[
+            "auth",
+            "--pool-id",
+            "us-east-1_pool",
+            "--username=bob",
+            "--no-cache",
+        ]))
+        .unwrap();
+        assert_eq!(parsed.command, "auth");
+        assert_eq!(parsed.required("pool-id").unwrap(), "us-east-1_pool");
+        assert_eq!(parsed.required("username").unwrap(), "bob");
+        assert!(parsed.has("no-cache"));
+        assert!(parsed.required("client-id").is_err());
+
+        assert!(Args::parse(&args(&["auth", "--password", "pw"])).is_err());
+        assert!(Args::parse(&args(&["auth", "--pool-id"])).is_err());
+        assert!(Args::parse(&args(&["auth", "extra"])).is_err());
+        assert_eq!(Args::parse(&[]).unwrap().command, "help");
+    }
+
+    #[test]
+    fn test_profile() {
+        let config = ProfileConfig::parse(
+            r#"
+            default_profile = "dev"
+            [profiles.dev]
+            pool_id = "us-east-1_dev"
+            client_id = "devclient"
+            username = "bob"
+            [profiles.prod]
+            pool_id = "us-east-1_prod"
+            client_id = "prodclient"
+            token_store = "none"
+            "#,
+        )
+        .unwrap();
+        let (parsed, store) = Args::parse(&args(&["whoami", "--username", "alice"]))
+            .unwrap()
+            .with_profile(&config)
+            .unwrap();
+        assert_eq!(parsed.required("pool-id").unwrap(), "us-east-1_dev");
+        assert_eq!(parsed.required("username").unwrap(), "alice");
+        assert_eq!(store, Some(TokenStoreKind::File));
+
+        let (parsed, store) = Args::parse(&args(&["whoami", "--profile=prod"]))
+            .unwrap()
+            .with_profile(&config)
+            .unwrap();
+        assert_eq!(parsed.required("client-id").unwrap(), "prodclient");
+        assert!(parsed.required("username").is_err());
+        assert_eq!(store, Some(TokenStoreKind::None));
+
+        let unknown = Args::parse(&args(&["whoami", "--profile", "qa"]))
+            .unwrap()
+            .with_profile(&config);
+        assert!(unknown.is_err());
+        let (_, store) = Args::parse(&args(&["whoami"]))
+            .unwrap()
+            .with_profile(&ProfileConfig::default())
+            .unwrap();
+        assert_eq!(store, None);
+    }
+
+    #[test]
+    fn test_auth_answers_mfa() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"ChallengeName":"PASSWORD_VERIFIER","ChallengeParameters":{
+                "SALT":"ab12cd34ef56","SECRET_BLOCK":"b3BhcXVlIHNlY3JldCBibG9jayBmcm9tIGNvZ25pdG8=",
+                "SRP_B":"82f7e185b83288814ad86154a1bfa657f70d27ec43f507a618d89eb5ac9f90e9",
+                "USERNAME":"bob","USER_ID_FOR_SRP":"bob"}}"#,
+        );
+        transport.respond(
+            200,
+            r#"{"ChallengeName":"SOFTWARE_TOKEN_MFA","Session":"s","ChallengeParameters":{}}"#,
+        );
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a","IdToken":"i","RefreshToken":"r","ExpiresIn":3600}}"#,
+        );
+        let mut prompts = Vec::new();
+        let mut prompt = |label: &str, secret: bool| {
+            prompts.push((label.to_owned(), secret));
+            Ok(" 123456\n".to_owned())
+        };
+        let mut out = Vec::new();
+        let mut context = Context {
+            transport: transport.clone(),
+            profiles: ProfileConfig::default(),
+            store: None,
+            client_secret: None,
+            password: Some("pw".into()),
+            prompt: &mut prompt,
+            out: &mut out,
+        };
+        run(
+            &mut context,
+            &args(&[
+                "auth",
+                "--pool-id",
+                "us-east-1_pool",
+                "--client-id",
+                "client",
+                "--username",
+                "bob",
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(prompts, [("MFA code: ".to_owned(), false)]);
+        let responses = transport.last_body();
+        let responses = responses.get("ChallengeResponses").unwrap();
+        assert_eq!(
+            responses.str_field("SOFTWARE_TOKEN_MFA_CODE").unwrap(),
+            "123456"
+        );
+        let printed = crate::json::parse(&String::from_utf8(out).unwrap()).unwrap();
+        assert_eq!(printed.str_field("AccessToken").unwrap(), "a");
+        assert_eq!(printed.str_field("RefreshToken").unwrap(), "r");
+    }
+
+    #[test]
+    fn test_refresh_and_whoami() {
+        let dir = env::temp_dir().join(format!("cogneato-test-{}", rand::random::<u64>()));
+        let transport = MockTransport::new();
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let payload = data_encoding::BASE64URL_NOPAD.encode(
+            br#"{"sub":"1234","token_use":"id","aud":"client","cognito:username":"bob","cognito:groups":["admin","ops"],"exp":1440938160}"#,
+        );
+        let cached = TokenSet {
+            access_token: "a1".to_owned(),
+            id_token: format!("e30.{}.sig", payload),
+            refresh_token: Some("r1".into()),
+            token_type: "Bearer".to_owned(),
+            expires_at: SystemTime::now(),
+        };
+        FileTokenStore::new(&dir)
+            .save(&TokenKey::new(&client, "bob"), &cached)
+            .unwrap();
+
+        let mut prompt = |_: &str, _: bool| -> Result<String> { panic!("no prompts expected") };
+        let mut out = Vec::new();
+        let mut context = Context {
+            transport: transport.clone(),
+            profiles: ProfileConfig::default(),
+            store: Some(Arc::new(FileTokenStore::new(&dir))),
+            client_secret: None,
+            password: None,
+            prompt: &mut prompt,
+            out: &mut out,
+        };
+        let common = [
+            "--pool-id",
+            "us-east-1_pool",
+            "--client-id",
+            "client",
+            "--username",
+            "bob",
+        ];
+        run(&mut context, &args(&[&["whoami"], &common[..]].concat())).unwrap();
+
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a2","IdToken":"i2","ExpiresIn":3600}}"#,
+        );
+        run(
+            &mut context,
+            &args(&[&["refresh"], &common[..], &["--output", "env"]].concat()),
+        )
+        .unwrap();
+        let body = transport.last_body();
+        assert_eq!(
+            body.get("AuthParameters")
+                .unwrap()
+                .str_field("REFRESH_TOKEN")
+                .unwrap(),
+            "r1"
+        );
+        let missing = run(
+            &mut context,
+            &args(&[
+                "refresh",
+                "--pool-id",
+                "us-east-1_pool",
+                "--client-id",
+                "client",
+                "--username",
+                "alice",
+            ]),
+        );
+        assert!(matches!(missing, Err(CognitoSrpError::SignedOut(_))));
+        drop(context);
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("username:    bob\nsub:         1234\ngroups:      admin, ops\n"));
+        assert!(out.contains("expires at:  2015-08-30T12:36:00Z\nexpired:     true\n"));
+        assert!(out.contains("export COGNEATO_ACCESS_TOKEN='a2'\n"));
+        let saved = FileTokenStore::new(&dir)
+            .load(&TokenKey::new(&client, "bob"))
+            .unwrap();
+        assert_eq!(saved.unwrap().refresh_token, Some("r1".into()));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_output_formats() {
+        let fields: Fields = vec![
+            ("AccessKeyId", Value::from("AKID")),
+            ("SessionToken", Value::from("it's \"x\"")),
+            (
+                "Groups",
+                Value::Array(vec![Value::from("a"), Value::from("b")]),
+            ),
+        ];
+        assert_eq!(
+            Format::Json.render(&fields, "AWS_"),
+            r#"{"AccessKeyId":"AKID","Groups":["a","b"],"SessionToken":"it's \"x\""}"#
+        );
+        assert_eq!(
+            Format::Env.render(&fields, "AWS_"),
+            "export AWS_ACCESS_KEY_ID='AKID'\nexport AWS_SESSION_TOKEN='it'\\''s \"x\"'\nexport AWS_GROUPS='a,b'"
+        );
+        assert_eq!(
+            Format::Dotenv.render(&fields, "AWS_"),
+            "AWS_ACCESS_KEY_ID=\"AKID\"\nAWS_SESSION_TOKEN=\"it's \\\"x\\\"\"\nAWS_GROUPS=\"a,b\""
+        );
+        assert_eq!(
+            Format::Text.render(&fields, "AWS_"),
+            "access key id:  AKID\nsession token:  it's \"x\"\ngroups:         a, b"
+        );
+        assert!(Format::parse("yaml").is_err());
+    }
+
+    #[test]
+    fn test_credentials() {
+        let dir = env::temp_dir().join(format!("cogneato-test-{}", rand::random::<u64>()));
+        let transport = MockTransport::new();
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let cached = TokenSet {
+            access_token: "a1".to_owned(),
+            id_token: "i1".to_owned(),
+            refresh_token: Some("r1".into()),
+            token_type: "Bearer".to_owned(),
+            expires_at: SystemTime::now() + std::time::Duration::from_secs(3600),
+        };
+        FileTokenStore::new(&dir)
+            .save(&TokenKey::new(&client, "bob"), &cached)
+            .unwrap();
+        for _ in 0..2 {
+            transport.respond(200, r#"{"IdentityId":"us-east-1:abcd"}"#);
+            transport.respond(
+                200,
+                r#"{"Credentials":{"AccessKeyId":"ASIA1","SecretKey":"secret",
+                    "SessionToken":"token","Expiration":1440938160}}"#,
+            );
+        }
+
+        let mut prompt = |_: &str, _: bool| -> Result<String> { panic!("no prompts expected") };
+        let mut out = Vec::new();
+        let mut context = Context {
+            transport: transport.clone(),
+            profiles: ProfileConfig::default(),
+            store: Some(Arc::new(FileTokenStore::new(&dir))),
+            client_secret: None,
+            password: None,
+            prompt: &mut prompt,
+            out: &mut out,
+        };
+        let common = [
+            "credentials",
+            "--pool-id",
+            "us-east-1_pool",
+            "--client-id",
+            "client",
+            "--username",
+            "bob",
+            "--identity-pool-id",
+            "us-east-1:guid",
+        ];
+        run(
+            &mut context,
+            &args(&[&common[..], &["--credential-process"]].concat()),
+        )
+        .unwrap();
+        run(
+            &mut context,
+            &args(&[&common[..], &["--output", "env"]].concat()),
+        )
+        .unwrap();
+        drop(context);
+
+        // The ID token was fresh, so only cognito-identity was called.
+        assert_eq!(transport.requests.lock().unwrap().len(), 4);
+        let out = String::from_utf8(out).unwrap();
+        let mut lines = out.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            r#"{"AccessKeyId":"ASIA1","Expiration":"2015-08-30T12:36:00Z","SecretAccessKey":"secret","SessionToken":"token","Version":1}"#
+        );
+        assert_eq!(lines.next().unwrap(), "export AWS_ACCESS_KEY_ID='ASIA1'");
+        assert!(out.contains("export AWS_CREDENTIAL_EXPIRATION='2015-08-30T12:36:00Z'\n"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,753 @@
+use crate::api::{CognitoApi, Offline};
+use crate::auth::Challenge;
+use crate::cancel::CancelToken;
+use crate::clock::{Clock, SystemClock};
+use crate::context_data::UserContextData;
+use crate::credentials::AwsCredentials;
+use crate::crypto::hmac_sha256;
+use crate::device::DeviceStore;
+use crate::error::{CognitoSrpError, Interruption, Result};
+pub use crate::error::{ServiceError, ServiceErrorKind};
+use crate::hooks::Hooks;
+use crate::json::{self, Value};
+use crate::metrics::{Measurement, Metrics};
+use crate::password::PasswordPolicy;
+use crate::pool_id::PoolId;
+use crate::retry::RetryPolicy;
+use crate::rng::SecureRng;
+use crate::secret::SecretString;
+use crate::sigv4;
+use crate::tokens::TokenSet;
+use crate::transport::{HttpRequest, Transport};
+use crate::username::UsernameNormalization;
+use data_encoding::BASE64;
+use rand::RngCore;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+#[derive(Clone)]
+pub struct Client {
+    pool_id: PoolId,
+    // Where cognito-idp requests go, and the base URL tokens are issued under.
+    endpoint: String,
+    issuer_base: String,
+    client_id: String,
+    client_secret: Option<SecretString>,
+    credentials: Option<AwsCredentials>,
+    // cognito-identity, for identity pool credentials; None derives it from the pool's region.
+    identity_endpoint: Option<String>,
+    sts_endpoint: Option<String>,
+    client_metadata: BTreeMap<String, String>,
+    user_context: Option<UserContextData>,
+    analytics_endpoint_id: Option<String>,
+    retry: RetryPolicy,
+    password_policy: Option<PasswordPolicy>,
+    username_normalization: UsernameNormalization,
+    device_store: Option<Arc<dyn DeviceStore>>,
+    deadline: Option<SystemTime>,
+    cancel: Option<CancelToken>,
+    // None draws from the thread RNG.
+    rng: Option<Arc<Mutex<dyn SecureRng>>>,
+    clock: Arc<dyn Clock>,
+    metrics: Option<Arc<dyn Metrics>>,
+    hooks: Hooks,
+    // None sends user pool calls over the transport.
+    api: Option<Arc<dyn CognitoApi>>,
+    transport: Arc<dyn Transport>,
+}
+
+impl Client {
+    pub fn new(
+        pool_id: &str,
+        client_id: &str,
+        transport: impl Transport + 'static,
+    ) -> Result<Self> {
+        let pool_id = PoolId::parse(pool_id)?;
+        let region = pool_id.region();
+        Ok(Self {
+            endpoint: format!("https://cognito-idp.{}.amazonaws.com/", region),
+            issuer_base: format!("https://cognito-idp.{}.amazonaws.com", region),
+            client_id: client_id.to_owned(),
+            pool_id,
+            client_secret: None,
+            credentials: None,
+            identity_endpoint: None,
+            sts_endpoint: None,
+            client_metadata: BTreeMap::new(),
+            user_context: None,
+            analytics_endpoint_id: None,
+            retry: RetryPolicy::none(),
+            password_policy: None,
+            username_normalization: UsernameNormalization::default(),
+            device_store: None,
+            deadline: None,
+            cancel: None,
+            rng: None,
+            clock: Arc::new(SystemClock),
+            metrics: None,
+            hooks: Hooks::default(),
+            api: None,
+            transport: Arc::new(transport),
+        })
+    }
+
+    // A client whose user pool calls all go to `api`, typically a MockCognitoApi in tests. There
+    // is no HTTP transport, so JWKS, identity pool and STS requests fail.
+    pub fn from_api(
+        pool_id: &str,
+        client_id: &str,
+        api: impl CognitoApi + 'static,
+    ) -> Result<Self> {
+        Ok(Self::new(pool_id, client_id, Offline)?.with_api(api))
+    }
+
+    pub fn with_api(mut self, api: impl CognitoApi + 'static) -> Self {
+        self.api = Some(Arc::new(api));
+        self
+    }
+
+    pub fn with_client_secret(mut self, client_secret: &str) -> Self {
+        self.client_secret = Some(client_secret.into());
+        self
+    }
+
+    // Only the Admin* operations use these; everything else is authorised by ClientId or tokens.
+    pub fn with_credentials(mut self, credentials: AwsCredentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    // Off by default. Retried: authenticate and admin_authenticate (with a fresh SRP ephemeral
+    // each time), token refresh and revocation, GetUser, GlobalSignOut, SetUserMFAPreference and
+    // the JWKS fetch. Calls that send codes or create users are never retried.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    // New passwords (sign-up, change, reset, NEW_PASSWORD_REQUIRED, admin_set_user_password) are
+    // checked against `policy` first and rejected without a call when they break it.
+    pub fn with_password_policy(mut self, policy: PasswordPolicy) -> Self {
+        self.password_policy = Some(policy);
+        self
+    }
+
+    // Applied to every username passed in, before it is sent or hashed into SECRET_HASH.
+    pub fn with_username_normalization(mut self, normalization: UsernameNormalization) -> Self {
+        self.username_normalization = normalization;
+        self
+    }
+
+    // Remembers the devices users sign in on (see crate::device), so pools that suppress MFA on
+    // remembered devices keep doing so across restarts.
+    pub fn with_device_store(mut self, store: impl DeviceStore + 'static) -> Self {
+        self.device_store = Some(Arc::new(store));
+        self
+    }
+
+    // No request is sent after `deadline` (by the client's clock); see crate::cancel.
+    pub fn with_deadline(mut self, deadline: SystemTime) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    // A deadline `timeout` from now, for one flow's clone of the client.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        let deadline = self.now() + timeout;
+        self.with_deadline(deadline)
+    }
+
+    pub fn with_cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    pub(crate) fn check_interrupted(&self, session: Option<&str>) -> Result<()> {
+        let reason = if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            Interruption::Cancelled
+        } else if self.deadline.is_some_and(|deadline| self.now() >= deadline) {
+            Interruption::DeadlineExceeded
+        } else {
+            return Ok(());
+        };
+        Err(CognitoSrpError::Interrupted {
+            reason,
+            session: session.map(str::to_owned),
+        })
+    }
+
+    pub(crate) fn device_store(&self) -> Option<&dyn DeviceStore> {
+        self.device_store.as_deref()
+    }
+
+    pub(crate) fn normalize_username(&self, username: &str) -> String {
+        self.username_normalization.apply(username)
+    }
+
+    pub(crate) fn check_password(&self, password: &str) -> Result<()> {
+        match &self.password_policy {
+            Some(policy) => policy.validate(password),
+            None => Ok(()),
+        }
+    }
+
+    // Sends every call to `url` instead of AWS, e.g. "http://localhost:9229/" for cognito-local
+    // or LocalStack. Such emulators also issue tokens under that URL, so the JWKS is fetched
+    // from there and issuers are checked against it.
+    pub fn with_endpoint(mut self, url: &str) -> Self {
+        let base = url.trim_end_matches('/');
+        self.endpoint = format!("{}/", base);
+        self.issuer_base = base.to_owned();
+        self
+    }
+
+    // # https://aws.amazon.com/compliance/fips/
+    // Tokens are still issued under the regular cognito-idp host.
+    pub fn with_fips_endpoint(mut self) -> Self {
+        self.endpoint = format!("https://cognito-idp-fips.{}.amazonaws.com/", self.region());
+        self
+    }
+
+    // Like with_endpoint, for the cognito-identity calls in crate::identity.
+    pub fn with_identity_endpoint(mut self, url: &str) -> Self {
+        self.identity_endpoint = Some(format!("{}/", url.trim_end_matches('/')));
+        self
+    }
+
+    // STS for crate::sts; defaults to the regional endpoint of the pool's region.
+    pub fn with_sts_endpoint(mut self, url: &str) -> Self {
+        self.sts_endpoint = Some(format!("{}/", url.trim_end_matches('/')));
+        self
+    }
+
+    // Sent as ClientMetadata with every operation that accepts it, for Lambda triggers to read.
+    // Clone the client to attach metadata to a single flow; continuations keep it because they
+    // go through the same client.
+    pub fn with_client_metadata(mut self, metadata: &[(&str, &str)]) -> Self {
+        self.client_metadata = metadata
+            .iter()
+            .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+            .collect();
+        self
+    }
+
+    pub fn client_metadata(&self) -> &BTreeMap<String, String> {
+        &self.client_metadata
+    }
+
+    // Sent as UserContextData on the public sign-in, sign-up and recovery operations so
+    // advanced security can score the end user's device and address.
+    pub fn with_user_context_data(mut self, context: UserContextData) -> Self {
+        self.user_context = Some(context);
+        self
+    }
+
+    // # https://docs.aws.amazon.com/cognito/latest/developerguide/cognito-user-pools-pinpoint-integration.html
+    // Sent as AnalyticsMetadata so sign-in and sign-up events land on this Pinpoint endpoint.
+    pub fn with_analytics_endpoint_id(mut self, endpoint_id: &str) -> Self {
+        self.analytics_endpoint_id = Some(endpoint_id.to_owned());
+        self
+    }
+
+    // Draw SRP ephemerals from `rng` instead of the thread RNG; clones of the client share it.
+    // Meant for known-answer tests, so only pass a cryptographically secure generator.
+    pub fn with_rng(mut self, rng: impl SecureRng + 'static) -> Self {
+        self.rng = Some(Arc::new(Mutex::new(rng)));
+        self
+    }
+
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    // The same client over another transport, e.g. one per worker thread in bulk.
+    pub fn with_transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    pub fn now(&self) -> SystemTime {
+        self.clock.now()
+    }
+
+    pub(crate) fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    // Called after every cognito-idp call and every authenticate, refresh and JWKS fetch, e.g.
+    // `.with_metrics(MetricsFacade)` or a closure feeding your own counters.
+    pub fn with_metrics(mut self, metrics: impl Metrics + 'static) -> Self {
+        self.metrics = Some(Arc::new(metrics));
+        self
+    }
+
+    // After authenticate or a challenge answer ends in tokens.
+    pub fn on_tokens_obtained(mut self, hook: impl Fn(&TokenSet) + Send + Sync + 'static) -> Self {
+        self.hooks.tokens_obtained.push(Arc::new(hook));
+        self
+    }
+
+    pub fn on_tokens_refreshed(mut self, hook: impl Fn(&TokenSet) + Send + Sync + 'static) -> Self {
+        self.hooks.tokens_refreshed.push(Arc::new(hook));
+        self
+    }
+
+    // After a refresh that came back with a new refresh token, from a pool or app client with
+    // refresh token rotation. `tokens` carries the new one; the one sent stops working once the
+    // pool's grace period is over, so anything that persisted it must save these instead.
+    // RefreshingTokenProvider and TokenManager already do for their store.
+    pub fn on_refresh_token_rotated(
+        mut self,
+        hook: impl Fn(&TokenSet) + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.refresh_token_rotated.push(Arc::new(hook));
+        self
+    }
+
+    // For every challenge handed back to the caller (MFA, NEW_PASSWORD_REQUIRED, ...); the
+    // PASSWORD_VERIFIER step that authenticate answers itself is not one of them.
+    pub fn on_challenge_received(
+        mut self,
+        hook: impl Fn(&Challenge) + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.challenge_received.push(Arc::new(hook));
+        self
+    }
+
+    // After a successful GlobalSignOut or RevokeToken.
+    pub fn on_sign_out(mut self, hook: impl Fn() + Send + Sync + 'static) -> Self {
+        self.hooks.sign_out.push(Arc::new(hook));
+        self
+    }
+
+    pub(crate) fn hooks(&self) -> &Hooks {
+        &self.hooks
+    }
+
+    // Runs `f` and reports it as `name`; without metrics it is just `f()`.
+    pub(crate) fn measure<T>(&self, name: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let Some(metrics) = &self.metrics else {
+            return f();
+        };
+        let start = self.now();
+        let result = f();
+        metrics.record(&Measurement {
+            name,
+            elapsed: self.now().duration_since(start).unwrap_or_default(),
+            error: result.as_ref().err(),
+        });
+        result
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    // The `iss` claim of this pool's tokens.
+    pub fn issuer(&self) -> String {
+        format!("{}/{}", self.issuer_base, self.pool_id)
+    }
+
+    pub fn pool_id(&self) -> &str {
+        self.pool_id.as_str()
+    }
+
+    pub fn pool(&self) -> &PoolId {
+        &self.pool_id
+    }
+
+    pub fn region(&self) -> &str {
+        self.pool_id.region()
+    }
+
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    pub(crate) fn client_secret(&self) -> Option<&str> {
+        self.client_secret.as_ref().map(SecretString::expose_secret)
+    }
+
+    pub(crate) fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    pub(crate) fn random<T>(&self, f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+        match &self.rng {
+            Some(rng) => f(&mut *rng.lock().unwrap_or_else(|e| e.into_inner())),
+            None => f(&mut rand::thread_rng()),
+        }
+    }
+
+    pub(crate) fn transport(&self) -> &dyn Transport {
+        self.transport.as_ref()
+    }
+
+    pub(crate) fn identity_endpoint(&self, region: &str) -> String {
+        self.identity_endpoint
+            .clone()
+            .unwrap_or_else(|| format!("https://cognito-identity.{}.amazonaws.com/", region))
+    }
+
+    // # https://docs.aws.amazon.com/cognito/latest/developerguide/signing-up-users-in-your-app.html#cognito-user-pools-computing-secret-hash
+    pub(crate) fn secret_hash(&self, username: &str) -> Option<String> {
+        self.client_secret.as_ref().map(|secret| {
+            let message = [username, &self.client_id].concat();
+            BASE64.encode(&hmac_sha256(
+                secret.expose_secret().as_bytes(),
+                message.as_bytes(),
+            ))
+        })
+    }
+
+    // Request body with ClientId and, for confidential app clients, SECRET_HASH already filled in.
+    pub(crate) fn request_for(&self, username: &str) -> Value {
+        let username = self.normalize_username(username);
+        let mut body = Value::object([
+            ("ClientId", Value::from(self.client_id.as_str())),
+            ("Username", Value::from(username.as_str())),
+        ]);
+        if let Some(hash) = self.secret_hash(&username) {
+            body.insert("SecretHash", Value::from(hash));
+        }
+        body
+    }
+
+    pub(crate) fn call(&self, operation: &str, body: Value) -> Result<Value> {
+        self.send(operation, body, false)
+    }
+
+    // For operations that are safe to repeat; retried according to the client's RetryPolicy.
+    pub(crate) fn idempotent_call(&self, operation: &str, body: Value) -> Result<Value> {
+        self.retry.run(|| self.call(operation, body.clone()))
+    }
+
+    pub(crate) fn admin_call(&self, operation: &str, mut body: Value) -> Result<Value> {
+        body.insert("UserPoolId", Value::from(self.pool_id.as_str()));
+        self.send(operation, body, true)
+    }
+
+    fn send(&self, operation: &str, body: Value, signed: bool) -> Result<Value> {
+        self.check_interrupted(body.get("Session").and_then(Value::as_str))?;
+        self.measure(operation, || self.send_once(operation, body, signed))
+    }
+
+    fn send_once(&self, operation: &str, mut body: Value, signed: bool) -> Result<Value> {
+        if !self.client_metadata.is_empty() && ACCEPTS_CLIENT_METADATA.contains(&operation) {
+            body.insert(
+                "ClientMetadata",
+                Value::object(
+                    self.client_metadata
+                        .iter()
+                        .map(|(k, v)| (k.as_str(), Value::from(v.as_str()))),
+                ),
+            );
+        }
+        if let Some(context) = &self.user_context {
+            if ACCEPTS_USER_CONTEXT.contains(&operation) {
+                let value = context.to_json(
+                    self.pool_id.as_str(),
+                    &self.client_id,
+                    &request_username(&body).unwrap_or_default(),
+                    self.now(),
+                );
+                body.insert("UserContextData", value);
+            }
+        }
+        if let Some(endpoint_id) = &self.analytics_endpoint_id {
+            if ACCEPTS_USER_CONTEXT.contains(&operation)
+                || ["AdminInitiateAuth", "AdminRespondToAuthChallenge"].contains(&operation)
+            {
+                body.insert(
+                    "AnalyticsMetadata",
+                    Value::object([("AnalyticsEndpointId", Value::from(endpoint_id.as_str()))]),
+                );
+            }
+        }
+        if let Some(api) = &self.api {
+            let response = api.call(operation, &body.to_string())?;
+            return parse_body(&response);
+        }
+        let mut request = json_request(
+            &self.endpoint,
+            &format!("AWSCognitoIdentityProviderService.{}", operation),
+            &body,
+        );
+        if signed {
+            let credentials = self.credentials.as_ref().ok_or_else(|| {
+                CognitoSrpError::Configuration(format!(
+                    "{} requires AWS credentials on the client",
+                    operation
+                ))
+            })?;
+            sigv4::sign(
+                &mut request,
+                credentials,
+                self.region(),
+                "cognito-idp",
+                self.now(),
+            )?;
+        }
+        self.dispatch(operation, request)
+    }
+
+    pub(crate) fn sts_endpoint(&self) -> String {
+        self.sts_endpoint
+            .clone()
+            .unwrap_or_else(|| format!("https://sts.{}.amazonaws.com/", self.region()))
+    }
+
+    // Sends an x-amz-json request and maps error responses to ServiceError.
+    pub(crate) fn dispatch(&self, operation: &str, request: HttpRequest) -> Result<Value> {
+        let response = self.transport.send(request)?;
+        if !(200..300).contains(&response.status) {
+            // Gateways sometimes answer 5xx with HTML, so don't require JSON here.
+            let value = json::parse(&response.body).unwrap_or(Value::Null);
+            let kind = match value.get("__type").and_then(Value::as_str) {
+                Some(type_name) => ServiceErrorKind::parse(type_name),
+                None => ServiceErrorKind::Other(format!("HTTP {}", response.status)),
+            };
+            let message = value
+                .str_field("message")
+                .or_else(|| value.str_field("Message"))
+                .unwrap_or_default();
+            return Err(ServiceError {
+                operation: operation.to_owned(),
+                status: response.status,
+                kind,
+                message,
+            }
+            .into());
+        }
+        parse_body(&response.body)
+    }
+}
+
+// Operations with an empty 200 body (GlobalSignOut, ...) come back as an empty object.
+fn parse_body(body: &str) -> Result<Value> {
+    if body.trim().is_empty() {
+        Ok(Value::object::<String>([]))
+    } else {
+        json::parse(body)
+    }
+}
+
+// The struct itself is the real thing: raw JSON in and out over HTTP, for calls the typed
+// methods don't cover yet.
+impl CognitoApi for Client {
+    fn call(&self, operation: &str, request: &str) -> Result<String> {
+        let body = json::parse(request)?;
+        let signed = operation.starts_with("Admin");
+        if signed {
+            self.admin_call(operation, body)
+        } else {
+            self.send(operation, body, false)
+        }
+        .map(|response| response.to_string())
+    }
+}
+
+// The client's clock, for the expiry helpers on TokenSet and Claims.
+impl Clock for Client {
+    fn now(&self) -> SystemTime {
+        self.clock.now()
+    }
+}
+
+// Operations whose request has a ClientMetadata member; the rest reject unknown fields.
+const ACCEPTS_CLIENT_METADATA: &[&str] = &[
+    "AdminConfirmSignUp",
+    "AdminCreateUser",
+    "AdminInitiateAuth",
+    "AdminRespondToAuthChallenge",
+    "AdminResetUserPassword",
+    "AdminUpdateUserAttributes",
+    "ConfirmForgotPassword",
+    "ConfirmSignUp",
+    "ForgotPassword",
+    "GetUserAttributeVerificationCode",
+    "InitiateAuth",
+    "ResendConfirmationCode",
+    "RespondToAuthChallenge",
+    "SignUp",
+    "UpdateUserAttributes",
+];
+
+// The unauthenticated operations with UserContextData and AnalyticsMetadata members. The Admin* equivalents take a
+// differently shaped ContextData instead.
+const ACCEPTS_USER_CONTEXT: &[&str] = &[
+    "ConfirmForgotPassword",
+    "ConfirmSignUp",
+    "ForgotPassword",
+    "InitiateAuth",
+    "ResendConfirmationCode",
+    "RespondToAuthChallenge",
+    "SignUp",
+];
+
+// Whichever of Username, AuthParameters.USERNAME or ChallengeResponses.USERNAME is present.
+fn request_username(body: &Value) -> Option<String> {
+    body.str_field("Username").or_else(|| {
+        ["AuthParameters", "ChallengeResponses"]
+            .iter()
+            .find_map(|field| body.get(field)?.str_field("USERNAME"))
+    })
+}
+
+pub(crate) fn json_request(url: &str, target: &str, body: &Value) -> HttpRequest {
+    HttpRequest {
+        method: "POST",
+        url: url.to_owned(),
+        headers: vec![
+            (
+                "Content-Type".to_owned(),
+                "application/x-amz-json-1.1".to_owned(),
+            ),
+            ("X-Amz-Target".to_owned(), target.to_owned()),
+        ],
+        body: body.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_pool_id() {
+        let client = Client::new("eu-west-2_AbCdEf", "client", MockTransport::new()).unwrap();
+        assert_eq!(client.region(), "eu-west-2");
+        assert!(Client::new("nounderscore", "client", MockTransport::new()).is_err());
+    }
+
+    #[test]
+    fn test_secret_hash() {
+        let client = Client::new("us-east-1_pool", "client", MockTransport::new()).unwrap();
+        assert_eq!(client.secret_hash("bob"), None);
+
+        let client = client.with_client_secret("secret");
+        assert_eq!(
+            client.secret_hash("bob").unwrap(),
+            "P1WGx30spzkjVO6gbksCMo0r3Ro1xm4a6xXZTTrvrD0="
+        );
+    }
+
+    #[test]
+    fn test_call_error() {
+        let transport = MockTransport::new();
+        transport.respond(
+            400,
+            r#"{"__type":"UsernameExistsException","message":"User already exists"}"#,
+        );
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let err = client
+            .call("SignUp", Value::object::<String>([]))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "SignUp failed: UsernameExistsException: User already exists"
+        );
+        assert_eq!(err.service_kind(), Some(&ServiceErrorKind::UsernameExists));
+        let request = transport.last_request();
+        assert!(!request
+            .headers
+            .iter()
+            .any(|(name, _)| name == "Authorization"));
+        assert_eq!(request.url, "https://cognito-idp.us-east-1.amazonaws.com/");
+        assert!(request.headers.contains(&(
+            "X-Amz-Target".to_owned(),
+            "AWSCognitoIdentityProviderService.SignUp".to_owned()
+        )));
+    }
+
+    #[test]
+    fn test_call_error_without_json() {
+        let transport = MockTransport::new();
+        transport.respond(502, "<html>Bad Gateway</html>");
+        let client = Client::new("us-east-1_pool", "client", transport).unwrap();
+        let err = client
+            .call("SignUp", Value::object::<String>([]))
+            .unwrap_err();
+        assert!(err.is_retryable());
+        assert_eq!(err.to_string(), "SignUp failed: HTTP 502: ");
+    }
+
+    #[test]
+    fn test_endpoint_override() {
+        let transport = MockTransport::new();
+        transport.respond(200, "{}");
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        assert_eq!(
+            client.issuer(),
+            "https://cognito-idp.us-east-1.amazonaws.com/us-east-1_pool"
+        );
+
+        let fips = client.clone().with_fips_endpoint();
+        assert_eq!(
+            fips.endpoint(),
+            "https://cognito-idp-fips.us-east-1.amazonaws.com/"
+        );
+        assert_eq!(fips.issuer(), client.issuer());
+
+        let local = client
+            .with_endpoint("http://localhost:9229")
+            .with_client_metadata(&[("tenant", "acme")]);
+        local.call("GetUser", Value::object::<String>([])).unwrap();
+        assert_eq!(transport.last_request().url, "http://localhost:9229/");
+        // GetUser has no ClientMetadata member, so none is sent.
+        assert_eq!(transport.last_request().body, "{}");
+        assert_eq!(local.issuer(), "http://localhost:9229/us-east-1_pool");
+    }
+
+    #[test]
+    fn test_admin_call_is_signed() {
+        let transport = MockTransport::new();
+        transport.respond(200, "{}");
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        assert!(client
+            .admin_call("AdminConfirmSignUp", Value::object::<String>([]))
+            .is_err());
+
+        let client = client.with_credentials(AwsCredentials::new("AKID", "secret"));
+        client
+            .admin_call("AdminConfirmSignUp", Value::object::<String>([]))
+            .unwrap();
+        let request = transport.last_request();
+        assert_eq!(request.body, r#"{"UserPoolId":"us-east-1_pool"}"#);
+        assert!(request
+            .headers
+            .iter()
+            .any(|(name, value)| name == "Authorization" && value.starts_with("AWS4-HMAC-SHA256")));
+    }
+
+    #[test]
+    fn test_metrics() {
+        let api = crate::api::MockCognitoApi::new();
+        api.fail(
+            "InitiateAuth",
+            ServiceErrorKind::NotAuthorized,
+            "Refresh Token has expired",
+        );
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        let sink = recorded.clone();
+        let client = Client::from_api("us-east-1_pool", "client", api)
+            .unwrap()
+            .with_metrics(move |m: &Measurement<'_>| {
+                let error_type = m.error_type().map(str::to_owned);
+                sink.lock().unwrap().push((m.name.to_owned(), error_type));
+            });
+
+        assert!(client.refresh_tokens("bob", "refresh").is_err());
+        let not_authorized = Some("NotAuthorizedException".to_owned());
+        assert_eq!(
+            *recorded.lock().unwrap(),
+            [
+                ("InitiateAuth".to_owned(), not_authorized.clone()),
+                ("refresh".to_owned(), not_authorized),
+            ]
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+// Where the client gets "now": the PASSWORD_VERIFIER timestamp, SigV4 dates and every token
+// expiry decision. Swap it for a fixed clock in tests, or a corrected one on hosts whose clock
+// can't be trusted.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        system_now()
+    }
+}
+
+// SystemTime::now panics on wasm32-unknown-unknown, which has no clock of its own; ask the
+// JavaScript host instead.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn system_now() -> SystemTime {
+    std::time::UNIX_EPOCH + Duration::from_secs_f64(js_sys::Date::now() / 1000.0)
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn system_now() -> SystemTime {
+    SystemTime::now()
+}
+
+// The TIMESTAMP a PASSWORD_VERIFIER answer carries, e.g. "Tue Oct 7 09:05:03 UTC 2025", for
+// callers driving srp::SrpSession themselves.
+#[cfg(feature = "client")]
+pub fn cognito_timestamp(now: SystemTime) -> String {
+    crate::time::cognito_timestamp(now)
+}
+
+// The system clock shifted by a known skew, e.g. measured against an NTP server or an HTTP
+// Date header.
+#[derive(Clone, Copy, Debug)]
+pub struct OffsetClock {
+    offset: Duration,
+    ahead: bool,
+}
+
+impl OffsetClock {
+    // For a system clock that runs `offset` slow.
+    pub fn ahead(offset: Duration) -> Self {
+        Self {
+            offset,
+            ahead: true,
+        }
+    }
+
+    // For a system clock that runs `offset` fast.
+    pub fn behind(offset: Duration) -> Self {
+        Self {
+            offset,
+            ahead: false,
+        }
+    }
+}
+
+impl Clock for OffsetClock {
+    fn now(&self) -> SystemTime {
+        if self.ahead {
+            system_now() + self.offset
+        } else {
+            system_now() - self.offset
+        }
+    }
+}
+
+pub(crate) fn expires_in(expires_at: SystemTime, clock: &(impl Clock + ?Sized)) -> Duration {
+    expires_at.duration_since(clock.now()).unwrap_or_default()
+}
+
+pub(crate) fn is_expired(
+    expires_at: SystemTime,
+    clock: &(impl Clock + ?Sized),
+    leeway: Duration,
+) -> bool {
+    expires_in(expires_at, clock) <= leeway
+}
+
+pub(crate) fn time_to_refresh(
+    expires_at: SystemTime,
+    clock: &(impl Clock + ?Sized),
+    lead: Duration,
+) -> Duration {
+    expires_in(expires_at, clock).saturating_sub(lead)
+}
+
+// Stands still until told otherwise.
+#[derive(Debug)]
+pub struct FixedClock(Mutex<SystemTime>);
+
+impl FixedClock {
+    pub fn new(now: SystemTime) -> Self {
+        Self(Mutex::new(now))
+    }
+
+    pub fn set(&self, now: SystemTime) {
+        *self.0.lock().unwrap() = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.0.lock().unwrap() += by;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        *self.0.lock().unwrap()
+    }
+}
+
+impl<T: Clock + ?Sized> Clock for std::sync::Arc<T> {
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+}
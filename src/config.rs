@@ -0,0 +1,190 @@
+// Client settings from the environment, for containers and Lambda functions configured without
+// code changes:
+//
+//     COGNITO_POOL_ID, COGNITO_CLIENT_ID, COGNITO_CLIENT_SECRET, COGNITO_ENDPOINT, AWS_REGION
+//
+// Anything unset there is looked up in the AWS shared config file's current profile, under the
+// extra keys cognito_pool_id, cognito_client_id and cognito_endpoint (plus the usual region):
+//
+//     [profile staging]
+//     region = eu-west-1
+//     cognito_pool_id = eu-west-1_AbCdEfGhI
+//     cognito_client_id = 1example23456789
+use crate::client::Client;
+use crate::credentials::AwsCredentials;
+use crate::error::{CognitoSrpError, Result};
+use crate::secret::SecretString;
+use crate::transport::Transport;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub pool_id: String,
+    pub client_id: String,
+    pub client_secret: Option<SecretString>,
+    pub endpoint: Option<String>,
+    // For the caller's other AWS clients; the pool id carries the pool's own region.
+    pub region: Option<String>,
+    // From AWS_ACCESS_KEY_ID and friends, for the Admin* operations.
+    pub credentials: Option<AwsCredentials>,
+}
+
+impl Config {
+    // Reads the environment, then AWS_CONFIG_FILE (default ~/.aws/config) for the profile named
+    // by AWS_PROFILE (default "default"). A missing file is not an error.
+    pub fn from_env() -> Result<Self> {
+        let var = |name: &str| env::var(name).ok().filter(|v| !v.is_empty());
+        let path = var("AWS_CONFIG_FILE")
+            .map(PathBuf::from)
+            .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".aws").join("config")));
+        let shared = match path {
+            Some(path) if path.exists() => Some(std::fs::read_to_string(path)?),
+            _ => None,
+        };
+        let mut config = Self::from_vars(var, shared.as_deref())?;
+        config.credentials = AwsCredentials::from_env().ok();
+        Ok(config)
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>, shared: Option<&str>) -> Result<Self> {
+        let profile_name = var("AWS_PROFILE").unwrap_or_else(|| "default".to_owned());
+        let profile = shared
+            .map(|text| aws_profile(text, &profile_name))
+            .unwrap_or_default();
+        let lookup = |name: &str, key: &str| var(name).or_else(|| profile.get(key).cloned());
+        let required = |name: &str, key: &str| {
+            lookup(name, key).ok_or_else(|| {
+                CognitoSrpError::Configuration(format!(
+                    "{} is not set and profile {} has no {}",
+                    name, profile_name, key
+                ))
+            })
+        };
+        Ok(Self {
+            pool_id: required("COGNITO_POOL_ID", "cognito_pool_id")?,
+            client_id: required("COGNITO_CLIENT_ID", "cognito_client_id")?,
+            client_secret: var("COGNITO_CLIENT_SECRET").map(SecretString::new),
+            endpoint: lookup("COGNITO_ENDPOINT", "cognito_endpoint"),
+            region: var("AWS_REGION")
+                .or_else(|| var("AWS_DEFAULT_REGION"))
+                .or_else(|| profile.get("region").cloned()),
+            credentials: None,
+        })
+    }
+
+    pub fn client(&self, transport: impl Transport + 'static) -> Result<Client> {
+        let mut client = Client::new(&self.pool_id, &self.client_id, transport)?;
+        if let Some(secret) = &self.client_secret {
+            client = client.with_client_secret(secret.expose_secret());
+        }
+        if let Some(endpoint) = &self.endpoint {
+            client = client.with_endpoint(endpoint);
+        }
+        if let Some(credentials) = &self.credentials {
+            client = client.with_credentials(credentials.clone());
+        }
+        Ok(client)
+    }
+}
+
+// # https://docs.aws.amazon.com/sdkref/latest/guide/file-format.html
+// The keys of one profile: `[default]` or `[profile <name>]`. Nested (indented) settings such as
+// `s3 =` sub-sections are skipped.
+fn aws_profile(text: &str, name: &str) -> BTreeMap<String, String> {
+    let mut keys = BTreeMap::new();
+    let mut in_profile = false;
+    for line in text.lines() {
+        if line.starts_with([' ', '\t']) {
+            continue;
+        }
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let section = section.trim();
+            in_profile = match section.strip_prefix("profile ") {
+                Some(profile) => profile.trim() == name,
+                None => section == "default" && name == "default",
+            };
+            continue;
+        }
+        if in_profile {
+            if let Some((key, value)) = line.split_once('=') {
+                let value = value
+                    .split([' ', '\t'])
+                    .take_while(|w| !w.starts_with(['#', ';']));
+                let value = value
+                    .filter(|w| !w.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                keys.insert(key.trim().to_owned(), value);
+            }
+        }
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHARED: &str = "
+[default]
+region = us-east-1
+
+[profile staging]
+region = eu-west-1   # Ireland
+cognito_pool_id = eu-west-1_AbCdEf
+cognito_client_id = stagingclient
+s3 =
+  max_concurrent_requests = 20
+";
+
+    fn vars<'a>(pairs: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            pairs
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn test_env_wins_over_profile() {
+        let env = [
+            ("AWS_PROFILE", "staging"),
+            ("COGNITO_CLIENT_ID", "envclient"),
+            ("COGNITO_CLIENT_SECRET", "shh"),
+        ];
+        let config = Config::from_vars(vars(&env), Some(SHARED)).unwrap();
+        assert_eq!(config.pool_id, "eu-west-1_AbCdEf");
+        assert_eq!(config.client_id, "envclient");
+        assert_eq!(config.region.as_deref(), Some("eu-west-1"));
+        assert_eq!(config.endpoint, None);
+        let client = config.client(crate::api::Offline).unwrap();
+        assert!(client.secret_hash("bob").is_some());
+    }
+
+    #[test]
+    fn test_env_only() {
+        let env = [
+            ("COGNITO_POOL_ID", "us-west-2_Pool"),
+            ("COGNITO_CLIENT_ID", "client"),
+            ("COGNITO_ENDPOINT", "http://localhost:9229"),
+            ("AWS_REGION", "us-west-2"),
+        ];
+        let config = Config::from_vars(vars(&env), None).unwrap();
+        assert_eq!(config.region.as_deref(), Some("us-west-2"));
+        let client = config.client(crate::api::Offline).unwrap();
+        assert_eq!(client.endpoint(), "http://localhost:9229/");
+
+        let err = Config::from_vars(vars(&[]), Some(SHARED)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "COGNITO_POOL_ID is not set and profile default has no cognito_pool_id"
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use crate::crypto::hmac_sha256;
+use crate::json::Value;
+use data_encoding::BASE64;
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Tags the EncodedData envelope the same way Amazon's mobile collectors do; Cognito uses it to
+// pick the signature scheme.
+const ENCODED_DATA_VERSION: &str = "ANDROID20171114";
+
+// # https://docs.aws.amazon.com/cognito/latest/developerguide/cognito-user-pool-settings-adaptive-authentication.html
+// UserContextData for pools with advanced security: the end user's IP address and an
+// EncodedData blob describing their device, which adaptive authentication scores.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UserContextData {
+    ip_address: Option<String>,
+    encoded_data: Option<String>,
+    device: BTreeMap<String, String>,
+}
+
+impl UserContextData {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The end user's address, when requests come from a server acting on their behalf.
+    pub fn with_ip_address(mut self, ip_address: &str) -> Self {
+        self.ip_address = Some(ip_address.to_owned());
+        self
+    }
+
+    // EncodedData collected elsewhere, e.g. by the advanced security script in a browser. Sent
+    // verbatim and takes precedence over device context.
+    pub fn with_encoded_data(mut self, encoded_data: &str) -> Self {
+        self.encoded_data = Some(encoded_data.to_owned());
+        self
+    }
+
+    // Device fields ("DeviceId", "DeviceFingerprint", "ApplicationName", ...) to generate
+    // EncodedData from; DevicePlatform defaults to the OS this runs on.
+    pub fn with_device_context(mut self, fields: &[(&str, &str)]) -> Self {
+        self.device = fields
+            .iter()
+            .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+            .collect();
+        self
+    }
+
+    // The request member, signing fresh EncodedData for `username` when there is device
+    // context but no pre-collected blob.
+    pub(crate) fn to_json(
+        &self,
+        pool_id: &str,
+        client_id: &str,
+        username: &str,
+        now: SystemTime,
+    ) -> Value {
+        let mut value = Value::object::<String>([]);
+        if let Some(ip_address) = &self.ip_address {
+            value.insert("IpAddress", Value::from(ip_address.as_str()));
+        }
+        let encoded_data = match &self.encoded_data {
+            Some(data) => Some(data.clone()),
+            None if !self.device.is_empty() => Some(self.encode(pool_id, client_id, username, now)),
+            None => None,
+        };
+        if let Some(data) = encoded_data {
+            value.insert("EncodedData", Value::from(data));
+        }
+        value
+    }
+
+    // base64 of {payload, signature, version}, where the payload names the user and pool and
+    // the signature is HMAC-SHA256(client id, version || payload).
+    fn encode(&self, pool_id: &str, client_id: &str, username: &str, now: SystemTime) -> String {
+        let mut device = Value::object([("DevicePlatform", Value::from(std::env::consts::OS))]);
+        for (name, field) in &self.device {
+            device.insert(name, Value::from(field.as_str()));
+        }
+        let millis = now
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let payload = Value::object([
+            ("contextData", device),
+            ("username", Value::from(username)),
+            ("userPoolId", Value::from(pool_id)),
+            ("timestamp", Value::from(millis.to_string())),
+        ])
+        .to_string();
+        let message = [ENCODED_DATA_VERSION, &payload].concat();
+        let signature = BASE64.encode(&hmac_sha256(client_id.as_bytes(), message.as_bytes()));
+        let envelope = Value::object([
+            ("payload", Value::from(payload)),
+            ("signature", Value::from(signature)),
+            ("version", Value::from(ENCODED_DATA_VERSION)),
+        ]);
+        BASE64.encode(envelope.to_string().as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+    use std::time::Duration;
+
+    #[test]
+    fn test_encoded_data() {
+        let now = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        let context = UserContextData::new()
+            .with_ip_address("203.0.113.7")
+            .with_device_context(&[("DeviceId", "dev-1")]);
+        let value = context.to_json("us-east-1_pool", "client", "bob", now);
+        assert_eq!(value.str_field("IpAddress").unwrap(), "203.0.113.7");
+
+        let envelope = BASE64
+            .decode(value.str_field("EncodedData").unwrap().as_bytes())
+            .unwrap();
+        let envelope = json::parse(std::str::from_utf8(&envelope).unwrap()).unwrap();
+        assert_eq!(envelope.str_field("version").unwrap(), ENCODED_DATA_VERSION);
+        let payload = envelope.str_field("payload").unwrap();
+        let expected = BASE64.encode(&hmac_sha256(
+            b"client",
+            [ENCODED_DATA_VERSION, &payload].concat().as_bytes(),
+        ));
+        assert_eq!(envelope.str_field("signature").unwrap(), expected);
+        let payload = json::parse(&payload).unwrap();
+        assert_eq!(payload.str_field("username").unwrap(), "bob");
+        assert_eq!(payload.str_field("timestamp").unwrap(), "1700000000123");
+        let device = payload.get("contextData").unwrap();
+        assert_eq!(device.str_field("DeviceId").unwrap(), "dev-1");
+        assert!(device.str_field("DevicePlatform").is_some());
+
+        // A pre-collected blob is passed through, and nothing is invented without context.
+        let value = UserContextData::new()
+            .with_encoded_data("blob")
+            .with_device_context(&[("DeviceId", "dev-1")])
+            .to_json("us-east-1_pool", "client", "bob", now);
+        assert_eq!(value.to_string(), r#"{"EncodedData":"blob"}"#);
+        let value = UserContextData::new().to_json("us-east-1_pool", "client", "bob", now);
+        assert_eq!(value.to_string(), "{}");
+    }
+}
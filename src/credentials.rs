@@ -0,0 +1,99 @@
+use crate::error::{CognitoSrpError, Result};
+use crate::json::Value;
+use crate::time::iso8601;
+use std::env;
+use std::fmt;
+use std::time::SystemTime;
+
+// IAM credentials, needed for the Admin* operations which Cognito only accepts SigV4-signed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+    pub expiration: Option<SystemTime>,
+}
+
+impl AwsCredentials {
+    pub fn new(access_key_id: &str, secret_access_key: &str) -> Self {
+        Self {
+            access_key_id: access_key_id.to_owned(),
+            secret_access_key: secret_access_key.to_owned(),
+            session_token: None,
+            expiration: None,
+        }
+    }
+
+    pub fn from_env() -> Result<Self> {
+        let var = |name: &str| env::var(name).ok().filter(|v| !v.is_empty());
+        let access_key_id = var("AWS_ACCESS_KEY_ID").ok_or_else(|| {
+            CognitoSrpError::Configuration("AWS_ACCESS_KEY_ID is not set".to_owned())
+        })?;
+        let secret_access_key = var("AWS_SECRET_ACCESS_KEY").ok_or_else(|| {
+            CognitoSrpError::Configuration("AWS_SECRET_ACCESS_KEY is not set".to_owned())
+        })?;
+        Ok(Self {
+            access_key_id,
+            secret_access_key,
+            session_token: var("AWS_SESSION_TOKEN"),
+            expiration: None,
+        })
+    }
+
+    // # https://docs.aws.amazon.com/sdkref/latest/guide/feature-process-credentials.html
+    // The JSON a `credential_process` command prints, so Cognito logins can back `aws` profiles.
+    pub fn to_credential_process_json(&self) -> String {
+        let mut value = Value::object([
+            ("Version", Value::from(1.0)),
+            ("AccessKeyId", Value::from(self.access_key_id.as_str())),
+            (
+                "SecretAccessKey",
+                Value::from(self.secret_access_key.as_str()),
+            ),
+        ]);
+        if let Some(session_token) = &self.session_token {
+            value.insert("SessionToken", Value::from(session_token.as_str()));
+        }
+        // Without an expiry the AWS CLI caches the credentials for good.
+        if let Some(expiration) = self.expiration {
+            value.insert("Expiration", Value::from(iso8601(expiration)));
+        }
+        value.to_string()
+    }
+}
+
+// The key id is not secret and helps tell credentials apart in logs; the rest is.
+impl fmt::Debug for AwsCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AwsCredentials")
+            .field("access_key_id", &self.access_key_id)
+            .field("secret_access_key", &"***")
+            .field("session_token", &self.session_token.as_ref().map(|_| "***"))
+            .field("expiration", &self.expiration)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_credential_process_json() {
+        let mut credentials = AwsCredentials::new("AKID", "secret");
+        assert_eq!(
+            credentials.to_credential_process_json(),
+            r#"{"AccessKeyId":"AKID","SecretAccessKey":"secret","Version":1}"#
+        );
+        credentials.session_token = Some("token".to_owned());
+        credentials.expiration = Some(UNIX_EPOCH + Duration::from_secs(1440938160));
+        assert_eq!(
+            credentials.to_credential_process_json(),
+            r#"{"AccessKeyId":"AKID","Expiration":"2015-08-30T12:36:00Z","SecretAccessKey":"secret","SessionToken":"token","Version":1}"#
+        );
+        let debug = format!("{:?}", credentials);
+        assert!(debug.contains("AKID"));
+        assert!(!debug.contains("secret\"") && !debug.contains("token\""));
+    }
+}
@@ -0,0 +1,299 @@
+// SHA-256, SHA-512, HMAC-SHA256, HKDF and RSA verification from whichever stack the build selected, so
+// callers never name ring or RustCrypto directly. ring wins when both backends are enabled.
+
+#[cfg(feature = "ring")]
+mod imp {
+    use ring::{digest, hkdf, hmac, signature};
+
+    pub(crate) fn sha256(data: &[u8]) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        out.copy_from_slice(digest::digest(&digest::SHA256, data).as_ref());
+        out
+    }
+
+    pub(crate) fn sha512(data: &[u8]) -> [u8; 64] {
+        let mut out = [0u8; 64];
+        out.copy_from_slice(digest::digest(&digest::SHA512, data).as_ref());
+        out
+    }
+
+    #[cfg_attr(not(feature = "client"), allow(dead_code))]
+    pub(crate) fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data);
+        out.copy_from_slice(tag.as_ref());
+        out
+    }
+
+    #[cfg_attr(not(feature = "client"), allow(dead_code))]
+    pub(crate) fn rsa_pkcs1_sha256_verify(n: &[u8], e: &[u8], message: &[u8], sig: &[u8]) -> bool {
+        signature::RsaPublicKeyComponents { n, e }
+            .verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, sig)
+            .is_ok()
+    }
+
+    struct Len(usize);
+
+    impl hkdf::KeyType for Len {
+        fn len(&self) -> usize {
+            self.0
+        }
+    }
+
+    pub(crate) fn hkdf_sha256(ikm: &[u8], salt: &[u8], info: &[u8], okm: &mut [u8]) -> bool {
+        let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, salt).extract(ikm);
+        let info = [info];
+        match prk.expand(&info, Len(okm.len())) {
+            Ok(expanded) => expanded.fill(okm).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    // # https://datatracker.ietf.org/doc/html/rfc8018#section-5.2
+    #[cfg(feature = "client")]
+    pub(crate) fn pbkdf2_hmac_sha256(
+        password: &[u8],
+        salt: &[u8],
+        iterations: core::num::NonZeroU32,
+    ) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        ring::pbkdf2::derive(
+            ring::pbkdf2::PBKDF2_HMAC_SHA256,
+            iterations,
+            salt,
+            password,
+            &mut out,
+        );
+        out
+    }
+
+    // # https://datatracker.ietf.org/doc/html/rfc8439
+    // The 16-byte tag is appended to the ciphertext.
+    #[cfg(feature = "client")]
+    pub(crate) fn chacha20_poly1305_seal(
+        key: &[u8; 32],
+        nonce: [u8; 12],
+        aad: &[u8],
+        data: &mut Vec<u8>,
+    ) {
+        use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305};
+
+        let key = LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, key).unwrap());
+        key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(aad), data)
+            .unwrap();
+    }
+
+    // None when the tag doesn't match: wrong key, wrong aad or a modified ciphertext.
+    #[cfg(feature = "client")]
+    pub(crate) fn chacha20_poly1305_open(
+        key: &[u8; 32],
+        nonce: [u8; 12],
+        aad: &[u8],
+        mut data: Vec<u8>,
+    ) -> Option<Vec<u8>> {
+        use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305};
+
+        let key = LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, key).unwrap());
+        let len = key
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(aad),
+                &mut data,
+            )
+            .ok()?
+            .len();
+        data.truncate(len);
+        Some(data)
+    }
+}
+
+#[cfg(not(feature = "ring"))]
+mod imp {
+    use hkdf::Hkdf;
+    use hmac::{Hmac, Mac, NewMac};
+    use sha2::{Digest, Sha256, Sha512};
+
+    pub(crate) fn sha256(data: &[u8]) -> [u8; 32] {
+        Sha256::digest(data).into()
+    }
+
+    pub(crate) fn sha512(data: &[u8]) -> [u8; 64] {
+        let mut out = [0u8; 64];
+        out.copy_from_slice(&Sha512::digest(data));
+        out
+    }
+
+    #[cfg_attr(not(feature = "client"), allow(dead_code))]
+    pub(crate) fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+        // HMAC accepts keys of any length, so this can't fail.
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+        mac.update(data);
+        mac.finalize().into_bytes().into()
+    }
+
+    // # https://datatracker.ietf.org/doc/html/rfc8017#section-8.2.2
+    // Public-key operation only, so plain modpow and a full-buffer comparison are fine here.
+    #[cfg_attr(not(feature = "client"), allow(dead_code))]
+    pub(crate) fn rsa_pkcs1_sha256_verify(n: &[u8], e: &[u8], message: &[u8], sig: &[u8]) -> bool {
+        use alloc::vec;
+        use num_bigint::BigUint;
+
+        const SHA256_DIGEST_INFO: &[u8] = &[
+            0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+            0x01, 0x05, 0x00, 0x04, 0x20,
+        ];
+        let modulus = BigUint::from_bytes_be(n);
+        let k = modulus.bits().div_ceil(8) as usize;
+        if modulus.bits() < 2048 || sig.len() != k {
+            return false;
+        }
+        let s = BigUint::from_bytes_be(sig);
+        if s >= modulus {
+            return false;
+        }
+        let em = s.modpow(&BigUint::from_bytes_be(e), &modulus).to_bytes_be();
+        let t_len = SHA256_DIGEST_INFO.len() + 32;
+        if k < t_len + 11 || em.len() != k - 1 {
+            return false;
+        }
+        // to_bytes_be drops the leading 0x00, so em starts at the 0x01 block type.
+        let mut expected = vec![0x01];
+        expected.resize(k - t_len - 2, 0xff);
+        expected.push(0x00);
+        expected.extend_from_slice(SHA256_DIGEST_INFO);
+        expected.extend_from_slice(&sha256(message));
+        em == expected
+    }
+
+    pub(crate) fn hkdf_sha256(ikm: &[u8], salt: &[u8], info: &[u8], okm: &mut [u8]) -> bool {
+        Hkdf::<Sha256>::new(Some(salt), ikm)
+            .expand(info, okm)
+            .is_ok()
+    }
+}
+
+pub(crate) use imp::*;
+
+// Compares MACs, keys and other secrets without leaking where they first differ. subtle keeps
+// the optimiser from turning this back into an early-exit loop; only the lengths may leak.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    use subtle::ConstantTimeEq;
+
+    a.ct_eq(b).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex::encode;
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            encode(sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_rsa_pkcs1_sha256_verify() {
+        let jwks = include_str!("../testdata/jwks.json");
+        let field = |name: &str| {
+            let start = jwks.find(&format!("\"{}\": \"", name)).unwrap() + name.len() + 5;
+            let end = start + jwks[start..].find('"').unwrap();
+            data_encoding::BASE64URL_NOPAD
+                .decode(&jwks.as_bytes()[start..end])
+                .unwrap()
+        };
+        let (n, e) = (field("n"), field("e"));
+        let tokens = include_str!("../testdata/tokens.json");
+        let start = tokens.find("\"id_admin\": \"").unwrap() + 13;
+        let token = &tokens[start..start + tokens[start..].find('"').unwrap()];
+        let (signing_input, sig) = token.rsplit_once('.').unwrap();
+        let sig = data_encoding::BASE64URL_NOPAD
+            .decode(sig.as_bytes())
+            .unwrap();
+
+        assert!(rsa_pkcs1_sha256_verify(
+            &n,
+            &e,
+            signing_input.as_bytes(),
+            &sig
+        ));
+        assert!(!rsa_pkcs1_sha256_verify(&n, &e, b"something else", &sig));
+        let mut bad = sig.clone();
+        bad[10] ^= 1;
+        assert!(!rsa_pkcs1_sha256_verify(
+            &n,
+            &e,
+            signing_input.as_bytes(),
+            &bad
+        ));
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd") && !constant_time_eq(b"abc", b"ab"));
+    }
+
+    #[test]
+    #[cfg(all(feature = "ring", feature = "client"))]
+    fn test_pbkdf2_and_chacha20_poly1305() {
+        // # https://datatracker.ietf.org/doc/html/rfc7914#section-11
+        assert_eq!(
+            encode(pbkdf2_hmac_sha256(
+                b"passwd",
+                b"salt",
+                1.try_into().unwrap()
+            )),
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc"
+        );
+        // # https://datatracker.ietf.org/doc/html/rfc8439#section-2.8.2
+        let key: [u8; 32] = core::array::from_fn(|i| 0x80 + i as u8);
+        let nonce = [
+            0x07, 0, 0, 0, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47,
+        ];
+        let aad = hex::decode("50515253c0c1c2c3c4c5c6c7").unwrap();
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only \
+                          one tip for the future, sunscreen would be it.";
+        let mut sealed = plaintext.to_vec();
+        chacha20_poly1305_seal(&key, nonce, &aad, &mut sealed);
+        assert_eq!(
+            encode(&sealed[plaintext.len()..]),
+            "1ae10b594f09e26a7e902ecbd0600691"
+        );
+        assert_eq!(encode(&sealed[..16]), "d31a8d34648e60db7b86afbc53ef7ec2");
+        assert_eq!(
+            chacha20_poly1305_open(&key, nonce, &aad, sealed.clone()).as_deref(),
+            Some(&plaintext[..])
+        );
+        sealed[0] ^= 1;
+        assert_eq!(chacha20_poly1305_open(&key, nonce, &aad, sealed), None);
+    }
+
+    // These vectors run against whichever backend was built, so CI covering both
+    // configurations pins them to identical outputs.
+    #[test]
+    fn test_hmac_sha256() {
+        // # https://datatracker.ietf.org/doc/html/rfc4231#section-4.3
+        assert_eq!(
+            encode(hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        // # https://datatracker.ietf.org/doc/html/rfc5869#appendix-A.1
+        let mut okm = [0; 42];
+        assert!(hkdf_sha256(
+            &[0x0b; 22],
+            &hex::decode("000102030405060708090a0b0c").unwrap(),
+            &hex::decode("f0f1f2f3f4f5f6f7f8f9").unwrap(),
+            &mut okm
+        ));
+        assert_eq!(
+            encode(okm),
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+        );
+        assert!(!hkdf_sha256(b"", b"", b"", &mut [0; 255 * 32 + 1]));
+    }
+}
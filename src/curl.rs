@@ -0,0 +1,302 @@
+use crate::error::{CognitoSrpError, Result};
+use crate::transport::{HttpRequest, HttpResponse, Transport};
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransportConfig {
+    pub connect_timeout: Duration,
+    // Give up when no data arrives for this long, however long the request has been running.
+    pub read_timeout: Duration,
+    // Cap on the whole request; None leaves only the connect and read timeouts.
+    pub total_timeout: Option<Duration>,
+    // e.g. "http://proxy.corp:3128"; None connects directly.
+    pub proxy: Option<String>,
+    // Comma-separated hosts that bypass the proxy, as in NO_PROXY.
+    pub no_proxy: Option<String>,
+    // Skips TLS certificate checks. Only for local emulators with self-signed certificates;
+    // never turn this on against AWS.
+    pub danger_accept_invalid_certs: bool,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(30),
+            total_timeout: None,
+            proxy: None,
+            no_proxy: None,
+            danger_accept_invalid_certs: false,
+        }
+    }
+}
+
+impl TransportConfig {
+    // Defaults plus HTTPS_PROXY (falling back to HTTP_PROXY) and NO_PROXY, in either case.
+    pub fn from_env() -> Self {
+        let var = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| env::var(name).ok().filter(|v| !v.is_empty()))
+        };
+        Self {
+            proxy: var(&["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy"]),
+            no_proxy: var(&["NO_PROXY", "no_proxy"]),
+            ..Self::default()
+        }
+    }
+}
+
+// # https://curl.se/docs/manpage.html#-K
+// curl config syntax: double-quoted values with backslash escapes.
+fn quote(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// Marks the status line appended after the body by --write-out.
+const STATUS_MARKER: &str = "\n--cog-neat-oh-status:";
+
+// Sends requests through the `curl` executable so no HTTP or TLS crate is needed. Everything,
+// headers and body included, goes over stdin as a curl config file, never on the command line
+// where other local users could read it.
+#[derive(Clone, Debug)]
+pub struct CurlTransport {
+    config: TransportConfig,
+    program: String,
+}
+
+impl CurlTransport {
+    pub fn new(config: TransportConfig) -> Self {
+        Self {
+            config,
+            program: "curl".to_owned(),
+        }
+    }
+
+    // Use a specific curl binary instead of the one on PATH.
+    pub fn with_program(mut self, program: &str) -> Self {
+        self.program = program.to_owned();
+        self
+    }
+
+    fn curl_config(&self, request: &HttpRequest) -> String {
+        let config = &self.config;
+        let mut lines = vec![
+            "silent".to_owned(),
+            "show-error".to_owned(),
+            format!("request = {}", quote(request.method)),
+            format!("url = {}", quote(&request.url)),
+            format!("connect-timeout = {}", config.connect_timeout.as_secs_f64()),
+            // Abort when the transfer stays below 1 byte/s for read_timeout.
+            "speed-limit = 1".to_owned(),
+            format!("speed-time = {}", config.read_timeout.as_secs().max(1)),
+            format!(
+                "write-out = {}",
+                quote(&format!("{}%{{http_code}}", STATUS_MARKER))
+            ),
+        ];
+        if config.danger_accept_invalid_certs {
+            lines.push("insecure".to_owned());
+        }
+        if let Some(total) = config.total_timeout {
+            lines.push(format!("max-time = {}", total.as_secs_f64()));
+        }
+        match &config.proxy {
+            Some(proxy) => lines.push(format!("proxy = {}", quote(proxy))),
+            None => lines.push(format!("noproxy = {}", quote("*"))),
+        }
+        if let Some(no_proxy) = &config.no_proxy {
+            lines.push(format!("noproxy = {}", quote(no_proxy)));
+        }
+        for (name, value) in &request.headers {
+            lines.push(format!(
+                "header = {}",
+                quote(&format!("{}: {}", name, value))
+            ));
+        }
+        if !request.body.is_empty() {
+            lines.push(format!("data-binary = {}", quote(&request.body)));
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+impl Default for CurlTransport {
+    fn default() -> Self {
+        Self::new(TransportConfig::from_env())
+    }
+}
+
+fn transport_error(message: String) -> CognitoSrpError {
+    CognitoSrpError::Transport(message.into())
+}
+
+impl Transport for CurlTransport {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let mut child = Command::new(&self.program)
+            .args(["--disable", "--config", "-"])
+            // The config decides about proxies; don't let curl pick them up on its own.
+            .env_remove("HTTPS_PROXY")
+            .env_remove("https_proxy")
+            .env_remove("HTTP_PROXY")
+            .env_remove("http_proxy")
+            .env_remove("ALL_PROXY")
+            .env_remove("all_proxy")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| transport_error(format!("could not run {}: {}", self.program, err)))?;
+        // curl reads the whole config before it connects, so this cannot deadlock on stdout.
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(self.curl_config(&request).as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(transport_error(format!(
+                "curl failed ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        let stdout = String::from_utf8(output.stdout)
+            .map_err(|_| CognitoSrpError::InvalidResponse("body is not UTF-8".to_owned()))?;
+        let (body, status) = stdout
+            .rsplit_once(STATUS_MARKER)
+            .ok_or_else(|| transport_error("curl did not report a status".to_owned()))?;
+        Ok(HttpResponse {
+            status: status
+                .trim()
+                .parse()
+                .map_err(|_| transport_error(format!("bad status from curl: {}", status)))?,
+            body: body.to_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_curl_config() {
+        let transport = CurlTransport::new(TransportConfig {
+            proxy: Some("http://proxy:3128".to_owned()),
+            total_timeout: Some(Duration::from_millis(1500)),
+            ..TransportConfig::default()
+        });
+        let config = transport.curl_config(&HttpRequest {
+            method: "POST",
+            url: "https://cognito-idp.us-east-1.amazonaws.com/".to_owned(),
+            headers: vec![("X-Amz-Target".to_owned(), "Svc.Op".to_owned())],
+            body: r#"{"a":"b\n"}"#.to_owned(),
+        });
+        assert!(config.contains("connect-timeout = 10\n"));
+        assert!(config.contains("speed-time = 30\n"));
+        assert!(config.contains("max-time = 1.5\n"));
+        assert!(config.contains("proxy = \"http://proxy:3128\"\n"));
+        assert!(config.contains("header = \"X-Amz-Target: Svc.Op\"\n"));
+        assert!(config.contains(r#"data-binary = "{\"a\":\"b\\n\"}""#));
+        assert!(!config.contains("noproxy"));
+        assert!(!config.contains("insecure"));
+
+        let transport = CurlTransport::new(TransportConfig {
+            danger_accept_invalid_certs: true,
+            ..TransportConfig::default()
+        });
+        let config = transport.curl_config(&HttpRequest {
+            method: "GET",
+            url: "https://localhost:9229/".to_owned(),
+            headers: Vec::new(),
+            body: String::new(),
+        });
+        assert!(config.contains("\ninsecure\n"));
+        assert!(config.contains("noproxy = \"*\"\n"));
+    }
+
+    #[test]
+    fn test_send_to_local_server() {
+        if Command::new("curl").arg("--version").output().is_err() {
+            return;
+        }
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = Vec::new();
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                if line == "\r\n" {
+                    break;
+                }
+                head.push(line);
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 11\r\n\r\n{\"ok\":true}")
+                .unwrap();
+            (head, String::from_utf8(body).unwrap())
+        });
+
+        let response = CurlTransport::new(TransportConfig::default())
+            .send(HttpRequest {
+                method: "POST",
+                url,
+                headers: vec![("X-Test".to_owned(), "yes".to_owned())],
+                body: "{\"q\":1}".to_owned(),
+            })
+            .unwrap();
+        assert_eq!(response.status, 400);
+        assert_eq!(response.body, "{\"ok\":true}");
+        let (head, body) = server.join().unwrap();
+        assert!(head[0].starts_with("POST / "));
+        assert!(head.contains(&"X-Test: yes\r\n".to_owned()));
+        assert_eq!(body, "{\"q\":1}");
+    }
+
+    #[test]
+    fn test_connect_failure() {
+        let transport =
+            CurlTransport::new(TransportConfig::default()).with_program("/nonexistent/curl");
+        let err = transport
+            .send(HttpRequest {
+                method: "GET",
+                url: "http://127.0.0.1:1/".to_owned(),
+                headers: Vec::new(),
+                body: String::new(),
+            })
+            .unwrap_err();
+        assert!(err.is_retryable());
+    }
+}
@@ -0,0 +1,225 @@
+// # https://docs.aws.amazon.com/cognito/latest/developerguide/amazon-cognito-user-pools-device-tracking.html
+// Remembered devices. With a DeviceStore on the client (Client::with_device_store), a sign-in
+// that comes back with NewDeviceMetadata confirms the device and saves its keys, and later
+// sign-ins by the same user answer DEVICE_SRP_AUTH with them, which is what lets the pool skip
+// MFA on that device. Devices are stored per pool and USER_ID_FOR_SRP.
+use crate::client::Client;
+use crate::crypto::sha256;
+use crate::error::{CognitoSrpError, Result};
+use crate::json::{self, Value};
+use crate::secret::SecretString;
+use crate::srp::DeviceVerifier;
+use crate::store::{write_private, FileTokenStore};
+use crate::wire::NewDeviceMetadata;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RememberedDevice {
+    pub device_key: String,
+    pub device_group_key: String,
+    // Generated here when the device is confirmed; Cognito only keeps its verifier.
+    pub device_password: SecretString,
+}
+
+pub trait DeviceStore: Send + Sync {
+    fn load(&self, pool_id: &str, username: &str) -> Result<Option<RememberedDevice>>;
+    fn save(&self, pool_id: &str, username: &str, device: &RememberedDevice) -> Result<()>;
+    fn clear(&self, pool_id: &str, username: &str) -> Result<()>;
+}
+
+impl<T: DeviceStore + ?Sized> DeviceStore for Arc<T> {
+    fn load(&self, pool_id: &str, username: &str) -> Result<Option<RememberedDevice>> {
+        (**self).load(pool_id, username)
+    }
+
+    fn save(&self, pool_id: &str, username: &str, device: &RememberedDevice) -> Result<()> {
+        (**self).save(pool_id, username, device)
+    }
+
+    fn clear(&self, pool_id: &str, username: &str) -> Result<()> {
+        (**self).clear(pool_id, username)
+    }
+}
+
+// One JSON file per pool and user, readable only by the current user. The device password is a
+// credential (it stands in for MFA), so keep the directory as private as the token cache.
+#[derive(Clone, Debug)]
+pub struct FileDeviceStore {
+    dir: PathBuf,
+}
+
+impl FileDeviceStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    // The devices directory next to FileTokenStore::in_cache_dir's files.
+    pub fn in_cache_dir() -> Result<Self> {
+        Ok(Self::new(
+            FileTokenStore::in_cache_dir()?.dir().join("devices"),
+        ))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, pool_id: &str, username: &str) -> PathBuf {
+        let id = hex::encode(sha256([pool_id, username].join("\0").as_bytes()));
+        self.dir.join(format!("{}.json", id))
+    }
+}
+
+impl DeviceStore for FileDeviceStore {
+    fn load(&self, pool_id: &str, username: &str) -> Result<Option<RememberedDevice>> {
+        let contents = match fs::read_to_string(self.path(pool_id, username)) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let value = json::parse(&contents)?;
+        // A hash collision or a hand-copied file; either way not this user's device.
+        if value.str_field("pool_id").as_deref() != Some(pool_id)
+            || value.str_field("username").as_deref() != Some(username)
+        {
+            return Ok(None);
+        }
+        let field = |name: &str| {
+            value.str_field(name).ok_or_else(|| {
+                CognitoSrpError::InvalidResponse(format!("saved device is missing {}", name))
+            })
+        };
+        Ok(Some(RememberedDevice {
+            device_key: field("device_key")?,
+            device_group_key: field("device_group_key")?,
+            device_password: SecretString::new(field("device_password")?),
+        }))
+    }
+
+    fn save(&self, pool_id: &str, username: &str, device: &RememberedDevice) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let value = Value::object([
+            ("pool_id", Value::from(pool_id)),
+            ("username", Value::from(username)),
+            ("device_key", Value::from(device.device_key.as_str())),
+            (
+                "device_group_key",
+                Value::from(device.device_group_key.as_str()),
+            ),
+            (
+                "device_password",
+                Value::from(device.device_password.expose_secret()),
+            ),
+        ]);
+        write_private(&self.path(pool_id, username), &value.to_string())
+    }
+
+    fn clear(&self, pool_id: &str, username: &str) -> Result<()> {
+        match fs::remove_file(self.path(pool_id, username)) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Client {
+    pub(crate) fn remembered_device(&self, username: &str) -> Result<Option<RememberedDevice>> {
+        match self.device_store() {
+            Some(store) => store.load(self.pool_id(), username),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn forget_remembered_device(&self, username: &str) {
+        if let Some(store) = self.device_store() {
+            store.clear(self.pool_id(), username).ok();
+        }
+    }
+
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_ConfirmDevice.html
+    // Registers the device Cognito just issued keys for, then saves it. Pools that leave
+    // remembering to the user answer UserConfirmationNecessary; configuring a DeviceStore is
+    // taken as that choice, so the device is marked remembered straight away.
+    pub(crate) fn confirm_device(
+        &self,
+        username: &str,
+        metadata: &NewDeviceMetadata,
+        access_token: &str,
+    ) -> Result<()> {
+        let Some(store) = self.device_store() else {
+            return Ok(());
+        };
+        let device_key = metadata.device_key.clone();
+        let device_group_key = metadata.device_group_key.clone();
+        let verifier =
+            self.random(|rng| DeviceVerifier::generate(&device_group_key, &device_key, rng))?;
+        let body = Value::object([
+            ("AccessToken", Value::from(access_token)),
+            ("DeviceKey", Value::from(device_key.as_str())),
+            (
+                "DeviceSecretVerifierConfig",
+                Value::object([
+                    ("PasswordVerifier", Value::from(verifier.verifier.as_str())),
+                    ("Salt", Value::from(verifier.salt.as_str())),
+                ]),
+            ),
+        ]);
+        let response = self.call("ConfirmDevice", body)?;
+        if response
+            .get("UserConfirmationNecessary")
+            .and_then(Value::as_bool)
+            == Some(true)
+        {
+            // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_UpdateDeviceStatus.html
+            let body = Value::object([
+                ("AccessToken", Value::from(access_token)),
+                ("DeviceKey", Value::from(device_key.as_str())),
+                ("DeviceRememberedStatus", Value::from("remembered")),
+            ]);
+            self.call("UpdateDeviceStatus", body)?;
+        }
+        store.save(
+            self.pool_id(),
+            username,
+            &RememberedDevice {
+                device_key,
+                device_group_key,
+                device_password: SecretString::new(verifier.password),
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_file_device_store() {
+        let dir = env::temp_dir().join(format!("cogneato-test-{}", rand::random::<u64>()));
+        let store = FileDeviceStore::new(&dir);
+        let device = RememberedDevice {
+            device_key: "us-east-1_0a1b2c3d-device".to_owned(),
+            device_group_key: "-group".to_owned(),
+            device_password: "device-password".into(),
+        };
+
+        assert_eq!(store.load("us-east-1_pool", "bob").unwrap(), None);
+        store.save("us-east-1_pool", "bob", &device).unwrap();
+        assert_eq!(
+            store.load("us-east-1_pool", "bob").unwrap(),
+            Some(device.clone())
+        );
+        assert_eq!(store.load("us-east-1_pool", "alice").unwrap(), None);
+        assert_eq!(store.load("us-east-1_other", "bob").unwrap(), None);
+
+        store.clear("us-east-1_pool", "bob").unwrap();
+        store.clear("us-east-1_pool", "bob").unwrap();
+        assert_eq!(store.load("us-east-1_pool", "bob").unwrap(), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
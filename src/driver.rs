@@ -0,0 +1,296 @@
+// Drives a sign-in from its first Authentication to tokens, whatever chain of challenges the pool
+// puts in between (MFA, a new password, Lambda-defined custom challenges, ...). Each challenge is
+// handed to a ChallengeResolver for its answer; the driver sends it with the challenge's session,
+// USERNAME and SECRET_HASH, and carries the next challenge's session forward:
+//
+//     let authentication = client.authenticate("bob", &password)?;
+//     let tokens = client.run_to_completion(authentication, &|challenge: &Challenge| {
+//         Ok(ChallengeAnswer::Code(prompt(challenge.name.as_str())?))
+//     })?;
+//
+// With the `async` feature, run_to_completion_async awaits an AsyncChallengeResolver instead, and
+// makes the blocking Cognito calls off the executor's threads.
+use crate::auth::{Authentication, Challenge};
+use crate::client::Client;
+use crate::error::Result;
+use crate::secret::SecretString;
+use crate::tokens::TokenSet;
+use crate::user::MfaType;
+
+// One challenge's answer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChallengeAnswer {
+    // SMS_MFA, SOFTWARE_TOKEN_MFA, EMAIL_OTP and SMS_OTP.
+    Code(String),
+    // NEW_PASSWORD_REQUIRED; checked against the client's password policy first.
+    NewPassword(SecretString),
+    // SELECT_MFA_TYPE.
+    MfaType(MfaType),
+    // WEB_AUTHN: the authenticator's PublicKeyCredential JSON.
+    Credential(String),
+    // Anything else, e.g. ANSWER for CUSTOM_CHALLENGE, as ChallengeResponses.
+    Responses(Vec<(String, String)>),
+}
+
+pub trait ChallengeResolver: Send + Sync {
+    // An error ends the sign-in and is returned from run_to_completion.
+    fn resolve(&self, challenge: &Challenge) -> Result<ChallengeAnswer>;
+}
+
+impl<F: Fn(&Challenge) -> Result<ChallengeAnswer> + Send + Sync> ChallengeResolver for F {
+    fn resolve(&self, challenge: &Challenge) -> Result<ChallengeAnswer> {
+        self(challenge)
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+pub trait AsyncChallengeResolver: Send + Sync {
+    async fn resolve(&self, challenge: &Challenge) -> Result<ChallengeAnswer>;
+}
+
+impl Client {
+    // Answers challenges until Cognito issues tokens. `authentication` is where the sign-in
+    // stands, e.g. from authenticate or authenticate_with_user_auth.
+    pub fn run_to_completion(
+        &self,
+        authentication: Authentication,
+        resolver: &dyn ChallengeResolver,
+    ) -> Result<TokenSet> {
+        self.drive(false, authentication, resolver)
+    }
+
+    // For sign-ins started with admin_authenticate or admin_authenticate_with_password.
+    pub fn admin_run_to_completion(
+        &self,
+        authentication: Authentication,
+        resolver: &dyn ChallengeResolver,
+    ) -> Result<TokenSet> {
+        self.drive(true, authentication, resolver)
+    }
+
+    #[cfg(feature = "async")]
+    pub async fn run_to_completion_async(
+        &self,
+        authentication: Authentication,
+        resolver: &dyn AsyncChallengeResolver,
+    ) -> Result<TokenSet> {
+        self.drive_async(false, authentication, resolver).await
+    }
+
+    #[cfg(feature = "async")]
+    pub async fn admin_run_to_completion_async(
+        &self,
+        authentication: Authentication,
+        resolver: &dyn AsyncChallengeResolver,
+    ) -> Result<TokenSet> {
+        self.drive_async(true, authentication, resolver).await
+    }
+
+    fn drive(
+        &self,
+        admin: bool,
+        mut authentication: Authentication,
+        resolver: &dyn ChallengeResolver,
+    ) -> Result<TokenSet> {
+        loop {
+            let challenge = match authentication {
+                Authentication::Tokens(tokens) => return Ok(tokens),
+                Authentication::Challenge(challenge) => challenge,
+            };
+            let answer = resolver.resolve(&challenge)?;
+            authentication = self.answer_challenge(admin, &challenge, answer)?;
+        }
+    }
+
+    #[cfg(feature = "async")]
+    async fn drive_async(
+        &self,
+        admin: bool,
+        mut authentication: Authentication,
+        resolver: &dyn AsyncChallengeResolver,
+    ) -> Result<TokenSet> {
+        loop {
+            let challenge = match authentication {
+                Authentication::Tokens(tokens) => return Ok(tokens),
+                Authentication::Challenge(challenge) => challenge,
+            };
+            let answer = resolver.resolve(&challenge).await?;
+            let client = self.clone();
+            authentication = tokio::task::spawn_blocking(move || {
+                client.answer_challenge(admin, &challenge, answer)
+            })
+            .await
+            .map_err(|_| crate::error::CognitoSrpError::TaskPanicked)??;
+        }
+    }
+
+    fn answer_challenge(
+        &self,
+        admin: bool,
+        challenge: &Challenge,
+        answer: ChallengeAnswer,
+    ) -> Result<Authentication> {
+        match (admin, answer) {
+            (false, ChallengeAnswer::Code(code)) => self.respond_to_mfa(challenge, &code),
+            (true, ChallengeAnswer::Code(code)) => self.admin_respond_to_mfa(challenge, &code),
+            (false, ChallengeAnswer::NewPassword(password)) => {
+                self.respond_to_new_password(challenge, password.expose_secret())
+            }
+            (true, ChallengeAnswer::NewPassword(password)) => {
+                self.admin_respond_to_new_password(challenge, password.expose_secret())
+            }
+            (false, ChallengeAnswer::MfaType(choice)) => {
+                self.respond_to_select_mfa_type(challenge, choice)
+            }
+            (true, ChallengeAnswer::MfaType(choice)) => {
+                self.admin_respond_to_select_mfa_type(challenge, choice)
+            }
+            (false, ChallengeAnswer::Credential(credential)) => {
+                self.respond_to_web_authn(challenge, &credential)
+            }
+            (true, ChallengeAnswer::Credential(credential)) => {
+                self.admin_respond_to_challenge(challenge, &[("CREDENTIAL", &credential)])
+            }
+            (admin, ChallengeAnswer::Responses(responses)) => {
+                let responses: Vec<(&str, &str)> = responses
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str()))
+                    .collect();
+                if admin {
+                    self.admin_respond_to_challenge(challenge, &responses)
+                } else {
+                    self.respond_to_challenge(challenge, &responses)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::ChallengeName;
+    use crate::credentials::AwsCredentials;
+    use crate::error::CognitoSrpError;
+    use crate::json;
+    use crate::transport::mock::MockTransport;
+
+    const TOKENS: &str =
+        r#"{"AuthenticationResult":{"AccessToken":"a","IdToken":"i","ExpiresIn":3600}}"#;
+
+    fn resolve(challenge: &Challenge) -> Result<ChallengeAnswer> {
+        Ok(match challenge.name {
+            ChallengeName::NewPasswordRequired => {
+                ChallengeAnswer::NewPassword("N3w-P@ssw0rd!".into())
+            }
+            ChallengeName::SelectMfaType => ChallengeAnswer::MfaType(MfaType::SoftwareToken),
+            ChallengeName::SoftwareTokenMfa => ChallengeAnswer::Code("123456".to_owned()),
+            ChallengeName::CustomChallenge => {
+                ChallengeAnswer::Responses(vec![("ANSWER".to_owned(), "42".to_owned())])
+            }
+            _ => {
+                return Err(CognitoSrpError::InvalidArgument(format!(
+                    "unexpected {}",
+                    challenge.name.as_str()
+                )))
+            }
+        })
+    }
+
+    #[test]
+    fn test_run_to_completion() {
+        let transport = MockTransport::new();
+        for body in [
+            r#"{"ChallengeName":"NEW_PASSWORD_REQUIRED","Session":"s1",
+                "ChallengeParameters":{"USERNAME":"bob-internal"}}"#,
+            r#"{"ChallengeName":"SELECT_MFA_TYPE","Session":"s2",
+                "ChallengeParameters":{"MFAS_CAN_CHOOSE":"[\"SMS_MFA\",\"SOFTWARE_TOKEN_MFA\"]"}}"#,
+            r#"{"ChallengeName":"SOFTWARE_TOKEN_MFA","Session":"s3","ChallengeParameters":{}}"#,
+            r#"{"ChallengeName":"CUSTOM_CHALLENGE","Session":"s4","ChallengeParameters":{}}"#,
+            TOKENS,
+        ] {
+            transport.respond(200, body);
+        }
+        let client = Client::new("us-east-1_pool", "client", transport.clone())
+            .unwrap()
+            .with_credentials(AwsCredentials::new("AKID", "secret"));
+        let authentication = client
+            .admin_authenticate_with_password("bob", "P@ssw0rd!")
+            .unwrap();
+        let tokens = client
+            .admin_run_to_completion(authentication, &resolve)
+            .unwrap();
+        assert_eq!(tokens.access_token, "a");
+
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests.len(), 5);
+        let answers: Vec<_> = requests[1..]
+            .iter()
+            .map(|request| json::parse(&request.body).unwrap())
+            .collect();
+        for (answer, (name, session)) in answers.iter().zip([
+            ("NEW_PASSWORD_REQUIRED", "s1"),
+            ("SELECT_MFA_TYPE", "s2"),
+            ("SOFTWARE_TOKEN_MFA", "s3"),
+            ("CUSTOM_CHALLENGE", "s4"),
+        ]) {
+            assert_eq!(answer.str_field("ChallengeName").unwrap(), name);
+            assert_eq!(answer.str_field("Session").unwrap(), session);
+            let responses = answer.get("ChallengeResponses").unwrap();
+            assert_eq!(responses.str_field("USERNAME").unwrap(), "bob-internal");
+        }
+        let responses = answers[3].get("ChallengeResponses").unwrap();
+        assert_eq!(responses.str_field("ANSWER").unwrap(), "42");
+    }
+
+    #[test]
+    fn test_resolver_error_stops() {
+        let transport = MockTransport::new();
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let challenge = Challenge {
+            name: ChallengeName::MfaSetup,
+            session: Some("s1".to_owned()),
+            parameters: Default::default(),
+            username: "bob".to_owned(),
+        };
+        assert!(matches!(
+            client.run_to_completion(Authentication::Challenge(challenge), &resolve),
+            Err(CognitoSrpError::InvalidArgument(_))
+        ));
+        assert!(transport.requests.lock().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_run_to_completion_async() {
+        struct Codes;
+
+        #[async_trait::async_trait]
+        impl AsyncChallengeResolver for Codes {
+            async fn resolve(&self, _: &Challenge) -> Result<ChallengeAnswer> {
+                Ok(ChallengeAnswer::Code("123456".to_owned()))
+            }
+        }
+
+        let transport = MockTransport::new();
+        transport.respond(200, TOKENS);
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let challenge = Challenge {
+            name: ChallengeName::SmsMfa,
+            session: Some("s1".to_owned()),
+            parameters: Default::default(),
+            username: "bob".to_owned(),
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let tokens = runtime
+            .block_on(client.run_to_completion_async(Authentication::Challenge(challenge), &Codes))
+            .unwrap();
+        assert_eq!(tokens.access_token, "a");
+        let answer = transport.last_body();
+        let responses = answer.get("ChallengeResponses").unwrap();
+        assert_eq!(responses.str_field("SMS_MFA_CODE").unwrap(), "123456");
+    }
+}
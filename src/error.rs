@@ -0,0 +1,407 @@
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
+
+// # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/CommonErrors.html
+// The exceptions callers commonly branch on; anything else is kept verbatim in `Other`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ServiceErrorKind {
+    NotAuthorized,
+    UserNotConfirmed,
+    UserNotFound,
+    UsernameExists,
+    PasswordResetRequired,
+    CodeMismatch,
+    ExpiredCode,
+    InvalidPassword,
+    InvalidParameter,
+    LimitExceeded,
+    TooManyRequests,
+    Throttling,
+    TooManyFailedAttempts,
+    ResourceNotFound,
+    InternalError,
+    Other(String),
+}
+
+impl ServiceErrorKind {
+    // Accepts both "NotAuthorizedException" and the namespaced
+    // "com.amazonaws.cognito#NotAuthorizedException" form of `__type`.
+    pub fn parse(type_name: &str) -> Self {
+        let name = type_name.rsplit('#').next().unwrap_or(type_name);
+        match name {
+            "NotAuthorizedException" => ServiceErrorKind::NotAuthorized,
+            "UserNotConfirmedException" => ServiceErrorKind::UserNotConfirmed,
+            "UserNotFoundException" => ServiceErrorKind::UserNotFound,
+            "UsernameExistsException" => ServiceErrorKind::UsernameExists,
+            "PasswordResetRequiredException" => ServiceErrorKind::PasswordResetRequired,
+            "CodeMismatchException" => ServiceErrorKind::CodeMismatch,
+            "ExpiredCodeException" => ServiceErrorKind::ExpiredCode,
+            "InvalidPasswordException" => ServiceErrorKind::InvalidPassword,
+            "InvalidParameterException" => ServiceErrorKind::InvalidParameter,
+            "LimitExceededException" => ServiceErrorKind::LimitExceeded,
+            "TooManyRequestsException" => ServiceErrorKind::TooManyRequests,
+            "ThrottlingException" => ServiceErrorKind::Throttling,
+            "TooManyFailedAttemptsException" => ServiceErrorKind::TooManyFailedAttempts,
+            "ResourceNotFoundException" => ServiceErrorKind::ResourceNotFound,
+            "InternalErrorException" => ServiceErrorKind::InternalError,
+            other => ServiceErrorKind::Other(other.to_owned()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            ServiceErrorKind::NotAuthorized => "NotAuthorizedException",
+            ServiceErrorKind::UserNotConfirmed => "UserNotConfirmedException",
+            ServiceErrorKind::UserNotFound => "UserNotFoundException",
+            ServiceErrorKind::UsernameExists => "UsernameExistsException",
+            ServiceErrorKind::PasswordResetRequired => "PasswordResetRequiredException",
+            ServiceErrorKind::CodeMismatch => "CodeMismatchException",
+            ServiceErrorKind::ExpiredCode => "ExpiredCodeException",
+            ServiceErrorKind::InvalidPassword => "InvalidPasswordException",
+            ServiceErrorKind::InvalidParameter => "InvalidParameterException",
+            ServiceErrorKind::LimitExceeded => "LimitExceededException",
+            ServiceErrorKind::TooManyRequests => "TooManyRequestsException",
+            ServiceErrorKind::Throttling => "ThrottlingException",
+            ServiceErrorKind::TooManyFailedAttempts => "TooManyFailedAttemptsException",
+            ServiceErrorKind::ResourceNotFound => "ResourceNotFoundException",
+            ServiceErrorKind::InternalError => "InternalErrorException",
+            ServiceErrorKind::Other(name) => name,
+        }
+    }
+}
+
+// An error response from cognito-idp itself, as opposed to a transport or parsing failure.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServiceError {
+    pub operation: String,
+    pub status: u16,
+    pub kind: ServiceErrorKind,
+    pub message: String,
+}
+
+impl ServiceError {
+    // Cognito is shedding load; back off before trying again.
+    pub fn is_throttle(&self) -> bool {
+        self.status == 429
+            || matches!(
+                self.kind,
+                ServiceErrorKind::TooManyRequests | ServiceErrorKind::Throttling
+            )
+    }
+
+    // The same request may succeed later: throttling or a fault on Cognito's side.
+    pub fn is_retryable(&self) -> bool {
+        self.is_throttle() || self.status >= 500 || self.kind == ServiceErrorKind::InternalError
+    }
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} failed: {}: {}",
+            self.operation,
+            self.kind.as_str(),
+            self.message
+        )
+    }
+}
+
+impl Error for ServiceError {}
+
+// A scope (from the access token's `scope`) or a group (`cognito:groups`) that a token can be
+// required to carry; see jwt::Claims::require_all.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Grant {
+    Scope(String),
+    Group(String),
+}
+
+impl Grant {
+    pub fn scope(scope: &str) -> Self {
+        Grant::Scope(scope.to_owned())
+    }
+
+    pub fn group(group: &str) -> Self {
+        Grant::Group(group.to_owned())
+    }
+}
+
+impl fmt::Display for Grant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Grant::Scope(scope) => write!(f, "scope {}", scope),
+            Grant::Group(group) => write!(f, "group {}", group),
+        }
+    }
+}
+
+// Why a flow stopped before Cognito was asked anything more; see CognitoSrpError::Interrupted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interruption {
+    Cancelled,
+    DeadlineExceeded,
+}
+
+impl fmt::Display for Interruption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Interruption::Cancelled => "cancelled",
+            Interruption::DeadlineExceeded => "deadline exceeded",
+        })
+    }
+}
+
+// Every way a call into this crate can fail. New variants may be added in minor releases.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CognitoSrpError {
+    // A hex value (SRP_B, SALT, ...) did not parse.
+    InvalidHex(String),
+    // SRP_B was 0 mod N, which would let a malicious server fix the session key.
+    InvalidServerB,
+    // SRP_A was 0 mod N: on the server, a client forcing S = 0; on the client, a broken group.
+    InvalidClientA,
+    // u = H(A | B) came out as 0, which would take the password out of S.
+    InvalidU,
+    Hkdf,
+    InvalidPoolId(String),
+    // Something the client needs (credentials, a client secret, ...) is missing.
+    Configuration(String),
+    InvalidArgument(String),
+    // The transport failed before any HTTP response arrived.
+    Transport(Box<dyn Error + Send + Sync>),
+    // A non-Cognito endpoint (e.g. the JWKS document) returned an unexpected status.
+    Http {
+        status: u16,
+    },
+    Service(ServiceError),
+    ChallengeMismatch {
+        expected: String,
+        actual: Option<String>,
+    },
+    // A response was missing fields or was not valid JSON.
+    InvalidResponse(String),
+    InvalidToken(String),
+    // A valid token without the scopes or groups asked for. `any_of` means none of `missing`
+    // was present (require_any); otherwise each one listed is absent (require_all).
+    Forbidden {
+        missing: Vec<Grant>,
+        any_of: bool,
+    },
+    SignedOut(String),
+    // The client's cancel token or deadline stopped a request from being sent. `session` is the
+    // Cognito Session that request would have answered, for resuming the flow instead of
+    // starting over.
+    Interrupted {
+        reason: Interruption,
+        session: Option<String>,
+    },
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    TaskPanicked,
+    // A token refresh failed; every caller that was waiting on it gets the same error.
+    RefreshFailed(Arc<CognitoSrpError>),
+}
+
+pub type Result<T, E = CognitoSrpError> = core::result::Result<T, E>;
+
+impl CognitoSrpError {
+    // The Cognito exception behind this error, if Cognito answered at all.
+    pub fn service_kind(&self) -> Option<&ServiceErrorKind> {
+        match self {
+            CognitoSrpError::Service(err) => Some(&err.kind),
+            CognitoSrpError::RefreshFailed(err) => err.service_kind(),
+            _ => None,
+        }
+    }
+
+    pub fn is_throttle(&self) -> bool {
+        match self {
+            CognitoSrpError::Service(err) => err.is_throttle(),
+            CognitoSrpError::RefreshFailed(err) => err.is_throttle(),
+            _ => false,
+        }
+    }
+
+    // Transport failures count as retryable: the request may never have reached Cognito.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            CognitoSrpError::Service(err) => err.is_retryable(),
+            CognitoSrpError::Transport(_) => true,
+            CognitoSrpError::RefreshFailed(err) => err.is_retryable(),
+            _ => false,
+        }
+    }
+
+    // The Session an interrupted flow can be resumed with.
+    pub fn interrupted_session(&self) -> Option<&str> {
+        match self {
+            CognitoSrpError::Interrupted { session, .. } => session.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CognitoSrpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CognitoSrpError::InvalidHex(err) => write!(f, "invalid hex: {}", err),
+            CognitoSrpError::InvalidServerB => write!(f, "server sent an invalid SRP_B"),
+            CognitoSrpError::InvalidClientA => write!(f, "SRP_A is 0 mod N"),
+            CognitoSrpError::InvalidU => write!(f, "SRP scrambling parameter u is 0"),
+            CognitoSrpError::Hkdf => write!(f, "HKDF expansion failed"),
+            CognitoSrpError::InvalidPoolId(pool_id) => {
+                write!(f, "invalid user pool id: {}", pool_id)
+            }
+            CognitoSrpError::Configuration(message) | CognitoSrpError::InvalidArgument(message) => {
+                f.write_str(message)
+            }
+            CognitoSrpError::Transport(err) => write!(f, "transport error: {}", err),
+            CognitoSrpError::Http { status } => write!(f, "unexpected HTTP status {}", status),
+            CognitoSrpError::Service(err) => err.fmt(f),
+            CognitoSrpError::ChallengeMismatch { expected, actual } => write!(
+                f,
+                "expected a {} challenge, got {}",
+                expected,
+                actual.as_deref().unwrap_or("none")
+            ),
+            CognitoSrpError::InvalidResponse(message) => {
+                write!(f, "invalid response: {}", message)
+            }
+            CognitoSrpError::InvalidToken(message) => write!(f, "invalid token: {}", message),
+            CognitoSrpError::Forbidden { missing, any_of } => {
+                f.write_str(if *any_of {
+                    "token has none of "
+                } else {
+                    "token lacks "
+                })?;
+                for (i, grant) in missing.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", grant)?;
+                }
+                Ok(())
+            }
+            CognitoSrpError::SignedOut(reason) => write!(f, "session is signed out: {}", reason),
+            CognitoSrpError::Interrupted { reason, session } => {
+                write!(f, "flow {}", reason)?;
+                if session.is_some() {
+                    f.write_str(" (resumable)")?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "std")]
+            CognitoSrpError::Io(err) => err.fmt(f),
+            CognitoSrpError::TaskPanicked => write!(f, "background task panicked"),
+            CognitoSrpError::RefreshFailed(err) => write!(f, "token refresh failed: {}", err),
+        }
+    }
+}
+
+impl Error for CognitoSrpError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CognitoSrpError::Transport(err) => Some(err.as_ref()),
+            CognitoSrpError::Service(err) => Some(err),
+            #[cfg(feature = "std")]
+            CognitoSrpError::Io(err) => Some(err),
+            CognitoSrpError::RefreshFailed(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<ServiceError> for CognitoSrpError {
+    fn from(err: ServiceError) -> Self {
+        CognitoSrpError::Service(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for CognitoSrpError {
+    fn from(err: std::io::Error) -> Self {
+        CognitoSrpError::Io(err)
+    }
+}
+
+impl From<hex::FromHexError> for CognitoSrpError {
+    fn from(err: hex::FromHexError) -> Self {
+        CognitoSrpError::InvalidHex(err.to_string())
+    }
+}
+
+impl From<num_bigint::ParseBigIntError> for CognitoSrpError {
+    fn from(err: num_bigint::ParseBigIntError) -> Self {
+        CognitoSrpError::InvalidHex(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_and_source() {
+        let err = CognitoSrpError::from(ServiceError {
+            operation: "SignUp".to_owned(),
+            status: 400,
+            kind: ServiceErrorKind::parse("UsernameExistsException"),
+            message: "taken".to_owned(),
+        });
+        assert_eq!(
+            err.to_string(),
+            "SignUp failed: UsernameExistsException: taken"
+        );
+        assert!(err.source().is_some());
+        assert_eq!(err.service_kind(), Some(&ServiceErrorKind::UsernameExists));
+        assert!(!err.is_retryable());
+
+        let err = CognitoSrpError::ChallengeMismatch {
+            expected: "PASSWORD_VERIFIER".to_owned(),
+            actual: None,
+        };
+        assert_eq!(
+            err.to_string(),
+            "expected a PASSWORD_VERIFIER challenge, got none"
+        );
+        assert!(matches!(
+            CognitoSrpError::from(hex::decode("zz").unwrap_err()),
+            CognitoSrpError::InvalidHex(_)
+        ));
+    }
+
+    #[test]
+    fn test_service_error_kinds() {
+        let error = |status: u16, type_name: &str| ServiceError {
+            operation: "InitiateAuth".to_owned(),
+            status,
+            kind: ServiceErrorKind::parse(type_name),
+            message: String::new(),
+        };
+        assert_eq!(
+            ServiceErrorKind::parse("com.amazonaws.cognito#NotAuthorizedException"),
+            ServiceErrorKind::NotAuthorized
+        );
+        assert_eq!(
+            ServiceErrorKind::parse("SomethingNewException").as_str(),
+            "SomethingNewException"
+        );
+
+        let throttled = error(400, "TooManyRequestsException");
+        assert!(throttled.is_throttle() && throttled.is_retryable());
+        assert!(error(400, "ThrottlingException").is_throttle());
+        let fault = error(500, "InternalErrorException");
+        assert!(!fault.is_throttle() && fault.is_retryable());
+        assert!(!error(400, "CodeMismatchException").is_retryable());
+        assert!(CognitoSrpError::Transport("reset".into()).is_retryable());
+        assert!(!CognitoSrpError::InvalidServerB.is_retryable());
+    }
+}
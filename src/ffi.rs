@@ -0,0 +1,321 @@
+// A C ABI over srp::SrpSession for C, C++ and Swift callers that do their own HTTP. Run
+// `cbindgen --config cbindgen.toml --output cog_neat_oh.h` for the header, and
+// `cargo rustc --release --features ffi --crate-type staticlib` (or cdylib) for the library.
+//
+// Conventions: handles are opaque and freed with their own `_free` function; every string the
+// library returns is NUL-terminated UTF-8 owned by the caller, released with `cog_string_free`.
+// Functions that can fail return a CogStatus, and `cog_last_error` describes the most recent
+// failure on the calling thread.
+use crate::clock::{cognito_timestamp, Clock, SystemClock};
+use crate::error::CognitoSrpError;
+use crate::srp::SrpSession;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CogStatus {
+    Ok = 0,
+    // A NULL pointer or a string that is not UTF-8.
+    NullOrInvalidUtf8 = 1,
+    // Bad input: pool id, hex, base64 or a missing challenge parameter.
+    InvalidArgument = 2,
+    Error = 3,
+}
+
+// One SRP handshake; create it for InitiateAuth and keep it until the PASSWORD_VERIFIER answer.
+pub struct CogSrpSession(SrpSession);
+
+// The ChallengeResponses for PASSWORD_VERIFIER. Add SECRET_HASH yourself for app clients with
+// a secret, computed over `username` (USER_ID_FOR_SRP, not the alias the user typed).
+#[repr(C)]
+pub struct CogPasswordVerifierResponse {
+    pub username: *mut c_char,
+    pub timestamp: *mut c_char,
+    pub secret_block: *mut c_char,
+    pub signature: *mut c_char,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn fail(status: CogStatus, message: String) -> CogStatus {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    status
+}
+
+fn fail_with(err: CognitoSrpError) -> CogStatus {
+    let status = match err {
+        CognitoSrpError::InvalidHex(_)
+        | CognitoSrpError::InvalidServerB
+        | CognitoSrpError::InvalidClientA
+        | CognitoSrpError::InvalidU
+        | CognitoSrpError::InvalidPoolId(_)
+        | CognitoSrpError::InvalidArgument(_)
+        | CognitoSrpError::InvalidResponse(_) => CogStatus::InvalidArgument,
+        _ => CogStatus::Error,
+    };
+    fail(status, err.to_string())
+}
+
+// SAFETY (callers): `ptr` is NULL or a NUL-terminated string valid for the call.
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, CogStatus> {
+    if ptr.is_null() {
+        return Err(fail(
+            CogStatus::NullOrInvalidUtf8,
+            format!("{} is NULL", name),
+        ));
+    }
+    CStr::from_ptr(ptr).to_str().map_err(|_| {
+        fail(
+            CogStatus::NullOrInvalidUtf8,
+            format!("{} is not UTF-8", name),
+        )
+    })
+}
+
+fn into_c_string(value: String) -> *mut c_char {
+    CString::new(value).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+// The message for the last failure on this thread, or NULL. Owned by the library and valid
+// until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn cog_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |m| m.as_ptr()))
+}
+
+// # Safety
+// `s` is NULL or a string returned by this library that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn cog_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+// Starts a handshake for `pool_id` ("us-east-1_XXXXXXXXX"), writing the handle to `out`.
+//
+// # Safety
+// `pool_id` is a NUL-terminated string and `out` points to writable storage for a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn cog_srp_session_new(
+    pool_id: *const c_char,
+    out: *mut *mut CogSrpSession,
+) -> CogStatus {
+    if out.is_null() {
+        return fail(CogStatus::NullOrInvalidUtf8, "out is NULL".to_owned());
+    }
+    let pool_id = match str_arg(pool_id, "pool_id") {
+        Ok(pool_id) => pool_id,
+        Err(status) => return status,
+    };
+    match SrpSession::new(pool_id, &mut rand::thread_rng()) {
+        Ok(session) => {
+            *out = Box::into_raw(Box::new(CogSrpSession(session)));
+            CogStatus::Ok
+        }
+        Err(err) => fail_with(err),
+    }
+}
+
+// # Safety
+// `session` is NULL or a handle from cog_srp_session_new that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn cog_srp_session_free(session: *mut CogSrpSession) {
+    if !session.is_null() {
+        drop(Box::from_raw(session));
+    }
+}
+
+// SRP_A for InitiateAuth's AuthParameters; NULL if `session` is NULL.
+//
+// # Safety
+// `session` is NULL or a live handle from cog_srp_session_new.
+#[no_mangle]
+pub unsafe extern "C" fn cog_srp_session_srp_a(session: *const CogSrpSession) -> *mut c_char {
+    match session.as_ref() {
+        Some(session) => into_c_string(session.0.srp_a()),
+        None => ptr::null_mut(),
+    }
+}
+
+// Answers the PASSWORD_VERIFIER challenge. The string arguments are its ChallengeParameters
+// USERNAME, USER_ID_FOR_SRP, SALT, SRP_B and SECRET_BLOCK; `timestamp` may be NULL for now.
+// On success `out` is filled in and must be released with cog_password_verifier_response_free.
+//
+// # Safety
+// `session` is a live handle, every string argument except `timestamp` is a NUL-terminated
+// string, and `out` points to a writable CogPasswordVerifierResponse.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn cog_srp_session_process_challenge(
+    session: *const CogSrpSession,
+    username: *const c_char,
+    user_id_for_srp: *const c_char,
+    salt: *const c_char,
+    srp_b: *const c_char,
+    secret_block: *const c_char,
+    password: *const c_char,
+    timestamp: *const c_char,
+    out: *mut CogPasswordVerifierResponse,
+) -> CogStatus {
+    let (Some(session), false) = (session.as_ref(), out.is_null()) else {
+        return fail(
+            CogStatus::NullOrInvalidUtf8,
+            "session or out is NULL".to_owned(),
+        );
+    };
+    let mut parameters = BTreeMap::new();
+    for (name, value) in [
+        ("USERNAME", username),
+        ("USER_ID_FOR_SRP", user_id_for_srp),
+        ("SALT", salt),
+        ("SRP_B", srp_b),
+        ("SECRET_BLOCK", secret_block),
+    ] {
+        match str_arg(value, name) {
+            Ok(value) => parameters.insert(name.to_owned(), value.to_owned()),
+            Err(status) => return status,
+        };
+    }
+    let password = match str_arg(password, "password") {
+        Ok(password) => password,
+        Err(status) => return status,
+    };
+    let timestamp = if timestamp.is_null() {
+        cognito_timestamp(SystemClock.now())
+    } else {
+        match str_arg(timestamp, "timestamp") {
+            Ok(timestamp) => timestamp.to_owned(),
+            Err(status) => return status,
+        }
+    };
+    let mut responses = match session
+        .0
+        .process_challenge(&parameters, password, &timestamp)
+    {
+        Ok(responses) => responses,
+        Err(err) => return fail_with(err),
+    };
+    let mut take = |name: &str| into_c_string(responses.remove(name).unwrap_or_default());
+    *out = CogPasswordVerifierResponse {
+        username: take("USERNAME"),
+        timestamp: take("TIMESTAMP"),
+        secret_block: take("PASSWORD_CLAIM_SECRET_BLOCK"),
+        signature: take("PASSWORD_CLAIM_SIGNATURE"),
+    };
+    CogStatus::Ok
+}
+
+// Frees the strings inside `response` (not `response` itself) and sets them to NULL.
+//
+// # Safety
+// `response` is NULL or was filled in by cog_srp_session_process_challenge.
+#[no_mangle]
+pub unsafe extern "C" fn cog_password_verifier_response_free(
+    response: *mut CogPasswordVerifierResponse,
+) {
+    if let Some(response) = response.as_mut() {
+        for field in [
+            &mut response.username,
+            &mut response.timestamp,
+            &mut response.secret_block,
+            &mut response.signature,
+        ] {
+            cog_string_free(*field);
+            *field = ptr::null_mut();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::srp::server::{PasswordVerifier, ServerSession};
+
+    #[test]
+    fn test_round_trip() {
+        let rng = &mut rand::thread_rng();
+        let verifier =
+            PasswordVerifier::generate("us-east-1_Pool123", "bob-internal", "P@ssw0rd!", rng)
+                .unwrap();
+        let server = ServerSession::new(&verifier, rng);
+        let c = |s: &str| CString::new(s).unwrap();
+        let (salt, srp_b, block) = (
+            c(server.salt()),
+            c(&server.srp_b()),
+            c(server.secret_block()),
+        );
+
+        unsafe {
+            let mut session = ptr::null_mut();
+            let pool_id = c("us-east-1_Pool123");
+            assert_eq!(
+                cog_srp_session_new(pool_id.as_ptr(), &mut session),
+                CogStatus::Ok
+            );
+            let srp_a = cog_srp_session_srp_a(session);
+            let mut response = CogPasswordVerifierResponse {
+                username: ptr::null_mut(),
+                timestamp: ptr::null_mut(),
+                secret_block: ptr::null_mut(),
+                signature: ptr::null_mut(),
+            };
+            let (user, password) = (c("bob-internal"), c("P@ssw0rd!"));
+            let status = cog_srp_session_process_challenge(
+                session,
+                user.as_ptr(),
+                user.as_ptr(),
+                salt.as_ptr(),
+                srp_b.as_ptr(),
+                block.as_ptr(),
+                password.as_ptr(),
+                ptr::null(),
+                &mut response,
+            );
+            assert_eq!(status, CogStatus::Ok);
+            let read = |p: *mut c_char| CStr::from_ptr(p).to_str().unwrap().to_owned();
+            server
+                .verify(
+                    &read(srp_a),
+                    &read(response.timestamp),
+                    &read(response.signature),
+                )
+                .unwrap();
+            assert_eq!(read(response.username), "bob-internal");
+
+            let status = cog_srp_session_process_challenge(
+                session,
+                user.as_ptr(),
+                ptr::null(),
+                salt.as_ptr(),
+                srp_b.as_ptr(),
+                block.as_ptr(),
+                password.as_ptr(),
+                ptr::null(),
+                &mut response,
+            );
+            assert_eq!(status, CogStatus::NullOrInvalidUtf8);
+            assert_eq!(
+                CStr::from_ptr(cog_last_error()).to_str().unwrap(),
+                "USER_ID_FOR_SRP is NULL"
+            );
+
+            cog_password_verifier_response_free(&mut response);
+            assert!(response.signature.is_null());
+            cog_string_free(srp_a);
+            cog_srp_session_free(session);
+
+            let bad = c("nounderscore");
+            assert_eq!(
+                cog_srp_session_new(bad.as_ptr(), &mut session),
+                CogStatus::InvalidArgument
+            );
+        }
+    }
+}
@@ -0,0 +1,474 @@
+// The SRP sign-in as typestates. Each state offers only the answers Cognito accepts next, and
+// answering consumes it, so answering MFA before the password, or reusing the SRP ephemeral of
+// an answered PASSWORD_VERIFIER, does not compile:
+//
+//     let tokens = match AwaitingPasswordVerifier::start(&client, "bob")?.answer(password)? {
+//         Step::Authenticated(done) => done.into_tokens(),
+//         Step::Mfa(mfa) => match mfa.answer(&read_code())? { .. },
+//         ..
+//     };
+//
+// Step is also the dynamic driver for generic code such as a prompt loop: Step::start, then
+// Step::answer with whatever the current state asks for until it is Authenticated.
+//
+// A flow started on a client with a deadline or cancel token (crate::cancel) stops with
+// CognitoSrpError::Interrupted. Once past the password there is a Cognito Session to go back to:
+// keep the challenge (or a SerializableSession of it) and Step::resume it, within the Session's
+// few minutes, instead of asking for the password again.
+use crate::auth::{Authentication, Challenge, ChallengeName};
+use crate::client::Client;
+use crate::error::{CognitoSrpError, Result};
+use crate::software_token::SoftwareTokenSetup;
+use crate::srp::SrpSession;
+use crate::tokens::TokenSet;
+use crate::user::MfaType;
+use crate::wire::AuthResponse;
+
+// InitiateAuth has been answered with PASSWORD_VERIFIER; holds the SRP ephemeral that sent SRP_A.
+pub struct AwaitingPasswordVerifier {
+    client: Client,
+    admin: bool,
+    srp: SrpSession,
+    response: AuthResponse,
+}
+
+impl AwaitingPasswordVerifier {
+    pub fn start(client: &Client, username: &str) -> Result<Self> {
+        Self::begin(client, false, username)
+    }
+
+    // Through AdminInitiateAuth and AdminRespondToAuthChallenge, as admin_authenticate.
+    pub fn start_admin(client: &Client, username: &str) -> Result<Self> {
+        Self::begin(client, true, username)
+    }
+
+    fn begin(client: &Client, admin: bool, username: &str) -> Result<Self> {
+        let (srp, response) = client.initiate_srp(admin, &client.normalize_username(username))?;
+        Ok(Self {
+            client: client.clone(),
+            admin,
+            srp,
+            response,
+        })
+    }
+
+    pub fn answer(self, password: &str) -> Result<Step> {
+        let authentication =
+            self.client
+                .answer_password_verifier(self.admin, self.srp, &self.response, password)?;
+        Ok(Step::next(self.client, self.admin, authentication))
+    }
+}
+
+// SMS_MFA, SOFTWARE_TOKEN_MFA or EMAIL_OTP.
+pub struct AwaitingMfa {
+    client: Client,
+    admin: bool,
+    challenge: Challenge,
+}
+
+impl AwaitingMfa {
+    pub fn challenge(&self) -> &Challenge {
+        &self.challenge
+    }
+
+    pub fn answer(self, code: &str) -> Result<Step> {
+        let authentication = if self.admin {
+            self.client.admin_respond_to_mfa(&self.challenge, code)?
+        } else {
+            self.client.respond_to_mfa(&self.challenge, code)?
+        };
+        Ok(Step::next(self.client, self.admin, authentication))
+    }
+}
+
+// NEW_PASSWORD_REQUIRED, for users created with a temporary password.
+pub struct AwaitingNewPassword {
+    client: Client,
+    admin: bool,
+    challenge: Challenge,
+}
+
+impl AwaitingNewPassword {
+    pub fn challenge(&self) -> &Challenge {
+        &self.challenge
+    }
+
+    pub fn answer(self, new_password: &str) -> Result<Step> {
+        let authentication = if self.admin {
+            self.client
+                .admin_respond_to_new_password(&self.challenge, new_password)?
+        } else {
+            self.client
+                .respond_to_new_password(&self.challenge, new_password)?
+        };
+        Ok(Step::next(self.client, self.admin, authentication))
+    }
+}
+
+// SELECT_MFA_TYPE: the user has more than one MFA method and picks the one to use now.
+pub struct AwaitingMfaChoice {
+    client: Client,
+    admin: bool,
+    challenge: Challenge,
+}
+
+impl AwaitingMfaChoice {
+    pub fn challenge(&self) -> &Challenge {
+        &self.challenge
+    }
+
+    pub fn choices(&self) -> Vec<MfaType> {
+        self.challenge.mfa_choices()
+    }
+
+    // Leads to Step::Mfa for the chosen method.
+    pub fn answer(self, choice: MfaType) -> Result<Step> {
+        let authentication = if self.admin {
+            self.client
+                .admin_respond_to_select_mfa_type(&self.challenge, choice)?
+        } else {
+            self.client
+                .respond_to_select_mfa_type(&self.challenge, choice)?
+        };
+        Ok(Step::next(self.client, self.admin, authentication))
+    }
+}
+
+// MFA_SETUP: the pool requires MFA and the user has none yet.
+pub struct AwaitingMfaSetup {
+    client: Client,
+    admin: bool,
+    challenge: Challenge,
+}
+
+impl AwaitingMfaSetup {
+    pub fn challenge(&self) -> &Challenge {
+        &self.challenge
+    }
+
+    // Gets an authenticator secret to show the user.
+    pub fn associate(self) -> Result<AwaitingTotpCode> {
+        let setup = self.client.begin_mfa_setup(&self.challenge)?;
+        Ok(AwaitingTotpCode {
+            client: self.client,
+            admin: self.admin,
+            setup,
+        })
+    }
+}
+
+// The first code from the authenticator just set up, which also finishes the sign-in.
+pub struct AwaitingTotpCode {
+    client: Client,
+    admin: bool,
+    setup: SoftwareTokenSetup,
+}
+
+impl AwaitingTotpCode {
+    pub fn setup(&self) -> &SoftwareTokenSetup {
+        &self.setup
+    }
+
+    pub fn answer(self, code: &str) -> Result<Step> {
+        let authentication = if self.admin {
+            self.client
+                .admin_complete_mfa_setup(&self.setup, code, None)?
+        } else {
+            self.client.complete_mfa_setup(&self.setup, code, None)?
+        };
+        Ok(Step::next(self.client, self.admin, authentication))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Authenticated {
+    tokens: TokenSet,
+}
+
+impl Authenticated {
+    pub fn tokens(&self) -> &TokenSet {
+        &self.tokens
+    }
+
+    pub fn into_tokens(self) -> TokenSet {
+        self.tokens
+    }
+}
+
+// The states that hold a Client are boxed, which keeps a Step small to move around.
+pub enum Step {
+    PasswordVerifier(Box<AwaitingPasswordVerifier>),
+    Mfa(Box<AwaitingMfa>),
+    NewPassword(Box<AwaitingNewPassword>),
+    MfaSetup(Box<AwaitingMfaSetup>),
+    SelectMfaType(Box<AwaitingMfaChoice>),
+    Authenticated(Authenticated),
+    // Any other challenge (CUSTOM_CHALLENGE, ...); answer it with
+    // Client::respond_to_challenge.
+    Other(Challenge),
+}
+
+impl Step {
+    pub fn start(client: &Client, username: &str) -> Result<Self> {
+        AwaitingPasswordVerifier::start(client, username)
+            .map(|state| Step::PasswordVerifier(Box::new(state)))
+    }
+
+    // Picks a flow back up at a challenge it was interrupted at, e.g. one restored from a
+    // SerializableSession.
+    pub fn resume(client: &Client, challenge: Challenge) -> Self {
+        Self::next(client.clone(), false, Authentication::Challenge(challenge))
+    }
+
+    pub fn resume_admin(client: &Client, challenge: Challenge) -> Self {
+        Self::next(client.clone(), true, Authentication::Challenge(challenge))
+    }
+
+    fn next(client: Client, admin: bool, authentication: Authentication) -> Self {
+        let challenge = match authentication {
+            Authentication::Tokens(tokens) => return Step::Authenticated(Authenticated { tokens }),
+            Authentication::Challenge(challenge) => challenge,
+        };
+        match challenge.name {
+            ChallengeName::SmsMfa
+            | ChallengeName::SoftwareTokenMfa
+            | ChallengeName::EmailOtp
+            | ChallengeName::SmsOtp => Step::Mfa(Box::new(AwaitingMfa {
+                client,
+                admin,
+                challenge,
+            })),
+            ChallengeName::NewPasswordRequired => {
+                Step::NewPassword(Box::new(AwaitingNewPassword {
+                    client,
+                    admin,
+                    challenge,
+                }))
+            }
+            ChallengeName::MfaSetup => Step::MfaSetup(Box::new(AwaitingMfaSetup {
+                client,
+                admin,
+                challenge,
+            })),
+            ChallengeName::SelectMfaType => Step::SelectMfaType(Box::new(AwaitingMfaChoice {
+                client,
+                admin,
+                challenge,
+            })),
+            _ => Step::Other(challenge),
+        }
+    }
+
+    // Feeds `input` (password, MFA code, new password, or an MFA method such as "SMS_MFA") to
+    // whichever state this is.
+    pub fn answer(self, input: &str) -> Result<Step> {
+        match self {
+            Step::PasswordVerifier(state) => state.answer(input),
+            Step::Mfa(state) => state.answer(input),
+            Step::NewPassword(state) => state.answer(input),
+            Step::SelectMfaType(state) => match MfaType::parse(input) {
+                Some(choice) => state.answer(choice),
+                None => Err(CognitoSrpError::InvalidArgument(format!(
+                    "{} is not an MFA method",
+                    input
+                ))),
+            },
+            // There is a secret to show before there can be a code to answer with.
+            Step::MfaSetup(_) => Err(CognitoSrpError::InvalidArgument(
+                "MFA_SETUP needs associate".to_owned(),
+            )),
+            Step::Authenticated(_) => Err(CognitoSrpError::InvalidArgument(
+                "already authenticated".to_owned(),
+            )),
+            Step::Other(challenge) => Err(CognitoSrpError::InvalidArgument(format!(
+                "{} needs respond_to_challenge",
+                challenge.name.as_str()
+            ))),
+        }
+    }
+
+    pub fn tokens(&self) -> Option<&TokenSet> {
+        match self {
+            Step::Authenticated(done) => Some(done.tokens()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cancel::CancelToken;
+    use crate::clock::FixedClock;
+    use crate::error::Interruption;
+    use crate::transport::mock::MockTransport;
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    const PASSWORD_VERIFIER: &str = r#"{"ChallengeName":"PASSWORD_VERIFIER","ChallengeParameters":{
+        "SALT":"ab12cd34ef56","SECRET_BLOCK":"b3BhcXVlIHNlY3JldCBibG9jayBmcm9tIGNvZ25pdG8=",
+        "SRP_B":"82f7e185b83288814ad86154a1bfa657f70d27ec43f507a618d89eb5ac9f90e9",
+        "USERNAME":"bob-internal","USER_ID_FOR_SRP":"bob-internal"}}"#;
+    const MFA: &str =
+        r#"{"ChallengeName":"SOFTWARE_TOKEN_MFA","Session":"s1","ChallengeParameters":{}}"#;
+    const TOKENS: &str =
+        r#"{"AuthenticationResult":{"AccessToken":"a","IdToken":"i","ExpiresIn":3600}}"#;
+
+    #[test]
+    fn test_typed_flow() {
+        let transport = MockTransport::new();
+        for body in [PASSWORD_VERIFIER, MFA, TOKENS] {
+            transport.respond(200, body);
+        }
+        let client = Client::new("us-east-1_Pool123", "client", transport.clone()).unwrap();
+
+        let state = AwaitingPasswordVerifier::start(&client, "bob").unwrap();
+        let Step::Mfa(mfa) = state.answer("P@ssw0rd!").unwrap() else {
+            panic!("expected MFA");
+        };
+        assert_eq!(mfa.challenge().username, "bob-internal");
+        let Step::Authenticated(done) = mfa.answer("123456").unwrap() else {
+            panic!("expected tokens");
+        };
+        assert_eq!(done.tokens().access_token, "a");
+        let answer = transport.last_body();
+        let responses = answer.get("ChallengeResponses").unwrap();
+        assert_eq!(
+            responses.str_field("SOFTWARE_TOKEN_MFA_CODE").unwrap(),
+            "123456"
+        );
+    }
+
+    #[test]
+    fn test_select_mfa_type() {
+        let transport = MockTransport::new();
+        for body in [
+            PASSWORD_VERIFIER,
+            r#"{"ChallengeName":"SELECT_MFA_TYPE","Session":"s0","ChallengeParameters":{
+                "MFAS_CAN_CHOOSE":"[\"SMS_MFA\",\"SOFTWARE_TOKEN_MFA\"]"}}"#,
+            MFA,
+            TOKENS,
+        ] {
+            transport.respond(200, body);
+        }
+        let client = Client::new("us-east-1_Pool123", "client", transport.clone()).unwrap();
+
+        let Step::SelectMfaType(choice) = Step::start(&client, "bob")
+            .unwrap()
+            .answer("P@ssw0rd!")
+            .unwrap()
+        else {
+            panic!("expected SELECT_MFA_TYPE");
+        };
+        assert_eq!(choice.choices(), [MfaType::Sms, MfaType::SoftwareToken]);
+        let Step::Mfa(mfa) = choice.answer(MfaType::SoftwareToken).unwrap() else {
+            panic!("expected MFA");
+        };
+        let step = mfa.answer("123456").unwrap();
+        assert_eq!(step.tokens().unwrap().access_token, "a");
+    }
+
+    #[test]
+    fn test_mfa_setup() {
+        let transport = MockTransport::new();
+        for body in [
+            PASSWORD_VERIFIER,
+            r#"{"ChallengeName":"MFA_SETUP","Session":"s1","ChallengeParameters":{}}"#,
+            r#"{"SecretCode":"JBSWY3DPEHPK3PXP","Session":"s2"}"#,
+            r#"{"Status":"SUCCESS","Session":"s3"}"#,
+            TOKENS,
+        ] {
+            transport.respond(200, body);
+        }
+        let client = Client::new("us-east-1_Pool123", "client", transport.clone()).unwrap();
+
+        let step = Step::start(&client, "bob")
+            .unwrap()
+            .answer("P@ssw0rd!")
+            .unwrap();
+        let Step::MfaSetup(setup) = step else {
+            panic!("expected MFA_SETUP");
+        };
+        let totp = setup.associate().unwrap();
+        assert_eq!(
+            totp.setup().secret_code().expose_secret(),
+            "JBSWY3DPEHPK3PXP"
+        );
+        let step = totp.answer("123456").unwrap();
+        assert_eq!(step.tokens().unwrap().access_token, "a");
+        assert_eq!(transport.last_body().str_field("Session").unwrap(), "s3");
+    }
+
+    #[test]
+    fn test_dynamic_driver() {
+        let transport = MockTransport::new();
+        for body in [PASSWORD_VERIFIER, MFA, TOKENS] {
+            transport.respond(200, body);
+        }
+        let client = Client::new("us-east-1_Pool123", "client", transport.clone()).unwrap();
+
+        let mut inputs = ["P@ssw0rd!", "123456"].into_iter();
+        let mut step = Step::start(&client, "bob").unwrap();
+        while step.tokens().is_none() {
+            step = step.answer(inputs.next().unwrap()).unwrap();
+        }
+        assert_eq!(step.tokens().unwrap().id_token, "i");
+        assert!(matches!(
+            step.answer("again"),
+            Err(CognitoSrpError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_interrupt_and_resume() {
+        let transport = MockTransport::new();
+        for body in [PASSWORD_VERIFIER, MFA, TOKENS] {
+            transport.respond(200, body);
+        }
+        let client = Client::new("us-east-1_Pool123", "client", transport.clone()).unwrap();
+        let token = CancelToken::new();
+        let flow = client.clone().with_cancel_token(token.clone());
+
+        let Step::Mfa(mfa) = Step::start(&flow, "bob")
+            .unwrap()
+            .answer("P@ssw0rd!")
+            .unwrap()
+        else {
+            panic!("expected MFA");
+        };
+        let challenge = mfa.challenge().clone();
+        token.cancel();
+        let err = mfa.answer("123456").err().unwrap();
+        assert!(matches!(
+            err,
+            CognitoSrpError::Interrupted {
+                reason: Interruption::Cancelled,
+                ..
+            }
+        ));
+        assert_eq!(err.interrupted_session(), Some("s1"));
+        assert_eq!(transport.requests.lock().unwrap().len(), 2);
+
+        let step = Step::resume(&client, challenge).answer("123456").unwrap();
+        assert_eq!(step.tokens().unwrap().access_token, "a");
+        assert_eq!(transport.last_body().str_field("Session").unwrap(), "s1");
+    }
+
+    #[test]
+    fn test_deadline() {
+        let transport = MockTransport::new();
+        let clock = Arc::new(FixedClock::new(
+            UNIX_EPOCH + Duration::from_secs(1_759_827_903),
+        ));
+        let client = Client::new("us-east-1_Pool123", "client", transport.clone())
+            .unwrap()
+            .with_clock(clock.clone())
+            .with_timeout(Duration::from_secs(30));
+
+        clock.advance(Duration::from_secs(30));
+        let err = Step::start(&client, "bob").err().unwrap();
+        assert_eq!(err.to_string(), "flow deadline exceeded");
+        assert!(transport.requests.lock().unwrap().is_empty());
+    }
+}
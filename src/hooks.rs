@@ -0,0 +1,105 @@
+// Callbacks registered on the Client (`.on_tokens_obtained(..)` and friends) for persisting
+// tokens, updating UI state or audit logging without wrapping every call site. They run inline
+// after the call succeeds, on the calling thread, in registration order.
+use crate::auth::Challenge;
+use crate::tokens::TokenSet;
+use std::sync::Arc;
+
+type Hook<T> = Arc<dyn Fn(&T) + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub(crate) struct Hooks {
+    pub(crate) tokens_obtained: Vec<Hook<TokenSet>>,
+    pub(crate) tokens_refreshed: Vec<Hook<TokenSet>>,
+    pub(crate) refresh_token_rotated: Vec<Hook<TokenSet>>,
+    pub(crate) challenge_received: Vec<Hook<Challenge>>,
+    pub(crate) sign_out: Vec<Arc<dyn Fn() + Send + Sync>>,
+}
+
+impl Hooks {
+    pub(crate) fn tokens_obtained(&self, tokens: &TokenSet) {
+        self.tokens_obtained.iter().for_each(|hook| hook(tokens));
+    }
+
+    pub(crate) fn tokens_refreshed(&self, tokens: &TokenSet) {
+        self.tokens_refreshed.iter().for_each(|hook| hook(tokens));
+    }
+
+    pub(crate) fn refresh_token_rotated(&self, tokens: &TokenSet) {
+        self.refresh_token_rotated
+            .iter()
+            .for_each(|hook| hook(tokens));
+    }
+
+    pub(crate) fn challenge_received(&self, challenge: &Challenge) {
+        self.challenge_received
+            .iter()
+            .for_each(|hook| hook(challenge));
+    }
+
+    pub(crate) fn sign_out(&self) {
+        self.sign_out.iter().for_each(|hook| hook());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::MockCognitoApi;
+    use crate::auth::{Authentication, Challenge, ChallengeName};
+    use crate::client::Client;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_hooks() {
+        let api = MockCognitoApi::new();
+        api.respond(
+            "RespondToAuthChallenge",
+            r#"{"ChallengeName":"SOFTWARE_TOKEN_MFA","Session":"s2","ChallengeParameters":{}}"#,
+        )
+        .respond(
+            "RespondToAuthChallenge",
+            r#"{"AuthenticationResult":{"AccessToken":"a","IdToken":"i","RefreshToken":"r","ExpiresIn":3600}}"#,
+        )
+        .respond(
+            "InitiateAuth",
+            r#"{"AuthenticationResult":{"AccessToken":"a2","IdToken":"i2","ExpiresIn":3600}}"#,
+        )
+        .respond("GlobalSignOut", "{}");
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let (e1, e2, e3, e4) = (
+            events.clone(),
+            events.clone(),
+            events.clone(),
+            events.clone(),
+        );
+        let client = Client::from_api("us-east-1_pool", "client", api)
+            .unwrap()
+            .on_challenge_received(move |c| e1.lock().unwrap().push(c.name.as_str().to_owned()))
+            .on_tokens_obtained(move |t| e2.lock().unwrap().push(t.access_token.clone()))
+            .on_tokens_refreshed(move |t| e3.lock().unwrap().push(format!("+{}", t.access_token)))
+            .on_sign_out(move || e4.lock().unwrap().push("signed out".to_owned()));
+
+        let challenge = Challenge {
+            name: ChallengeName::CustomChallenge,
+            session: Some("s1".to_owned()),
+            parameters: Default::default(),
+            username: "bob".to_owned(),
+        };
+        let Authentication::Challenge(mfa) = client
+            .respond_to_challenge(&challenge, &[("ANSWER", "42")])
+            .unwrap()
+        else {
+            panic!("expected the MFA challenge");
+        };
+        client.respond_to_mfa(&mfa, "123456").unwrap();
+        client.refresh_tokens("bob", "r").unwrap();
+        client.global_sign_out("a2").unwrap();
+        assert!(client.global_sign_out("a2").is_err());
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            ["SOFTWARE_TOKEN_MFA", "a", "+a2", "signed out"]
+        );
+    }
+}
@@ -0,0 +1,359 @@
+use crate::client::{json_request, Client};
+use crate::credentials::AwsCredentials;
+use crate::error::{CognitoSrpError, Result};
+use crate::json::Value;
+use crate::provider::TokenProvider;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+// Temporary AWS credentials for an identity pool identity.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IdentityCredentials {
+    // Stable per user and identity pool; cache it to skip GetId next time.
+    pub identity_id: String,
+    pub credentials: AwsCredentials,
+}
+
+// "us-east-1:0123abcd-..." -> "us-east-1".
+fn identity_pool_region(identity_pool_id: &str) -> Result<&str> {
+    match identity_pool_id.split_once(':') {
+        Some((region, id)) if !region.is_empty() && !id.is_empty() => Ok(region),
+        _ => Err(CognitoSrpError::InvalidArgument(format!(
+            "invalid identity pool id: {}",
+            identity_pool_id
+        ))),
+    }
+}
+
+impl Client {
+    // # https://docs.aws.amazon.com/cognito/latest/developerguide/authentication-flow.html
+    // The Logins map names this user pool as the provider, keyed the way identity pools expect:
+    // the issuer without its scheme.
+    fn logins(&self, id_token: &str) -> Value {
+        let issuer = self.issuer();
+        let provider = issuer
+            .strip_prefix("https://")
+            .or_else(|| issuer.strip_prefix("http://"))
+            .unwrap_or(&issuer);
+        Value::object([(provider, Value::from(id_token))])
+    }
+
+    fn identity_call(&self, region: &str, operation: &str, body: Value) -> Result<Value> {
+        let url = self.identity_endpoint(region);
+        let target = format!("AWSCognitoIdentityService.{}", operation);
+        // Both calls only read state, so they are safe to retry.
+        self.retry_policy()
+            .run(|| self.dispatch(operation, json_request(&url, &target, &body)))
+    }
+
+    // # https://docs.aws.amazon.com/cognitoidentity/latest/APIReference/API_GetId.html
+    pub fn get_identity_id(&self, identity_pool_id: &str, id_token: &str) -> Result<String> {
+        let region = identity_pool_region(identity_pool_id)?;
+        let body = Value::object([
+            ("IdentityPoolId", Value::from(identity_pool_id)),
+            ("Logins", self.logins(id_token)),
+        ]);
+        self.identity_call(region, "GetId", body)?
+            .str_field("IdentityId")
+            .ok_or_else(|| {
+                CognitoSrpError::InvalidResponse("GetId returned no IdentityId".to_owned())
+            })
+    }
+
+    // # https://docs.aws.amazon.com/cognitoidentity/latest/APIReference/API_GetCredentialsForIdentity.html
+    // Identity ids start with the identity pool's region, which decides the endpoint.
+    pub fn get_credentials_for_identity(
+        &self,
+        identity_id: &str,
+        id_token: &str,
+    ) -> Result<AwsCredentials> {
+        let region = identity_pool_region(identity_id)?;
+        let body = Value::object([
+            ("IdentityId", Value::from(identity_id)),
+            ("Logins", self.logins(id_token)),
+        ]);
+        let response = self.identity_call(region, "GetCredentialsForIdentity", body)?;
+        let missing = |name: &str| {
+            CognitoSrpError::InvalidResponse(format!(
+                "GetCredentialsForIdentity returned no {}",
+                name
+            ))
+        };
+        let credentials = response
+            .get("Credentials")
+            .ok_or_else(|| missing("Credentials"))?;
+        let field = |name: &str| credentials.str_field(name).ok_or_else(|| missing(name));
+        let expiration = match credentials.get("Expiration") {
+            // Epoch seconds, possibly fractional.
+            Some(Value::Number(secs)) if *secs >= 0.0 => {
+                Some(UNIX_EPOCH + Duration::from_secs(*secs as u64))
+            }
+            _ => return Err(missing("Expiration")),
+        };
+        Ok(AwsCredentials {
+            access_key_id: field("AccessKeyId")?,
+            secret_access_key: field("SecretKey")?,
+            session_token: Some(field("SessionToken")?),
+            expiration,
+        })
+    }
+
+    // GetId then GetCredentialsForIdentity, for the usual "sign in, then call AWS" flow.
+    pub fn identity_credentials(
+        &self,
+        identity_pool_id: &str,
+        id_token: &str,
+    ) -> Result<IdentityCredentials> {
+        let identity_id = self.get_identity_id(identity_pool_id, id_token)?;
+        let credentials = self.get_credentials_for_identity(&identity_id, id_token)?;
+        Ok(IdentityCredentials {
+            identity_id,
+            credentials,
+        })
+    }
+}
+
+// Auto-refreshing identity pool credentials. With the `aws-sdk` feature it is a
+// ProvideCredentials, for an aws-sdk-* config:
+//
+//     let s3 = aws_sdk_s3::Client::from_conf(
+//         aws_sdk_s3::Config::builder()
+//             .credentials_provider(CognitoSrpCredentialsProvider::new(client, pool, tokens))
+//             ...
+//
+// Clones share the cached credentials.
+#[derive(Clone)]
+pub struct CognitoSrpCredentialsProvider {
+    client: Client,
+    identity_pool_id: String,
+    tokens: Arc<dyn TokenProvider>,
+    lead_time: Duration,
+    // Held across the fetch, so concurrent callers wait for one set of calls.
+    cached: Arc<Mutex<Option<IdentityCredentials>>>,
+}
+
+impl fmt::Debug for CognitoSrpCredentialsProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CognitoSrpCredentialsProvider")
+            .field("identity_pool_id", &self.identity_pool_id)
+            .field("lead_time", &self.lead_time)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CognitoSrpCredentialsProvider {
+    pub fn new(
+        client: Client,
+        identity_pool_id: &str,
+        tokens: impl TokenProvider + 'static,
+    ) -> Self {
+        Self {
+            client,
+            identity_pool_id: identity_pool_id.to_owned(),
+            tokens: Arc::new(tokens),
+            lead_time: Duration::from_secs(300),
+            cached: Arc::default(),
+        }
+    }
+
+    // How long before expiry to fetch new credentials; defaults to five minutes.
+    pub fn with_lead_time(mut self, lead_time: Duration) -> Self {
+        self.lead_time = lead_time;
+        self
+    }
+
+    pub fn credentials(&self) -> Result<AwsCredentials> {
+        let mut cached = self.cached.lock().unwrap();
+        let now = self.client.now();
+        let identity_id = match cached.as_ref() {
+            Some(identity) => match identity.credentials.expiration {
+                Some(expiration) if now + self.lead_time < expiration => {
+                    return Ok(identity.credentials.clone())
+                }
+                _ => Some(identity.identity_id.clone()),
+            },
+            None => None,
+        };
+        let id_token = self.tokens.id_token()?;
+        // The identity id never changes for a user, so GetId happens only once.
+        let identity = match identity_id {
+            Some(identity_id) => IdentityCredentials {
+                credentials: self
+                    .client
+                    .get_credentials_for_identity(&identity_id, &id_token)?,
+                identity_id,
+            },
+            None => self
+                .client
+                .identity_credentials(&self.identity_pool_id, &id_token)?,
+        };
+        let credentials = identity.credentials.clone();
+        *cached = Some(identity);
+        Ok(credentials)
+    }
+}
+
+#[cfg(feature = "aws-sdk")]
+impl From<AwsCredentials> for aws_credential_types::Credentials {
+    fn from(credentials: AwsCredentials) -> Self {
+        aws_credential_types::Credentials::new(
+            credentials.access_key_id,
+            credentials.secret_access_key,
+            credentials.session_token,
+            credentials.expiration,
+            "CognitoSrpCredentialsProvider",
+        )
+    }
+}
+
+// # https://docs.rs/aws-credential-types/latest/aws_credential_types/provider/trait.ProvideCredentials.html
+// The Cognito calls block, so they run on tokio's blocking pool, as the SDK runs on tokio.
+#[cfg(feature = "aws-sdk")]
+impl aws_credential_types::provider::ProvideCredentials for CognitoSrpCredentialsProvider {
+    fn provide_credentials<'a>(
+        &'a self,
+    ) -> aws_credential_types::provider::future::ProvideCredentials<'a>
+    where
+        Self: 'a,
+    {
+        use aws_credential_types::provider::error::CredentialsError;
+
+        let provider = self.clone();
+        aws_credential_types::provider::future::ProvideCredentials::new(async move {
+            tokio::task::spawn_blocking(move || provider.credentials())
+                .await
+                .map_err(CredentialsError::provider_error)?
+                .map(Into::into)
+                .map_err(CredentialsError::provider_error)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+    use std::time::SystemTime;
+
+    #[test]
+    fn test_identity_credentials() {
+        let transport = MockTransport::new();
+        transport.respond(200, r#"{"IdentityId":"us-east-1:abcd"}"#);
+        transport.respond(
+            200,
+            r#"{"IdentityId":"us-east-1:abcd","Credentials":{"AccessKeyId":"ASIA1",
+                "SecretKey":"secret","SessionToken":"token","Expiration":1440938160.5}}"#,
+        );
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+
+        let identity = client
+            .identity_credentials("us-east-1:pool-guid", "id.token")
+            .unwrap();
+        assert_eq!(identity.identity_id, "us-east-1:abcd");
+        assert_eq!(identity.credentials.access_key_id, "ASIA1");
+        assert_eq!(identity.credentials.secret_access_key, "secret");
+        assert_eq!(identity.credentials.session_token.as_deref(), Some("token"));
+        assert_eq!(
+            identity.credentials.expiration,
+            Some(UNIX_EPOCH + Duration::from_secs(1440938160))
+        );
+
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(
+            requests[0].url,
+            "https://cognito-identity.us-east-1.amazonaws.com/"
+        );
+        assert!(requests[0].headers.contains(&(
+            "X-Amz-Target".to_owned(),
+            "AWSCognitoIdentityService.GetId".to_owned()
+        )));
+        assert_eq!(
+            requests[0].body,
+            r#"{"IdentityPoolId":"us-east-1:pool-guid","Logins":{"cognito-idp.us-east-1.amazonaws.com/us-east-1_pool":"id.token"}}"#
+        );
+        assert!(requests[1]
+            .body
+            .starts_with(r#"{"IdentityId":"us-east-1:abcd","Logins":"#));
+        drop(requests);
+
+        assert!(matches!(
+            client.get_identity_id("no-region", "id.token"),
+            Err(CognitoSrpError::InvalidArgument(_))
+        ));
+    }
+
+    struct StaticTokens;
+
+    impl TokenProvider for StaticTokens {
+        fn tokens(&self) -> Result<crate::tokens::TokenSet> {
+            Ok(crate::tokens::TokenSet {
+                access_token: "a".to_owned(),
+                id_token: "id.token".to_owned(),
+                refresh_token: None,
+                token_type: "Bearer".to_owned(),
+                expires_at: SystemTime::now() + Duration::from_secs(3600),
+            })
+        }
+    }
+
+    #[test]
+    fn test_credentials_provider() {
+        let transport = MockTransport::new();
+        let expiring = |secs: u64| {
+            let expiration = SystemTime::now() + Duration::from_secs(secs);
+            format!(
+                r#"{{"Credentials":{{"AccessKeyId":"ASIA{}","SecretKey":"s","SessionToken":"t","Expiration":{}}}}}"#,
+                secs,
+                expiration.duration_since(UNIX_EPOCH).unwrap().as_secs()
+            )
+        };
+        transport.respond(200, r#"{"IdentityId":"us-east-1:abcd"}"#);
+        transport.respond(200, &expiring(60));
+        transport.respond(200, &expiring(3600));
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let provider = CognitoSrpCredentialsProvider::new(client, "us-east-1:guid", StaticTokens);
+
+        assert_eq!(provider.credentials().unwrap().access_key_id, "ASIA60");
+        // Within the lead time: new credentials, but no second GetId.
+        assert_eq!(provider.credentials().unwrap().access_key_id, "ASIA3600");
+        assert_eq!(provider.credentials().unwrap().access_key_id, "ASIA3600");
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[2]
+            .body
+            .starts_with(r#"{"IdentityId":"us-east-1:abcd""#));
+    }
+
+    #[cfg(feature = "aws-sdk")]
+    #[test]
+    fn test_provide_credentials() {
+        use aws_credential_types::provider::ProvideCredentials;
+
+        let transport = MockTransport::new();
+        transport.respond(200, r#"{"IdentityId":"us-east-1:abcd"}"#);
+        transport.respond(
+            200,
+            r#"{"IdentityId":"us-east-1:abcd","Credentials":{"AccessKeyId":"ASIA1",
+                "SecretKey":"secret","SessionToken":"token","Expiration":4102444800}}"#,
+        );
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let provider = CognitoSrpCredentialsProvider::new(client, "us-east-1:guid", StaticTokens);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let credentials = runtime.block_on(provider.provide_credentials()).unwrap();
+        assert_eq!(credentials.access_key_id(), "ASIA1");
+        assert_eq!(credentials.secret_access_key(), "secret");
+        assert_eq!(credentials.session_token(), Some("token"));
+        assert_eq!(
+            credentials.expiry(),
+            Some(UNIX_EPOCH + Duration::from_secs(4102444800))
+        );
+        // Cached: the second call makes no requests.
+        runtime.block_on(provider.provide_credentials()).unwrap();
+        assert_eq!(transport.requests.lock().unwrap().len(), 2);
+        assert!(!format!("{:?}", provider).contains("secret"));
+    }
+}
@@ -0,0 +1,777 @@
+#[cfg(feature = "client")]
+use crate::client::Client;
+use crate::clock::{self, Clock, SystemClock};
+use crate::crypto::rsa_pkcs1_sha256_verify;
+use crate::error::{CognitoSrpError, Grant, Result};
+use crate::json::{self, Value};
+#[cfg(feature = "client")]
+use crate::metrics;
+use crate::pool_id::PoolId;
+#[cfg(feature = "client")]
+use crate::tasks::BackgroundTask;
+#[cfg(feature = "client")]
+use crate::transport::HttpRequest;
+use data_encoding::BASE64URL_NOPAD;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+#[cfg(feature = "client")]
+use std::sync::{RwLock, RwLockReadGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum TokenUse {
+    Id,
+    Access,
+}
+
+impl TokenUse {
+    // Which kind a raw token is, read from its payload without verifying it.
+    pub fn of(token: &str) -> Result<Self> {
+        Ok(Claims::decode_unverified(token)?.token_use)
+    }
+}
+
+// Serialized as the token's own JSON payload, so custom claims survive the round trip.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "Value", try_from = "Value"))]
+pub struct Claims {
+    pub sub: String,
+    pub iss: String,
+    pub token_use: TokenUse,
+    // `aud` on id tokens, `client_id` on access tokens.
+    pub client_id: String,
+    // `cognito:username` on id tokens, `username` on access tokens.
+    pub username: Option<String>,
+    pub groups: Vec<String>,
+    pub email: Option<String>,
+    pub iat: u64,
+    pub exp: u64,
+    raw: Value,
+}
+
+fn decode_segment(segment: &str) -> Result<Vec<u8>> {
+    BASE64URL_NOPAD
+        .decode(segment.as_bytes())
+        .map_err(|err| CognitoSrpError::InvalidToken(format!("malformed JWT segment: {}", err)))
+}
+
+fn decode_json(segment: &str) -> Result<Value> {
+    let bytes = decode_segment(segment)?;
+    let text = std::str::from_utf8(&bytes)
+        .map_err(|_| CognitoSrpError::InvalidToken("JWT segment is not UTF-8".to_owned()))?;
+    json::parse(text)
+}
+
+// Far above what Cognito issues (a few KB with many groups), and checked before anything is
+// decoded, so a request header can't make the verifier parse megabytes of attacker-chosen JSON.
+const MAX_TOKEN_LEN: usize = 16 * 1024;
+// Cognito's headers are just kid and alg.
+const MAX_HEADER_LEN: usize = 1024;
+
+fn split_token(token: &str) -> Result<(&str, &str, &str)> {
+    if token.len() > MAX_TOKEN_LEN {
+        return Err(CognitoSrpError::InvalidToken("JWT is too long".to_owned()));
+    }
+    let mut parts = token.split('.');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(header), Some(payload), Some(signature), None) => Ok((header, payload, signature)),
+        _ => Err(CognitoSrpError::InvalidToken(
+            "JWT must have three segments".to_owned(),
+        )),
+    }
+}
+
+fn secs(value: Option<&Value>) -> u64 {
+    match value {
+        Some(Value::Number(n)) if *n >= 0.0 => *n as u64,
+        _ => 0,
+    }
+}
+
+impl Claims {
+    // Reads the payload without checking the signature; only for display or for tokens that
+    // were just received from Cognito over TLS.
+    pub fn decode_unverified(token: &str) -> Result<Self> {
+        let (_, payload, _) = split_token(token)?;
+        Self::from_payload(decode_json(payload)?)
+    }
+
+    fn from_payload(raw: Value) -> Result<Self> {
+        let token_use = match raw.get("token_use").and_then(Value::as_str) {
+            Some("id") => TokenUse::Id,
+            Some("access") => TokenUse::Access,
+            other => {
+                return Err(CognitoSrpError::InvalidToken(format!(
+                    "unexpected token_use: {:?}",
+                    other
+                )))
+            }
+        };
+        let (client_claim, username_claim) = match token_use {
+            TokenUse::Id => ("aud", "cognito:username"),
+            TokenUse::Access => ("client_id", "username"),
+        };
+        let groups = match raw.get("cognito:groups") {
+            Some(Value::Array(items)) => items
+                .iter()
+                .filter_map(|g| g.as_str().map(str::to_owned))
+                .collect(),
+            _ => Vec::new(),
+        };
+        Ok(Self {
+            sub: raw.str_field("sub").ok_or_else(|| {
+                CognitoSrpError::InvalidToken("token has no sub claim".to_owned())
+            })?,
+            iss: raw.str_field("iss").unwrap_or_default(),
+            token_use,
+            client_id: raw.str_field(client_claim).unwrap_or_default(),
+            username: raw.str_field(username_claim),
+            groups,
+            email: raw.str_field("email"),
+            iat: secs(raw.get("iat")),
+            exp: secs(raw.get("exp")),
+            raw,
+        })
+    }
+
+    pub fn expires_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.exp)
+    }
+
+    // As on TokenSet, from `exp`.
+    pub fn expires_in(&self, clock: &(impl Clock + ?Sized)) -> Duration {
+        clock::expires_in(self.expires_at(), clock)
+    }
+
+    pub fn is_expired(&self, clock: &(impl Clock + ?Sized), leeway: Duration) -> bool {
+        clock::is_expired(self.expires_at(), clock, leeway)
+    }
+
+    pub fn time_to_refresh(&self, clock: &(impl Clock + ?Sized), lead: Duration) -> Duration {
+        clock::time_to_refresh(self.expires_at(), clock, lead)
+    }
+
+    // Any string claim, including custom:* attributes.
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        self.raw.get(name).and_then(Value::as_str)
+    }
+
+    // The space-separated `scope` claim of access tokens; id tokens have none.
+    pub fn scopes(&self) -> impl Iterator<Item = &str> {
+        self.get_str("scope").unwrap_or_default().split_whitespace()
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes().any(|s| s == scope)
+    }
+
+    pub fn in_group(&self, group: &str) -> bool {
+        self.groups.iter().any(|g| g == group)
+    }
+
+    pub fn has(&self, grant: &Grant) -> bool {
+        match grant {
+            Grant::Scope(scope) => self.has_scope(scope),
+            Grant::Group(group) => self.in_group(group),
+        }
+    }
+
+    // Fails with Forbidden listing every grant the token lacks.
+    pub fn require_all(&self, grants: &[Grant]) -> Result<()> {
+        let missing: Vec<Grant> = grants.iter().filter(|g| !self.has(g)).cloned().collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(CognitoSrpError::Forbidden {
+                missing,
+                any_of: false,
+            })
+        }
+    }
+
+    // Fails with Forbidden unless the token has at least one of `grants`; an empty list passes.
+    pub fn require_any(&self, grants: &[Grant]) -> Result<()> {
+        if grants.is_empty() || grants.iter().any(|g| self.has(g)) {
+            Ok(())
+        } else {
+            Err(CognitoSrpError::Forbidden {
+                missing: grants.to_vec(),
+                any_of: true,
+            })
+        }
+    }
+
+    // The whole payload as the caller's own type, for custom:* attributes beyond get_str:
+    // `#[serde(rename = "custom:tenant_id")] tenant_id: String`.
+    #[cfg(feature = "serde")]
+    pub fn deserialize<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_str(&self.raw.to_string()).map_err(|err| {
+            CognitoSrpError::InvalidToken(format!("claims do not match the expected type: {}", err))
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Claims> for Value {
+    fn from(claims: Claims) -> Self {
+        claims.raw
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<Value> for Claims {
+    type Error = CognitoSrpError;
+
+    fn try_from(payload: Value) -> Result<Self> {
+        Self::from_payload(payload)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Jwk {
+    kid: String,
+    n: Vec<u8>,
+    e: Vec<u8>,
+}
+
+// What Verifier checks beyond the signature. from_jwks starts from the pool's AWS issuer and
+// its one app client; with_options replaces the lot.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationOptions {
+    // App clients whose tokens are accepted (`aud` on id tokens, `client_id` on access tokens).
+    // Empty accepts none.
+    pub client_ids: Vec<String>,
+    // None is the pool's own https://cognito-idp.<region>.amazonaws.com/<pool id>.
+    pub issuer: Option<String>,
+    // None accepts both id and access tokens.
+    pub token_use: Option<TokenUse>,
+    // How long past `exp` a token is still accepted, for hosts whose clock runs slow.
+    pub clock_skew: Duration,
+    // Check only the signature and expiry, ignoring the three settings above; for tools that
+    // inspect tokens of any app client in the pool.
+    pub exp_only: bool,
+}
+
+// Checks RS256 signatures against the pool's JWKS, then issuer, expiry and app client.
+#[derive(Clone)]
+pub struct Verifier {
+    pool_issuer: String,
+    options: ValidationOptions,
+    keys: Vec<Jwk>,
+    clock: Arc<dyn Clock>,
+}
+
+impl fmt::Debug for Verifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Verifier")
+            .field("pool_issuer", &self.pool_issuer)
+            .field("options", &self.options)
+            .field("keys", &self.keys)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Verifier {
+    pub fn from_jwks(pool_id: &str, client_id: &str, jwks: &str) -> Result<Self> {
+        let pool_id = PoolId::parse(pool_id)?;
+        let document = json::parse(jwks)?;
+        let keys = match document.get("keys") {
+            Some(Value::Array(keys)) => keys,
+            _ => {
+                return Err(CognitoSrpError::InvalidToken(
+                    "JWKS document has no keys".to_owned(),
+                ))
+            }
+        };
+        let keys = keys
+            .iter()
+            .filter(|key| key.get("kty").and_then(Value::as_str) == Some("RSA"))
+            .map(|key| {
+                let field = |name: &str| {
+                    key.str_field(name).ok_or_else(|| {
+                        CognitoSrpError::InvalidToken(format!("JWK is missing {}", name))
+                    })
+                };
+                Ok(Jwk {
+                    kid: field("kid")?,
+                    n: decode_segment(&field("n")?)?,
+                    e: decode_segment(&field("e")?)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            pool_issuer: format!(
+                "https://cognito-idp.{}.amazonaws.com/{}",
+                pool_id.region(),
+                pool_id
+            ),
+            options: ValidationOptions {
+                client_ids: vec![client_id.to_owned()],
+                ..ValidationOptions::default()
+            },
+            keys,
+            clock: Arc::new(SystemClock),
+        })
+    }
+
+    // Pinned keys, for services that must never fetch them: embed the pool's jwks.json with
+    // include_bytes!, or ship it beside the binary (from_jwks_file) or in its environment
+    // (from_jwks_env). When Cognito rotates its keys the pinned copy has to be redeployed; tokens
+    // signed with a new key fail with "no JWK with kid" until then.
+    pub fn from_jwks_bytes(pool_id: &str, client_id: &str, jwks: &[u8]) -> Result<Self> {
+        let jwks = std::str::from_utf8(jwks)
+            .map_err(|_| CognitoSrpError::InvalidToken("JWKS document is not UTF-8".to_owned()))?;
+        Self::from_jwks(pool_id, client_id, jwks)
+    }
+
+    pub fn from_jwks_file(pool_id: &str, client_id: &str, path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_jwks(pool_id, client_id, &fs::read_to_string(path)?)
+    }
+
+    // `var` holds the document itself, not a path to it.
+    pub fn from_jwks_env(pool_id: &str, client_id: &str, var: &str) -> Result<Self> {
+        let jwks = env::var(var)
+            .map_err(|_| CognitoSrpError::Configuration(format!("{} is not set", var)))?;
+        Self::from_jwks(pool_id, client_id, &jwks)
+    }
+
+    // The `kid`s of the keys held, e.g. to log at startup which pinned set was deployed.
+    pub fn key_ids(&self) -> impl Iterator<Item = &str> {
+        self.keys.iter().map(|key| key.kid.as_str())
+    }
+
+    // # https://docs.aws.amazon.com/cognito/latest/developerguide/amazon-cognito-user-pools-using-tokens-verifying-a-jwt.html
+    #[cfg(feature = "client")]
+    pub fn fetch(client: &Client) -> Result<Self> {
+        client.measure(metrics::JWKS_FETCH, || {
+            let response = client.retry_policy().run(|| {
+                client.transport().send(HttpRequest {
+                    method: "GET",
+                    url: format!("{}/.well-known/jwks.json", client.issuer()),
+                    headers: Vec::new(),
+                    body: String::new(),
+                })
+            })?;
+            if response.status != 200 {
+                return Err(CognitoSrpError::Http {
+                    status: response.status,
+                });
+            }
+            let mut verifier =
+                Self::from_jwks(client.pool_id(), client.client_id(), &response.body)?
+                    .with_issuer(&client.issuer());
+            verifier.clock = client.clock();
+            Ok(verifier)
+        })
+    }
+
+    // For tokens from an emulator or proxy whose `iss` is not the AWS one.
+    pub fn with_issuer(mut self, issuer: &str) -> Self {
+        self.options.issuer = Some(issuer.to_owned());
+        self
+    }
+
+    // E.g. one verifier for a web and a mobile app client:
+    //
+    //     verifier.with_options(ValidationOptions {
+    //         client_ids: vec![web.to_owned(), mobile.to_owned()],
+    //         token_use: Some(TokenUse::Access),
+    //         ..verifier.options().clone()
+    //     })
+    pub fn with_options(mut self, options: ValidationOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn options(&self) -> &ValidationOptions {
+        &self.options
+    }
+
+    // Expiry is checked against this clock; fetch uses the client's.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims> {
+        self.verify_at(token, self.clock.now())
+    }
+
+    fn verify_at(&self, token: &str, now: SystemTime) -> Result<Claims> {
+        let (header_segment, payload, signature) = split_token(token)?;
+        if header_segment.len() > MAX_HEADER_LEN {
+            return Err(CognitoSrpError::InvalidToken(
+                "JWT header is too long".to_owned(),
+            ));
+        }
+        let header = decode_json(header_segment)?;
+        if header.get("alg").and_then(Value::as_str) != Some("RS256") {
+            return Err(CognitoSrpError::InvalidToken(
+                "unsupported JWT alg".to_owned(),
+            ));
+        }
+        let kid = header
+            .get("kid")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let key =
+            self.keys.iter().find(|key| key.kid == kid).ok_or_else(|| {
+                CognitoSrpError::InvalidToken(format!("no JWK with kid {:?}", kid))
+            })?;
+        // "header.payload", the part the signature covers.
+        let signing_input = &token[..header_segment.len() + payload.len() + 1];
+        if !rsa_pkcs1_sha256_verify(
+            &key.n,
+            &key.e,
+            signing_input.as_bytes(),
+            &decode_segment(signature)?,
+        ) {
+            return Err(CognitoSrpError::InvalidToken(
+                "invalid JWT signature".to_owned(),
+            ));
+        }
+
+        let claims = Claims::from_payload(decode_json(payload)?)?;
+        let options = &self.options;
+        if !options.exp_only {
+            let issuer = options.issuer.as_deref().unwrap_or(&self.pool_issuer);
+            if claims.iss != issuer {
+                return Err(CognitoSrpError::InvalidToken(format!(
+                    "unexpected issuer {}",
+                    claims.iss
+                )));
+            }
+            if !options.client_ids.contains(&claims.client_id) {
+                return Err(CognitoSrpError::InvalidToken(format!(
+                    "token was issued to app client {}",
+                    claims.client_id
+                )));
+            }
+            if options
+                .token_use
+                .is_some_and(|expected| claims.token_use != expected)
+            {
+                return Err(CognitoSrpError::InvalidToken(format!(
+                    "expected an {} token",
+                    match options.token_use {
+                        Some(TokenUse::Access) => "access",
+                        _ => "id",
+                    }
+                )));
+            }
+        }
+        if claims.expires_at() + options.clock_skew <= now {
+            return Err(CognitoSrpError::InvalidToken(
+                "token has expired".to_owned(),
+            ));
+        }
+        Ok(claims)
+    }
+}
+
+// A Verifier whose keys are re-fetched from the pool, so a key rotation is picked up without a
+// restart. Clones share the keys; spawn_refresh runs the re-fetch on a BackgroundTask.
+#[cfg(feature = "client")]
+#[derive(Clone)]
+pub struct RefreshingVerifier {
+    client: Client,
+    verifier: Arc<RwLock<Verifier>>,
+}
+
+#[cfg(feature = "client")]
+impl RefreshingVerifier {
+    // `verifier` keeps its options and clock; only its keys are replaced.
+    pub fn new(client: Client, verifier: Verifier) -> Self {
+        Self {
+            client,
+            verifier: Arc::new(RwLock::new(verifier)),
+        }
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims> {
+        self.read().verify(token)
+    }
+
+    pub fn verifier(&self) -> Verifier {
+        self.read().clone()
+    }
+
+    // Fetches the JWKS now. On failure the keys already held stay in use.
+    pub fn refresh(&self) -> Result<()> {
+        let fetched = Verifier::fetch(&self.client)?;
+        self.verifier
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .keys = fetched.keys;
+        Ok(())
+    }
+
+    // Re-fetches every `interval` until the task is dropped or shut down. Cognito rotates keys
+    // rarely; an hour is plenty.
+    pub fn spawn_refresh(&self, interval: Duration) -> Result<BackgroundTask> {
+        let verifier = self.clone();
+        BackgroundTask::spawn_periodic("cogneato-jwks", interval, move || {
+            let _ = verifier.refresh();
+        })
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Verifier> {
+        self.verifier.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::Verifier;
+
+    pub(crate) const JWKS: &str = include_str!("../testdata/jwks.json");
+
+    pub(crate) fn token(name: &str) -> String {
+        crate::json::parse(include_str!("../testdata/tokens.json"))
+            .unwrap()
+            .str_field(name)
+            .unwrap()
+    }
+
+    pub(crate) fn verifier() -> Verifier {
+        Verifier::from_jwks("us-east-1_pool", "client", JWKS).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::{self, token, verifier, JWKS};
+    use super::*;
+    #[cfg(feature = "client")]
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_verify_id_and_access_tokens() {
+        let claims = verifier().verify(&token("id_admin")).unwrap();
+        assert_eq!(claims.token_use, TokenUse::Id);
+        assert_eq!(claims.sub, "sub-bob");
+        assert_eq!(claims.username.as_deref(), Some("bob"));
+        assert_eq!(claims.groups, vec!["admins".to_owned()]);
+        assert_eq!(claims.email.as_deref(), Some("bob@example.com"));
+        assert_eq!(claims.get_str("aud"), Some("client"));
+
+        let claims = verifier().verify(&token("access_admin")).unwrap();
+        assert_eq!(claims.token_use, TokenUse::Access);
+        assert_eq!(claims.client_id, "client");
+        assert_eq!(claims.username.as_deref(), Some("bob"));
+    }
+
+    #[test]
+    fn test_verify_rejects() {
+        let verifier = verifier();
+        for name in [
+            "id_expired",
+            "id_other_client",
+            "id_unknown_kid",
+            "id_tampered",
+        ] {
+            assert!(verifier.verify(&token(name)).is_err(), "{}", name);
+        }
+        assert!(verifier.verify("not.a.jwt.at-all").is_err());
+
+        let other_pool = Verifier::from_jwks("us-east-1_other", "client", JWKS).unwrap();
+        assert!(other_pool.verify(&token("id_admin")).is_err());
+    }
+
+    #[test]
+    fn test_scopes_and_groups() {
+        let claims = verifier().verify(&token("access_admin")).unwrap();
+        assert_eq!(
+            claims.scopes().collect::<Vec<_>>(),
+            ["aws.cognito.signin.user.admin", "resource/read"]
+        );
+        assert!(claims.has_scope("resource/read") && !claims.has_scope("resource"));
+        assert!(claims.in_group("admins") && !claims.in_group("admin"));
+
+        let read = Grant::scope("resource/read");
+        let write = Grant::scope("resource/write");
+        claims
+            .require_all(&[read.clone(), Grant::group("admins")])
+            .unwrap();
+        claims.require_any(&[write.clone(), read]).unwrap();
+        let err = claims
+            .require_all(&[write.clone(), Grant::group("admins"), Grant::group("ops")])
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "token lacks scope resource/write, group ops"
+        );
+        let err = claims
+            .require_any(&[write, Grant::group("ops")])
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            CognitoSrpError::Forbidden { ref missing, any_of: true } if missing.len() == 2
+        ));
+        assert_eq!(
+            err.to_string(),
+            "token has none of scope resource/write, group ops"
+        );
+    }
+
+    #[test]
+    fn test_validation_options() {
+        let base = verifier();
+        let id = token("id_other_client");
+        let other_client = Claims::decode_unverified(&id).unwrap().client_id;
+        let both = base.clone().with_options(ValidationOptions {
+            client_ids: vec!["client".to_owned(), other_client],
+            ..base.options().clone()
+        });
+        both.verify(&id).unwrap();
+        both.verify(&token("id_admin")).unwrap();
+
+        let access_only = both.clone().with_options(ValidationOptions {
+            token_use: Some(TokenUse::Access),
+            ..both.options().clone()
+        });
+        access_only.verify(&token("access_admin")).unwrap();
+        let err = access_only.verify(&token("id_admin")).unwrap_err();
+        assert_eq!(err.to_string(), "invalid token: expected an access token");
+
+        let emulator = base
+            .clone()
+            .with_issuer("http://localhost:9229/us-east-1_pool");
+        assert!(emulator.verify(&token("id_admin")).is_err());
+        let exp_only = emulator.with_options(ValidationOptions {
+            exp_only: true,
+            ..ValidationOptions::default()
+        });
+        exp_only.verify(&id).unwrap();
+
+        let exp = Claims::decode_unverified(&token("id_expired"))
+            .unwrap()
+            .expires_at();
+        let late = exp + Duration::from_secs(30);
+        assert!(base.verify_at(&token("id_expired"), late).is_err());
+        let lenient = base.clone().with_options(ValidationOptions {
+            clock_skew: Duration::from_secs(60),
+            ..base.options().clone()
+        });
+        lenient.verify_at(&token("id_expired"), late).unwrap();
+        assert!(lenient
+            .verify_at(&token("id_expired"), exp + Duration::from_secs(60))
+            .is_err());
+    }
+
+    #[test]
+    fn test_decode_unverified() {
+        let claims = Claims::decode_unverified(&token("id_expired")).unwrap();
+        assert_eq!(claims.exp, 1600000000);
+        assert!(verifier()
+            .verify_at(
+                &token("id_expired"),
+                UNIX_EPOCH + Duration::from_secs(1500000000)
+            )
+            .is_ok());
+
+        let clock = crate::clock::FixedClock::new(UNIX_EPOCH + Duration::from_secs(1599999000));
+        assert_eq!(claims.expires_in(&clock), Duration::from_secs(1000));
+        assert_eq!(
+            claims.time_to_refresh(&clock, Duration::from_secs(300)),
+            Duration::from_secs(700)
+        );
+        assert!(!claims.is_expired(&clock, Duration::from_secs(60)));
+        assert!(claims.is_expired(&SystemClock, Duration::ZERO));
+        assert!(verifier().verify(&token("id_expired")).is_err());
+        assert!(verifier()
+            .with_clock(clock)
+            .verify(&token("id_expired"))
+            .is_ok());
+        assert_eq!(
+            TokenUse::of(&token("access_admin")).unwrap(),
+            TokenUse::Access
+        );
+        assert!(TokenUse::of("not-a-token").is_err());
+    }
+
+    #[test]
+    fn test_pinned_jwks() {
+        let embedded = include_bytes!("../testdata/jwks.json");
+        let verifier = Verifier::from_jwks_bytes("us-east-1_pool", "client", embedded).unwrap();
+        assert!(verifier.verify(&token("id_admin")).is_ok());
+        assert_eq!(
+            verifier.key_ids().count(),
+            fixtures::verifier().key_ids().count()
+        );
+
+        let path =
+            std::env::temp_dir().join(format!("cogneato-jwks-{}.json", rand::random::<u64>()));
+        fs::write(&path, JWKS).unwrap();
+        let verifier = Verifier::from_jwks_file("us-east-1_pool", "client", &path).unwrap();
+        assert!(verifier.verify(&token("access_admin")).is_ok());
+        fs::remove_file(&path).unwrap();
+
+        env::set_var("COGNEATO_TEST_PINNED_JWKS", JWKS);
+        let verifier =
+            Verifier::from_jwks_env("us-east-1_pool", "client", "COGNEATO_TEST_PINNED_JWKS")
+                .unwrap();
+        assert!(verifier.verify(&token("id_admin")).is_ok());
+        let err = Verifier::from_jwks_env("us-east-1_pool", "client", "COGNEATO_TEST_NO_JWKS")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "COGNEATO_TEST_NO_JWKS is not set");
+        assert!(Verifier::from_jwks_bytes("us-east-1_pool", "client", b"\xff").is_err());
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_fetch() {
+        let transport = MockTransport::new();
+        transport.respond(200, JWKS);
+        let client = Client::new("us-east-1_pool", "client", transport.clone())
+            .unwrap()
+            .with_clock(crate::clock::FixedClock::new(
+                UNIX_EPOCH + Duration::from_secs(1500000000),
+            ));
+
+        let verifier = Verifier::fetch(&client).unwrap();
+        assert!(verifier.verify(&token("id_admin")).is_ok());
+        // Checked against the client's clock, before id_expired's exp.
+        assert!(verifier.verify(&token("id_expired")).is_ok());
+        assert!(verifier
+            .with_issuer("http://localhost:9229/us-east-1_pool")
+            .verify(&token("id_admin"))
+            .is_err());
+        let request = transport.last_request();
+        assert_eq!(request.method, "GET");
+        assert_eq!(
+            request.url,
+            "https://cognito-idp.us-east-1.amazonaws.com/us-east-1_pool/.well-known/jwks.json"
+        );
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_refreshing_verifier() {
+        let transport = MockTransport::new();
+        transport.respond(200, r#"{"keys":[]}"#);
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let shared = RefreshingVerifier::new(client, verifier());
+        let clone = shared.clone();
+        assert!(clone.verify(&token("id_admin")).is_ok());
+
+        // The pool rotated its keys away from the one id_admin was signed with.
+        shared.refresh().unwrap();
+        assert_eq!(clone.verifier().key_ids().count(), 0);
+        assert!(clone.verify(&token("id_admin")).is_err());
+
+        transport.respond(200, JWKS);
+        let task = shared.spawn_refresh(Duration::from_millis(1)).unwrap();
+        while clone.verifier().key_ids().count() == 0 {
+            std::thread::yield_now();
+        }
+        task.shutdown().unwrap();
+        assert!(clone.verify(&token("id_admin")).is_ok());
+    }
+}
@@ -0,0 +1,429 @@
+use crate::error::{CognitoSrpError, Result};
+use crate::store::{TokenKey, TokenStore};
+use crate::tokens::TokenSet;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Backend {
+    // # https://specifications.freedesktop.org/secret-service/
+    // Through libsecret's `secret-tool`, which takes the secret on stdin.
+    SecretService,
+    // # https://ss64.com/mac/security.html
+    // Through `security -i`, which reads its commands from stdin.
+    Keychain,
+    // # https://learn.microsoft.com/en-us/windows/win32/api/wincred/
+    // Generic credentials through CredWriteW and CredReadW.
+    #[cfg(windows)]
+    CredentialManager,
+}
+
+// Keeps tokens in the platform secret store, so refresh tokens never sit on disk in plain text.
+// On macOS and Linux, like CurlTransport, it drives the platform's own command-line tool rather
+// than linking a native library; secrets only ever pass over pipes, never on a command line.
+// Windows has no such tool, so there it calls the Credential Manager API directly.
+#[derive(Clone, Debug)]
+pub struct KeyringTokenStore {
+    service: String,
+    backend: Backend,
+    program: String,
+}
+
+impl KeyringTokenStore {
+    pub fn new() -> Result<Self> {
+        #[cfg(windows)]
+        let (backend, program) = (Backend::CredentialManager, "");
+        #[cfg(not(windows))]
+        let (backend, program) = if cfg!(target_os = "macos") {
+            (Backend::Keychain, "security")
+        } else if cfg!(unix) {
+            (Backend::SecretService, "secret-tool")
+        } else {
+            return Err(CognitoSrpError::Configuration(
+                "no supported keyring on this platform".to_owned(),
+            ));
+        };
+        Ok(Self {
+            service: "cogneato".to_owned(),
+            backend,
+            program: program.to_owned(),
+        })
+    }
+
+    // Entries are filed under this service name; defaults to "cogneato".
+    pub fn with_service(mut self, service: &str) -> Self {
+        self.service = service.to_owned();
+        self
+    }
+
+    // Use a specific secret-tool or security binary instead of the one on PATH. Ignored on
+    // Windows.
+    pub fn with_program(mut self, program: &str) -> Self {
+        self.program = program.to_owned();
+        self
+    }
+
+    fn run(&self, args: &[&str], stdin: &str) -> Result<Output> {
+        let mut child = Command::new(&self.program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| {
+                CognitoSrpError::Configuration(format!("could not run {}: {}", self.program, err))
+            })?;
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(stdin.as_bytes())?;
+        Ok(child.wait_with_output()?)
+    }
+
+    fn check(&self, output: Output) -> Result<Output> {
+        if output.status.success() {
+            return Ok(output);
+        }
+        Err(CognitoSrpError::Io(std::io::Error::other(format!(
+            "{} failed ({}): {}",
+            self.program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))))
+    }
+}
+
+impl TokenStore for KeyringTokenStore {
+    fn load(&self, key: &TokenKey) -> Result<Option<TokenSet>> {
+        let account = key.id();
+        let output = match self.backend {
+            Backend::SecretService => self.run(
+                &["lookup", "service", &self.service, "account", &account],
+                "",
+            )?,
+            Backend::Keychain => self.run(
+                &[
+                    "find-generic-password",
+                    "-s",
+                    &self.service,
+                    "-a",
+                    &account,
+                    "-w",
+                ],
+                "",
+            )?,
+            #[cfg(windows)]
+            Backend::CredentialManager => {
+                return match self.read_chunks(&account)? {
+                    Some(contents) => key.decode(&contents),
+                    None => Ok(None),
+                }
+            }
+        };
+        // Both tools exit non-zero when there is no such entry.
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let stored = String::from_utf8_lossy(&output.stdout);
+        let contents = match self.backend {
+            Backend::SecretService => stored.trim_end().to_owned(),
+            // Stored hex-encoded; see save.
+            Backend::Keychain => String::from_utf8(hex::decode(stored.trim())?).map_err(|_| {
+                CognitoSrpError::InvalidResponse("keychain entry is not UTF-8".to_owned())
+            })?,
+            #[cfg(windows)]
+            Backend::CredentialManager => unreachable!("loaded above"),
+        };
+        key.decode(&contents)
+    }
+
+    fn save(&self, key: &TokenKey, tokens: &TokenSet) -> Result<()> {
+        let account = key.id();
+        let contents = key.encode(tokens);
+        let label = format!("{} tokens for {}", self.service, key.username);
+        let output = match self.backend {
+            Backend::SecretService => self.run(
+                &[
+                    "store",
+                    "--label",
+                    &label,
+                    "service",
+                    &self.service,
+                    "account",
+                    &account,
+                ],
+                &contents,
+            )?,
+            // Hex keeps the JSON clear of `security -i`'s own quoting rules.
+            Backend::Keychain if !is_bare_word(&self.service) => {
+                return Err(CognitoSrpError::InvalidArgument(format!(
+                    "keychain service name must be a bare word: {}",
+                    self.service
+                )))
+            }
+            Backend::Keychain => self.run(
+                &["-i"],
+                &format!(
+                    "add-generic-password -U -s {} -a {} -w {}\n",
+                    self.service,
+                    account,
+                    hex::encode(contents)
+                ),
+            )?,
+            #[cfg(windows)]
+            Backend::CredentialManager => {
+                return self.write_chunks(&account, &key.username, &label, &contents)
+            }
+        };
+        self.check(output)?;
+        Ok(())
+    }
+
+    fn clear(&self, key: &TokenKey) -> Result<()> {
+        let account = key.id();
+        // A missing entry is not an error, so the exit status is ignored.
+        match self.backend {
+            Backend::SecretService => self.run(
+                &["clear", "service", &self.service, "account", &account],
+                "",
+            )?,
+            Backend::Keychain => self.run(
+                &[
+                    "delete-generic-password",
+                    "-s",
+                    &self.service,
+                    "-a",
+                    &account,
+                ],
+                "",
+            )?,
+            #[cfg(windows)]
+            Backend::CredentialManager => return self.delete_chunks(&account, 0),
+        };
+        Ok(())
+    }
+}
+
+// A generic credential holds at most CRED_MAX_CREDENTIAL_BLOB_SIZE (2560) bytes, less than a
+// typical token set, so the JSON is split across `<service>/<account>/0`, `/1`, ...
+#[cfg(windows)]
+impl KeyringTokenStore {
+    fn target(&self, account: &str, chunk: usize) -> String {
+        format!("{}/{}/{}", self.service, account, chunk)
+    }
+
+    fn read_chunks(&self, account: &str) -> Result<Option<String>> {
+        let mut contents = Vec::new();
+        for chunk in 0.. {
+            match wincred::read(&self.target(account, chunk))? {
+                Some(part) => contents.extend(part),
+                None if chunk == 0 => return Ok(None),
+                None => break,
+            }
+        }
+        String::from_utf8(contents)
+            .map(Some)
+            .map_err(|_| CognitoSrpError::InvalidResponse("credential is not UTF-8".to_owned()))
+    }
+
+    fn write_chunks(
+        &self,
+        account: &str,
+        username: &str,
+        label: &str,
+        contents: &str,
+    ) -> Result<()> {
+        let parts = contents.as_bytes().chunks(wincred::MAX_BLOB);
+        let count = parts.len();
+        for (chunk, part) in parts.enumerate() {
+            wincred::write(&self.target(account, chunk), username, label, part)?;
+        }
+        // Drop the tail of a longer set saved earlier.
+        self.delete_chunks(account, count)
+    }
+
+    fn delete_chunks(&self, account: &str, from: usize) -> Result<()> {
+        for chunk in from.. {
+            if !wincred::delete(&self.target(account, chunk))? {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod wincred {
+    use std::io;
+    use std::ptr;
+    use windows_sys::Win32::Foundation::ERROR_NOT_FOUND;
+    use windows_sys::Win32::Security::Credentials::{
+        CredDeleteW, CredFree, CredReadW, CredWriteW, CREDENTIALW, CRED_MAX_CREDENTIAL_BLOB_SIZE,
+        CRED_PERSIST_LOCAL_MACHINE, CRED_TYPE_GENERIC,
+    };
+
+    pub(super) const MAX_BLOB: usize = CRED_MAX_CREDENTIAL_BLOB_SIZE as usize;
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(Some(0)).collect()
+    }
+
+    fn not_found(err: &io::Error) -> bool {
+        err.raw_os_error() == Some(ERROR_NOT_FOUND as i32)
+    }
+
+    pub(super) fn read(target: &str) -> io::Result<Option<Vec<u8>>> {
+        let target = wide(target);
+        let mut credential: *mut CREDENTIALW = ptr::null_mut();
+        // SAFETY: target is NUL-terminated; on success credential points to a CREDENTIALW that
+        // stays valid until CredFree.
+        unsafe {
+            if CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut credential) == 0 {
+                let err = io::Error::last_os_error();
+                return if not_found(&err) { Ok(None) } else { Err(err) };
+            }
+            let size = (*credential).CredentialBlobSize as usize;
+            let blob = if size == 0 {
+                Vec::new()
+            } else {
+                std::slice::from_raw_parts((*credential).CredentialBlob, size).to_vec()
+            };
+            CredFree(credential as *const _);
+            Ok(Some(blob))
+        }
+    }
+
+    pub(super) fn write(
+        target: &str,
+        username: &str,
+        comment: &str,
+        blob: &[u8],
+    ) -> io::Result<()> {
+        let (target, username, comment) = (wide(target), wide(username), wide(comment));
+        // SAFETY: CREDENTIALW is plain data, for which all zeroes is empty; the strings and blob
+        // outlive the CredWriteW call, which copies them.
+        unsafe {
+            let mut credential: CREDENTIALW = std::mem::zeroed();
+            credential.Type = CRED_TYPE_GENERIC;
+            credential.TargetName = target.as_ptr() as *mut u16;
+            credential.UserName = username.as_ptr() as *mut u16;
+            credential.Comment = comment.as_ptr() as *mut u16;
+            credential.CredentialBlobSize = blob.len() as u32;
+            credential.CredentialBlob = blob.as_ptr() as *mut u8;
+            credential.Persist = CRED_PERSIST_LOCAL_MACHINE;
+            if CredWriteW(&credential, 0) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    // False when there was no such credential.
+    pub(super) fn delete(target: &str) -> io::Result<bool> {
+        let target = wide(target);
+        // SAFETY: target is NUL-terminated.
+        if unsafe { CredDeleteW(target.as_ptr(), CRED_TYPE_GENERIC, 0) } == 0 {
+            let err = io::Error::last_os_error();
+            return if not_found(&err) { Ok(false) } else { Err(err) };
+        }
+        Ok(true)
+    }
+}
+
+// Service names go on the `security -i` command line too, where only bare words are safe.
+fn is_bare_word(word: &str) -> bool {
+    word.bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b"-_.".contains(&b))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    // Stands in for secret-tool, keeping each entry in a file named after its attributes.
+    const FAKE_SECRET_TOOL: &str = r#"#!/bin/sh
+dir=$(dirname "$0")
+case "$1" in
+  store) cat > "$dir/entry-$5-$7" ;;
+  lookup) cat "$dir/entry-$3-$5" 2>/dev/null ;;
+  clear) rm -f "$dir/entry-$3-$5" ;;
+esac
+"#;
+
+    #[test]
+    fn test_secret_service() {
+        let dir = std::env::temp_dir().join(format!("cogneato-test-{}", rand::random::<u64>()));
+        fs::create_dir_all(&dir).unwrap();
+        let program = dir.join("secret-tool");
+        fs::write(&program, FAKE_SECRET_TOOL).unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o700)).unwrap();
+
+        let store = KeyringTokenStore {
+            service: "cogneato".to_owned(),
+            backend: Backend::SecretService,
+            program: program.to_str().unwrap().to_owned(),
+        };
+        let key = TokenKey {
+            pool_id: "us-east-1_pool".to_owned(),
+            client_id: "client".to_owned(),
+            username: "bob".to_owned(),
+        };
+        let tokens = TokenSet {
+            access_token: "a1".to_owned(),
+            id_token: "i1".to_owned(),
+            refresh_token: Some("r1".into()),
+            token_type: "Bearer".to_owned(),
+            expires_at: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        };
+
+        assert_eq!(store.load(&key).unwrap(), None);
+        store.save(&key, &tokens).unwrap();
+        assert_eq!(store.load(&key).unwrap(), Some(tokens));
+        store.clear(&key).unwrap();
+        assert_eq!(store.load(&key).unwrap(), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
+
+#[cfg(all(test, windows))]
+mod windows_tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_credential_manager() {
+        let store = KeyringTokenStore::new()
+            .unwrap()
+            .with_service(&format!("cogneato-test-{}", rand::random::<u64>()));
+        let key = TokenKey {
+            pool_id: "us-east-1_pool".to_owned(),
+            client_id: "client".to_owned(),
+            username: "bob".to_owned(),
+        };
+        // Longer than one credential holds.
+        let tokens = TokenSet {
+            access_token: "a".repeat(3000),
+            id_token: "i".repeat(3000),
+            refresh_token: Some("r1".into()),
+            token_type: "Bearer".to_owned(),
+            expires_at: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        };
+
+        assert_eq!(store.load(&key).unwrap(), None);
+        store.save(&key, &tokens).unwrap();
+        assert_eq!(store.load(&key).unwrap(), Some(tokens.clone()));
+        let shorter = TokenSet {
+            access_token: "a2".to_owned(),
+            id_token: "i2".to_owned(),
+            ..tokens
+        };
+        store.save(&key, &shorter).unwrap();
+        assert_eq!(store.load(&key).unwrap(), Some(shorter));
+        store.clear(&key).unwrap();
+        assert_eq!(store.load(&key).unwrap(), None);
+    }
+}
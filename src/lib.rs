@@ -1 +1,160 @@
-mod aws_srp;
+//! A port of pycognito: SRP authentication and a client for the Cognito user pool API.
+//!
+//! The crate root re-exports and [`prelude`] are the stable surface; they keep their names and
+//! signatures across minor releases even when the modules behind them are reorganised.
+//!
+//! Without the `std` feature only the SRP math is built, on `core` and `alloc`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "client")]
+mod account;
+#[cfg(feature = "client")]
+pub mod admin;
+#[cfg(feature = "client")]
+pub mod api;
+#[cfg(feature = "client")]
+pub mod attributes;
+#[cfg(feature = "client")]
+pub mod auth;
+#[cfg(feature = "client")]
+pub mod bearer;
+pub mod build_info;
+#[cfg(feature = "client")]
+pub mod bulk;
+#[cfg(feature = "client")]
+pub mod cancel;
+#[cfg(feature = "cli")]
+pub mod cli;
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(any(feature = "client", feature = "verify"))]
+pub mod clock;
+#[cfg(feature = "client")]
+pub mod config;
+#[cfg(feature = "client")]
+pub mod context_data;
+#[cfg(feature = "client")]
+pub mod credentials;
+#[cfg_attr(not(feature = "core"), allow(dead_code))]
+mod crypto;
+#[cfg(feature = "client")]
+pub mod curl;
+#[cfg(feature = "client")]
+pub mod device;
+#[cfg(feature = "client")]
+pub mod driver;
+pub mod error;
+#[cfg(feature = "ffi")]
+mod ffi;
+#[cfg(feature = "client")]
+pub mod flow;
+#[cfg(feature = "client")]
+mod hooks;
+#[cfg(feature = "client")]
+pub mod identity;
+#[cfg(any(feature = "client", feature = "verify"))]
+#[cfg_attr(not(feature = "client"), allow(dead_code))]
+mod json;
+#[cfg(any(feature = "client", feature = "verify"))]
+pub mod jwt;
+#[cfg(feature = "keyring")]
+pub mod keyring;
+#[cfg(feature = "client")]
+pub mod loopback;
+#[cfg(feature = "client")]
+pub mod manager;
+#[cfg(feature = "client")]
+pub mod metrics;
+#[cfg(feature = "tower")]
+pub mod middleware;
+#[cfg(feature = "node")]
+mod node;
+#[cfg(feature = "client")]
+pub mod oauth;
+#[cfg(feature = "client")]
+pub mod passkey;
+#[cfg(feature = "client")]
+pub mod password;
+pub mod pool_id;
+#[cfg(feature = "reqwest")]
+pub mod pooled;
+#[cfg(feature = "client")]
+pub mod prelude;
+#[cfg(feature = "client")]
+pub mod profile;
+#[cfg(feature = "client")]
+pub mod provider;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "client")]
+pub mod registration;
+#[cfg(feature = "client")]
+pub mod resumable;
+#[cfg(feature = "client")]
+pub mod retry;
+#[cfg(feature = "client")]
+pub mod rng;
+#[cfg_attr(not(feature = "core"), allow(dead_code))]
+pub mod secret;
+#[cfg(feature = "client")]
+pub mod session;
+#[cfg(feature = "client")]
+mod sigv4;
+#[cfg(feature = "client")]
+pub mod software_token;
+#[cfg(feature = "client")]
+pub mod store;
+// The one SRP implementation; only the client drives the exchange, but its known-answer mode
+// and vectors come with the `core` math.
+#[cfg(feature = "core")]
+#[cfg_attr(not(feature = "client"), allow(dead_code))]
+pub mod srp;
+// Plain RFC 5054 SRP-6a over any of the standard groups; `srp` is its Cognito flavour.
+#[cfg(feature = "core")]
+#[cfg_attr(not(feature = "client"), allow(dead_code))]
+pub mod srp6a;
+#[cfg(feature = "client")]
+pub mod sts;
+#[cfg(feature = "client")]
+pub mod tasks;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+#[cfg(feature = "client")]
+mod time;
+#[cfg(feature = "client")]
+pub mod tokens;
+#[cfg(feature = "client")]
+pub mod transport;
+#[cfg(feature = "client")]
+mod url;
+#[cfg(feature = "client")]
+pub mod user;
+#[cfg(feature = "client")]
+pub mod user_auth;
+#[cfg(feature = "client")]
+pub mod username;
+#[cfg(feature = "client")]
+pub mod wire;
+
+pub use error::CognitoSrpError;
+pub use pool_id::PoolId;
+#[cfg(feature = "client")]
+pub use prelude::*;
+// The entry points of the feature modules, for callers that go beyond the prelude.
+#[cfg(feature = "client")]
+pub use api::{CognitoApi, MockCognitoApi};
+#[cfg(feature = "client")]
+pub use credentials::AwsCredentials;
+#[cfg(feature = "client")]
+pub use identity::{CognitoSrpCredentialsProvider, IdentityCredentials};
+#[cfg(feature = "tower")]
+pub use middleware::CognitoAuthLayer;
+#[cfg(feature = "client")]
+pub use oauth::OAuthClient;
+#[cfg(feature = "core")]
+pub use srp::SrpSession;
+#[cfg(feature = "client")]
+pub use store::{FileTokenStore, TokenStore};
@@ -0,0 +1,296 @@
+use crate::client::{ServiceError, ServiceErrorKind};
+use crate::crypto::constant_time_eq;
+use crate::error::{CognitoSrpError, Result};
+use crate::oauth::{OAuthClient, Pkce};
+use crate::tokens::TokenSet;
+use crate::url;
+use data_encoding::BASE64URL_NOPAD;
+use rand::RngCore;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// # https://datatracker.ietf.org/doc/html/rfc8252#section-7.3
+// The native-app hosted UI flow: listen on localhost, send the browser to the login page and
+// catch the redirect. "http://localhost:<port>/callback" must be one of the app client's
+// callback URLs, so pick a fixed port.
+pub struct LoopbackRedirect {
+    listener: TcpListener,
+    redirect_uri: String,
+    state: String,
+    pkce: Pkce,
+}
+
+impl LoopbackRedirect {
+    // Port 0 picks a free port, which only works with emulators that accept any callback.
+    pub fn bind(port: u16) -> Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        let port = listener.local_addr()?.port();
+        let mut state = [0; 16];
+        rand::thread_rng().fill_bytes(&mut state);
+        Ok(Self {
+            listener,
+            redirect_uri: format!("http://localhost:{}/callback", port),
+            state: BASE64URL_NOPAD.encode(&state),
+            pkce: Pkce::generate(),
+        })
+    }
+
+    pub fn redirect_uri(&self) -> &str {
+        &self.redirect_uri
+    }
+
+    // Carries this listener's state and PKCE challenge.
+    pub fn authorize_url(&self, oauth: &OAuthClient, scopes: &[&str]) -> String {
+        oauth
+            .authorize_url(&self.redirect_uri)
+            .with_scopes(scopes)
+            .with_state(&self.state)
+            .with_pkce(&self.pkce)
+            .to_string()
+    }
+
+    // Serves requests until the callback arrives or `timeout` passes; anything other than
+    // /callback (a favicon, say) gets a 404. A callback with the wrong state gets a 400 and is
+    // otherwise ignored, so a forged redirect can't cut the real sign-in short. So is a
+    // connection that fails: browsers open speculative connections to localhost that never send
+    // anything, and a peer may hang up before it reads the response.
+    pub fn wait_for_code(&self, timeout: Duration) -> Result<String> {
+        let deadline = Instant::now() + timeout;
+        self.listener.set_nonblocking(true)?;
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if let Some(result) = self.handle(stream) {
+                        return result;
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    if Instant::now() >= deadline {
+                        return Err(CognitoSrpError::Io(std::io::Error::new(
+                            ErrorKind::TimedOut,
+                            "timed out waiting for the hosted UI redirect",
+                        )));
+                    }
+                    thread::sleep(Duration::from_millis(50));
+                }
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        ErrorKind::ConnectionAborted
+                            | ErrorKind::ConnectionReset
+                            | ErrorKind::Interrupted
+                    ) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    // None unless this connection was the callback.
+    fn handle(&self, mut stream: TcpStream) -> Option<Result<String>> {
+        let request_line = read_request_line(&stream).ok()?;
+        // "GET /callback?code=...&state=... HTTP/1.1"
+        let target = request_line.split(' ').nth(1).unwrap_or_default();
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        if path != "/callback" {
+            let _ = respond(&mut stream, "404 Not Found", "Not found.");
+            return None;
+        }
+        let query = url::parse_query(query);
+        let param = |name: &str| {
+            query
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.as_str())
+        };
+        let state = param("state").unwrap_or_default();
+        if !constant_time_eq(state.as_bytes(), self.state.as_bytes()) {
+            let _ = respond(
+                &mut stream,
+                "400 Bad Request",
+                "This sign-in link is stale or forged.",
+            );
+            return None;
+        }
+        let result = if let Some(error) = param("error") {
+            Err(ServiceError {
+                operation: "oauth2/authorize".to_owned(),
+                status: 400,
+                kind: ServiceErrorKind::parse(error),
+                message: param("error_description").unwrap_or_default().to_owned(),
+            }
+            .into())
+        } else {
+            param("code")
+                .map(str::to_owned)
+                .ok_or_else(|| CognitoSrpError::InvalidResponse("redirect has no code".to_owned()))
+        };
+        // The redirect has been read; a browser that's gone by now doesn't change the outcome.
+        let _ = match &result {
+            Ok(_) => respond(
+                &mut stream,
+                "200 OK",
+                "Signed in. You can close this window.",
+            ),
+            Err(_) => respond(
+                &mut stream,
+                "400 Bad Request",
+                "Sign-in failed. Return to the application for details.",
+            ),
+        };
+        Some(result)
+    }
+
+    // Waits for the redirect and trades the code for tokens.
+    pub fn finish(&self, oauth: &OAuthClient, timeout: Duration) -> Result<TokenSet> {
+        let code = self.wait_for_code(timeout)?;
+        oauth.exchange_code(&code, &self.redirect_uri, Some(&self.pkce.verifier))
+    }
+}
+
+fn read_request_line(stream: &TcpStream) -> std::io::Result<String> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut request_line = String::new();
+    BufReader::new(stream).read_line(&mut request_line)?;
+    Ok(request_line)
+}
+
+fn respond(stream: &mut TcpStream, status: &str, message: &str) -> Result<()> {
+    let body = format!(
+        "<!doctype html><title>cog-neat-oh</title><p>{}</p>",
+        message
+    );
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+// Opens `url` in the default browser.
+pub fn open_browser(url: &str) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        // Not `cmd /C start`: cmd would split the URL at its first `&`.
+        let mut command = Command::new("rundll32");
+        command.arg("url.dll,FileProtocolHandler");
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    let status = command.arg(url).status()?;
+    if !status.success() {
+        return Err(CognitoSrpError::Io(std::io::Error::other(format!(
+            "could not open a browser ({})",
+            status
+        ))));
+    }
+    Ok(())
+}
+
+impl OAuthClient {
+    // Everything at once: listen on `port`, open the browser on the authorize URL and wait up
+    // to `timeout` for the user to finish signing in.
+    pub fn sign_in_with_browser(
+        &self,
+        port: u16,
+        scopes: &[&str],
+        timeout: Duration,
+    ) -> Result<TokenSet> {
+        let redirect = LoopbackRedirect::bind(port)?;
+        open_browser(&redirect.authorize_url(self, scopes))?;
+        redirect.finish(self, timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Client;
+    use crate::transport::mock::MockTransport;
+    use std::io::Read;
+
+    fn get(redirect_uri: &str, path: &str) -> String {
+        let address = redirect_uri
+            .trim_start_matches("http://localhost:")
+            .trim_end_matches("/callback");
+        let mut stream =
+            TcpStream::connect(("127.0.0.1", address.parse::<u16>().unwrap())).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_loopback_sign_in() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"access_token":"a","id_token":"i","refresh_token":"r","expires_in":3600}"#,
+        );
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let oauth = OAuthClient::new(client, "auth.example.com");
+        let redirect = LoopbackRedirect::bind(0).unwrap();
+        let url = redirect.authorize_url(&oauth, &["openid"]);
+        assert!(url.contains(&format!("state={}", redirect.state)));
+
+        let (redirect_uri, state) = (redirect.redirect_uri.clone(), redirect.state.clone());
+        let browser = thread::spawn(move || {
+            let not_found = get(&redirect_uri, "/favicon.ico");
+            let forged = get(&redirect_uri, "/callback?code=evil&state=wrong");
+            let ok = get(
+                &redirect_uri,
+                &format!("/callback?code=c%2D1&state={}", state),
+            );
+            (not_found, forged, ok)
+        });
+        // The forged redirect is turned away and the wait goes on to the real one.
+        let tokens = redirect.finish(&oauth, Duration::from_secs(10)).unwrap();
+        assert_eq!(tokens.access_token, "a");
+
+        let (not_found, forged, ok) = browser.join().unwrap();
+        assert!(not_found.starts_with("HTTP/1.1 404"));
+        assert!(forged.starts_with("HTTP/1.1 400"));
+        assert!(ok.starts_with("HTTP/1.1 200"));
+        let body = transport.last_request().body;
+        assert!(body.contains("code=c-1&"));
+        assert!(body.contains(&format!("code_verifier={}", redirect.pkce.verifier)));
+    }
+
+    #[test]
+    fn test_idle_connections() {
+        let redirect = LoopbackRedirect::bind(0).unwrap();
+        let (redirect_uri, state) = (redirect.redirect_uri.clone(), redirect.state.clone());
+        let port = redirect.listener.local_addr().unwrap().port();
+        let browser = thread::spawn(move || {
+            // A preconnect that's closed unused, and one that sits idle past the read timeout.
+            drop(TcpStream::connect(("127.0.0.1", port)).unwrap());
+            let idle = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            thread::sleep(Duration::from_millis(5500));
+            drop(idle);
+            get(&redirect_uri, &format!("/callback?code=c&state={}", state))
+        });
+        assert_eq!(
+            redirect.wait_for_code(Duration::from_secs(15)).unwrap(),
+            "c"
+        );
+        assert!(browser.join().unwrap().starts_with("HTTP/1.1 200"));
+    }
+
+    #[test]
+    fn test_wait_times_out() {
+        let redirect = LoopbackRedirect::bind(0).unwrap();
+        let err = redirect
+            .wait_for_code(Duration::from_millis(100))
+            .unwrap_err();
+        assert!(matches!(err, CognitoSrpError::Io(err) if err.kind() == ErrorKind::TimedOut));
+    }
+}
@@ -0,0 +1,296 @@
+// Tokens for every signed-in user of a server (a backend-for-frontend, a gateway, ...), behind
+// one handle that clones cheaply into axum State or a tonic service:
+//
+//     let tokens = TokenManager::new(client).with_store(FileTokenStore::in_cache_dir()?);
+//     tokens.insert("bob", signed_in)?;                   // after sign-in
+//     let access_token = tokens.access_token("bob")?;     // in any handler, on any thread
+//
+// Each user's tokens live in their own RefreshingTokenProvider, so refreshes are single-flight
+// per user and one user's refresh never holds up another's. A single-user service can share a
+// RefreshingTokenProvider in an Arc directly.
+use crate::client::Client;
+use crate::error::{CognitoSrpError, Result};
+use crate::provider::{RefreshingTokenProvider, TokenProvider};
+use crate::store::{TokenKey, TokenStore};
+use crate::tasks::BackgroundTask;
+use crate::tokens::TokenSet;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+
+type Users = BTreeMap<String, Arc<RefreshingTokenProvider>>;
+
+#[derive(Clone)]
+pub struct TokenManager {
+    client: Client,
+    lead_time: Option<Duration>,
+    store: Option<Arc<dyn TokenStore>>,
+    users: Arc<RwLock<Users>>,
+}
+
+impl TokenManager {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            lead_time: None,
+            store: None,
+            users: Arc::default(),
+        }
+    }
+
+    // See RefreshingTokenProvider::with_lead_time.
+    pub fn with_lead_time(mut self, lead_time: Duration) -> Self {
+        self.lead_time = Some(lead_time);
+        self
+    }
+
+    // Saves every user's tokens, and picks up users signed in before a restart on first use.
+    pub fn with_store(mut self, store: impl TokenStore + 'static) -> Self {
+        self.store = Some(Arc::new(store));
+        self
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    // Starts managing (or replaces) `username`'s tokens.
+    pub fn insert(&self, username: &str, tokens: TokenSet) -> Result<Arc<RefreshingTokenProvider>> {
+        let provider = match &self.store {
+            Some(store) => {
+                store.save(&TokenKey::new(&self.client, username), &tokens)?;
+                RefreshingTokenProvider::from_store(self.client.clone(), username, store.clone())
+            }
+            None => RefreshingTokenProvider::new(self.client.clone(), username, tokens),
+        };
+        let provider = Arc::new(self.configure(provider));
+        self.write().insert(username.to_owned(), provider.clone());
+        Ok(provider)
+    }
+
+    // The provider behind `username`, e.g. for a BearerAuth; None for a user never inserted (and,
+    // with a store, with nothing stored).
+    pub fn provider(&self, username: &str) -> Result<Option<Arc<RefreshingTokenProvider>>> {
+        if let Some(provider) = self.read().get(username) {
+            return Ok(Some(provider.clone()));
+        }
+        let Some(store) = &self.store else {
+            return Ok(None);
+        };
+        if store
+            .load(&TokenKey::new(&self.client, username))?
+            .is_none()
+        {
+            return Ok(None);
+        }
+        let mut users = self.write();
+        // Another thread may have got here first; keep its provider so there is one per user.
+        let provider = users.entry(username.to_owned()).or_insert_with(|| {
+            let provider =
+                RefreshingTokenProvider::from_store(self.client.clone(), username, store.clone());
+            Arc::new(self.configure(provider))
+        });
+        Ok(Some(provider.clone()))
+    }
+
+    pub fn tokens(&self, username: &str) -> Result<TokenSet> {
+        self.provider(username)?
+            .ok_or_else(|| CognitoSrpError::SignedOut(format!("no tokens for {}", username)))?
+            .tokens()
+    }
+
+    pub fn access_token(&self, username: &str) -> Result<String> {
+        Ok(self.tokens(username)?.access_token)
+    }
+
+    pub fn id_token(&self, username: &str) -> Result<String> {
+        Ok(self.tokens(username)?.id_token)
+    }
+
+    // See TokenProvider::invalidate.
+    pub fn invalidate(&self, username: &str) {
+        if let Some(provider) = self.read().get(username) {
+            provider.invalidate();
+        }
+    }
+
+    // Signs `username` out everywhere: revokes their refresh token with Cognito and forgets their
+    // tokens here and in the store, even if the revocation fails.
+    pub fn revoke(&self, username: &str) -> Result<()> {
+        let revoked = match self.provider(username)? {
+            Some(provider) => provider.revoke(),
+            None => Ok(()),
+        };
+        self.remove(username)?;
+        revoked
+    }
+
+    // Forgets `username`'s tokens here and in the store without revoking them; see revoke.
+    pub fn remove(&self, username: &str) -> Result<()> {
+        self.write().remove(username);
+        match &self.store {
+            Some(store) => store.clear(&TokenKey::new(&self.client, username)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn usernames(&self) -> Vec<String> {
+        self.read().keys().cloned().collect()
+    }
+
+    // Every `interval`, refreshes any user whose tokens are within the lead time of expiring, so
+    // handlers rarely wait on a refresh. Failures are left for the next access_token to report.
+    // Runs until the task is dropped or shut down.
+    pub fn spawn_refresh_ahead(&self, interval: Duration) -> Result<BackgroundTask> {
+        let manager = self.clone();
+        BackgroundTask::spawn_periodic("cogneato-refresh", interval, move || {
+            let providers: Vec<_> = manager.read().values().cloned().collect();
+            for provider in providers {
+                let _ = provider.tokens();
+            }
+        })
+    }
+
+    fn configure(&self, provider: RefreshingTokenProvider) -> RefreshingTokenProvider {
+        match self.lead_time {
+            Some(lead_time) => provider.with_lead_time(lead_time),
+            None => provider,
+        }
+    }
+
+    // A panic elsewhere while holding the lock leaves the map itself intact.
+    fn read(&self) -> RwLockReadGuard<'_, Users> {
+        self.users.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Users> {
+        self.users.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::FileTokenStore;
+    use crate::transport::mock::MockTransport;
+    use std::thread;
+    use std::time::SystemTime;
+
+    const REFRESHED: &str =
+        r#"{"AuthenticationResult":{"AccessToken":"a2","IdToken":"i2","ExpiresIn":3600}}"#;
+
+    fn tokens(access_token: &str, expires_in: u64) -> TokenSet {
+        TokenSet {
+            access_token: access_token.to_owned(),
+            id_token: "i1".to_owned(),
+            refresh_token: Some("r1".into()),
+            token_type: "Bearer".to_owned(),
+            expires_at: SystemTime::now() + Duration::from_secs(expires_in),
+        }
+    }
+
+    fn assert_shareable<T: Clone + Send + Sync + 'static>() {}
+
+    #[test]
+    fn test_shared_across_threads() {
+        assert_shareable::<TokenManager>();
+        let transport = MockTransport::new();
+        transport.respond(200, REFRESHED);
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let manager = TokenManager::new(client);
+        manager.insert("alice", tokens("alice1", 3600)).unwrap();
+        manager.insert("bob", tokens("bob1", 0)).unwrap();
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let manager = manager.clone();
+                thread::spawn(move || {
+                    (
+                        manager.access_token("alice").unwrap(),
+                        manager.access_token("bob").unwrap(),
+                    )
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(
+                handle.join().unwrap(),
+                ("alice1".to_owned(), "a2".to_owned())
+            );
+        }
+        assert_eq!(transport.requests.lock().unwrap().len(), 1);
+        assert_eq!(manager.usernames(), ["alice", "bob"]);
+
+        manager.remove("bob").unwrap();
+        assert!(matches!(
+            manager.tokens("bob"),
+            Err(CognitoSrpError::SignedOut(_))
+        ));
+    }
+
+    #[test]
+    fn test_store_survives_restart() {
+        let dir = std::env::temp_dir().join(format!("cogneato-test-{}", rand::random::<u64>()));
+        let transport = MockTransport::new();
+        let manager = || {
+            let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+            TokenManager::new(client).with_store(FileTokenStore::new(&dir))
+        };
+        manager().insert("bob", tokens("a1", 3600)).unwrap();
+
+        let restarted = manager();
+        assert!(restarted.usernames().is_empty());
+        assert_eq!(restarted.access_token("bob").unwrap(), "a1");
+        assert!(restarted.provider("alice").unwrap().is_none());
+        restarted.remove("bob").unwrap();
+        assert!(manager().provider("bob").unwrap().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_revoke() {
+        let dir = std::env::temp_dir().join(format!("cogneato-test-{}", rand::random::<u64>()));
+        let transport = MockTransport::new();
+        transport.respond(200, "{}");
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let store = FileTokenStore::new(&dir);
+        let manager = TokenManager::new(client.clone()).with_store(store.clone());
+        manager.insert("bob", tokens("a1", 3600)).unwrap();
+
+        manager.revoke("bob").unwrap();
+        let body = transport.last_body();
+        assert_eq!(body.str_field("Token").unwrap(), "r1");
+        assert_eq!(store.load(&TokenKey::new(&client, "bob")).unwrap(), None);
+        assert!(manager.usernames().is_empty());
+        assert!(matches!(
+            manager.tokens("bob"),
+            Err(CognitoSrpError::SignedOut(_))
+        ));
+        // Nothing left to revoke.
+        manager.revoke("bob").unwrap();
+        assert_eq!(transport.requests.lock().unwrap().len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_refresh_ahead() {
+        let transport = MockTransport::new();
+        transport.respond(200, REFRESHED);
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let manager = TokenManager::new(client);
+        manager.insert("alice", tokens("alice1", 3600)).unwrap();
+        manager.insert("bob", tokens("bob1", 60)).unwrap();
+
+        let task = manager
+            .spawn_refresh_ahead(Duration::from_millis(1))
+            .unwrap();
+        while transport.requests.lock().unwrap().is_empty() {
+            thread::yield_now();
+        }
+        task.shutdown().unwrap();
+        // Only bob was due; his handler gets the new set without a refresh of its own.
+        assert_eq!(manager.access_token("bob").unwrap(), "a2");
+        assert_eq!(manager.access_token("alice").unwrap(), "alice1");
+        assert_eq!(transport.requests.lock().unwrap().len(), 1);
+    }
+}
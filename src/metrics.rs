@@ -0,0 +1,114 @@
+use crate::error::{CognitoSrpError, ServiceErrorKind};
+use std::time::Duration;
+
+// The user-level flows the client measures as a whole, retries included. Each cognito-idp call
+// inside them is measured as well, under its operation name ("InitiateAuth", "SignUp", ...).
+pub const AUTHENTICATE: &str = "authenticate";
+pub const ADMIN_AUTHENTICATE: &str = "admin_authenticate";
+pub const REFRESH: &str = "refresh";
+pub const JWKS_FETCH: &str = "jwks_fetch";
+
+// One finished operation: a flow above or a single cognito-idp call.
+#[derive(Clone, Copy, Debug)]
+pub struct Measurement<'a> {
+    pub name: &'a str,
+    // By the client's Clock, so a FixedClock reports zero.
+    pub elapsed: Duration,
+    pub error: Option<&'a CognitoSrpError>,
+}
+
+impl Measurement<'_> {
+    // A stable label for the failure: the Cognito exception ("NotAuthorizedException",
+    // "TooManyRequestsException", ...), "Transport" when no response came back, "Client" for
+    // anything that failed on this side.
+    pub fn error_type(&self) -> Option<&str> {
+        self.error.map(|err| match err.service_kind() {
+            Some(kind) => kind.as_str(),
+            None if matches!(err, CognitoSrpError::Transport(_)) => "Transport",
+            None => "Client",
+        })
+    }
+
+    pub fn is_throttle(&self) -> bool {
+        self.error.is_some_and(CognitoSrpError::is_throttle)
+    }
+
+    pub fn is_not_authorized(&self) -> bool {
+        self.error.and_then(CognitoSrpError::service_kind) == Some(&ServiceErrorKind::NotAuthorized)
+    }
+}
+
+// Receives a Measurement for every flow and call; see Client::with_metrics. Keep it cheap, it
+// runs inline on the calling thread.
+pub trait Metrics: Send + Sync {
+    fn record(&self, measurement: &Measurement<'_>);
+}
+
+impl<F: Fn(&Measurement<'_>) + Send + Sync> Metrics for F {
+    fn record(&self, measurement: &Measurement<'_>) {
+        self(measurement)
+    }
+}
+
+// # https://docs.rs/metrics/
+// Reports through the `metrics` facade to whichever recorder (Prometheus, StatsD, ...) the
+// application installed:
+//   cognito_operations_total{operation, outcome="ok"|"error", error_type}
+//   cognito_operation_duration_seconds{operation}
+#[cfg(feature = "metrics")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MetricsFacade;
+
+#[cfg(feature = "metrics")]
+impl Metrics for MetricsFacade {
+    fn record(&self, measurement: &Measurement<'_>) {
+        let operation = measurement.name.to_owned();
+        let (outcome, error_type) = match measurement.error_type() {
+            Some(error_type) => ("error", error_type.to_owned()),
+            None => ("ok", String::new()),
+        };
+        metrics::counter!(
+            "cognito_operations_total",
+            "operation" => operation.clone(),
+            "outcome" => outcome,
+            "error_type" => error_type
+        )
+        .increment(1);
+        metrics::histogram!("cognito_operation_duration_seconds", "operation" => operation)
+            .record(measurement.elapsed.as_secs_f64());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ServiceError;
+
+    #[test]
+    fn test_error_type() {
+        let measurement = |error| Measurement {
+            name: AUTHENTICATE,
+            elapsed: Duration::ZERO,
+            error,
+        };
+        assert_eq!(measurement(None).error_type(), None);
+
+        let err = CognitoSrpError::from(ServiceError {
+            operation: "InitiateAuth".to_owned(),
+            status: 400,
+            kind: ServiceErrorKind::NotAuthorized,
+            message: String::new(),
+        });
+        assert_eq!(
+            measurement(Some(&err)).error_type(),
+            Some("NotAuthorizedException")
+        );
+        assert!(measurement(Some(&err)).is_not_authorized());
+        assert!(!measurement(Some(&err)).is_throttle());
+
+        let err = CognitoSrpError::Transport("reset".into());
+        assert_eq!(measurement(Some(&err)).error_type(), Some("Transport"));
+        let err = CognitoSrpError::InvalidServerB;
+        assert_eq!(measurement(Some(&err)).error_type(), Some("Client"));
+    }
+}
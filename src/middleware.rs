@@ -0,0 +1,188 @@
+// A tower layer that turns away requests without a valid Cognito bearer token, so an API
+// protects its routes with one `.layer(..)`:
+//
+//     let verifier = Verifier::fetch(&client)?;
+//     let app = Router::new().route("/me", get(me)).layer(CognitoAuthLayer::new(verifier));
+//     async fn me(claims: Claims) -> String { claims.sub }
+//
+// The signature, issuer, app client and expiry are checked by jwt::Verifier; the layer adds the
+// token_use requirement, answers 401 on failure and puts the Claims in the request extensions.
+use crate::jwt::{Claims, TokenUse, Verifier};
+use futures_util::future::Either;
+use http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use http::{HeaderValue, Request, Response, StatusCode};
+use std::future::{ready, Ready};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+// # https://datatracker.ietf.org/doc/html/rfc6750#section-2.1
+fn bearer_token<B>(request: &Request<B>) -> Option<&str> {
+    let value = request.headers().get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim())
+        .filter(|token| !token.is_empty())
+}
+
+#[derive(Clone, Debug)]
+pub struct CognitoAuthLayer {
+    verifier: Arc<Verifier>,
+    token_use: TokenUse,
+}
+
+impl CognitoAuthLayer {
+    // Accepts access tokens, which is what API clients should send.
+    pub fn new(verifier: Verifier) -> Self {
+        Self {
+            verifier: Arc::new(verifier),
+            token_use: TokenUse::Access,
+        }
+    }
+
+    // For APIs that take the ID token instead, e.g. behind an API Gateway Cognito authorizer.
+    pub fn with_token_use(mut self, token_use: TokenUse) -> Self {
+        self.token_use = token_use;
+        self
+    }
+
+    fn authorize<B>(&self, request: &Request<B>) -> Option<Claims> {
+        let claims = self.verifier.verify(bearer_token(request)?).ok()?;
+        (claims.token_use == self.token_use).then_some(claims)
+    }
+}
+
+impl<S> Layer<S> for CognitoAuthLayer {
+    type Service = CognitoAuth<S>;
+
+    fn layer(&self, inner: S) -> CognitoAuth<S> {
+        CognitoAuth {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CognitoAuth<S> {
+    inner: S,
+    layer: CognitoAuthLayer,
+}
+
+// # https://datatracker.ietf.org/doc/html/rfc6750#section-3
+// The reason stays out of the response; a client that sent a bad token learns only that.
+fn unauthorized<B: Default>() -> Response<B> {
+    let mut response = Response::new(B::default());
+    *response.status_mut() = StatusCode::UNAUTHORIZED;
+    response.headers_mut().insert(
+        WWW_AUTHENTICATE,
+        HeaderValue::from_static("Bearer error=\"invalid_token\""),
+    );
+    response
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for CognitoAuth<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: Default,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = Either<Ready<Result<Response<ResBody>, S::Error>>, S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        match self.layer.authorize(&request) {
+            Some(claims) => {
+                request.extensions_mut().insert(claims);
+                Either::Right(self.inner.call(request))
+            }
+            None => Either::Left(ready(Ok(unauthorized()))),
+        }
+    }
+}
+
+// # https://docs.rs/axum/latest/axum/extract/index.html
+// Handlers behind CognitoAuthLayer take `claims: Claims`; without the layer it is a 401 too.
+#[cfg(feature = "axum")]
+#[axum::async_trait]
+impl<S: Send + Sync> axum::extract::FromRequestParts<S> for Claims {
+    type Rejection = Response<axum::body::Body>;
+
+    async fn from_request_parts(
+        parts: &mut http::request::Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Claims>()
+            .cloned()
+            .ok_or_else(unauthorized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jwt::fixtures::{token, verifier};
+    use futures_util::FutureExt;
+    use std::convert::Infallible;
+
+    fn call(layer: &CognitoAuthLayer, authorization: Option<&str>) -> Response<String> {
+        let mut service = layer.layer(tower::service_fn(|request: Request<()>| {
+            let claims = request.extensions().get::<Claims>().cloned();
+            ready(Ok::<_, Infallible>(Response::new(
+                claims.map(|c| c.sub).unwrap_or_default(),
+            )))
+        }));
+        let mut request = Request::new(());
+        if let Some(value) = authorization {
+            request
+                .headers_mut()
+                .insert(AUTHORIZATION, HeaderValue::from_str(value).unwrap());
+        }
+        service.call(request).now_or_never().unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_layer() {
+        let layer = CognitoAuthLayer::new(verifier());
+        let response = call(&layer, Some(&format!("Bearer {}", token("access_admin"))));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), "sub-bob");
+
+        for authorization in [
+            None,
+            Some("Basic Ym9iOnB3".to_owned()),
+            Some("Bearer ".to_owned()),
+            Some(format!("Bearer {}", token("id_admin"))),
+            Some(format!("Bearer {}", token("id_expired"))),
+        ] {
+            let response = call(&layer, authorization.as_deref());
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(
+                response.headers()[WWW_AUTHENTICATE],
+                "Bearer error=\"invalid_token\""
+            );
+        }
+
+        let layer = layer.with_token_use(TokenUse::Id);
+        let response = call(&layer, Some(&format!("bearer {}", token("id_admin"))));
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_nested_header() {
+        let layer = CognitoAuthLayer::new(verifier());
+        // Past the header length cap, and within it but past the JSON depth cap.
+        for depth in [6000, 600] {
+            let header = data_encoding::BASE64URL_NOPAD.encode("[".repeat(depth).as_bytes());
+            let response = call(&layer, Some(&format!("Bearer {}.e30.c2ln", header)));
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+}
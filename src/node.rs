@@ -0,0 +1,238 @@
+// # https://napi.rs/
+// Node bindings for server-side code replacing the SRP part of amazon-cognito-identity-js.
+// `authenticate` and `refresh` return promises and run on the libuv thread pool, so the event
+// loop never waits on curl or the modpow; `SrpSession` is the sans-I/O piece for callers that
+// keep their own AWS SDK calls:
+//
+//     const { authenticate, SrpSession } = require("cog-neat-oh");
+//     const result = await authenticate({ poolId, clientId }, "bob", password);
+//     if (result.tokens) use(result.tokens.accessToken);
+use crate::auth::Authentication;
+use crate::client::Client;
+use crate::clock::{cognito_timestamp, Clock, SystemClock};
+use crate::curl::{CurlTransport, TransportConfig};
+use crate::error::CognitoSrpError;
+use crate::secret::SecretString;
+use crate::srp;
+use crate::tokens::TokenSet;
+use napi::bindgen_prelude::AsyncTask;
+use napi::{Env, Task};
+use napi_derive::napi;
+use std::collections::HashMap;
+use std::time::UNIX_EPOCH;
+
+fn to_napi(err: CognitoSrpError) -> napi::Error {
+    let status = match err {
+        CognitoSrpError::InvalidHex(_)
+        | CognitoSrpError::InvalidPoolId(_)
+        | CognitoSrpError::InvalidArgument(_)
+        | CognitoSrpError::Configuration(_) => napi::Status::InvalidArg,
+        _ => napi::Status::GenericFailure,
+    };
+    napi::Error::new(status, err.to_string())
+}
+
+#[napi(object)]
+pub struct ClientConfig {
+    pub pool_id: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    // e.g. "http://localhost:9229/" for cognito-local.
+    pub endpoint: Option<String>,
+}
+
+impl ClientConfig {
+    // Requests go through CurlTransport with HTTPS_PROXY and NO_PROXY from the environment.
+    fn client(&self) -> crate::error::Result<Client> {
+        let mut client = Client::new(
+            &self.pool_id,
+            &self.client_id,
+            CurlTransport::new(TransportConfig::from_env()),
+        )?;
+        if let Some(secret) = &self.client_secret {
+            client = client.with_client_secret(secret);
+        }
+        if let Some(endpoint) = &self.endpoint {
+            client = client.with_endpoint(endpoint);
+        }
+        Ok(client)
+    }
+}
+
+#[napi(object)]
+pub struct Tokens {
+    pub access_token: String,
+    pub id_token: String,
+    pub refresh_token: Option<String>,
+    pub token_type: String,
+    // Milliseconds since the epoch, ready for `new Date(expiresAt)`.
+    pub expires_at: f64,
+}
+
+impl From<TokenSet> for Tokens {
+    fn from(tokens: TokenSet) -> Self {
+        Self {
+            expires_at: tokens
+                .expires_at
+                .duration_since(UNIX_EPOCH)
+                .map_or(0.0, |d| d.as_millis() as f64),
+            refresh_token: tokens
+                .refresh_token
+                .as_ref()
+                .map(|t| t.expose_secret().to_owned()),
+            access_token: tokens.access_token,
+            id_token: tokens.id_token,
+            token_type: tokens.token_type,
+        }
+    }
+}
+
+#[napi(object)]
+pub struct ChallengeInfo {
+    pub name: String,
+    pub session: Option<String>,
+    pub username: String,
+    pub parameters: HashMap<String, String>,
+}
+
+// Exactly one of the two is set.
+#[napi(object)]
+pub struct AuthResult {
+    pub tokens: Option<Tokens>,
+    pub challenge: Option<ChallengeInfo>,
+}
+
+impl From<Authentication> for AuthResult {
+    fn from(authentication: Authentication) -> Self {
+        match authentication {
+            Authentication::Tokens(tokens) => Self {
+                tokens: Some(tokens.into()),
+                challenge: None,
+            },
+            Authentication::Challenge(challenge) => Self {
+                tokens: None,
+                challenge: Some(ChallengeInfo {
+                    name: challenge.name.as_str().to_owned(),
+                    session: challenge.session,
+                    username: challenge.username,
+                    parameters: challenge.parameters.into_iter().collect(),
+                }),
+            },
+        }
+    }
+}
+
+pub struct Authenticate {
+    config: ClientConfig,
+    username: String,
+    password: SecretString,
+}
+
+impl Task for Authenticate {
+    type Output = Authentication;
+    type JsValue = AuthResult;
+
+    fn compute(&mut self) -> napi::Result<Authentication> {
+        self.config
+            .client()
+            .and_then(|client| client.authenticate(&self.username, self.password.expose_secret()))
+            .map_err(to_napi)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Authentication) -> napi::Result<AuthResult> {
+        Ok(output.into())
+    }
+}
+
+// USER_SRP_AUTH through PASSWORD_VERIFIER; resolves to tokens or the next challenge.
+#[napi]
+pub fn authenticate(
+    config: ClientConfig,
+    username: String,
+    password: String,
+) -> AsyncTask<Authenticate> {
+    AsyncTask::new(Authenticate {
+        config,
+        username,
+        password: SecretString::new(password),
+    })
+}
+
+pub struct Refresh {
+    config: ClientConfig,
+    username: String,
+    refresh_token: SecretString,
+}
+
+impl Task for Refresh {
+    type Output = TokenSet;
+    type JsValue = Tokens;
+
+    fn compute(&mut self) -> napi::Result<TokenSet> {
+        self.config
+            .client()
+            .and_then(|client| {
+                client.refresh_tokens(&self.username, self.refresh_token.expose_secret())
+            })
+            .map_err(to_napi)
+    }
+
+    fn resolve(&mut self, _env: Env, output: TokenSet) -> napi::Result<Tokens> {
+        Ok(output.into())
+    }
+}
+
+// REFRESH_TOKEN_AUTH; `username` only matters for app clients with a secret.
+#[napi]
+pub fn refresh(
+    config: ClientConfig,
+    username: String,
+    refresh_token: String,
+) -> AsyncTask<Refresh> {
+    AsyncTask::new(Refresh {
+        config,
+        username,
+        refresh_token: SecretString::new(refresh_token),
+    })
+}
+
+#[napi(js_name = "SrpSession")]
+pub struct NodeSrpSession {
+    inner: srp::SrpSession,
+}
+
+#[napi]
+impl NodeSrpSession {
+    #[napi(constructor)]
+    pub fn new(pool_id: String) -> napi::Result<Self> {
+        Ok(Self {
+            inner: srp::SrpSession::new(&pool_id, &mut rand::thread_rng()).map_err(to_napi)?,
+        })
+    }
+
+    #[napi]
+    pub fn srp_a(&self) -> String {
+        self.inner.srp_a()
+    }
+
+    // ChallengeParameters of PASSWORD_VERIFIER in, ChallengeResponses out; `timestamp`
+    // defaults to now.
+    #[napi]
+    pub fn process_challenge(
+        &self,
+        parameters: HashMap<String, String>,
+        password: String,
+        timestamp: Option<String>,
+    ) -> napi::Result<HashMap<String, String>> {
+        let password = SecretString::new(password);
+        let timestamp = timestamp.unwrap_or_else(|| cognito_timestamp(SystemClock.now()));
+        self.inner
+            .process_challenge(
+                &parameters.into_iter().collect(),
+                password.expose_secret(),
+                &timestamp,
+            )
+            .map(|responses| responses.into_iter().collect())
+            .map_err(to_napi)
+    }
+}
@@ -0,0 +1,400 @@
+use crate::client::{Client, ServiceError, ServiceErrorKind};
+use crate::crypto::sha256;
+use crate::error::{CognitoSrpError, Result};
+use crate::json::{self, Value};
+use crate::secret::SecretString;
+use crate::tokens::TokenSet;
+use crate::transport::HttpRequest;
+use crate::url;
+use data_encoding::{BASE64, BASE64URL_NOPAD};
+use rand::RngCore;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+// # https://datatracker.ietf.org/doc/html/rfc7636
+// A PKCE pair: send `challenge` (method S256) with the authorization request and `verifier`
+// with the code exchange, so an intercepted code is useless on its own.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+}
+
+impl Pkce {
+    pub const METHOD: &'static str = "S256";
+
+    // 32 bytes from the same CSPRNG as the SRP ephemerals, giving a 43-character verifier.
+    pub fn generate() -> Self {
+        let mut bytes = [0; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self::from_verifier(&BASE64URL_NOPAD.encode(&bytes)).expect("base64url is a valid verifier")
+    }
+
+    // For verifiers made elsewhere: 43 to 128 unreserved characters.
+    pub fn from_verifier(verifier: &str) -> Result<Self> {
+        let unreserved = |b: u8| b.is_ascii_alphanumeric() || b"-._~".contains(&b);
+        if !(43..=128).contains(&verifier.len()) || !verifier.bytes().all(unreserved) {
+            return Err(CognitoSrpError::InvalidArgument(
+                "PKCE verifier must be 43-128 unreserved characters".to_owned(),
+            ));
+        }
+        Ok(Self {
+            verifier: verifier.to_owned(),
+            challenge: BASE64URL_NOPAD.encode(&sha256(verifier.as_bytes())),
+        })
+    }
+}
+
+// The verifier is what makes an intercepted code useless; keep it out of logs.
+impl fmt::Debug for Pkce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pkce")
+            .field("verifier", &"***")
+            .field("challenge", &self.challenge)
+            .finish()
+    }
+}
+
+// The hosted UI's OAuth 2.0 endpoints on the pool's domain, for apps that sign users in through
+// a browser instead of SRP. Uses the Client's app client id, secret, transport and retry policy.
+#[derive(Clone)]
+pub struct OAuthClient {
+    client: Client,
+    // "https://myapp.auth.us-east-1.amazoncognito.com" or a custom domain, without a trailing '/'.
+    domain: String,
+}
+
+impl OAuthClient {
+    // `domain` may be given with or without "https://".
+    pub fn new(client: Client, domain: &str) -> Self {
+        let domain = domain.trim_end_matches('/');
+        let domain = if domain.contains("://") {
+            domain.to_owned()
+        } else {
+            format!("https://{}", domain)
+        };
+        Self { client, domain }
+    }
+
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    // # https://docs.aws.amazon.com/cognito/latest/developerguide/authorization-endpoint.html
+    // Sends the browser straight to the sign-in page, or to `identity_provider` if set.
+    pub fn authorize_url(&self, redirect_uri: &str) -> AuthorizeUrl {
+        AuthorizeUrl::new(self, "/oauth2/authorize", redirect_uri)
+    }
+
+    // # https://docs.aws.amazon.com/cognito/latest/developerguide/login-endpoint.html
+    // The hosted UI's own sign-in page; same parameters as authorize_url.
+    pub fn login_url(&self, redirect_uri: &str) -> AuthorizeUrl {
+        AuthorizeUrl::new(self, "/login", redirect_uri)
+    }
+
+    // # https://docs.aws.amazon.com/cognito/latest/developerguide/logout-endpoint.html
+    // Ends the hosted UI session and redirects to `logout_uri`, which must be one of the app
+    // client's sign-out URLs.
+    pub fn logout_url(&self, logout_uri: &str) -> String {
+        format!(
+            "{}/logout?{}",
+            self.domain,
+            url::form(&[
+                ("client_id", self.client.client_id()),
+                ("logout_uri", logout_uri)
+            ])
+        )
+    }
+
+    // # https://docs.aws.amazon.com/cognito/latest/developerguide/token-endpoint.html
+    // Confidential clients authenticate with HTTP Basic; public ones just send client_id.
+    fn token_request(&self, form: &[(&str, &str)]) -> Result<TokenSet> {
+        let mut form: Vec<(&str, &str)> = form.to_vec();
+        form.push(("client_id", self.client.client_id()));
+        let mut headers = vec![(
+            "Content-Type".to_owned(),
+            "application/x-www-form-urlencoded".to_owned(),
+        )];
+        if let Some(secret) = self.client.client_secret() {
+            let credentials = format!("{}:{}", self.client.client_id(), secret);
+            headers.push((
+                "Authorization".to_owned(),
+                format!("Basic {}", BASE64.encode(credentials.as_bytes())),
+            ));
+        }
+        let request = HttpRequest {
+            method: "POST",
+            url: format!("{}/oauth2/token", self.domain),
+            headers,
+            body: url::form(&form),
+        };
+        let response = self.client.transport().send(request)?;
+        let value = json::parse(&response.body).unwrap_or(Value::Null);
+        if !(200..300).contains(&response.status) {
+            // RFC 6749 errors: {"error":"invalid_grant","error_description":"..."}.
+            return Err(ServiceError {
+                operation: "oauth2/token".to_owned(),
+                status: response.status,
+                kind: match value.str_field("error") {
+                    Some(error) => ServiceErrorKind::parse(&error),
+                    None => ServiceErrorKind::Other(format!("HTTP {}", response.status)),
+                },
+                message: value.str_field("error_description").unwrap_or_default(),
+            }
+            .into());
+        }
+        tokens_from(
+            &value,
+            form_value(&form, "refresh_token"),
+            self.client.now(),
+        )
+    }
+
+    // Trades the `code` from the redirect for tokens. `redirect_uri` must match the one the
+    // authorization request used; pass the PKCE verifier if that request sent a challenge.
+    pub fn exchange_code(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+        code_verifier: Option<&str>,
+    ) -> Result<TokenSet> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+        ];
+        if let Some(verifier) = code_verifier {
+            form.push(("code_verifier", verifier));
+        }
+        // Codes are single-use, so this is never retried.
+        self.token_request(&form)
+    }
+
+    pub fn refresh(&self, refresh_token: &str) -> Result<TokenSet> {
+        let tokens = self.client.retry_policy().run(|| {
+            self.token_request(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token),
+            ])
+        })?;
+        if tokens.rotated_from(refresh_token) {
+            self.client.hooks().refresh_token_rotated(&tokens);
+        }
+        Ok(tokens)
+    }
+}
+
+// Builds an authorization URL; `to_string()` gives the finished, encoded URL.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthorizeUrl {
+    base: String,
+    client_id: String,
+    redirect_uri: String,
+    scopes: Vec<String>,
+    state: Option<String>,
+    identity_provider: Option<String>,
+    code_challenge: Option<String>,
+}
+
+impl AuthorizeUrl {
+    fn new(oauth: &OAuthClient, path: &str, redirect_uri: &str) -> Self {
+        Self {
+            base: format!("{}{}", oauth.domain, path),
+            client_id: oauth.client.client_id().to_owned(),
+            redirect_uri: redirect_uri.to_owned(),
+            scopes: Vec::new(),
+            state: None,
+            identity_provider: None,
+            code_challenge: None,
+        }
+    }
+
+    // e.g. ["openid", "email"]; without any, the app client's allowed scopes apply.
+    pub fn with_scopes(mut self, scopes: &[&str]) -> Self {
+        self.scopes = scopes.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    // Echoed back on the redirect; check it there to stop cross-site request forgery.
+    pub fn with_state(mut self, state: &str) -> Self {
+        self.state = Some(state.to_owned());
+        self
+    }
+
+    // "Google", "SignInWithApple", a SAML provider name, or "COGNITO".
+    pub fn with_identity_provider(mut self, identity_provider: &str) -> Self {
+        self.identity_provider = Some(identity_provider.to_owned());
+        self
+    }
+
+    pub fn with_pkce(mut self, pkce: &Pkce) -> Self {
+        self.code_challenge = Some(pkce.challenge.clone());
+        self
+    }
+}
+
+impl fmt::Display for AuthorizeUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scope = self.scopes.join(" ");
+        let mut query = vec![
+            ("response_type", "code"),
+            ("client_id", self.client_id.as_str()),
+            ("redirect_uri", self.redirect_uri.as_str()),
+        ];
+        if !scope.is_empty() {
+            query.push(("scope", &scope));
+        }
+        if let Some(state) = &self.state {
+            query.push(("state", state));
+        }
+        if let Some(identity_provider) = &self.identity_provider {
+            query.push(("identity_provider", identity_provider));
+        }
+        if let Some(challenge) = &self.code_challenge {
+            query.push(("code_challenge_method", Pkce::METHOD));
+            query.push(("code_challenge", challenge));
+        }
+        write!(f, "{}?{}", self.base, url::form(&query))
+    }
+}
+
+fn form_value<'a>(form: &[(&str, &'a str)], name: &str) -> Option<&'a str> {
+    form.iter().find(|(n, _)| *n == name).map(|(_, v)| *v)
+}
+
+// The token endpoint's snake_case cousin of AuthenticationResult. A refresh answer has no
+// refresh_token, so the one that was sent is kept.
+fn tokens_from(value: &Value, refresh_token: Option<&str>, now: SystemTime) -> Result<TokenSet> {
+    let field = |name: &str| {
+        value.str_field(name).ok_or_else(|| {
+            CognitoSrpError::InvalidResponse(format!("token endpoint returned no {}", name))
+        })
+    };
+    let expires_in = match value.get("expires_in") {
+        Some(Value::Number(secs)) if *secs >= 0.0 => *secs as u64,
+        _ => {
+            return Err(CognitoSrpError::InvalidResponse(
+                "token endpoint returned no expires_in".to_owned(),
+            ))
+        }
+    };
+    Ok(TokenSet {
+        access_token: field("access_token")?,
+        // Only issued when the openid scope was requested.
+        id_token: value.str_field("id_token").unwrap_or_default(),
+        refresh_token: value
+            .str_field("refresh_token")
+            .or_else(|| refresh_token.map(str::to_owned))
+            .map(SecretString::new),
+        token_type: value
+            .str_field("token_type")
+            .unwrap_or_else(|| "Bearer".to_owned()),
+        expires_at: now + Duration::from_secs(expires_in),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_pkce() {
+        // # https://datatracker.ietf.org/doc/html/rfc7636#appendix-B
+        let pkce = Pkce::from_verifier("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk").unwrap();
+        assert_eq!(
+            pkce.challenge,
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+
+        let (a, b) = (Pkce::generate(), Pkce::generate());
+        assert_eq!(a.verifier.len(), 43);
+        assert_ne!(a.verifier, b.verifier);
+        assert_eq!(Pkce::from_verifier(&a.verifier).unwrap(), a);
+        assert!(Pkce::from_verifier("too-short").is_err());
+        assert!(Pkce::from_verifier(&"+".repeat(43)).is_err());
+        assert!(!format!("{:?}", a).contains(&a.verifier));
+    }
+
+    #[test]
+    fn test_urls() {
+        let client = Client::new("us-east-1_pool", "client", MockTransport::new()).unwrap();
+        let oauth = OAuthClient::new(client, "https://auth.example.com");
+        let pkce = Pkce::from_verifier("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk").unwrap();
+
+        assert_eq!(
+            oauth
+                .authorize_url("myapp://callback")
+                .with_scopes(&["openid", "email"])
+                .with_state("xyz&1")
+                .with_identity_provider("Google")
+                .with_pkce(&pkce)
+                .to_string(),
+            "https://auth.example.com/oauth2/authorize?response_type=code&client_id=client\
+             &redirect_uri=myapp%3A%2F%2Fcallback&scope=openid%20email&state=xyz%261\
+             &identity_provider=Google&code_challenge_method=S256\
+             &code_challenge=E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+        assert_eq!(
+            oauth.login_url("https://app.example.com/").to_string(),
+            "https://auth.example.com/login?response_type=code&client_id=client\
+             &redirect_uri=https%3A%2F%2Fapp.example.com%2F"
+        );
+        assert_eq!(
+            oauth.logout_url("https://app.example.com/bye"),
+            "https://auth.example.com/logout?client_id=client\
+             &logout_uri=https%3A%2F%2Fapp.example.com%2Fbye"
+        );
+    }
+
+    #[test]
+    fn test_exchange_code_and_refresh() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"access_token":"a","id_token":"i","refresh_token":"r","token_type":"Bearer","expires_in":3600}"#,
+        );
+        transport.respond(
+            200,
+            r#"{"access_token":"a2","id_token":"i2","expires_in":3600}"#,
+        );
+        transport.respond(400, r#"{"error":"invalid_grant"}"#);
+        let client = Client::new("us-east-1_pool", "client", transport.clone())
+            .unwrap()
+            .with_client_secret("secret");
+        let oauth = OAuthClient::new(client, "myapp.auth.us-east-1.amazoncognito.com/");
+
+        let tokens = oauth
+            .exchange_code("code-1", "http://localhost:8080/cb", Some("verifier"))
+            .unwrap();
+        assert_eq!(tokens.refresh_token, Some("r".into()));
+        let request = transport.last_request();
+        assert_eq!(
+            request.url,
+            "https://myapp.auth.us-east-1.amazoncognito.com/oauth2/token"
+        );
+        assert_eq!(
+            request.body,
+            "grant_type=authorization_code&code=code-1\
+             &redirect_uri=http%3A%2F%2Flocalhost%3A8080%2Fcb&code_verifier=verifier&client_id=client"
+        );
+        assert!(request.headers.contains(&(
+            "Authorization".to_owned(),
+            "Basic Y2xpZW50OnNlY3JldA==".to_owned()
+        )));
+
+        let tokens = oauth.refresh("r").unwrap();
+        assert_eq!(tokens.access_token, "a2");
+        assert_eq!(tokens.refresh_token, Some("r".into()));
+
+        let err = oauth.refresh("r").unwrap_err();
+        assert_eq!(
+            err.service_kind(),
+            Some(&ServiceErrorKind::Other("invalid_grant".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,204 @@
+// # https://docs.aws.amazon.com/cognito/latest/developerguide/authentication-flows-selection-sdk.html#authentication-flows-selection-passkey
+// Passkey sign-in through the USER_AUTH flow. Cognito answers with a WEB_AUTHN challenge whose
+// CREDENTIAL_REQUEST_OPTIONS are WebAuthn PublicKeyCredentialRequestOptions as JSON; the platform
+// authenticator signs them, and its PublicKeyCredential, also as JSON, goes back as CREDENTIAL.
+// The signing is left to a PasskeyAuthenticator, so that a native app can hand the options to
+// ASAuthorizationController, Credential Manager or WebAuthn.dll:
+//
+//     let authentication = client.authenticate_with_passkey("bob", &|options: &str| {
+//         platform_get_assertion(options)
+//     })?;
+//
+// Callers that can't block on the authenticator drive the two halves themselves with
+// initiate_passkey_auth and respond_to_web_authn.
+use crate::auth::{Authentication, Challenge, ChallengeName};
+use crate::client::Client;
+use crate::error::{CognitoSrpError, Result};
+use crate::json;
+use crate::user_auth::FirstFactor;
+use std::sync::Arc;
+
+pub trait PasskeyAuthenticator: Send + Sync {
+    // Takes PublicKeyCredentialRequestOptions as JSON and returns the signed PublicKeyCredential
+    // (the assertion) as JSON, in the form of PublicKeyCredential.toJSON().
+    fn get_assertion(&self, request_options: &str) -> Result<String>;
+}
+
+impl<F: Fn(&str) -> Result<String> + Send + Sync> PasskeyAuthenticator for F {
+    fn get_assertion(&self, request_options: &str) -> Result<String> {
+        self(request_options)
+    }
+}
+
+impl<T: PasskeyAuthenticator + ?Sized> PasskeyAuthenticator for Arc<T> {
+    fn get_assertion(&self, request_options: &str) -> Result<String> {
+        (**self).get_assertion(request_options)
+    }
+}
+
+impl Challenge {
+    // For WEB_AUTHN, the PublicKeyCredentialRequestOptions JSON to give the authenticator.
+    pub fn credential_request_options(&self) -> Option<&str> {
+        if self.name != ChallengeName::WebAuthn {
+            return None;
+        }
+        self.parameters
+            .get("CREDENTIAL_REQUEST_OPTIONS")
+            .map(String::as_str)
+    }
+}
+
+impl Client {
+    // USER_AUTH with WEB_AUTHN preferred. The result is normally the WEB_AUTHN challenge; a pool
+    // that offers the user a choice is answered with WEB_AUTHN. Needs the USER_AUTH flow enabled on
+    // the app client and passkeys allowed on the pool.
+    pub fn initiate_passkey_auth(&self, username: &str) -> Result<Authentication> {
+        match self.initiate_user_auth(username, Some(FirstFactor::WebAuthn))? {
+            Authentication::Challenge(challenge)
+                if challenge.name == ChallengeName::SelectChallenge =>
+            {
+                self.respond_to_select_challenge(&challenge, FirstFactor::WebAuthn)
+            }
+            authentication => Ok(authentication),
+        }
+    }
+
+    // Answers WEB_AUTHN with the authenticator's PublicKeyCredential JSON.
+    pub fn respond_to_web_authn(
+        &self,
+        challenge: &Challenge,
+        credential: &str,
+    ) -> Result<Authentication> {
+        check_web_authn(challenge)?;
+        if json::parse(credential).is_err() {
+            return Err(CognitoSrpError::InvalidArgument(
+                "passkey credential is not JSON".to_owned(),
+            ));
+        }
+        self.respond_to_challenge(challenge, &[("CREDENTIAL", credential)])
+    }
+
+    // Both halves, with `authenticator` signing in between. Any challenge other than WEB_AUTHN
+    // comes back for the caller to answer.
+    pub fn authenticate_with_passkey(
+        &self,
+        username: &str,
+        authenticator: &dyn PasskeyAuthenticator,
+    ) -> Result<Authentication> {
+        let challenge = match self.initiate_passkey_auth(username)? {
+            Authentication::Challenge(challenge) if challenge.name == ChallengeName::WebAuthn => {
+                challenge
+            }
+            authentication => return Ok(authentication),
+        };
+        let options = challenge.credential_request_options().ok_or_else(|| {
+            CognitoSrpError::InvalidResponse(
+                "WEB_AUTHN challenge is missing CREDENTIAL_REQUEST_OPTIONS".to_owned(),
+            )
+        })?;
+        let credential = authenticator.get_assertion(options)?;
+        self.respond_to_web_authn(&challenge, &credential)
+    }
+}
+
+fn check_web_authn(challenge: &Challenge) -> Result<()> {
+    if challenge.name != ChallengeName::WebAuthn {
+        return Err(CognitoSrpError::InvalidArgument(format!(
+            "{} is not WEB_AUTHN",
+            challenge.name.as_str()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::Value;
+    use crate::transport::mock::MockTransport;
+
+    const OPTIONS: &str = r#"{"challenge":"Y2hhbGxlbmdl","rpId":"example.com","timeout":180000}"#;
+    const ASSERTION: &str = r#"{"id":"cred","rawId":"cred","type":"public-key","response":{}}"#;
+
+    fn web_authn_challenge() -> String {
+        let parameters = Value::object([
+            ("USERNAME", Value::from("bob-internal")),
+            ("CREDENTIAL_REQUEST_OPTIONS", Value::from(OPTIONS)),
+        ]);
+        format!(
+            r#"{{"ChallengeName":"WEB_AUTHN","Session":"s2","ChallengeParameters":{}}}"#,
+            parameters
+        )
+    }
+
+    #[test]
+    fn test_authenticate_with_passkey() {
+        let transport = MockTransport::new();
+        transport.respond(200, &web_authn_challenge());
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a","IdToken":"i","ExpiresIn":3600}}"#,
+        );
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let authenticator = |options: &str| -> Result<String> {
+            assert_eq!(options, OPTIONS);
+            Ok(ASSERTION.to_owned())
+        };
+        let authentication = client
+            .authenticate_with_passkey("bob", &authenticator)
+            .unwrap();
+        assert!(matches!(authentication, Authentication::Tokens(_)));
+
+        let requests = transport.requests.lock().unwrap();
+        let initiate = json::parse(&requests[0].body).unwrap();
+        assert_eq!(initiate.str_field("AuthFlow").unwrap(), "USER_AUTH");
+        let parameters = initiate.get("AuthParameters").unwrap();
+        assert_eq!(
+            parameters.str_field("PREFERRED_CHALLENGE").unwrap(),
+            "WEB_AUTHN"
+        );
+        let answer = json::parse(&requests[1].body).unwrap();
+        assert_eq!(answer.str_field("ChallengeName").unwrap(), "WEB_AUTHN");
+        assert_eq!(answer.str_field("Session").unwrap(), "s2");
+        let responses = answer.get("ChallengeResponses").unwrap();
+        assert_eq!(responses.str_field("CREDENTIAL").unwrap(), ASSERTION);
+        assert_eq!(responses.str_field("USERNAME").unwrap(), "bob-internal");
+    }
+
+    #[test]
+    fn test_select_challenge() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"ChallengeName":"SELECT_CHALLENGE","Session":"s1",
+                "AvailableChallenges":["PASSWORD_SRP","WEB_AUTHN"],"ChallengeParameters":{}}"#,
+        );
+        transport.respond(200, &web_authn_challenge());
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let Authentication::Challenge(challenge) = client.initiate_passkey_auth("bob").unwrap()
+        else {
+            panic!("expected WEB_AUTHN");
+        };
+        assert_eq!(challenge.name, ChallengeName::WebAuthn);
+        assert_eq!(challenge.credential_request_options(), Some(OPTIONS));
+        let answer = transport.last_body();
+        assert_eq!(
+            answer.str_field("ChallengeName").unwrap(),
+            "SELECT_CHALLENGE"
+        );
+        let responses = answer.get("ChallengeResponses").unwrap();
+        assert_eq!(responses.str_field("ANSWER").unwrap(), "WEB_AUTHN");
+
+        assert!(matches!(
+            client.respond_to_web_authn(&challenge, "not json"),
+            Err(CognitoSrpError::InvalidArgument(_))
+        ));
+        let other = Challenge {
+            name: ChallengeName::SmsMfa,
+            ..challenge
+        };
+        assert_eq!(other.credential_request_options(), None);
+        assert!(client.respond_to_web_authn(&other, ASSERTION).is_err());
+        assert_eq!(transport.requests.lock().unwrap().len(), 2);
+    }
+}
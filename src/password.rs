@@ -0,0 +1,281 @@
+use crate::client::Client;
+use crate::error::{CognitoSrpError, Result};
+use crate::json::Value;
+use crate::registration::CodeDeliveryDetails;
+use std::fmt;
+
+// # https://docs.aws.amazon.com/cognito/latest/developerguide/user-pool-settings-policies.html
+// The special characters Cognito counts as symbols. A space counts too, but not at either end.
+const SYMBOLS: &str = "^$*.[]{}()?\"!@#%&/\\,><':;|_~`=+- ";
+
+// A user pool's password rules, checked locally so weak passwords fail before SignUp,
+// ChangePassword or a reset reaches Cognito. Default is Cognito's default policy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PasswordPolicy {
+    pub minimum_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_numbers: bool,
+    pub require_symbols: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            minimum_length: 8,
+            require_lowercase: true,
+            require_uppercase: true,
+            require_numbers: true,
+            require_symbols: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PasswordViolation {
+    TooShort { minimum_length: usize },
+    MissingLowercase,
+    MissingUppercase,
+    MissingNumber,
+    MissingSymbol,
+    // Cognito rejects these whatever the policy.
+    LeadingOrTrailingSpace,
+}
+
+impl fmt::Display for PasswordViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PasswordViolation::TooShort { minimum_length } => {
+                write!(f, "shorter than {} characters", minimum_length)
+            }
+            PasswordViolation::MissingLowercase => f.write_str("no lowercase letter"),
+            PasswordViolation::MissingUppercase => f.write_str("no uppercase letter"),
+            PasswordViolation::MissingNumber => f.write_str("no number"),
+            PasswordViolation::MissingSymbol => f.write_str("no symbol"),
+            PasswordViolation::LeadingOrTrailingSpace => f.write_str("starts or ends with a space"),
+        }
+    }
+}
+
+impl PasswordPolicy {
+    // Every rule `password` breaks; empty when it passes.
+    pub fn check(&self, password: &str) -> Vec<PasswordViolation> {
+        let has = |f: fn(char) -> bool| password.chars().any(f);
+        let mut violations = Vec::new();
+        if password.chars().count() < self.minimum_length {
+            violations.push(PasswordViolation::TooShort {
+                minimum_length: self.minimum_length,
+            });
+        }
+        if self.require_lowercase && !has(|c| c.is_ascii_lowercase()) {
+            violations.push(PasswordViolation::MissingLowercase);
+        }
+        if self.require_uppercase && !has(|c| c.is_ascii_uppercase()) {
+            violations.push(PasswordViolation::MissingUppercase);
+        }
+        if self.require_numbers && !has(|c| c.is_ascii_digit()) {
+            violations.push(PasswordViolation::MissingNumber);
+        }
+        if self.require_symbols
+            && !password
+                .trim_matches(' ')
+                .contains(|c: char| SYMBOLS.contains(c))
+        {
+            violations.push(PasswordViolation::MissingSymbol);
+        }
+        if password.starts_with(' ') || password.ends_with(' ') {
+            violations.push(PasswordViolation::LeadingOrTrailingSpace);
+        }
+        violations
+    }
+
+    // check as an InvalidArgument error naming every violation.
+    pub fn validate(&self, password: &str) -> Result<()> {
+        let violations = self.check(password);
+        if violations.is_empty() {
+            return Ok(());
+        }
+        let reasons: Vec<String> = violations.iter().map(ToString::to_string).collect();
+        Err(CognitoSrpError::InvalidArgument(format!(
+            "password does not meet the policy: {}",
+            reasons.join(", ")
+        )))
+    }
+
+    fn from_json(policy: &Value) -> Self {
+        let flag = |name: &str| policy.get(name).and_then(Value::as_bool).unwrap_or(false);
+        Self {
+            minimum_length: match policy.get("MinimumLength") {
+                Some(Value::Number(n)) if *n >= 0.0 => *n as usize,
+                _ => 6,
+            },
+            require_lowercase: flag("RequireLowercase"),
+            require_uppercase: flag("RequireUppercase"),
+            require_numbers: flag("RequireNumbers"),
+            require_symbols: flag("RequireSymbols"),
+        }
+    }
+}
+
+impl Client {
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_DescribeUserPool.html
+    // The pool's actual policy, for with_password_policy. DescribeUserPool is an admin call, so
+    // this needs AWS credentials on the client.
+    pub fn describe_password_policy(&self) -> Result<PasswordPolicy> {
+        let response = self.admin_call("DescribeUserPool", Value::object::<String>([]))?;
+        let policy = response
+            .get("UserPool")
+            .and_then(|pool| pool.get("Policies"))
+            .and_then(|policies| policies.get("PasswordPolicy"))
+            .ok_or_else(|| {
+                CognitoSrpError::InvalidResponse(
+                    "DescribeUserPool returned no PasswordPolicy".to_owned(),
+                )
+            })?;
+        Ok(PasswordPolicy::from_json(policy))
+    }
+
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_ForgotPassword.html
+    pub fn forgot_password(&self, username: &str) -> Result<Option<CodeDeliveryDetails>> {
+        let response = self.call("ForgotPassword", self.request_for(username))?;
+        CodeDeliveryDetails::from_response(&response)
+    }
+
+    pub fn confirm_forgot_password(
+        &self,
+        username: &str,
+        code: &str,
+        new_password: &str,
+    ) -> Result<()> {
+        self.check_password(new_password)?;
+        let mut body = self.request_for(username);
+        body.insert("ConfirmationCode", Value::from(code));
+        body.insert("Password", Value::from(new_password));
+        self.call("ConfirmForgotPassword", body)?;
+        Ok(())
+    }
+
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_ChangePassword.html
+    pub fn change_password(
+        &self,
+        access_token: &str,
+        old_password: &str,
+        new_password: &str,
+    ) -> Result<()> {
+        self.check_password(new_password)?;
+        let body = Value::object([
+            ("AccessToken", Value::from(access_token)),
+            ("PreviousPassword", Value::from(old_password)),
+            ("ProposedPassword", Value::from(new_password)),
+        ]);
+        self.call("ChangePassword", body)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registration::DeliveryMedium;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_forgot_password() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"CodeDeliveryDetails":{"AttributeName":"email","DeliveryMedium":"EMAIL","Destination":"b***@e***"}}"#,
+        );
+        transport.respond(200, "");
+        let client = Client::new("us-east-1_pool", "client", transport.clone())
+            .unwrap()
+            .with_client_secret("secret");
+
+        let details = client.forgot_password("bob").unwrap().unwrap();
+        assert_eq!(details.delivery_medium, Some(DeliveryMedium::Email));
+        assert_eq!(
+            transport.last_body().str_field("SecretHash").unwrap(),
+            "P1WGx30spzkjVO6gbksCMo0r3Ro1xm4a6xXZTTrvrD0="
+        );
+
+        client
+            .confirm_forgot_password("bob", "123456", "n3w-Password")
+            .unwrap();
+        let body = transport.last_body();
+        assert_eq!(body.str_field("ConfirmationCode").unwrap(), "123456");
+        assert_eq!(body.str_field("Password").unwrap(), "n3w-Password");
+        assert!(body.get("SecretHash").is_some());
+    }
+
+    #[test]
+    fn test_change_password() {
+        let transport = MockTransport::new();
+        transport.respond(200, "{}");
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+
+        client.change_password("access", "old", "new").unwrap();
+        assert_eq!(
+            transport.last_body().to_string(),
+            r#"{"AccessToken":"access","PreviousPassword":"old","ProposedPassword":"new"}"#
+        );
+    }
+
+    #[test]
+    fn test_password_policy() {
+        let policy = PasswordPolicy::default();
+        assert!(policy.check("Sup3r secret!").is_empty());
+        assert_eq!(
+            policy.check(" abc"),
+            [
+                PasswordViolation::TooShort { minimum_length: 8 },
+                PasswordViolation::MissingUppercase,
+                PasswordViolation::MissingNumber,
+                PasswordViolation::MissingSymbol,
+                PasswordViolation::LeadingOrTrailingSpace,
+            ]
+        );
+        let relaxed = PasswordPolicy {
+            minimum_length: 6,
+            require_symbols: false,
+            ..PasswordPolicy::default()
+        };
+        assert!(relaxed.validate("Abc123").is_ok());
+        assert_eq!(
+            relaxed.validate("abcdef").unwrap_err().to_string(),
+            "password does not meet the policy: no uppercase letter, no number"
+        );
+    }
+
+    #[test]
+    fn test_policy_rejects_before_calling() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"UserPool":{"Policies":{"PasswordPolicy":{"MinimumLength":12,
+                "RequireUppercase":true,"RequireLowercase":true,"RequireNumbers":false,
+                "RequireSymbols":false,"TemporaryPasswordValidityDays":7}}}}"#,
+        );
+        let client = Client::new("us-east-1_pool", "client", transport.clone())
+            .unwrap()
+            .with_credentials(crate::credentials::AwsCredentials::new("AKID", "secret"));
+        let policy = client.describe_password_policy().unwrap();
+        assert_eq!(
+            policy,
+            PasswordPolicy {
+                minimum_length: 12,
+                require_lowercase: true,
+                require_uppercase: true,
+                require_numbers: false,
+                require_symbols: false,
+            }
+        );
+
+        let client = client.with_password_policy(policy);
+        assert!(client.sign_up("bob", "Short1", &[]).is_err());
+        assert!(client
+            .change_password("access", "old", "alllowercase")
+            .is_err());
+        assert_eq!(transport.requests.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::error::{CognitoSrpError, Result};
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+// A validated user pool id such as "us-east-1_AbCdEfGhI": "<region>_<pool name>".
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PoolId {
+    id: String,
+    // Index of the '_' separating region and pool name.
+    split: usize,
+}
+
+// Region names look like "us-east-1" or "us-gov-west-1": lowercase words joined by '-', ending
+// in a number.
+fn is_region(region: &str) -> bool {
+    let mut parts = region.split('-');
+    let first = parts.next().unwrap_or_default();
+    let rest: Vec<&str> = parts.collect();
+    match rest.split_last() {
+        Some((number, words)) => {
+            !first.is_empty()
+                && first.bytes().all(|b| b.is_ascii_lowercase())
+                && words
+                    .iter()
+                    .all(|w| !w.is_empty() && w.bytes().all(|b| b.is_ascii_lowercase()))
+                && !number.is_empty()
+                && number.bytes().all(|b| b.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+impl PoolId {
+    pub fn parse(id: &str) -> Result<Self> {
+        let invalid = || CognitoSrpError::InvalidPoolId(id.to_owned());
+        let split = id.find('_').ok_or_else(invalid)?;
+        let (region, name) = (&id[..split], &id[split + 1..]);
+        if !is_region(region) || name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphanumeric())
+        {
+            return Err(invalid());
+        }
+        Ok(Self {
+            id: id.to_owned(),
+            split,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.id
+    }
+
+    pub fn region(&self) -> &str {
+        &self.id[..self.split]
+    }
+
+    // The part after the region; SRP hashes it into x together with the username.
+    pub fn name(&self) -> &str {
+        &self.id[self.split + 1..]
+    }
+}
+
+impl FromStr for PoolId {
+    type Err = CognitoSrpError;
+
+    fn from_str(id: &str) -> Result<Self> {
+        Self::parse(id)
+    }
+}
+
+impl fmt::Display for PoolId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.id)
+    }
+}
+
+impl AsRef<str> for PoolId {
+    fn as_ref(&self) -> &str {
+        &self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let pool: PoolId = "us-east-1_AbCdEfGhI".parse().unwrap();
+        assert_eq!(pool.region(), "us-east-1");
+        assert_eq!(pool.name(), "AbCdEfGhI");
+        assert_eq!(pool.to_string(), "us-east-1_AbCdEfGhI");
+        assert_eq!(
+            PoolId::parse("us-gov-west-1_x1").unwrap().region(),
+            "us-gov-west-1"
+        );
+
+        for id in [
+            "nounderscore",
+            "_AbCd",
+            "us-east-1_",
+            "US-EAST-1_AbCd",
+            "us-east_AbCd",
+            "useast1_AbCd",
+            "us-east-1_Ab_Cd",
+            "us-east-1_Ab Cd",
+            "us--east-1_AbCd",
+        ] {
+            assert!(
+                matches!(PoolId::parse(id), Err(CognitoSrpError::InvalidPoolId(_))),
+                "{}",
+                id
+            );
+        }
+    }
+}
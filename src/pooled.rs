@@ -0,0 +1,207 @@
+use crate::curl::TransportConfig;
+use crate::error::{CognitoSrpError, Result};
+use crate::transport::{HttpRequest, HttpResponse, Transport};
+use std::sync::Arc;
+
+// A Transport that keeps its connections open: one reqwest::Client, whose pool reuses the
+// connection (and the TLS session) to cognito-idp from request to request, driven by a runtime
+// of its own so that `send` stays blocking. Clones share both, so threads can each hold one.
+// Like reqwest::blocking, it must not be called from inside an async task.
+//
+// The crate turns on no reqwest TLS backend; enable `rustls-tls` or `default-tls` on the
+// application's own reqwest dependency, or https URLs fail with a transport error.
+#[derive(Clone, Debug)]
+pub struct PooledTransport {
+    client: reqwest::Client,
+    runtime: Arc<tokio::runtime::Runtime>,
+}
+
+fn transport_error(err: impl std::error::Error + Send + Sync + 'static) -> CognitoSrpError {
+    CognitoSrpError::Transport(Box::new(err))
+}
+
+impl PooledTransport {
+    // The same timeouts and proxy settings as CurlTransport. danger_accept_invalid_certs is
+    // refused, since without a TLS backend of its own the crate can't turn checks off; build the
+    // reqwest::Client yourself and use from_client.
+    pub fn new(config: TransportConfig) -> Result<Self> {
+        if config.danger_accept_invalid_certs {
+            return Err(CognitoSrpError::Configuration(
+                "PooledTransport can't skip certificate checks; use from_client".to_owned(),
+            ));
+        }
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .read_timeout(config.read_timeout);
+        if let Some(total) = config.total_timeout {
+            builder = builder.timeout(total);
+        }
+        builder = match &config.proxy {
+            Some(proxy) => builder.proxy(
+                reqwest::Proxy::all(proxy)
+                    .map_err(transport_error)?
+                    .no_proxy(
+                        config
+                            .no_proxy
+                            .as_deref()
+                            .and_then(reqwest::NoProxy::from_string),
+                    ),
+            ),
+            None => builder.no_proxy(),
+        };
+        Self::from_client(builder.build().map_err(transport_error)?)
+    }
+
+    pub fn from_client(client: reqwest::Client) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("cog-neat-oh-http")
+            .enable_all()
+            .build()?;
+        Ok(Self {
+            client,
+            runtime: Arc::new(runtime),
+        })
+    }
+}
+
+impl Default for PooledTransport {
+    // TransportConfig::from_env; panics if HTTPS_PROXY is not a URL or the runtime can't start.
+    fn default() -> Self {
+        Self::new(TransportConfig::from_env()).expect("could not start the HTTP runtime")
+    }
+}
+
+impl Transport for PooledTransport {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let method =
+            reqwest::Method::from_bytes(request.method.as_bytes()).map_err(transport_error)?;
+        let mut builder = self.client.request(method, &request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if !request.body.is_empty() {
+            builder = builder.body(request.body);
+        }
+        let (status, body) = self
+            .runtime
+            .block_on(async {
+                let response = builder.send().await?;
+                let status = response.status().as_u16();
+                Ok::<_, reqwest::Error>((status, response.bytes().await?))
+            })
+            .map_err(transport_error)?;
+        Ok(HttpResponse {
+            status,
+            body: String::from_utf8(body.into())
+                .map_err(|_| CognitoSrpError::InvalidResponse("body is not UTF-8".to_owned()))?,
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    // A keep-alive HTTP/1.1 server that answers every request with `status` and `body` and
+    // counts the connections it accepted.
+    pub(crate) struct KeepAliveServer {
+        pub(crate) url: String,
+        pub(crate) connections: Arc<AtomicUsize>,
+        pub(crate) requests: Arc<AtomicUsize>,
+    }
+
+    impl KeepAliveServer {
+        pub(crate) fn start(status: &'static str, body: &'static str) -> Self {
+            let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+            let url = format!("http://{}/", listener.local_addr().unwrap());
+            let connections = Arc::new(AtomicUsize::new(0));
+            let requests = Arc::new(AtomicUsize::new(0));
+            let (accepted, served) = (connections.clone(), requests.clone());
+            thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    accepted.fetch_add(1, Ordering::SeqCst);
+                    let served = served.clone();
+                    thread::spawn(move || serve(stream, status, body, &served));
+                }
+            });
+            Self {
+                url,
+                connections,
+                requests,
+            }
+        }
+    }
+
+    fn serve(stream: TcpStream, status: &str, body: &str, served: &AtomicUsize) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut stream = stream;
+        loop {
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                    return;
+                }
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse().unwrap_or(0);
+                    }
+                }
+            }
+            let mut request_body = vec![0; length];
+            if reader.read_exact(&mut request_body).is_err() {
+                return;
+            }
+            served.fetch_add(1, Ordering::SeqCst);
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/x-amz-json-1.1\r\nContent-Length: {}\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            if stream.write_all(response.as_bytes()).is_err() {
+                return;
+            }
+        }
+    }
+
+    #[test]
+    fn test_reuses_connection() {
+        let server = KeepAliveServer::start("200 OK", r#"{"ok":true}"#);
+        let transport = PooledTransport::new(TransportConfig::default()).unwrap();
+        for _ in 0..5 {
+            let response = transport
+                .send(HttpRequest {
+                    method: "POST",
+                    url: server.url.clone(),
+                    headers: vec![("X-Amz-Target".to_owned(), "Test.Op".to_owned())],
+                    body: "{}".to_owned(),
+                })
+                .unwrap();
+            assert_eq!(response.status, 200);
+            assert_eq!(response.body, r#"{"ok":true}"#);
+        }
+        assert_eq!(server.requests.load(Ordering::SeqCst), 5);
+        assert_eq!(server.connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_refuses_insecure() {
+        let config = TransportConfig {
+            danger_accept_invalid_certs: true,
+            ..TransportConfig::default()
+        };
+        assert!(matches!(
+            PooledTransport::new(config),
+            Err(CognitoSrpError::Configuration(_))
+        ));
+    }
+}
@@ -0,0 +1,13 @@
+// Everything most applications need: `use cog_neat_oh::prelude::*;`.
+pub use crate::auth::{Authentication, Challenge, ChallengeName};
+pub use crate::client::Client;
+pub use crate::error::{CognitoSrpError, Grant, ServiceError, ServiceErrorKind};
+pub use crate::jwt::{Claims, TokenUse, Verifier};
+pub use crate::manager::TokenManager;
+pub use crate::pool_id::PoolId;
+pub use crate::provider::{RefreshingTokenProvider, TokenProvider};
+pub use crate::retry::RetryPolicy;
+pub use crate::session::{Session, SessionEvent};
+pub use crate::tokens::TokenSet;
+pub use crate::transport::{HttpRequest, HttpResponse, Transport};
+pub use crate::user::User;
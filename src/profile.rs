@@ -0,0 +1,322 @@
+// Named connection settings in $XDG_CONFIG_HOME/cogneato/config.toml (~/.config/cogneato/...),
+// for people working against several pools or environments:
+//
+//     default_profile = "dev"
+//
+//     [profiles.dev]
+//     pool_id = "us-east-1_AbCdEfGhI"
+//     client_id = "1example23456789"
+//     username = "bob"
+//     endpoint = "http://localhost:9229/"
+//
+//     [profiles.prod]
+//     pool_id = "eu-west-1_XyZ"
+//     client_id = "7prod"
+//     region = "eu-west-1"
+//     token_store = "keyring"
+//
+// Only the part of TOML this needs is understood: tables, comments and string values.
+use crate::client::Client;
+use crate::error::{CognitoSrpError, Result};
+use crate::pool_id::PoolId;
+use crate::transport::Transport;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+
+// Where a profile's tokens are cached between runs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TokenStoreKind {
+    // FileTokenStore::in_cache_dir.
+    #[default]
+    File,
+    // KeyringTokenStore; needs the `keyring` feature.
+    Keyring,
+    // Nothing is cached.
+    None,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    pub pool_id: Option<String>,
+    pub client_id: Option<String>,
+    // Redundant with the pool id; when given, the two must agree.
+    pub region: Option<String>,
+    pub username: Option<String>,
+    pub endpoint: Option<String>,
+    pub token_store: TokenStoreKind,
+}
+
+impl Profile {
+    fn required<'a>(&self, value: &'a Option<String>, key: &str) -> Result<&'a str> {
+        value.as_deref().ok_or_else(|| {
+            CognitoSrpError::Configuration(format!("profile {} has no {}", self.name, key))
+        })
+    }
+
+    // A client for the profile's pool, app client and endpoint. The client secret is not kept in
+    // the file; add it with with_client_secret.
+    pub fn client(&self, transport: impl Transport + 'static) -> Result<Client> {
+        let client = Client::new(
+            self.required(&self.pool_id, "pool_id")?,
+            self.required(&self.client_id, "client_id")?,
+            transport,
+        )?;
+        Ok(match &self.endpoint {
+            Some(endpoint) => client.with_endpoint(endpoint),
+            None => client,
+        })
+    }
+
+    fn set(&mut self, key: &str, value: String) -> std::result::Result<(), String> {
+        match key {
+            "pool_id" => {
+                PoolId::parse(&value).map_err(|err| err.to_string())?;
+                self.pool_id = Some(value);
+            }
+            "client_id" => self.client_id = Some(value),
+            "region" => self.region = Some(value),
+            "username" => self.username = Some(value),
+            "endpoint" => self.endpoint = Some(value),
+            "token_store" => {
+                self.token_store = match value.as_str() {
+                    "file" => TokenStoreKind::File,
+                    "keyring" => TokenStoreKind::Keyring,
+                    "none" => TokenStoreKind::None,
+                    other => {
+                        return Err(format!(
+                            "unknown token_store {}; expected file, keyring or none",
+                            other
+                        ))
+                    }
+                }
+            }
+            other => return Err(format!("unknown key {}", other)),
+        }
+        Ok(())
+    }
+
+    fn check(&self) -> Result<()> {
+        if let (Some(pool_id), Some(region)) = (&self.pool_id, &self.region) {
+            if PoolId::parse(pool_id)?.region() != region {
+                return Err(CognitoSrpError::Configuration(format!(
+                    "profile {}: pool {} is not in region {}",
+                    self.name, pool_id, region
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProfileConfig {
+    pub default_profile: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl ProfileConfig {
+    // $XDG_CONFIG_HOME/cogneato/config.toml, falling back to ~/.config/cogneato/config.toml.
+    pub fn default_path() -> Result<PathBuf> {
+        let var = |name: &str| env::var_os(name).filter(|v| !v.is_empty());
+        let config = match (var("XDG_CONFIG_HOME"), var("HOME")) {
+            (Some(config), _) => PathBuf::from(config),
+            (None, Some(home)) => Path::new(&home).join(".config"),
+            (None, None) => {
+                return Err(CognitoSrpError::Configuration(
+                    "neither XDG_CONFIG_HOME nor HOME is set".to_owned(),
+                ))
+            }
+        };
+        Ok(config.join("cogneato").join("config.toml"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    // The file at default_path, or no profiles at all if it doesn't exist.
+    pub fn load_default() -> Result<Self> {
+        let path = Self::default_path()?;
+        if path.exists() {
+            Self::load(&path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut config = Self::default();
+        let mut current: Option<String> = None;
+        for (number, line) in text.lines().enumerate() {
+            let error = |message: String| {
+                CognitoSrpError::Configuration(format!("line {}: {}", number + 1, message))
+            };
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[') {
+                let name = header
+                    .strip_suffix(']')
+                    .and_then(|table| table.trim().strip_prefix("profiles."))
+                    .map(|name| unquote(name.trim()).unwrap_or_else(|| name.trim().to_owned()))
+                    .filter(|name| !name.is_empty())
+                    .ok_or_else(|| error(format!("expected [profiles.<name>], got {}", line)))?;
+                config
+                    .profiles
+                    .entry(name.clone())
+                    .or_insert_with(|| Profile {
+                        name: name.clone(),
+                        ..Profile::default()
+                    });
+                current = Some(name);
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error(format!("expected key = \"value\", got {}", line)))?;
+            let key = key.trim();
+            let value = unquote(value.trim())
+                .ok_or_else(|| error(format!("{} must be a quoted string", key)))?;
+            match &current {
+                Some(profile) => config
+                    .profiles
+                    .get_mut(profile)
+                    .expect("table was inserted")
+                    .set(key, value)
+                    .map_err(error)?,
+                None if key == "default_profile" => config.default_profile = Some(value),
+                None => return Err(error(format!("unknown key {}", key))),
+            }
+        }
+        config.profiles.values().try_for_each(Profile::check)?;
+        Ok(config)
+    }
+
+    // `name` if given, else default_profile, else a profile called "default"; None when the
+    // file names nothing to fall back to.
+    pub fn profile(&self, name: Option<&str>) -> Result<Option<&Profile>> {
+        let name = match name.or(self.default_profile.as_deref()) {
+            Some(name) => name,
+            None => return Ok(self.profiles.get("default")),
+        };
+        self.profiles
+            .get(name)
+            .map(Some)
+            .ok_or_else(|| CognitoSrpError::Configuration(format!("no profile named {}", name)))
+    }
+}
+
+// Drops a trailing `# comment`, leaving any '#' inside quotes alone.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+// # https://toml.io/en/v1.0.0#string
+// A basic ("...", with the common escapes) or literal ('...') string.
+fn unquote(value: &str) -> Option<String> {
+    if let Some(literal) = value.strip_prefix('\'') {
+        return literal
+            .strip_suffix('\'')
+            .filter(|s| !s.contains('\''))
+            .map(str::to_owned);
+    }
+    let body = value.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::new();
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return None,
+            '\\' => out.push(match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                '"' => '"',
+                '\\' => '\\',
+                _ => return None,
+            }),
+            c => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    const CONFIG: &str = r#"
+        # Which profile to use without --profile.
+        default_profile = "dev"
+
+        [profiles.dev]
+        pool_id = "us-east-1_AbCdEf"
+        client_id = "devclient"
+        username = "bob"   # the usual one
+        endpoint = 'http://localhost:9229/'
+
+        [profiles."prod #1"]
+        pool_id = "eu-west-1_XyZ"
+        client_id = "prodclient"
+        region = "eu-west-1"
+        token_store = "none"
+    "#;
+
+    #[test]
+    fn test_parse() {
+        let config = ProfileConfig::parse(CONFIG).unwrap();
+        let dev = config.profile(None).unwrap().unwrap();
+        assert_eq!(dev.name, "dev");
+        assert_eq!(dev.username.as_deref(), Some("bob"));
+        assert_eq!(dev.token_store, TokenStoreKind::File);
+        let client = dev.client(MockTransport::new()).unwrap();
+        assert_eq!(client.endpoint(), "http://localhost:9229/");
+
+        let prod = config.profile(Some("prod #1")).unwrap().unwrap();
+        assert_eq!(prod.pool_id.as_deref(), Some("eu-west-1_XyZ"));
+        assert_eq!(prod.token_store, TokenStoreKind::None);
+        assert!(config.profile(Some("staging")).is_err());
+
+        assert_eq!(ProfileConfig::default().profile(None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (text, message) in [
+            ("pool_id = \"x\"", "line 1: unknown key pool_id"),
+            ("[dev]", "line 1: expected [profiles.<name>], got [dev]"),
+            (
+                "[profiles.a]\npool_id = us-east-1_x",
+                "line 2: pool_id must be a quoted string",
+            ),
+            (
+                "[profiles.a]\ntoken_store = \"disk\"",
+                "line 2: unknown token_store disk; expected file, keyring or none",
+            ),
+            (
+                "[profiles.a]\npool_id = \"us-east-1_x\"\nregion = \"eu-west-1\"",
+                "profile a: pool us-east-1_x is not in region eu-west-1",
+            ),
+        ] {
+            let err = ProfileConfig::parse(text).unwrap_err();
+            assert_eq!(err.to_string(), message);
+        }
+    }
+}
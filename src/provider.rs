@@ -0,0 +1,525 @@
+use crate::auth::Authentication;
+use crate::client::Client;
+use crate::error::{CognitoSrpError, Result, ServiceErrorKind};
+use crate::secret::SecretString;
+use crate::store::{TokenKey, TokenStore};
+use crate::tokens::TokenSet;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+// Hands out tokens that are valid right now; how they are obtained is up to the implementation.
+pub trait TokenProvider: Send + Sync {
+    fn tokens(&self) -> Result<TokenSet>;
+
+    fn access_token(&self) -> Result<String> {
+        Ok(self.tokens()?.access_token)
+    }
+
+    fn id_token(&self) -> Result<String> {
+        Ok(self.tokens()?.id_token)
+    }
+
+    // The server rejected the current tokens (a 401 before they were due to expire, e.g. after a
+    // sign-out elsewhere); the next `tokens` call should renew rather than hand them out again.
+    fn invalidate(&self) {}
+}
+
+impl<T: TokenProvider + ?Sized> TokenProvider for Arc<T> {
+    fn tokens(&self) -> Result<TokenSet> {
+        (**self).tokens()
+    }
+
+    fn access_token(&self) -> Result<String> {
+        (**self).access_token()
+    }
+
+    fn id_token(&self) -> Result<String> {
+        (**self).id_token()
+    }
+
+    fn invalidate(&self) {
+        (**self).invalidate()
+    }
+}
+
+type Reauthenticate = Box<dyn Fn(&Client) -> Result<TokenSet> + Send + Sync>;
+
+#[derive(Default)]
+struct State {
+    tokens: Option<TokenSet>,
+    // A refresh is in flight; everyone else waits for its outcome instead of starting another.
+    refreshing: bool,
+    // Bumped when a refresh finishes, so waiters can tell theirs completed.
+    generation: u64,
+    // Bumped by revoke, so a refresh that finished just before it doesn't save its tokens back.
+    revocations: u64,
+    failure: Option<Arc<CognitoSrpError>>,
+}
+
+// Caches a TokenSet and refreshes it once it is within `lead_time` of expiring. When the refresh
+// token itself is rejected (expired or revoked), it signs in again if it has a way to.
+//
+// Refreshes are single-flight: concurrent callers share one refresh call. While it runs, callers
+// whose cached tokens have not actually expired yet get those instead of waiting.
+pub struct RefreshingTokenProvider {
+    client: Client,
+    username: String,
+    lead_time: Duration,
+    reauthenticate: Option<Reauthenticate>,
+    store: Option<Box<dyn TokenStore>>,
+    state: Mutex<State>,
+    refreshed: Condvar,
+    // Held across a renewed set's save and revoke's clear, so they can't interleave.
+    saving: Mutex<()>,
+}
+
+// Ends the flight even if the refresh panics, so waiters are not stuck forever.
+struct Flight<'a> {
+    provider: &'a RefreshingTokenProvider,
+    outcome: Option<Result<TokenSet, Arc<CognitoSrpError>>>,
+}
+
+impl Drop for Flight<'_> {
+    fn drop(&mut self) {
+        let mut state = self.provider.state.lock().unwrap();
+        state.refreshing = false;
+        state.generation += 1;
+        match self.outcome.take() {
+            Some(Ok(tokens)) => {
+                state.tokens = Some(tokens);
+                state.failure = None;
+            }
+            Some(Err(err)) => state.failure = Some(err),
+            None => state.failure = Some(Arc::new(CognitoSrpError::TaskPanicked)),
+        }
+        self.provider.refreshed.notify_all();
+    }
+}
+
+impl RefreshingTokenProvider {
+    pub fn new(client: Client, username: &str, tokens: TokenSet) -> Self {
+        Self::with_tokens(client, username, Some(tokens))
+    }
+
+    fn with_tokens(client: Client, username: &str, tokens: Option<TokenSet>) -> Self {
+        Self {
+            client,
+            username: username.to_owned(),
+            lead_time: Duration::from_secs(300),
+            reauthenticate: None,
+            store: None,
+            state: Mutex::new(State {
+                tokens,
+                ..State::default()
+            }),
+            refreshed: Condvar::new(),
+            saving: Mutex::new(()),
+        }
+    }
+
+    // Signs in with SRP now and again whenever the refresh token stops working.
+    pub fn sign_in(client: Client, username: &str, password: &str) -> Result<Self> {
+        let provider = Self::with_tokens(client, username, None).with_password(password);
+        provider.tokens()?;
+        Ok(provider)
+    }
+
+    // Starts from whatever `store` has for this user and saves every renewed set back to it.
+    // Add with_password or with_reauthentication for when the store is empty or stale.
+    pub fn from_store(client: Client, username: &str, store: impl TokenStore + 'static) -> Self {
+        let mut provider = Self::with_tokens(client, username, None);
+        provider.store = Some(Box::new(store));
+        provider
+    }
+
+    // How long before expiry to refresh; defaults to five minutes.
+    pub fn with_lead_time(mut self, lead_time: Duration) -> Self {
+        self.lead_time = lead_time;
+        self
+    }
+
+    pub fn with_reauthentication(
+        mut self,
+        reauthenticate: impl Fn(&Client) -> Result<TokenSet> + Send + Sync + 'static,
+    ) -> Self {
+        self.reauthenticate = Some(Box::new(reauthenticate));
+        self
+    }
+
+    // Re-runs SRP with this password; fails if the pool asks for MFA or another challenge.
+    pub fn with_password(self, password: &str) -> Self {
+        let username = self.username.clone();
+        let password = SecretString::from(password);
+        self.with_reauthentication(move |client| {
+            match client.authenticate(&username, password.expose_secret())? {
+                Authentication::Tokens(tokens) => Ok(tokens),
+                Authentication::Challenge(challenge) => Err(CognitoSrpError::ChallengeMismatch {
+                    expected: "no challenge".to_owned(),
+                    actual: Some(challenge.name.as_str().to_owned()),
+                }),
+            }
+        })
+    }
+
+    // Logs the user out: revokes the refresh token with Cognito, then forgets the tokens here and
+    // in the store. They are forgotten even if the call fails, so nothing refreshes them again;
+    // a later `tokens` call signs in afresh if it has a way to, and fails otherwise. A refresh
+    // already in flight is waited for, and its tokens are the ones revoked.
+    pub fn revoke(&self) -> Result<()> {
+        let key = TokenKey::new(&self.client, &self.username);
+        let mut tokens = {
+            let mut state = self
+                .refreshed
+                .wait_while(self.state.lock().unwrap(), |state| state.refreshing)
+                .unwrap();
+            state.revocations += 1;
+            state.tokens.take()
+        };
+        let _saving = self.saving.lock().unwrap();
+        let cleared = match &self.store {
+            Some(store) => {
+                if tokens.is_none() {
+                    tokens = store.load(&key).ok().flatten();
+                }
+                store.clear(&key)
+            }
+            None => Ok(()),
+        };
+        let revoked = match tokens.and_then(|tokens| tokens.refresh_token) {
+            Some(refresh_token) => self.client.revoke_token(refresh_token.expose_secret()),
+            None => Ok(()),
+        };
+        revoked.and(cleared)
+    }
+
+    fn is_fresh(&self, tokens: &TokenSet) -> bool {
+        !tokens.is_expired(&self.client, self.lead_time)
+    }
+
+    // The tokens to hand out, and whether they are new (and so still to be saved).
+    fn load_or_renew(&self, current: Option<TokenSet>) -> Result<(TokenSet, bool)> {
+        let key = TokenKey::new(&self.client, &self.username);
+        let current = match (current, &self.store) {
+            (None, Some(store)) => store.load(&key)?,
+            (current, _) => current,
+        };
+        if let Some(tokens) = current.as_ref().filter(|tokens| self.is_fresh(tokens)) {
+            return Ok((tokens.clone(), false));
+        }
+        Ok((self.renew(current)?, true))
+    }
+
+    fn renew(&self, current: Option<TokenSet>) -> Result<TokenSet> {
+        let refreshed = match current.and_then(|tokens| tokens.refresh_token) {
+            Some(refresh_token) => self
+                .client
+                .refresh_tokens(&self.username, refresh_token.expose_secret()),
+            None => Err(CognitoSrpError::SignedOut("no refresh token".to_owned())),
+        };
+        match (refreshed, &self.reauthenticate) {
+            (Err(err), Some(reauthenticate)) if refresh_token_rejected(&err) => {
+                reauthenticate(&self.client)
+            }
+            (result, _) => result,
+        }
+    }
+}
+
+fn refresh_token_rejected(err: &CognitoSrpError) -> bool {
+    matches!(err, CognitoSrpError::SignedOut(_))
+        || err.service_kind() == Some(&ServiceErrorKind::NotAuthorized)
+}
+
+impl TokenProvider for RefreshingTokenProvider {
+    fn tokens(&self) -> Result<TokenSet> {
+        let mut state = self.state.lock().unwrap();
+        if state.refreshing {
+            if let Some(tokens) = &state.tokens {
+                if !tokens.is_expired(&self.client, Duration::ZERO) {
+                    return Ok(tokens.clone());
+                }
+            }
+            let generation = state.generation;
+            state = self
+                .refreshed
+                .wait_while(state, |state| state.generation == generation)
+                .unwrap();
+            return match (&state.failure, &state.tokens) {
+                (Some(err), _) => Err(CognitoSrpError::RefreshFailed(err.clone())),
+                (None, Some(tokens)) => Ok(tokens.clone()),
+                (None, None) => Err(CognitoSrpError::SignedOut("no tokens".to_owned())),
+            };
+        }
+        if let Some(tokens) = &state.tokens {
+            if self.is_fresh(tokens) {
+                return Ok(tokens.clone());
+            }
+        }
+        state.refreshing = true;
+        let current = state.tokens.clone();
+        let revocations = state.revocations;
+        drop(state);
+
+        let mut flight = Flight {
+            provider: self,
+            outcome: None,
+        };
+        let outcome = self.load_or_renew(current);
+        let (result, outcome) = match outcome {
+            Ok((tokens, renewed)) => (Ok((tokens.clone(), renewed)), Ok(tokens)),
+            Err(err) => {
+                let err = Arc::new(err);
+                (Err(CognitoSrpError::RefreshFailed(err.clone())), Err(err))
+            }
+        };
+        flight.outcome = Some(outcome);
+        drop(flight);
+        let (tokens, renewed) = result?;
+        // Saved once the new set is cached: with refresh token rotation the one just sent is on
+        // its way out, so a failed save must not cost the provider the new one as well.
+        if let (true, Some(store)) = (renewed, &self.store) {
+            let _saving = self.saving.lock().unwrap();
+            if self.state.lock().unwrap().revocations != revocations {
+                return Err(CognitoSrpError::SignedOut("tokens were revoked".to_owned()));
+            }
+            store.save(&TokenKey::new(&self.client, &self.username), &tokens)?;
+        }
+        Ok(tokens)
+    }
+
+    // Marks the cached set expired; the refresh token in it is still used for the renewal.
+    fn invalidate(&self) {
+        if let Some(tokens) = &mut self.state.lock().unwrap().tokens {
+            tokens.expires_at = UNIX_EPOCH;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+    use crate::transport::{HttpRequest, HttpResponse, Transport};
+    use std::sync::Barrier;
+    use std::thread;
+    use std::time::SystemTime;
+
+    struct SlowTransport(Arc<MockTransport>);
+
+    impl Transport for SlowTransport {
+        fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+            thread::sleep(Duration::from_millis(100));
+            self.0.send(request)
+        }
+    }
+
+    fn tokens(access_token: &str, expires_in: u64) -> TokenSet {
+        TokenSet {
+            access_token: access_token.to_owned(),
+            id_token: "i1".to_owned(),
+            refresh_token: Some("r1".into()),
+            token_type: "Bearer".to_owned(),
+            expires_at: SystemTime::now() + Duration::from_secs(expires_in),
+        }
+    }
+
+    fn client(transport: Arc<MockTransport>) -> Client {
+        Client::new("us-east-1_pool", "client", transport).unwrap()
+    }
+
+    #[test]
+    fn test_refresh_ahead() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a2","IdToken":"i2","ExpiresIn":3600}}"#,
+        );
+        let provider =
+            RefreshingTokenProvider::new(client(transport.clone()), "bob", tokens("a1", 3600));
+        assert_eq!(provider.access_token().unwrap(), "a1");
+        assert!(transport.requests.lock().unwrap().is_empty());
+
+        let provider =
+            RefreshingTokenProvider::new(client(transport.clone()), "bob", tokens("a1", 60));
+        assert_eq!(provider.access_token().unwrap(), "a2");
+        // The refreshed set is cached and keeps the old refresh token.
+        assert_eq!(provider.tokens().unwrap().refresh_token, Some("r1".into()));
+        assert_eq!(transport.requests.lock().unwrap().len(), 1);
+
+        // A 401 downstream forces the next call to refresh even though a2 has an hour left.
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a3","IdToken":"i3","ExpiresIn":3600}}"#,
+        );
+        provider.invalidate();
+        assert_eq!(provider.access_token().unwrap(), "a3");
+        assert_eq!(transport.requests.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_single_flight() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a2","IdToken":"i2","ExpiresIn":3600}}"#,
+        );
+        let client =
+            Client::new("us-east-1_pool", "client", SlowTransport(transport.clone())).unwrap();
+        let provider = RefreshingTokenProvider::new(client, "bob", tokens("a1", 0));
+        let barrier = Barrier::new(8);
+
+        let tokens: Vec<String> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        provider.access_token().unwrap()
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        });
+        assert!(tokens.iter().all(|token| token == "a2"));
+        assert_eq!(transport.requests.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_revoke_waits_for_refresh() {
+        use crate::store::FileTokenStore;
+
+        let dir = std::env::temp_dir().join(format!("cogneato-test-{}", rand::random::<u64>()));
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a2","IdToken":"i2","RefreshToken":"r2","ExpiresIn":3600}}"#,
+        );
+        transport.respond(200, "{}");
+        let client =
+            Client::new("us-east-1_pool", "client", SlowTransport(transport.clone())).unwrap();
+        let key = TokenKey::new(&client, "bob");
+        let store = FileTokenStore::new(&dir);
+        store.save(&key, &tokens("a1", 0)).unwrap();
+        let provider = RefreshingTokenProvider::from_store(client, "bob", store.clone());
+
+        thread::scope(|scope| {
+            let refresh = scope.spawn(|| provider.tokens());
+            // Sign out while the refresh is on the wire.
+            thread::sleep(Duration::from_millis(30));
+            provider.revoke().unwrap();
+            let _ = refresh.join().unwrap();
+        });
+        // The refreshed set's rotated token is the one revoked, and nothing comes back.
+        let revoke = transport.last_body();
+        assert_eq!(revoke.str_field("Token").unwrap(), "r2");
+        assert_eq!(store.load(&key).unwrap(), None);
+        assert!(provider.state.lock().unwrap().tokens.is_none());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_from_store() {
+        use crate::store::FileTokenStore;
+
+        let dir = std::env::temp_dir().join(format!("cogneato-test-{}", rand::random::<u64>()));
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a2","IdToken":"i2","ExpiresIn":3600}}"#,
+        );
+        let provider = |transport: &Arc<MockTransport>| {
+            RefreshingTokenProvider::from_store(
+                client(transport.clone()),
+                "bob",
+                FileTokenStore::new(&dir),
+            )
+        };
+        let key = TokenKey::new(&client(transport.clone()), "bob");
+        FileTokenStore::new(&dir)
+            .save(&key, &tokens("a1", 60))
+            .unwrap();
+
+        // The stored set is near expiry, so it is refreshed and the result saved.
+        assert_eq!(provider(&transport).access_token().unwrap(), "a2");
+        assert_eq!(provider(&transport).access_token().unwrap(), "a2");
+        assert_eq!(transport.requests.lock().unwrap().len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_refresh_token_rotation() {
+        use crate::store::FileTokenStore;
+
+        let dir = std::env::temp_dir().join(format!("cogneato-test-{}", rand::random::<u64>()));
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a2","IdToken":"i2","RefreshToken":"r2","ExpiresIn":3600}}"#,
+        );
+        let rotations = Arc::new(Mutex::new(Vec::new()));
+        let seen = rotations.clone();
+        let client = client(transport.clone()).on_refresh_token_rotated(move |tokens| {
+            let refresh_token = tokens.refresh_token.as_ref().unwrap();
+            seen.lock()
+                .unwrap()
+                .push(refresh_token.expose_secret().to_owned());
+        });
+        let key = TokenKey::new(&client, "bob");
+        let store = FileTokenStore::new(&dir);
+        store.save(&key, &tokens("a1", 0)).unwrap();
+
+        let provider = RefreshingTokenProvider::from_store(client, "bob", store.clone());
+        let tokens = provider.tokens().unwrap();
+        assert_eq!(tokens.refresh_token, Some("r2".into()));
+        assert!(tokens.rotated_from("r1"));
+        assert_eq!(*rotations.lock().unwrap(), ["r2"]);
+        // The old refresh token is gone from the store as well as from memory.
+        assert_eq!(
+            store.load(&key).unwrap().unwrap().refresh_token,
+            Some("r2".into())
+        );
+
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a3","IdToken":"i3","ExpiresIn":3600}}"#,
+        );
+        provider.invalidate();
+        assert_eq!(provider.access_token().unwrap(), "a3");
+        assert_eq!(
+            transport
+                .last_body()
+                .get("AuthParameters")
+                .unwrap()
+                .str_field("REFRESH_TOKEN"),
+            Some("r2".to_owned())
+        );
+        assert_eq!(rotations.lock().unwrap().len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sign_in() {
+        let transport = MockTransport::new();
+        transport.respond(400, r#"{"__type":"UserNotFoundException","message":""}"#);
+        assert!(RefreshingTokenProvider::sign_in(client(transport.clone()), "bob", "pw").is_err());
+        let body = transport.last_body();
+        assert_eq!(body.str_field("AuthFlow").unwrap(), "USER_SRP_AUTH");
+    }
+
+    #[test]
+    fn test_reauthenticates_when_refresh_token_dies() {
+        let transport = MockTransport::new();
+        transport.respond(
+            400,
+            r#"{"__type":"NotAuthorizedException","message":"Refresh Token has expired"}"#,
+        );
+        let provider =
+            RefreshingTokenProvider::new(client(transport.clone()), "bob", tokens("a1", 0))
+                .with_reauthentication(|_| Ok(tokens("a3", 3600)));
+        assert_eq!(provider.access_token().unwrap(), "a3");
+
+        transport.respond(400, r#"{"__type":"InternalErrorException","message":""}"#);
+        let provider = RefreshingTokenProvider::new(client(transport), "bob", tokens("a1", 0))
+            .with_reauthentication(|_| Ok(tokens("a3", 3600)));
+        assert!(provider.access_token().is_err());
+    }
+}
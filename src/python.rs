@@ -0,0 +1,79 @@
+// # https://pyo3.rs/
+// The SRP hot path as a Python extension module, shaped like pycognito's AWSSRP so code moving
+// off pycognito or warrant keeps its own boto3 calls and only swaps the maths:
+//
+//     session = cog_neat_oh.SrpSession("us-east-1_XXXXXXXXX")
+//     response = idp.initiate_auth(AuthParameters={"USERNAME": user, "SRP_A": session.srp_a()}, ...)
+//     responses = session.process_challenge(response["ChallengeParameters"], password)
+//
+// Build the wheel with maturin; pyproject.toml turns on this feature, and maturin builds the
+// cdylib itself with `cargo rustc --crate-type cdylib`.
+use crate::clock::{cognito_timestamp, Clock, SystemClock};
+use crate::error::CognitoSrpError;
+use crate::srp;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use std::collections::BTreeMap;
+
+// Malformed input is the caller's to fix, so it surfaces as ValueError like pycognito's own
+// checks; anything else is a RuntimeError carrying the Rust message.
+impl From<CognitoSrpError> for PyErr {
+    fn from(err: CognitoSrpError) -> Self {
+        match err {
+            CognitoSrpError::InvalidHex(_)
+            | CognitoSrpError::InvalidServerB
+            | CognitoSrpError::InvalidClientA
+            | CognitoSrpError::InvalidU
+            | CognitoSrpError::InvalidPoolId(_)
+            | CognitoSrpError::InvalidArgument(_)
+            | CognitoSrpError::InvalidResponse(_) => PyValueError::new_err(err.to_string()),
+            _ => PyRuntimeError::new_err(err.to_string()),
+        }
+    }
+}
+
+#[pyclass(name = "SrpSession", module = "cog_neat_oh")]
+struct SrpSession {
+    inner: srp::SrpSession,
+}
+
+#[pymethods]
+impl SrpSession {
+    #[new]
+    fn new(pool_id: &str) -> PyResult<Self> {
+        Ok(Self {
+            inner: srp::SrpSession::new(pool_id, &mut rand::thread_rng())?,
+        })
+    }
+
+    fn srp_a(&self) -> String {
+        self.inner.srp_a()
+    }
+
+    // Takes the PASSWORD_VERIFIER ChallengeParameters dict and returns ChallengeResponses;
+    // `timestamp` defaults to now. The GIL is released for the modpow.
+    #[pyo3(signature = (parameters, password, timestamp = None))]
+    fn process_challenge(
+        &self,
+        py: Python<'_>,
+        parameters: BTreeMap<String, String>,
+        password: &str,
+        timestamp: Option<&str>,
+    ) -> PyResult<BTreeMap<String, String>> {
+        let timestamp = timestamp
+            .map(str::to_owned)
+            .unwrap_or_else(|| cognito_timestamp(SystemClock.now()));
+        let responses = py.allow_threads(|| {
+            self.inner
+                .process_challenge(&parameters, password, &timestamp)
+        })?;
+        Ok(responses)
+    }
+}
+
+#[pymodule]
+fn cog_neat_oh(_py: Python<'_>, module: &PyModule) -> PyResult<()> {
+    module.add_class::<SrpSession>()?;
+    module.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use crate::client::Client;
+use crate::error::{CognitoSrpError, Result};
+use crate::json::Value;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeliveryMedium {
+    Sms,
+    Email,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CodeDeliveryDetails {
+    pub destination: Option<String>,
+    pub delivery_medium: Option<DeliveryMedium>,
+    pub attribute_name: Option<String>,
+}
+
+impl DeliveryMedium {
+    // Reads the optional DeliveryMedium field of `value`.
+    pub(crate) fn from_field(value: &Value) -> Result<Option<Self>> {
+        Ok(match value.get("DeliveryMedium").and_then(Value::as_str) {
+            Some("SMS") => Some(DeliveryMedium::Sms),
+            Some("EMAIL") => Some(DeliveryMedium::Email),
+            Some(other) => {
+                return Err(CognitoSrpError::InvalidResponse(format!(
+                    "unknown delivery medium: {}",
+                    other
+                )))
+            }
+            None => None,
+        })
+    }
+}
+
+impl CodeDeliveryDetails {
+    pub(crate) fn from_json(value: &Value) -> Result<Self> {
+        Ok(Self {
+            destination: value.str_field("Destination"),
+            delivery_medium: DeliveryMedium::from_field(value)?,
+            attribute_name: value.str_field("AttributeName"),
+        })
+    }
+
+    pub(crate) fn from_response(response: &Value) -> Result<Option<Self>> {
+        response
+            .get("CodeDeliveryDetails")
+            .map(Self::from_json)
+            .transpose()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignUpResult {
+    pub user_confirmed: bool,
+    pub user_sub: String,
+    pub code_delivery_details: Option<CodeDeliveryDetails>,
+}
+
+pub(crate) fn attribute_list(attributes: &[(&str, &str)]) -> Value {
+    Value::Array(
+        attributes
+            .iter()
+            .map(|(name, value)| {
+                Value::object([("Name", Value::from(*name)), ("Value", Value::from(*value))])
+            })
+            .collect(),
+    )
+}
+
+impl Client {
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_SignUp.html
+    pub fn sign_up(
+        &self,
+        username: &str,
+        password: &str,
+        attributes: &[(&str, &str)],
+    ) -> Result<SignUpResult> {
+        self.check_password(password)?;
+        let mut body = self.request_for(username);
+        body.insert("Password", Value::from(password));
+        body.insert("UserAttributes", attribute_list(attributes));
+
+        let response = self.call("SignUp", body)?;
+        Ok(SignUpResult {
+            user_confirmed: response
+                .get("UserConfirmed")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+            user_sub: response.str_field("UserSub").ok_or_else(|| {
+                CognitoSrpError::InvalidResponse("SignUp response is missing UserSub".to_owned())
+            })?,
+            code_delivery_details: CodeDeliveryDetails::from_response(&response)?,
+        })
+    }
+
+    pub fn confirm_sign_up(&self, username: &str, code: &str) -> Result<()> {
+        let mut body = self.request_for(username);
+        body.insert("ConfirmationCode", Value::from(code));
+        self.call("ConfirmSignUp", body)?;
+        Ok(())
+    }
+
+    pub fn resend_confirmation_code(&self, username: &str) -> Result<Option<CodeDeliveryDetails>> {
+        let response = self.call("ResendConfirmationCode", self.request_for(username))?;
+        CodeDeliveryDetails::from_response(&response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_sign_up() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"UserConfirmed":false,"UserSub":"1234-abcd",
+                "CodeDeliveryDetails":{"AttributeName":"email","DeliveryMedium":"EMAIL","Destination":"b***@e***"}}"#,
+        );
+        let client = Client::new("us-east-1_pool", "client", transport.clone())
+            .unwrap()
+            .with_client_secret("secret");
+
+        let result = client
+            .sign_up("bob", "hunter2", &[("email", "bob@example.com")])
+            .unwrap();
+        assert_eq!(
+            result,
+            SignUpResult {
+                user_confirmed: false,
+                user_sub: "1234-abcd".to_owned(),
+                code_delivery_details: Some(CodeDeliveryDetails {
+                    destination: Some("b***@e***".to_owned()),
+                    delivery_medium: Some(DeliveryMedium::Email),
+                    attribute_name: Some("email".to_owned()),
+                }),
+            }
+        );
+
+        let body = transport.last_body();
+        assert_eq!(body.str_field("Username").unwrap(), "bob");
+        assert_eq!(
+            body.str_field("SecretHash").unwrap(),
+            "P1WGx30spzkjVO6gbksCMo0r3Ro1xm4a6xXZTTrvrD0="
+        );
+        assert_eq!(
+            body.get("UserAttributes").unwrap().to_string(),
+            r#"[{"Name":"email","Value":"bob@example.com"}]"#
+        );
+    }
+
+    #[test]
+    fn test_confirm_and_resend() {
+        let transport = MockTransport::new();
+        transport.respond(200, "{}");
+        transport.respond(
+            200,
+            r#"{"CodeDeliveryDetails":{"DeliveryMedium":"SMS","Destination":"+*******1234"}}"#,
+        );
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+
+        client.confirm_sign_up("bob", "123456").unwrap();
+        assert_eq!(
+            transport.last_body().str_field("ConfirmationCode").unwrap(),
+            "123456"
+        );
+        assert!(transport.last_body().get("SecretHash").is_none());
+
+        let details = client.resend_confirmation_code("bob").unwrap().unwrap();
+        assert_eq!(details.delivery_medium, Some(DeliveryMedium::Sms));
+        assert_eq!(details.attribute_name, None);
+    }
+}
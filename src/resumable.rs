@@ -0,0 +1,201 @@
+use crate::auth::{Challenge, ChallengeName};
+use crate::client::Client;
+use crate::clock::Clock;
+use crate::error::{CognitoSrpError, Result};
+use crate::json::{self, Value};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Bumped whenever the serialized form changes; older versions are rejected, not guessed at.
+const FORMAT_VERSION: u32 = 1;
+
+// A pending challenge that can be written out (to DynamoDB, a cookie, ...) and answered by a
+// later process, e.g. initiate in one Lambda invocation and answer MFA in the next.
+// `serialize` gives versioned JSON built with the crate's own json module; with the `serde`
+// feature the type also implements Serialize and Deserialize, unversioned.
+//
+// By the time Cognito asks for MFA or a new password the SRP exchange is finished, so the
+// ephemeral `a` is neither stored nor needed: Cognito's Session string carries the rest. It does
+// carry that Session, which stands in for a password check for a few minutes, so store it as you
+// would a credential.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableSession {
+    pool_id: String,
+    client_id: String,
+    created_at: SystemTime,
+    challenge: Challenge,
+}
+
+impl SerializableSession {
+    pub fn new(client: &Client, challenge: Challenge) -> Self {
+        Self {
+            pool_id: client.pool_id().to_owned(),
+            client_id: client.client_id().to_owned(),
+            created_at: client.now(),
+            challenge,
+        }
+    }
+
+    pub fn challenge(&self) -> &Challenge {
+        &self.challenge
+    }
+
+    // Cognito sessions last three minutes by default (up to 15); callers that know their app
+    // client's setting can check before bothering the user for a code. Pass the client, or any
+    // clock that agrees with the one that created the session.
+    pub fn is_older_than(&self, clock: &(impl Clock + ?Sized), age: Duration) -> bool {
+        self.created_at + age < clock.now()
+    }
+
+    // Hands back the challenge for respond_to_mfa and friends, after checking it was started
+    // against the same pool and app client.
+    pub fn resume(&self, client: &Client) -> Result<&Challenge> {
+        if self.pool_id != client.pool_id() || self.client_id != client.client_id() {
+            return Err(CognitoSrpError::Configuration(format!(
+                "session belongs to {} client {}",
+                self.pool_id, self.client_id
+            )));
+        }
+        Ok(&self.challenge)
+    }
+
+    pub fn serialize(&self) -> String {
+        let challenge = &self.challenge;
+        let mut value = Value::object([
+            ("version", Value::from(FORMAT_VERSION as f64)),
+            ("pool_id", Value::from(self.pool_id.as_str())),
+            ("client_id", Value::from(self.client_id.as_str())),
+            (
+                "created_at",
+                Value::from(
+                    self.created_at
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or(0) as f64,
+                ),
+            ),
+            ("challenge_name", Value::from(challenge.name.as_str())),
+            ("username", Value::from(challenge.username.as_str())),
+            (
+                "parameters",
+                Value::object(
+                    challenge
+                        .parameters
+                        .iter()
+                        .map(|(k, v)| (k.as_str(), Value::from(v.as_str()))),
+                ),
+            ),
+        ]);
+        if let Some(session) = &challenge.session {
+            value.insert("session", Value::from(session.as_str()));
+        }
+        value.to_string()
+    }
+
+    pub fn deserialize(serialized: &str) -> Result<Self> {
+        let invalid = |what: &str| CognitoSrpError::InvalidArgument(format!("session {}", what));
+        let value = json::parse(serialized)?;
+        match value.get("version") {
+            Some(Value::Number(version)) if *version == FORMAT_VERSION as f64 => {}
+            _ => return Err(invalid("has an unsupported format version")),
+        }
+        let field = |name: &str| {
+            value
+                .str_field(name)
+                .ok_or_else(|| invalid(&format!("is missing {}", name)))
+        };
+        let created_at = match value.get("created_at") {
+            Some(Value::Number(secs)) if *secs >= 0.0 => {
+                UNIX_EPOCH + Duration::from_secs(*secs as u64)
+            }
+            _ => return Err(invalid("is missing created_at")),
+        };
+        let parameters = match value.get("parameters") {
+            Some(Value::Object(map)) => map
+                .iter()
+                .map(|(k, v)| {
+                    v.as_str()
+                        .map(|v| (k.clone(), v.to_owned()))
+                        .ok_or_else(|| invalid("has a non-string parameter"))
+                })
+                .collect::<Result<_>>()?,
+            _ => return Err(invalid("is missing parameters")),
+        };
+        Ok(Self {
+            pool_id: field("pool_id")?,
+            client_id: field("client_id")?,
+            created_at,
+            challenge: Challenge {
+                name: ChallengeName::parse(&field("challenge_name")?),
+                session: value.str_field("session"),
+                parameters,
+                username: field("username")?,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Authentication;
+    use crate::clock::FixedClock;
+    use crate::transport::mock::MockTransport;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_round_trip_and_resume() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"ChallengeName":"SOFTWARE_TOKEN_MFA","Session":"sess-1",
+                "ChallengeParameters":{"USERNAME":"bob-internal"}}"#,
+        );
+        let clock = Arc::new(FixedClock::new(SystemTime::now()));
+        let client = Client::new("us-east-1_pool", "client", transport.clone())
+            .unwrap()
+            .with_clock(clock.clone());
+        let challenge = match client.respond_to_challenge(
+            &Challenge {
+                name: ChallengeName::CustomChallenge,
+                session: None,
+                parameters: Default::default(),
+                username: "bob".to_owned(),
+            },
+            &[("ANSWER", "42")],
+        ) {
+            Ok(Authentication::Challenge(challenge)) => challenge,
+            other => panic!("expected a challenge, got {:?}", other),
+        };
+
+        let serialized = SerializableSession::new(&client, challenge).serialize();
+        let session = SerializableSession::deserialize(&serialized).unwrap();
+        assert_eq!(session.challenge().session.as_deref(), Some("sess-1"));
+        assert!(!session.is_older_than(&client, Duration::from_secs(180)));
+        clock.advance(Duration::from_secs(181));
+        assert!(session.is_older_than(&client, Duration::from_secs(180)));
+
+        // A later invocation builds its own client and finishes the challenge.
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a","IdToken":"i","ExpiresIn":3600}}"#,
+        );
+        let later = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let challenge = session.resume(&later).unwrap();
+        assert!(matches!(
+            later.respond_to_mfa(challenge, "123456").unwrap(),
+            Authentication::Tokens(_)
+        ));
+        let body = transport.last_body();
+        assert_eq!(body.str_field("Session").unwrap(), "sess-1");
+        let responses = body.get("ChallengeResponses").unwrap();
+        assert_eq!(responses.str_field("USERNAME").unwrap(), "bob-internal");
+
+        let other = Client::new("us-east-1_pool", "other", transport).unwrap();
+        assert!(session.resume(&other).is_err());
+        assert!(SerializableSession::deserialize(
+            &serialized.replace("\"version\":1", "\"version\":9")
+        )
+        .is_err());
+    }
+}
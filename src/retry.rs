@@ -0,0 +1,137 @@
+use crate::error::Result;
+use rand::Rng;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Jitter {
+    None,
+    // Sleep a random time in [0, delay].
+    Full,
+    // Sleep delay/2 plus a random time in [0, delay/2].
+    Equal,
+}
+
+// # https://aws.amazon.com/blogs/architecture/exponential-backoff-and-jitter/
+// Only applied to operations that are safe to repeat; see Client::with_retry_policy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    // Total tries including the first; 1 disables retries.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: Jitter,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            jitter: Jitter::Full,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    // How long to wait after failed attempt number `attempt` (starting at 1).
+    fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+        let random = |max: Duration| {
+            Duration::from_nanos(rand::thread_rng().gen_range(0..=max.as_nanos() as u64))
+        };
+        match self.jitter {
+            Jitter::None => delay,
+            Jitter::Full => random(delay),
+            Jitter::Equal => delay / 2 + random(delay / 2),
+        }
+    }
+
+    pub(crate) fn run<T>(&self, mut op: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut attempt = 1;
+        loop {
+            match op() {
+                Err(err) if err.is_retryable() && attempt < self.max_attempts => {
+                    thread::sleep(self.delay(attempt));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CognitoSrpError;
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(350),
+            jitter: Jitter::None,
+        };
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(350));
+        assert_eq!(policy.delay(40), Duration::from_millis(350));
+
+        for jitter in [Jitter::Full, Jitter::Equal] {
+            let policy = RetryPolicy {
+                jitter,
+                ..policy.clone()
+            };
+            let delay = policy.delay(2);
+            assert!(delay <= Duration::from_millis(200));
+            if jitter == Jitter::Equal {
+                assert!(delay >= Duration::from_millis(100));
+            }
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        let policy = RetryPolicy {
+            base_delay: Duration::ZERO,
+            ..RetryPolicy::default()
+        };
+        let mut calls = 0;
+        let result: Result<()> = policy.run(|| {
+            calls += 1;
+            Err(CognitoSrpError::Transport("reset".into()))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+
+        calls = 0;
+        let result: Result<()> = policy.run(|| {
+            calls += 1;
+            Err(CognitoSrpError::InvalidServerB)
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+
+        calls = 0;
+        let result: Result<()> = RetryPolicy::none().run(|| {
+            calls += 1;
+            Err(CognitoSrpError::Transport("reset".into()))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+}
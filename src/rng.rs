@@ -0,0 +1,7 @@
+use rand::{CryptoRng, RngCore};
+
+// Randomness for SRP ephemerals. Production uses the OS-seeded thread RNG; tests can hand the
+// client a seeded CSPRNG (e.g. rand_chacha::ChaCha20Rng) to make whole handshakes repeatable.
+pub trait SecureRng: RngCore + CryptoRng + Send {}
+
+impl<T: RngCore + CryptoRng + Send> SecureRng for T {}
@@ -0,0 +1,122 @@
+use crate::crypto::constant_time_eq;
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{compiler_fence, Ordering};
+
+// Overwrites `bytes` with zeros through volatile writes, which the optimiser can't drop as dead
+// stores the way it can a plain fill right before a free.
+pub(crate) fn zeroize(bytes: &mut [u8]) {
+    for byte in bytes.iter_mut() {
+        // SAFETY: `byte` is a valid, aligned &mut u8.
+        unsafe { core::ptr::write_volatile(byte, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+// A password, refresh token or other secret string: cleared from memory on drop and shown as
+// "***" by Debug. Read it with `expose_secret` so every use stands out.
+#[derive(Clone, Default)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(secret: String) -> Self {
+        Self(secret)
+    }
+
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        Self(secret.to_owned())
+    }
+}
+
+// Compares in constant time so equality checks don't leak how much of a secret matched.
+impl PartialEq for SecretString {
+    fn eq(&self, other: &Self) -> bool {
+        constant_time_eq(self.0.as_bytes(), other.0.as_bytes())
+    }
+}
+
+impl Eq for SecretString {}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString(***)")
+    }
+}
+
+// Serialized in the clear: persisting a TokenSet means persisting its refresh token.
+#[cfg(feature = "serde")]
+impl serde::Serialize for SecretString {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SecretString {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <String as serde::Deserialize>::deserialize(deserializer).map(Self)
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        // SAFETY: all zeros is valid UTF-8, and the string is never read again.
+        zeroize(unsafe { self.0.as_bytes_mut() });
+    }
+}
+
+// Fixed-size key material (HKDF output, cache keys) that is cleared on drop.
+#[derive(Clone)]
+pub(crate) struct SecretBytes<const N: usize>(pub(crate) [u8; N]);
+
+impl<const N: usize> Deref for SecretBytes<N> {
+    type Target = [u8; N];
+
+    fn deref(&self) -> &[u8; N] {
+        &self.0
+    }
+}
+
+impl<const N: usize> DerefMut for SecretBytes<N> {
+    fn deref_mut(&mut self) -> &mut [u8; N] {
+        &mut self.0
+    }
+}
+
+impl<const N: usize> Drop for SecretBytes<N> {
+    fn drop(&mut self) {
+        zeroize(&mut self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_string() {
+        let secret = SecretString::from("hunter2");
+        assert_eq!(secret.expose_secret(), "hunter2");
+        assert_eq!(format!("{:?}", secret), "SecretString(***)");
+        assert_eq!(secret, SecretString::new("hunter2".to_owned()));
+        assert_ne!(secret, SecretString::from("hunter3"));
+
+        let mut bytes = SecretBytes([7u8; 4]);
+        zeroize(&mut bytes[..2]);
+        assert_eq!(*bytes, [0, 0, 7, 7]);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::client::Client;
+use crate::error::{CognitoSrpError, Result};
+use crate::jwt::{Claims, Verifier};
+use crate::store::{TokenKey, TokenStore};
+use crate::tokens::TokenSet;
+use std::sync::Arc;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SessionEvent {
+    Refreshed,
+    // The session dropped its tokens because the refreshed id token failed revalidation.
+    ForcedSignOut { reason: String },
+}
+
+type Predicate = Box<dyn Fn(&Claims) -> bool + Send + Sync>;
+type Listener = Box<dyn Fn(&SessionEvent) + Send + Sync>;
+
+// A signed-in user's tokens plus the client needed to keep them fresh.
+pub struct Session {
+    client: Client,
+    username: String,
+    tokens: Option<TokenSet>,
+    revalidation: Option<(Verifier, Predicate)>,
+    listeners: Vec<Listener>,
+    store: Option<Arc<dyn TokenStore>>,
+}
+
+impl Session {
+    pub fn new(client: Client, username: &str, tokens: TokenSet) -> Self {
+        Self {
+            client,
+            username: username.to_owned(),
+            tokens: Some(tokens),
+            revalidation: None,
+            listeners: Vec::new(),
+            store: None,
+        }
+    }
+
+    // Saves each refreshed set to `store`, and clears it when the session is revoked or signed
+    // out, so a restart never picks up tokens this session has given up.
+    pub fn with_store(mut self, store: impl TokenStore + 'static) -> Self {
+        self.store = Some(Arc::new(store));
+        self
+    }
+
+    // After every refresh, verify the new id token and require `predicate` to accept its claims
+    // (e.g. "still in the admins group"); otherwise the session is signed out.
+    pub fn with_revalidation(
+        mut self,
+        verifier: Verifier,
+        predicate: impl Fn(&Claims) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.revalidation = Some((verifier, Box::new(predicate)));
+        self
+    }
+
+    pub fn on_event(&mut self, listener: impl Fn(&SessionEvent) + Send + Sync + 'static) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn tokens(&self) -> Option<&TokenSet> {
+        self.tokens.as_ref()
+    }
+
+    pub fn is_signed_in(&self) -> bool {
+        self.tokens.is_some()
+    }
+
+    fn emit(&self, event: SessionEvent) {
+        for listener in &self.listeners {
+            listener(&event);
+        }
+    }
+
+    fn revalidate(&self, tokens: &TokenSet) -> Result<(), String> {
+        match &self.revalidation {
+            None => Ok(()),
+            Some((verifier, predicate)) => {
+                let claims = verifier
+                    .verify(&tokens.id_token)
+                    .map_err(|err| format!("refreshed id token failed verification: {}", err))?;
+                if predicate(&claims) {
+                    Ok(())
+                } else {
+                    Err("refreshed claims were rejected by the revalidation predicate".to_owned())
+                }
+            }
+        }
+    }
+
+    fn key(&self) -> TokenKey {
+        TokenKey::new(&self.client, &self.username)
+    }
+
+    fn clear_store(&self) -> Result<()> {
+        match &self.store {
+            Some(store) => store.clear(&self.key()),
+            None => Ok(()),
+        }
+    }
+
+    // Logs out: revokes the refresh token with Cognito and forgets the cached tokens, here and in
+    // the store. The tokens are dropped even if the call fails, so a revoked session can never
+    // be refreshed again.
+    pub fn revoke(&mut self) -> Result<()> {
+        let revoked = match self.tokens.take().and_then(|tokens| tokens.refresh_token) {
+            Some(refresh_token) => self.client.revoke_token(refresh_token.expose_secret()),
+            None => Ok(()),
+        };
+        let cleared = self.clear_store();
+        revoked.and(cleared)
+    }
+
+    pub fn refresh(&mut self) -> Result<&TokenSet> {
+        let refresh_token = self
+            .tokens
+            .as_ref()
+            .ok_or_else(|| CognitoSrpError::SignedOut("no tokens".to_owned()))?
+            .refresh_token
+            .clone()
+            .ok_or_else(|| {
+                CognitoSrpError::InvalidArgument("session has no refresh token".to_owned())
+            })?;
+        let tokens = self
+            .client
+            .refresh_tokens(&self.username, refresh_token.expose_secret())?;
+
+        if let Err(reason) = self.revalidate(&tokens) {
+            self.tokens = None;
+            self.clear_store()?;
+            self.emit(SessionEvent::ForcedSignOut {
+                reason: reason.clone(),
+            });
+            return Err(CognitoSrpError::SignedOut(reason));
+        }
+        if let Some(store) = &self.store {
+            store.save(&self.key(), &tokens)?;
+        }
+        self.emit(SessionEvent::Refreshed);
+        Ok(self.tokens.insert(tokens))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jwt::fixtures::{token, verifier};
+    use crate::transport::mock::MockTransport;
+    use std::sync::{Arc, Mutex};
+    use std::time::SystemTime;
+
+    fn session(transport: Arc<MockTransport>) -> Session {
+        let client = Client::new("us-east-1_pool", "client", transport).unwrap();
+        let tokens = TokenSet {
+            access_token: "a1".to_owned(),
+            id_token: token("id_admin"),
+            refresh_token: Some("r1".into()),
+            token_type: "Bearer".to_owned(),
+            expires_at: SystemTime::now(),
+        };
+        Session::new(client, "bob", tokens).with_revalidation(verifier(), |claims| {
+            claims.groups.iter().any(|g| g == "admins")
+        })
+    }
+
+    fn refresh_response(id_token: &str) -> String {
+        format!(
+            r#"{{"AuthenticationResult":{{"AccessToken":"a2","IdToken":"{}","ExpiresIn":3600}}}}"#,
+            id_token
+        )
+    }
+
+    #[test]
+    fn test_refresh_passes_revalidation() {
+        let transport = MockTransport::new();
+        transport.respond(200, &refresh_response(&token("id_admin")));
+        let mut session = session(transport);
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        session.on_event(move |event| sink.lock().unwrap().push(event.clone()));
+
+        let tokens = session.refresh().unwrap();
+        assert_eq!(tokens.access_token, "a2");
+        assert_eq!(tokens.refresh_token, Some("r1".into()));
+        assert_eq!(*events.lock().unwrap(), vec![SessionEvent::Refreshed]);
+    }
+
+    #[test]
+    fn test_refresh_forces_sign_out() {
+        let transport = MockTransport::new();
+        transport.respond(200, &refresh_response(&token("id_guest")));
+        transport.respond(200, &refresh_response(&token("id_tampered")));
+        let mut session = session(transport.clone());
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        session.on_event(move |event| sink.lock().unwrap().push(event.clone()));
+
+        assert!(session.refresh().is_err());
+        assert!(!session.is_signed_in());
+        assert!(matches!(
+            events.lock().unwrap().as_slice(),
+            [SessionEvent::ForcedSignOut { .. }]
+        ));
+        assert!(session.refresh().is_err());
+
+        let mut session = self::session(transport);
+        let err = session.refresh().unwrap_err();
+        assert!(err.to_string().contains("failed verification"));
+        assert!(!session.is_signed_in());
+    }
+
+    #[test]
+    fn test_revoke() {
+        let transport = MockTransport::new();
+        transport.respond(
+            400,
+            r#"{"__type":"UnsupportedTokenTypeException","message":"no"}"#,
+        );
+        let mut session = session(transport.clone());
+
+        assert!(session.revoke().is_err());
+        assert!(!session.is_signed_in());
+        assert_eq!(transport.last_body().str_field("Token").unwrap(), "r1");
+        assert!(session.refresh().is_err());
+        session.revoke().unwrap();
+    }
+
+    #[test]
+    fn test_revoke_clears_store() {
+        let dir = std::env::temp_dir().join(format!("cogneato-test-{}", rand::random::<u64>()));
+        let transport = MockTransport::new();
+        transport.respond(200, &refresh_response(&token("id_admin")));
+        transport.respond(200, "{}");
+        let store = crate::store::FileTokenStore::new(&dir);
+        let mut session = session(transport.clone()).with_store(store.clone());
+        let key = session.key();
+
+        session.refresh().unwrap();
+        assert_eq!(store.load(&key).unwrap().unwrap().access_token, "a2");
+        session.revoke().unwrap();
+        assert_eq!(transport.last_body().str_field("Token").unwrap(), "r1");
+        assert_eq!(store.load(&key).unwrap(), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,128 @@
+use crate::credentials::AwsCredentials;
+use crate::crypto::{hmac_sha256, sha256};
+use crate::error::{CognitoSrpError, Result};
+use crate::time::amz_date;
+use crate::transport::HttpRequest;
+use hex::encode;
+use std::time::SystemTime;
+
+fn host_and_path(url: &str) -> Result<(&str, &str)> {
+    let rest = url
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .ok_or_else(|| CognitoSrpError::InvalidArgument(format!("invalid url: {}", url)))?;
+    Ok(match rest.find('/') {
+        Some(idx) => (&rest[..idx], &rest[idx..]),
+        None => (rest, "/"),
+    })
+}
+
+// # https://docs.aws.amazon.com/IAM/latest/UserGuide/create-signed-request.html
+// Only what cognito-idp needs: POST to a query-less path with a JSON body.
+pub(crate) fn sign(
+    request: &mut HttpRequest,
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    now: SystemTime,
+) -> Result<()> {
+    let (host, path) = host_and_path(&request.url)?;
+    let (host, path) = (host.to_owned(), path.to_owned());
+    let timestamp = amz_date(now);
+    let date = &timestamp[..8];
+
+    request.headers.push(("Host".to_owned(), host));
+    request
+        .headers
+        .push(("X-Amz-Date".to_owned(), timestamp.clone()));
+    if let Some(token) = &credentials.session_token {
+        request
+            .headers
+            .push(("X-Amz-Security-Token".to_owned(), token.clone()));
+    }
+
+    let mut headers: Vec<(String, String)> = request
+        .headers
+        .iter()
+        .map(|(name, value)| (name.to_lowercase(), value.trim().to_owned()))
+        .collect();
+    headers.sort();
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        request.method,
+        path,
+        canonical_headers,
+        signed_headers,
+        encode(sha256(request.body.as_bytes()))
+    );
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        timestamp,
+        scope,
+        encode(sha256(canonical_request.as_bytes()))
+    );
+
+    let secret = format!("AWS4{}", credentials.secret_access_key);
+    let k_date = hmac_sha256(secret.as_bytes(), date.as_bytes());
+    let k_region = hmac_sha256(&k_date, region.as_bytes());
+    let k_service = hmac_sha256(&k_region, service.as_bytes());
+    let k_signing = hmac_sha256(&k_service, b"aws4_request");
+    let signature = encode(hmac_sha256(&k_signing, string_to_sign.as_bytes()));
+
+    request.headers.push((
+        "Authorization".to_owned(),
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key_id, scope, signed_headers, signature
+        ),
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_sign() {
+        let mut request = HttpRequest {
+            method: "POST",
+            url: "https://cognito-idp.us-east-1.amazonaws.com/".to_owned(),
+            headers: vec![
+                (
+                    "Content-Type".to_owned(),
+                    "application/x-amz-json-1.1".to_owned(),
+                ),
+                (
+                    "X-Amz-Target".to_owned(),
+                    "AWSCognitoIdentityProviderService.AdminConfirmSignUp".to_owned(),
+                ),
+            ],
+            body: r#"{"UserPoolId":"us-east-1_pool","Username":"bob"}"#.to_owned(),
+        };
+        let credentials =
+            AwsCredentials::new("AKIDEXAMPLE", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY");
+        let now = UNIX_EPOCH + Duration::from_secs(1440938160);
+        sign(&mut request, &credentials, "us-east-1", "cognito-idp", now).unwrap();
+
+        let authorization = &request.headers.last().unwrap().1;
+        assert_eq!(
+            authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/cognito-idp/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date;x-amz-target, \
+             Signature=f7ef3b5bd6921509d97771c86835e203ff40ac8442ff4a69f1fd895c1efd11aa"
+        );
+    }
+}
@@ -0,0 +1,261 @@
+// # https://docs.aws.amazon.com/cognito/latest/developerguide/user-pool-settings-mfa-totp.html
+// TOTP (authenticator app) MFA. A signed-in user adds it with associate_software_token and
+// verify_software_token on their access token. A pool that requires MFA stops a user with none
+// configured at MFA_SETUP instead of issuing tokens; the same two calls then run on the
+// challenge's Session, and the sign-in picks up where it stopped:
+//
+//     let setup = client.begin_mfa_setup(&challenge)?;
+//     show_qr_code(&setup.otpauth_uri("My App", "bob@example.com"));
+//     let authentication = client.complete_mfa_setup(&setup, &read_code(), None)?;
+use crate::auth::{Authentication, Challenge, ChallengeName};
+use crate::client::Client;
+use crate::error::{CognitoSrpError, Result};
+use crate::json::Value;
+use crate::secret::SecretString;
+use crate::url;
+
+// An authenticator secret issued for an MFA_SETUP challenge, waiting for its first code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SoftwareTokenSetup {
+    secret_code: SecretString,
+    // MFA_SETUP with the Session AssociateSoftwareToken handed back.
+    challenge: Challenge,
+}
+
+impl SoftwareTokenSetup {
+    // The base32 key to type into an authenticator app.
+    pub fn secret_code(&self) -> &SecretString {
+        &self.secret_code
+    }
+
+    pub fn challenge(&self) -> &Challenge {
+        &self.challenge
+    }
+
+    // The same key as an otpauth:// URI, for a QR code. `account` is only a label in the app.
+    pub fn otpauth_uri(&self, issuer: &str, account: &str) -> String {
+        otpauth_uri(self.secret_code.expose_secret(), issuer, account)
+    }
+}
+
+fn otpauth_uri(secret_code: &str, issuer: &str, account: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}",
+        url::encode(issuer),
+        url::encode(account),
+        secret_code,
+        url::encode(issuer)
+    )
+}
+
+impl Client {
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_AssociateSoftwareToken.html
+    // A new authenticator secret for the signed-in user. It only takes effect once
+    // verify_software_token accepts a code from it; enable it with set_user_mfa_preference.
+    pub fn associate_software_token(&self, access_token: &str) -> Result<SecretString> {
+        let (secret_code, _) = self.associate(("AccessToken", access_token))?;
+        Ok(secret_code)
+    }
+
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_VerifySoftwareToken.html
+    // `friendly_name` labels the device in the user's MFA settings.
+    pub fn verify_software_token(
+        &self,
+        access_token: &str,
+        code: &str,
+        friendly_name: Option<&str>,
+    ) -> Result<()> {
+        self.verify(("AccessToken", access_token), code, friendly_name)?;
+        Ok(())
+    }
+
+    // Associates an authenticator with the user an MFA_SETUP challenge is for.
+    pub fn begin_mfa_setup(&self, challenge: &Challenge) -> Result<SoftwareTokenSetup> {
+        check_mfa_setup(challenge)?;
+        let session = challenge.session.as_deref().ok_or_else(|| {
+            CognitoSrpError::InvalidArgument("MFA_SETUP challenge has no session".to_owned())
+        })?;
+        let (secret_code, session) = self.associate(("Session", session))?;
+        Ok(SoftwareTokenSetup {
+            secret_code,
+            challenge: Challenge {
+                session: session.or_else(|| challenge.session.clone()),
+                ..challenge.clone()
+            },
+        })
+    }
+
+    // Verifies the first code from the authenticator, then answers MFA_SETUP; the result is the
+    // sign-in's tokens, or whatever Cognito asks for next.
+    pub fn complete_mfa_setup(
+        &self,
+        setup: &SoftwareTokenSetup,
+        code: &str,
+        friendly_name: Option<&str>,
+    ) -> Result<Authentication> {
+        let challenge = self.verify_setup(setup, code, friendly_name)?;
+        self.respond_to_challenge(&challenge, &[])
+    }
+
+    // For MFA_SETUP from admin_authenticate; the association itself has no Admin* operation.
+    pub fn admin_complete_mfa_setup(
+        &self,
+        setup: &SoftwareTokenSetup,
+        code: &str,
+        friendly_name: Option<&str>,
+    ) -> Result<Authentication> {
+        let challenge = self.verify_setup(setup, code, friendly_name)?;
+        self.admin_respond_to_challenge(&challenge, &[])
+    }
+
+    fn verify_setup(
+        &self,
+        setup: &SoftwareTokenSetup,
+        code: &str,
+        friendly_name: Option<&str>,
+    ) -> Result<Challenge> {
+        let session = setup.challenge.session.as_deref().unwrap_or_default();
+        let session = self.verify(("Session", session), code, friendly_name)?;
+        Ok(Challenge {
+            session: session.or_else(|| setup.challenge.session.clone()),
+            ..setup.challenge.clone()
+        })
+    }
+
+    // Either credential, AccessToken or Session; returns the secret and any new Session.
+    fn associate(&self, credential: (&str, &str)) -> Result<(SecretString, Option<String>)> {
+        let (name, value) = credential;
+        let response = self.call(
+            "AssociateSoftwareToken",
+            Value::object([(name, Value::from(value))]),
+        )?;
+        let secret_code = response.str_field("SecretCode").ok_or_else(|| {
+            CognitoSrpError::InvalidResponse(
+                "AssociateSoftwareToken response is missing SecretCode".to_owned(),
+            )
+        })?;
+        Ok((
+            SecretString::new(secret_code),
+            response.str_field("Session"),
+        ))
+    }
+
+    fn verify(
+        &self,
+        credential: (&str, &str),
+        code: &str,
+        friendly_name: Option<&str>,
+    ) -> Result<Option<String>> {
+        let (name, value) = credential;
+        let mut fields = vec![(name, Value::from(value)), ("UserCode", Value::from(code))];
+        if let Some(friendly_name) = friendly_name {
+            fields.push(("FriendlyDeviceName", Value::from(friendly_name)));
+        }
+        let response = self.call("VerifySoftwareToken", Value::object(fields))?;
+        if response.str_field("Status").as_deref() == Some("ERROR") {
+            return Err(CognitoSrpError::InvalidResponse(
+                "VerifySoftwareToken did not accept the code".to_owned(),
+            ));
+        }
+        Ok(response.str_field("Session"))
+    }
+}
+
+// MFA_SETUP lists the methods the pool lets the user set up, e.g. ["SMS_MFA","SOFTWARE_TOKEN_MFA"].
+fn check_mfa_setup(challenge: &Challenge) -> Result<()> {
+    if challenge.name != ChallengeName::MfaSetup {
+        return Err(CognitoSrpError::InvalidArgument(format!(
+            "{} is not MFA_SETUP",
+            challenge.name.as_str()
+        )));
+    }
+    match challenge.parameters.get("MFAS_CAN_SETUP") {
+        Some(methods) if !methods.contains("SOFTWARE_TOKEN_MFA") => {
+            Err(CognitoSrpError::InvalidArgument(format!(
+                "the pool only offers {} for MFA setup",
+                methods
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::credentials::AwsCredentials;
+    use crate::transport::mock::MockTransport;
+
+    const MFA_SETUP: &str = r#"{"ChallengeName":"MFA_SETUP","Session":"s1",
+        "ChallengeParameters":{"USERNAME":"bob-internal",
+        "MFAS_CAN_SETUP":"[\"SMS_MFA\",\"SOFTWARE_TOKEN_MFA\"]"}}"#;
+
+    #[test]
+    fn test_mfa_setup() {
+        let transport = MockTransport::new();
+        for body in [
+            MFA_SETUP,
+            r#"{"SecretCode":"JBSWY3DPEHPK3PXP","Session":"s2"}"#,
+            r#"{"Status":"SUCCESS","Session":"s3"}"#,
+            r#"{"AuthenticationResult":{"AccessToken":"a","IdToken":"i","ExpiresIn":3600}}"#,
+        ] {
+            transport.respond(200, body);
+        }
+        let client = Client::new("us-east-1_pool", "client", transport.clone())
+            .unwrap()
+            .with_credentials(AwsCredentials::new("AKID", "secret"));
+        let Authentication::Challenge(challenge) = client
+            .admin_authenticate_with_password("bob", "P@ssw0rd!")
+            .unwrap()
+        else {
+            panic!("expected MFA_SETUP");
+        };
+        assert_eq!(challenge.name, ChallengeName::MfaSetup);
+
+        let setup = client.begin_mfa_setup(&challenge).unwrap();
+        assert_eq!(setup.secret_code().expose_secret(), "JBSWY3DPEHPK3PXP");
+        assert_eq!(transport.last_request().body, r#"{"Session":"s1"}"#);
+        assert_eq!(
+            setup.otpauth_uri("My App", "bob@example.com"),
+            "otpauth://totp/My%20App:bob%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=My%20App"
+        );
+
+        let authentication = client
+            .admin_complete_mfa_setup(&setup, "123456", Some("phone"))
+            .unwrap();
+        assert!(matches!(authentication, Authentication::Tokens(_)));
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(
+            requests[2].body,
+            r#"{"FriendlyDeviceName":"phone","Session":"s2","UserCode":"123456"}"#
+        );
+        let answer = crate::json::parse(&requests[3].body).unwrap();
+        assert_eq!(answer.str_field("ChallengeName").unwrap(), "MFA_SETUP");
+        assert_eq!(answer.str_field("Session").unwrap(), "s3");
+    }
+
+    #[test]
+    fn test_rejected_setups() {
+        let transport = MockTransport::new();
+        transport.respond(200, r#"{"Status":"ERROR"}"#);
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let mut challenge = Challenge {
+            name: ChallengeName::MfaSetup,
+            session: Some("s1".to_owned()),
+            parameters: [("MFAS_CAN_SETUP".to_owned(), r#"["SMS_MFA"]"#.to_owned())].into(),
+            username: "bob".to_owned(),
+        };
+        assert!(matches!(
+            client.begin_mfa_setup(&challenge),
+            Err(CognitoSrpError::InvalidArgument(_))
+        ));
+        challenge.name = ChallengeName::SoftwareTokenMfa;
+        assert!(client.begin_mfa_setup(&challenge).is_err());
+        assert!(transport.requests.lock().unwrap().is_empty());
+
+        assert!(matches!(
+            client.verify_software_token("access", "000000", None),
+            Err(CognitoSrpError::InvalidResponse(_))
+        ));
+    }
+}
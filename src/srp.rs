@@ -0,0 +1,556 @@
+use crate::crypto::hmac_sha256;
+use crate::error::{CognitoSrpError, Result};
+use crate::pool_id::PoolId;
+use crate::secret::{zeroize, SecretBytes};
+use crate::srp6a::{hkdf_into, scrub, SrpContext};
+use alloc::borrow::ToOwned;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use data_encoding::BASE64;
+use hex::decode;
+use num_bigint::{BigUint, ParseBigIntError};
+use num_traits::{Num, Zero};
+use rand::RngCore;
+
+pub mod primitives;
+pub mod server;
+pub mod vectors;
+const INFO_BITS: &[u8] = "Caldera Derived Key".as_bytes();
+
+fn hex_to_long(hex_str: &str) -> Result<BigUint, ParseBigIntError> {
+    BigUint::from_str_radix(hex_str, 16)
+}
+
+// `num_bytes` from a CSPRNG: 128 for SRP ephemerals, 16 for salts, 40 for device passwords.
+pub(crate) fn get_random(rng: &mut dyn RngCore, num_bytes: usize) -> Vec<u8> {
+    let mut bytes = vec![0; num_bytes];
+    rng.fill_bytes(&mut bytes);
+    bytes
+}
+
+// Signed padding (see srp6a::Padding); every hash input goes through this.
+fn pad(value: &BigUint) -> Vec<u8> {
+    group().pad(value)
+}
+
+// SALT arrives as hex whose leading zeros are significant, so it is padded as bytes rather than
+// going through BigUint.
+fn salt_bytes(salt_hex: &str) -> Result<Vec<u8>> {
+    let bytes = if salt_hex.len() % 2 == 1 {
+        decode(format!("0{}", salt_hex))?
+    } else {
+        decode(salt_hex)?
+    };
+    Ok(match bytes.first() {
+        Some(first) if first & 0x80 != 0 => [&[0], &bytes[..]].concat(),
+        _ => bytes,
+    })
+}
+
+fn compute_hkdf(ikm: &[u8], salt: &[u8]) -> Result<[u8; 16]> {
+    let mut okm = [0u8; 16];
+
+    // pycognito hand-rolls HKDF as HMAC(prk, info || 0x01); that trailing 0x01 is the block
+    // counter HKDF-Expand appends itself, so it must not be part of `info` here. (Passing it
+    // in `info` as well, as this crate once did, hashes info || 0x01 || 0x01 and derives a key
+    // Cognito rejects.)
+    hkdf_into(ikm, salt, INFO_BITS, &mut okm)?;
+    Ok(okm)
+}
+
+fn calculate_u(big_a: &BigUint, big_b: &BigUint) -> BigUint {
+    group().u(big_a, big_b)
+}
+
+// x = H(salt | H(pool name | username | ":" | password)): plain SRP-6a x with the pool name
+// prefixed to the identity.
+fn calculate_x(pool_name: &str, username: &str, password: &str, salt: &str) -> Result<BigUint> {
+    let identity = [pool_name, username].concat();
+    Ok(group().x(&salt_bytes(salt)?, &identity, password))
+}
+
+// PASSWORD_CLAIM_SIGNATURE = HMAC(key, pool name | username | secret block | timestamp)
+fn claim_signature(
+    key: &[u8; 16],
+    pool_name: &str,
+    user_id_for_srp: &str,
+    secret_block_b64: &str,
+    timestamp: &str,
+) -> Result<String> {
+    let msg = [
+        pool_name.as_bytes(),
+        user_id_for_srp.as_bytes(),
+        &BASE64.decode(secret_block_b64.as_bytes()).map_err(|_| {
+            CognitoSrpError::InvalidResponse("SECRET_BLOCK is not base64".to_owned())
+        })?,
+        timestamp.as_bytes(),
+    ]
+    .concat();
+    Ok(BASE64.encode(&hmac_sha256(key, &msg)))
+}
+
+// Cognito's flavour of the generic SRP-6a group, parsed and hashed once per process, with its
+// powers of g precomputed for every handshake after the first.
+fn group() -> &'static SrpContext {
+    SrpContext::cognito()
+}
+
+// # https://github.com/pvizeli/pycognito/blob/master/pycognito/aws_srp.py
+// One SRP handshake: holds the ephemeral a/A for a single USER_SRP_AUTH attempt.
+pub(crate) struct AwsSrp {
+    pool_name: String,
+    group: &'static SrpContext,
+    small_a_value: BigUint,
+    large_a_value: BigUint,
+}
+
+impl AwsSrp {
+    pub(crate) fn new(pool_id: &PoolId, rng: &mut dyn RngCore) -> Result<Self> {
+        let mut bytes = get_random(rng, 128);
+        let small_a = BigUint::from_bytes_be(&bytes);
+        zeroize(&mut bytes);
+        Self::with_small_a(pool_id, small_a)
+    }
+
+    // For DEVICE_SRP_AUTH, where the device group key stands in for the pool name and the
+    // device key for the username.
+    pub(crate) fn for_device(device_group_key: &str, rng: &mut dyn RngCore) -> Self {
+        let mut bytes = get_random(rng, 128);
+        let small_a = BigUint::from_bytes_be(&bytes);
+        zeroize(&mut bytes);
+        Self::with_pool_name(device_group_key, small_a)
+    }
+
+    fn with_small_a(pool_id: &PoolId, random: BigUint) -> Result<Self> {
+        Ok(Self::with_pool_name(pool_id.name(), random))
+    }
+
+    fn with_pool_name(pool_name: &str, random: BigUint) -> Self {
+        let group = group();
+        let small_a_value = random % &group.big_n;
+        let large_a_value = group.public_a(&small_a_value);
+        Self {
+            pool_name: pool_name.to_owned(),
+            group,
+            small_a_value,
+            large_a_value,
+        }
+    }
+
+    // SRP_A for InitiateAuth.
+    pub(crate) fn srp_a(&self) -> String {
+        format!("{:x}", self.large_a_value)
+    }
+
+    fn get_password_authentication_key(
+        &self,
+        username: &str,
+        password: &str,
+        server_b_value: &BigUint,
+        salt: &str,
+    ) -> Result<SecretBytes<16>> {
+        Ok(self
+            .derive(username, password, server_b_value, salt)?
+            .key
+            .clone())
+    }
+
+    fn derive(
+        &self,
+        username: &str,
+        password: &str,
+        server_b_value: &BigUint,
+        salt: &str,
+    ) -> Result<Derivation> {
+        // A is reduced mod N already; g^a is never 0 for a prime N, so this is belt and braces.
+        if self.large_a_value.is_zero() {
+            return Err(CognitoSrpError::InvalidClientA);
+        }
+        let u_value = calculate_u(&self.large_a_value, server_b_value);
+        let x_value = calculate_x(&self.pool_name, username, password, salt)?;
+        let s_value =
+            self.group
+                .client_premaster(server_b_value, &x_value, &self.small_a_value, &u_value)?;
+        let mut ikm = pad(&s_value);
+        let key = compute_hkdf(&ikm, &pad(&u_value));
+        zeroize(&mut ikm);
+        Ok(Derivation {
+            u: u_value,
+            x: x_value,
+            s: s_value,
+            key: SecretBytes(key?),
+        })
+    }
+
+    // PASSWORD_CLAIM_SIGNATURE for the PASSWORD_VERIFIER challenge.
+    pub(crate) fn password_claim_signature(
+        &self,
+        user_id_for_srp: &str,
+        password: &str,
+        salt_hex: &str,
+        srp_b_hex: &str,
+        secret_block_b64: &str,
+        timestamp: &str,
+    ) -> Result<String> {
+        let server_b = hex_to_long(srp_b_hex)?;
+        if server_b.is_zero() {
+            return Err(CognitoSrpError::InvalidServerB);
+        }
+        let hkdf =
+            self.get_password_authentication_key(user_id_for_srp, password, &server_b, salt_hex)?;
+        claim_signature(
+            &hkdf,
+            &self.pool_name,
+            user_id_for_srp,
+            secret_block_b64,
+            timestamp,
+        )
+    }
+}
+
+impl Drop for AwsSrp {
+    fn drop(&mut self) {
+        scrub(&mut self.small_a_value);
+    }
+}
+
+// # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_DeviceSecretVerifierConfigType.html
+// A new device's password, and the DeviceSecretVerifierConfig that registers it with
+// ConfirmDevice: a fresh salt and v = g^x, with x computed as for a user but over the device
+// group key and device key. All three are base64, as ConfirmDevice and DEVICE_SRP_AUTH use them.
+pub(crate) struct DeviceVerifier {
+    pub(crate) password: String,
+    pub(crate) salt: String,
+    pub(crate) verifier: String,
+}
+
+impl DeviceVerifier {
+    pub(crate) fn generate(
+        device_group_key: &str,
+        device_key: &str,
+        rng: &mut dyn RngCore,
+    ) -> Result<Self> {
+        let password = BASE64.encode(&get_random(rng, 40));
+        let salt = hex::encode(get_random(rng, 16));
+        let mut x = calculate_x(device_group_key, device_key, &password, &salt)?;
+        let verifier = group().verifier(&x);
+        scrub(&mut x);
+        Ok(Self {
+            password,
+            salt: BASE64.encode(&salt_bytes(&salt)?),
+            verifier: BASE64.encode(&pad(&verifier)),
+        })
+    }
+}
+
+// The client side of USER_SRP_AUTH without any I/O, for callers that send InitiateAuth and
+// RespondToAuthChallenge themselves: a wasm32 front-end going through the browser's fetch, or
+// another language's HTTP stack.
+pub struct SrpSession {
+    srp: AwsSrp,
+}
+
+impl SrpSession {
+    pub fn new(pool_id: &str, rng: &mut dyn RngCore) -> Result<Self> {
+        Ok(Self {
+            srp: AwsSrp::new(&PoolId::parse(pool_id)?, rng)?,
+        })
+    }
+
+    // Goes into InitiateAuth's AuthParameters as SRP_A.
+    pub fn srp_a(&self) -> String {
+        self.srp.srp_a()
+    }
+
+    // The ChallengeResponses for a PASSWORD_VERIFIER challenge, given its ChallengeParameters.
+    // `timestamp` is "Tue Oct 7 09:05:03 UTC 2025" for now; SECRET_HASH is left to the caller.
+    pub fn process_challenge(
+        &self,
+        parameters: &BTreeMap<String, String>,
+        password: &str,
+        timestamp: &str,
+    ) -> Result<BTreeMap<String, String>> {
+        let param = |name: &str| {
+            parameters.get(name).map(String::as_str).ok_or_else(|| {
+                CognitoSrpError::InvalidResponse(format!(
+                    "PASSWORD_VERIFIER challenge is missing {}",
+                    name
+                ))
+            })
+        };
+        let user_id_for_srp = param("USER_ID_FOR_SRP")?;
+        let secret_block = param("SECRET_BLOCK")?;
+        let signature = self.srp.password_claim_signature(
+            user_id_for_srp,
+            password,
+            param("SALT")?,
+            param("SRP_B")?,
+            secret_block,
+            timestamp,
+        )?;
+        Ok([
+            // The real username, also for sign-ins by email or phone alias.
+            ("USERNAME", user_id_for_srp),
+            ("TIMESTAMP", timestamp),
+            ("PASSWORD_CLAIM_SECRET_BLOCK", secret_block),
+            ("PASSWORD_CLAIM_SIGNATURE", &signature),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+        .collect())
+    }
+}
+
+// The intermediates of one password verification; x and S are as secret as the password.
+struct Derivation {
+    u: BigUint,
+    x: BigUint,
+    s: BigUint,
+    key: SecretBytes<16>,
+}
+
+impl Drop for Derivation {
+    fn drop(&mut self) {
+        scrub(&mut self.x);
+        scrub(&mut self.s);
+    }
+}
+
+// Everything that goes into one PASSWORD_VERIFIER answer, with the ephemeral a and the
+// timestamp fixed instead of drawn from the RNG and clock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Inputs<'a> {
+    pub pool_id: &'a str,
+    pub user_id_for_srp: &'a str,
+    pub password: &'a str,
+    // Hex, reduced mod N before use, as pycognito does.
+    pub small_a: &'a str,
+    pub salt: &'a str,
+    pub srp_b: &'a str,
+    pub secret_block: &'a str,
+    pub timestamp: &'a str,
+}
+
+// Each intermediate as pycognito would print it: lowercase hex without padding, and the
+// signature in base64.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trace {
+    pub srp_a: String,
+    pub u: String,
+    pub x: String,
+    pub s: String,
+    pub hkdf_key: String,
+    pub signature: String,
+}
+
+// Deterministic mode: runs the client side of the handshake on fixed inputs and reports every
+// intermediate, for comparing against pycognito or amazon-cognito-identity-js step by step.
+pub fn known_answer(inputs: &Inputs<'_>) -> Result<Trace> {
+    let srp = AwsSrp::with_small_a(
+        &PoolId::parse(inputs.pool_id)?,
+        hex_to_long(inputs.small_a)?,
+    )?;
+    let server_b = hex_to_long(inputs.srp_b)?;
+    if server_b.is_zero() {
+        return Err(CognitoSrpError::InvalidServerB);
+    }
+    let derivation = srp.derive(
+        inputs.user_id_for_srp,
+        inputs.password,
+        &server_b,
+        inputs.salt,
+    )?;
+    Ok(Trace {
+        srp_a: srp.srp_a(),
+        u: format!("{:x}", derivation.u),
+        x: format!("{:x}", derivation.x),
+        s: format!("{:x}", derivation.s),
+        hkdf_key: hex::encode(*derivation.key),
+        signature: claim_signature(
+            &derivation.key,
+            &srp.pool_name,
+            inputs.user_id_for_srp,
+            inputs.secret_block,
+            inputs.timestamp,
+        )?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pad() {
+        assert_eq!(pad(&BigUint::from(0x8fu32)), [0x00, 0x8f]);
+        assert_eq!(pad(&BigUint::from(0x8f1u32)), [0x08, 0xf1]);
+        assert_eq!(pad(&BigUint::from(0x77u32)), [0x77]);
+        assert_eq!(pad(&BigUint::zero()), [0x00]);
+
+        assert_eq!(salt_bytes("8F").unwrap(), [0x00, 0x8f]);
+        assert_eq!(salt_bytes("8F1").unwrap(), [0x08, 0xf1]);
+        assert_eq!(salt_bytes("0077").unwrap(), [0x00, 0x77]);
+        assert!(salt_bytes("").unwrap().is_empty());
+        assert!(salt_bytes("zz").is_err());
+    }
+
+    #[test]
+    fn test_get_random() {
+        let rng = &mut rand::thread_rng();
+        assert_eq!(get_random(rng, 128).len(), 128);
+        assert_eq!(get_random(rng, 16).len(), 16);
+        assert!(get_random(rng, 0).is_empty());
+        assert_ne!(get_random(rng, 16), get_random(rng, 16));
+    }
+
+    // Both vectors are pycognito's compute_hkdf output:
+    //     prk = hmac.new(salt, ikm, sha256).digest()
+    //     hmac.new(prk, b"Caldera Derived Key" + b"\x01", sha256).digest()[:16]
+    // The first replaces 66, 74, 90, 134, ..., which was computed with the doubled 0x01.
+    #[test]
+    fn test_compute_hkdf() {
+        let ikm: &[u8] = &[1, 2, 3];
+        let salt: &[u8] = &[4, 5, 6];
+        let expected: &[u8; 16] = &[
+            22, 51, 221, 232, 239, 11, 170, 105, 132, 231, 75, 60, 20, 139, 197, 190,
+        ];
+        assert_eq!(&compute_hkdf(ikm, salt).unwrap(), expected);
+
+        // Cognito's sizes: a 384-byte pad(S) and a 32-byte pad(u).
+        let ikm: Vec<u8> = (0..=255).chain(0..128).collect();
+        let salt: Vec<u8> = (32..64).collect();
+        assert_eq!(
+            hex::encode(compute_hkdf(&ikm, &salt).unwrap()),
+            "4c65c303ed6062c070ceecb8f77c4052"
+        );
+    }
+
+    #[test]
+    fn test_compute_u() {
+        let mut expected =
+            "111107538766589913434873047715306230301105682089803398192367409276144360002523"
+                .to_string()
+                .parse::<BigUint>();
+        assert_eq!(
+            calculate_u(&BigUint::from(123u32), &BigUint::from(456u32)),
+            expected.unwrap()
+        );
+
+        expected = "17514626659148735040093355417193195988959136054689477767575367834973296020833"
+            .to_string()
+            .parse::<BigUint>();
+        assert_eq!(
+            calculate_u(
+                &BigUint::from(123212123123345345345345345u128),
+                &BigUint::from(45636345345345345345345345345345345u128)
+            ),
+            expected.unwrap()
+        );
+    }
+
+    // Values from a straight Python port of pycognito's AWSSRP with a fixed a and a server-side
+    // b, so the whole key derivation is pinned rather than just its pieces.
+    const SMALL_A: &str = "d2d7b304f7f0c4e48ee5b663c9e378480d7b31c11ffaf48b0b09d3565115450a\
+        a917d72037f7d594a6d745ae3b0c8aa749a7908d7d20eb106d2efd4d00ce0b3e\
+        d2d7b304f7f0c4e48ee5b663c9e378480d7b31c11ffaf48b0b09d3565115450a\
+        a917d72037f7d594a6d745ae3b0c8aa749a7908d7d20eb106d2efd4d00ce0b3e";
+    const SRP_B: &str = "82f7e185b83288814ad86154a1bfa657f70d27ec43f507a618d89eb5ac9f90e9\
+        9f860ac544c52754e6d9f8a6e142983ae09161e8abaa13d3f7c484476fe75b5d\
+        6aad2e3ed1f511892d16f9f9b893e35e296b674b0cd8dfa3ca9fa52bcdb196d6\
+        daa5693db0121b6dd2b87bc49da23045ca96ae468f4cece7ed717ac22d592297\
+        cf33d39c53e70a1d27fd116757dd804b404eeeb56561c686cac0b02ec43b0ac6\
+        06be0148a017595841e5669ec3a26a27acd22520706ea3d8a63f853dc496886e\
+        f1b9f94ac515ef2aac435b28311ca7ca561da1e07f082f54c16b564a21ef1e63\
+        2b7c7295719418e421a118d8ac00be4ed21080cade9a181923cab10937e9df92\
+        b59e1f9320767d03fe7a8b32b04997bf0b8766433d6ec59f81e6c893a4acbb19\
+        fa50c5ce08e1a1f5e6306ca405870a5dd5bc56b980fe372066738f3e34d87f0a\
+        0ee1fd00bac2cee6d94adc094a4d05a98c35fd7ed9bb2723c7ba1069319676c6\
+        6fcdaac9d9669bc2a284893d00cb0d653e03a39f63925c1ac10af72b62126931";
+
+    fn fixed_srp() -> AwsSrp {
+        AwsSrp::with_small_a(
+            &PoolId::parse("us-east-1_Pool123").unwrap(),
+            hex_to_long(SMALL_A).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_srp_a() {
+        let srp = fixed_srp();
+        assert!(srp.srp_a().starts_with("e87be745b27550810d759d9c25f801df"));
+        assert!(srp.srp_a().ends_with("409bddd022a56262b2ad2a"));
+        assert_eq!(
+            format!("{:X}", srp.group.val_k),
+            "538282C4354742D7CBBDE2359FCF67F9F5B3A6B08791E5011B43B8A5B66D9EE6"
+        );
+    }
+
+    #[test]
+    fn test_password_authentication_key() {
+        let key = fixed_srp()
+            .get_password_authentication_key(
+                "bob-internal",
+                "P@ssw0rd!",
+                &hex_to_long(SRP_B).unwrap(),
+                "ab12cd34ef56",
+            )
+            .unwrap();
+        assert_eq!(hex::encode(*key), "0a145bd15605245ce3718abf5795d3e3");
+    }
+
+    #[test]
+    fn test_password_claim_signature() {
+        let signature = fixed_srp()
+            .password_claim_signature(
+                "bob-internal",
+                "P@ssw0rd!",
+                "ab12cd34ef56",
+                SRP_B,
+                "b3BhcXVlIHNlY3JldCBibG9jayBmcm9tIGNvZ25pdG8=",
+                "Tue Oct 7 09:05:03 UTC 2025",
+            )
+            .unwrap();
+        assert_eq!(signature, "Zlyp/QpkkklnfLskpEWenNNpw04o2mnfiFN/tbke37g=");
+
+        assert!(fixed_srp()
+            .password_claim_signature("bob", "pw", "ab", "0", "", "")
+            .is_err());
+    }
+
+    #[test]
+    fn test_srp_session() {
+        let session = SrpSession { srp: fixed_srp() };
+        let mut parameters: BTreeMap<String, String> = [
+            ("USERNAME", "bob"),
+            ("USER_ID_FOR_SRP", "bob-internal"),
+            ("SALT", "ab12cd34ef56"),
+            ("SRP_B", SRP_B),
+            (
+                "SECRET_BLOCK",
+                "b3BhcXVlIHNlY3JldCBibG9jayBmcm9tIGNvZ25pdG8=",
+            ),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+        .collect();
+        let responses = session
+            .process_challenge(&parameters, "P@ssw0rd!", "Tue Oct 7 09:05:03 UTC 2025")
+            .unwrap();
+        assert_eq!(responses["USERNAME"], "bob-internal");
+        assert_eq!(
+            responses["PASSWORD_CLAIM_SIGNATURE"],
+            "Zlyp/QpkkklnfLskpEWenNNpw04o2mnfiFN/tbke37g="
+        );
+        assert_eq!(session.srp_a(), fixed_srp().srp_a());
+
+        parameters.remove("SALT");
+        assert!(matches!(
+            session.process_challenge(&parameters, "P@ssw0rd!", ""),
+            Err(CognitoSrpError::InvalidResponse(_))
+        ));
+    }
+}
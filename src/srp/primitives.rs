@@ -0,0 +1,182 @@
+// The steps of Cognito's SRP computation, one function each, for embedding the math in a client
+// that does its own InitiateAuth and RespondToAuthChallenge. Names and encodings follow
+// pycognito's aws_srp.py. Every number is taken mod N of the 3072-bit Cognito group, whose
+// A = g^a a caller computes with srp6a::SrpContext::cognito().public_a.
+//
+// The whole chain, given PASSWORD_VERIFIER's ChallengeParameters:
+//
+//     let u = calculate_u(&big_a, &big_b);
+//     let x = calculate_x(pool_name, user_id_for_srp, password, salt)?;
+//     let s = calculate_s(&big_b, &x, &small_a, &u)?;
+//     let key = compute_hkdf(&hex::decode(pad_hex(&s))?, &hex::decode(pad_hex(&u))?)?;
+//     let signature = claim_signature(&key, pool_name, user_id_for_srp, secret_block, timestamp)?;
+//
+// These are stable; SrpSession remains the simpler way in, and srp::vectors pins the outputs.
+use crate::crypto::sha256;
+use crate::error::Result;
+use alloc::string::String;
+use alloc::vec::Vec;
+use num_bigint::BigUint;
+
+// The value as even-length lowercase hex, with "00" in front when the top bit of its first byte
+// is set so that it reads as positive, as Java's BigInteger.toByteArray would. Every number that
+// is hashed goes through this first. Zero is "00".
+pub fn pad_hex(value: &BigUint) -> String {
+    hex::encode(super::pad(value))
+}
+
+// SHA-256 of the bytes `hex_str` encodes, as 64 lowercase hex digits. Fails on odd-length or
+// non-hex input; pad the value first.
+pub fn hex_hash(hex_str: &str) -> Result<String> {
+    Ok(hex::encode(sha256(&hex::decode(hex_str)?)))
+}
+
+// u = H(pad(A) | pad(B)). Both sides must have been checked to be non-zero mod N; a u of zero
+// means the handshake must be abandoned.
+pub fn calculate_u(big_a: &BigUint, big_b: &BigUint) -> BigUint {
+    super::calculate_u(big_a, big_b)
+}
+
+// x = H(salt | H(pool name | username | ":" | password)). `pool_name` is the part of the pool id
+// after the underscore (the device group key for DEVICE_SRP_AUTH), `username` is USER_ID_FOR_SRP
+// (or the device key) and `salt` is SALT as hex, whose leading zeros are kept. Fails when the salt
+// is not hex. x is as secret as the password.
+pub fn calculate_x(pool_name: &str, username: &str, password: &str, salt: &str) -> Result<BigUint> {
+    super::calculate_x(pool_name, username, password, salt)
+}
+
+// The premaster secret S = (B - k * g^x)^(a + u * x) mod N, for the `small_a` behind the A that
+// was sent. Fails with InvalidServerB when B is 0 mod N, which would let the server pick S, and
+// with InvalidU when u is 0, which would take the password out of it.
+pub fn calculate_s(
+    big_b: &BigUint,
+    x: &BigUint,
+    small_a: &BigUint,
+    u: &BigUint,
+) -> Result<BigUint> {
+    super::group().client_premaster(big_b, x, small_a, u)
+}
+
+// The 16-byte password authentication key: HKDF-SHA256 with info "Caldera Derived Key", where
+// Cognito passes pad(S) as `ikm` and pad(u) as `salt` (decoded from pad_hex). Only fails if
+// the crypto backend does.
+pub fn compute_hkdf(ikm: &[u8], salt: &[u8]) -> Result<[u8; 16]> {
+    super::compute_hkdf(ikm, salt)
+}
+
+// "Caldera Derived Key", the info compute_hkdf uses.
+pub const HKDF_INFO: &[u8] = super::INFO_BITS;
+
+// compute_hkdf with any `info` and `len` up to 8160 bytes (255 SHA-256 blocks); with HKDF_INFO
+// and 16 it gives compute_hkdf's key. Longer lengths fail with InvalidArgument.
+pub fn compute_hkdf_with(ikm: &[u8], salt: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>> {
+    crate::srp6a::hkdf(ikm, salt, info, len)
+}
+
+// PASSWORD_CLAIM_SIGNATURE: base64 HMAC-SHA256, under the key from compute_hkdf, of pool name |
+// USER_ID_FOR_SRP | SECRET_BLOCK's bytes | TIMESTAMP. `secret_block` is as Cognito sent it;
+// fails with InvalidResponse when it is not base64.
+pub fn claim_signature(
+    key: &[u8; 16],
+    pool_name: &str,
+    user_id_for_srp: &str,
+    secret_block: &str,
+    timestamp: &str,
+) -> Result<String> {
+    super::claim_signature(key, pool_name, user_id_for_srp, secret_block, timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CognitoSrpError;
+    use crate::srp::vectors::VECTORS;
+    use crate::srp6a::{Group, SrpContext};
+    use alloc::format;
+    use num_traits::{Num, Zero};
+
+    #[test]
+    fn test_pad_hex_and_hex_hash() {
+        assert_eq!(pad_hex(&BigUint::from(0x8fu32)), "008f");
+        assert_eq!(pad_hex(&BigUint::from(0x8f1u32)), "08f1");
+        assert_eq!(pad_hex(&BigUint::from(0x77u32)), "77");
+        assert_eq!(pad_hex(&BigUint::zero()), "00");
+
+        assert_eq!(
+            hex_hash("").unwrap(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert!(hex_hash("abc").is_err());
+    }
+
+    #[test]
+    fn test_compute_hkdf_with() {
+        let key = compute_hkdf(b"ikm", b"salt").unwrap();
+        assert_eq!(
+            compute_hkdf_with(b"ikm", b"salt", HKDF_INFO, 16).unwrap(),
+            key
+        );
+        let long = compute_hkdf_with(b"ikm", b"salt", HKDF_INFO, 64).unwrap();
+        assert_eq!(long[..16], key);
+        assert_ne!(
+            compute_hkdf_with(b"ikm", b"salt", b"other", 16).unwrap(),
+            key
+        );
+    }
+
+    #[test]
+    fn test_chain_matches_vectors() {
+        for vector in VECTORS {
+            let inputs = &vector.inputs;
+            let hex = |value: &str| BigUint::from_str_radix(value, 16).unwrap();
+            let pool_name = inputs.pool_id.split_once('_').unwrap().1;
+            let small_a = hex(inputs.small_a) % Group::cognito().n();
+            let big_a = SrpContext::cognito().public_a(&small_a);
+            let big_b = hex(inputs.srp_b);
+
+            let u = calculate_u(&big_a, &big_b);
+            let x = calculate_x(
+                pool_name,
+                inputs.user_id_for_srp,
+                inputs.password,
+                inputs.salt,
+            )
+            .unwrap();
+            let s = calculate_s(&big_b, &x, &small_a, &u).unwrap();
+            let key = compute_hkdf(
+                &hex::decode(pad_hex(&s)).unwrap(),
+                &hex::decode(pad_hex(&u)).unwrap(),
+            )
+            .unwrap();
+            let signature = claim_signature(
+                &key,
+                pool_name,
+                inputs.user_id_for_srp,
+                inputs.secret_block,
+                inputs.timestamp,
+            )
+            .unwrap();
+
+            assert_eq!(format!("{:x}", big_a), vector.srp_a, "{}", vector.name);
+            assert_eq!(format!("{:x}", u), vector.u, "{}", vector.name);
+            assert_eq!(format!("{:x}", x), vector.x, "{}", vector.name);
+            assert_eq!(format!("{:x}", s), vector.s, "{}", vector.name);
+            assert_eq!(hex::encode(key), vector.hkdf_key, "{}", vector.name);
+            assert_eq!(signature, vector.signature, "{}", vector.name);
+        }
+    }
+
+    #[test]
+    fn test_rejects_zero_b() {
+        let group = Group::cognito();
+        let one = BigUint::from(1u32);
+        assert!(matches!(
+            calculate_s(group.n(), &one, &one, &one),
+            Err(CognitoSrpError::InvalidServerB)
+        ));
+        assert!(matches!(
+            claim_signature(&[0; 16], "Pool123", "bob", "not base64!", ""),
+            Err(CognitoSrpError::InvalidResponse(_))
+        ));
+    }
+}
@@ -0,0 +1,215 @@
+use super::{
+    calculate_u, calculate_x, claim_signature, compute_hkdf, get_random, group, hex_to_long, pad,
+    scrub,
+};
+use crate::crypto::constant_time_eq;
+use crate::error::{Result, ServiceError, ServiceErrorKind};
+use crate::pool_id::PoolId;
+use crate::secret::zeroize;
+use alloc::borrow::ToOwned;
+use alloc::format;
+use alloc::string::String;
+use data_encoding::BASE64;
+use num_bigint::BigUint;
+use rand::RngCore;
+
+// What Cognito stores per user instead of the password: the salt and v = g^x mod N.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PasswordVerifier {
+    pool_name: String,
+    user_id_for_srp: String,
+    // Hex, exactly as it goes out in the SALT challenge parameter.
+    salt: String,
+    verifier: BigUint,
+}
+
+impl PasswordVerifier {
+    // Registers `password` under a fresh 16-byte salt.
+    pub fn generate(
+        pool_id: &str,
+        user_id_for_srp: &str,
+        password: &str,
+        rng: &mut dyn RngCore,
+    ) -> Result<Self> {
+        let salt = hex::encode(get_random(rng, 16));
+        Self::with_salt(pool_id, user_id_for_srp, password, &salt)
+    }
+
+    pub fn with_salt(
+        pool_id: &str,
+        user_id_for_srp: &str,
+        password: &str,
+        salt_hex: &str,
+    ) -> Result<Self> {
+        let pool_name = PoolId::parse(pool_id)?.name().to_owned();
+        let mut x = calculate_x(&pool_name, user_id_for_srp, password, salt_hex)?;
+        let verifier = group().verifier(&x);
+        scrub(&mut x);
+        Ok(Self {
+            pool_name,
+            user_id_for_srp: user_id_for_srp.to_owned(),
+            salt: salt_hex.to_owned(),
+            verifier,
+        })
+    }
+
+    // A remembered device as ConfirmDevice registered it, from DeviceSecretVerifierConfig.
+    #[cfg(test)]
+    pub(crate) fn for_device(
+        device_group_key: &str,
+        device_key: &str,
+        salt_b64: &str,
+        verifier_b64: &str,
+    ) -> Self {
+        let decode = |value: &str| BASE64.decode(value.as_bytes()).unwrap_or_default();
+        Self {
+            pool_name: device_group_key.to_owned(),
+            user_id_for_srp: device_key.to_owned(),
+            salt: hex::encode(decode(salt_b64)),
+            verifier: BigUint::from_bytes_be(&decode(verifier_b64)),
+        }
+    }
+
+    pub fn salt(&self) -> &str {
+        &self.salt
+    }
+
+    pub fn user_id_for_srp(&self) -> &str {
+        &self.user_id_for_srp
+    }
+}
+
+// Cognito's half of one USER_SRP_AUTH: answers SRP_A with SRP_B, SALT and SECRET_BLOCK, then
+// checks the PASSWORD_VERIFIER response.
+pub struct ServerSession<'v> {
+    verifier: &'v PasswordVerifier,
+    small_b: BigUint,
+    large_b: BigUint,
+    secret_block: String,
+}
+
+impl<'v> ServerSession<'v> {
+    pub fn new(verifier: &'v PasswordVerifier, rng: &mut dyn RngCore) -> Self {
+        let mut bytes = get_random(rng, 128);
+        let small_b = BigUint::from_bytes_be(&bytes);
+        zeroize(&mut bytes);
+        let secret_block = BASE64.encode(&get_random(rng, 64));
+        Self::with_small_b(verifier, small_b, &secret_block)
+    }
+
+    // Fixed b and SECRET_BLOCK, for reproducible tests.
+    pub fn with_small_b(
+        verifier: &'v PasswordVerifier,
+        small_b: BigUint,
+        secret_block: &str,
+    ) -> Self {
+        let group = group();
+        let small_b = small_b % &group.big_n;
+        let large_b = group.public_b(&verifier.verifier, &small_b);
+        Self {
+            verifier,
+            small_b,
+            large_b,
+            secret_block: secret_block.to_owned(),
+        }
+    }
+
+    // SRP_B as the challenge parameter carries it.
+    pub fn srp_b(&self) -> String {
+        format!("{:x}", self.large_b)
+    }
+
+    pub fn salt(&self) -> &str {
+        &self.verifier.salt
+    }
+
+    pub fn secret_block(&self) -> &str {
+        &self.secret_block
+    }
+
+    // Checks PASSWORD_CLAIM_SIGNATURE against SRP_A from InitiateAuth; a wrong password fails
+    // the way Cognito reports it, as NotAuthorizedException.
+    pub fn verify(&self, srp_a_hex: &str, timestamp: &str, signature_b64: &str) -> Result<()> {
+        let large_a = hex_to_long(srp_a_hex)?;
+        let u = calculate_u(&large_a, &self.large_b);
+        // Refuses A = 0 mod N, which would force S = 0 whatever the password, and u = 0.
+        let mut s =
+            group().server_premaster(&large_a, &self.verifier.verifier, &self.small_b, &u)?;
+        let mut ikm = pad(&s);
+        scrub(&mut s);
+        let key = compute_hkdf(&ikm, &pad(&u));
+        zeroize(&mut ikm);
+        let mut key = key?;
+        let expected = claim_signature(
+            &key,
+            &self.verifier.pool_name,
+            &self.verifier.user_id_for_srp,
+            &self.secret_block,
+            timestamp,
+        );
+        zeroize(&mut key);
+        if constant_time_eq(expected?.as_bytes(), signature_b64.as_bytes()) {
+            Ok(())
+        } else {
+            Err(ServiceError {
+                operation: "RespondToAuthChallenge".to_owned(),
+                status: 400,
+                kind: ServiceErrorKind::NotAuthorized,
+                message: "Incorrect username or password.".to_owned(),
+            }
+            .into())
+        }
+    }
+}
+
+impl Drop for ServerSession<'_> {
+    fn drop(&mut self) {
+        scrub(&mut self.small_b);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CognitoSrpError;
+    use crate::srp::AwsSrp;
+
+    #[test]
+    fn test_round_trip() {
+        let rng = &mut rand::thread_rng();
+        let pool = PoolId::parse("us-east-1_Pool123").unwrap();
+        let verifier =
+            PasswordVerifier::generate(pool.as_str(), "bob-internal", "P@ssw0rd!", rng).unwrap();
+        let server = ServerSession::new(&verifier, rng);
+        let timestamp = "Tue Oct 7 09:05:03 UTC 2025";
+
+        let mut sign = |password: &str| {
+            let client = AwsSrp::new(&pool, rng).unwrap();
+            let signature = client
+                .password_claim_signature(
+                    "bob-internal",
+                    password,
+                    server.salt(),
+                    &server.srp_b(),
+                    server.secret_block(),
+                    timestamp,
+                )
+                .unwrap();
+            (client.srp_a(), signature)
+        };
+        let (srp_a, signature) = sign("P@ssw0rd!");
+        server.verify(&srp_a, timestamp, &signature).unwrap();
+
+        let err = server
+            .verify(&srp_a, "Tue Oct 7 09:05:04 UTC 2025", &signature)
+            .unwrap_err();
+        assert_eq!(err.service_kind(), Some(&ServiceErrorKind::NotAuthorized));
+        let (srp_a, signature) = sign("wrong");
+        assert!(server.verify(&srp_a, timestamp, &signature).is_err());
+        let n = format!("{:x}", group().big_n);
+        assert!(matches!(
+            server.verify(&n, timestamp, &signature),
+            Err(CognitoSrpError::InvalidClientA)
+        ));
+    }
+}
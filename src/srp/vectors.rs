@@ -0,0 +1,179 @@
+use super::{known_answer, Inputs, Trace};
+use crate::error::{CognitoSrpError, Result};
+use alloc::format;
+
+// A fixed handshake and what pycognito's AWSSRP computes for it. Run `check` on each before
+// pointing a port of this crate, or a fork of it, at a live pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Vector {
+    pub name: &'static str,
+    pub inputs: Inputs<'static>,
+    pub srp_a: &'static str,
+    pub u: &'static str,
+    pub x: &'static str,
+    pub s: &'static str,
+    pub hkdf_key: &'static str,
+    pub signature: &'static str,
+}
+
+impl Vector {
+    // Recomputes the trace and names the first intermediate that differs.
+    pub fn check(&self) -> Result<()> {
+        let Trace {
+            srp_a,
+            u,
+            x,
+            s,
+            hkdf_key,
+            signature,
+        } = known_answer(&self.inputs)?;
+        for (name, expected, actual) in [
+            ("SRP_A", self.srp_a, srp_a),
+            ("u", self.u, u),
+            ("x", self.x, x),
+            ("S", self.s, s),
+            ("HKDF key", self.hkdf_key, hkdf_key),
+            ("PASSWORD_CLAIM_SIGNATURE", self.signature, signature),
+        ] {
+            if expected != actual {
+                return Err(CognitoSrpError::InvalidArgument(format!(
+                    "{}: {} is {}, expected {}",
+                    self.name, name, actual, expected
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+// From a line-for-line Python transcription of pycognito's aws_srp.py (pad_hex, hex_hash,
+// compute_hkdf, process_challenge) fed these fixed a values instead of random ones. The second
+// covers an odd-length salt with its top bit set and a passphrase with spaces.
+pub const VECTORS: &[Vector] = &[
+    Vector {
+        name: "pycognito-bob",
+        inputs: Inputs {
+            pool_id: "us-east-1_Pool123",
+            user_id_for_srp: "bob-internal",
+            password: "P@ssw0rd!",
+            small_a: "d2d7b304f7f0c4e48ee5b663c9e378480d7b31c11ffaf48b0b09d3565115450a\
+            a917d72037f7d594a6d745ae3b0c8aa749a7908d7d20eb106d2efd4d00ce0b3e\
+            d2d7b304f7f0c4e48ee5b663c9e378480d7b31c11ffaf48b0b09d3565115450a\
+            a917d72037f7d594a6d745ae3b0c8aa749a7908d7d20eb106d2efd4d00ce0b3e",
+            salt: "ab12cd34ef56",
+            srp_b: "82f7e185b83288814ad86154a1bfa657f70d27ec43f507a618d89eb5ac9f90e9\
+            9f860ac544c52754e6d9f8a6e142983ae09161e8abaa13d3f7c484476fe75b5d\
+            6aad2e3ed1f511892d16f9f9b893e35e296b674b0cd8dfa3ca9fa52bcdb196d6\
+            daa5693db0121b6dd2b87bc49da23045ca96ae468f4cece7ed717ac22d592297\
+            cf33d39c53e70a1d27fd116757dd804b404eeeb56561c686cac0b02ec43b0ac6\
+            06be0148a017595841e5669ec3a26a27acd22520706ea3d8a63f853dc496886e\
+            f1b9f94ac515ef2aac435b28311ca7ca561da1e07f082f54c16b564a21ef1e63\
+            2b7c7295719418e421a118d8ac00be4ed21080cade9a181923cab10937e9df92\
+            b59e1f9320767d03fe7a8b32b04997bf0b8766433d6ec59f81e6c893a4acbb19\
+            fa50c5ce08e1a1f5e6306ca405870a5dd5bc56b980fe372066738f3e34d87f0a\
+            0ee1fd00bac2cee6d94adc094a4d05a98c35fd7ed9bb2723c7ba1069319676c6\
+            6fcdaac9d9669bc2a284893d00cb0d653e03a39f63925c1ac10af72b62126931",
+            secret_block: "b3BhcXVlIHNlY3JldCBibG9jayBmcm9tIGNvZ25pdG8=",
+            timestamp: "Tue Oct 7 09:05:03 UTC 2025",
+        },
+        srp_a: "e87be745b27550810d759d9c25f801dfd8bf8257f8ec44720981250942eae5f4\
+        ce3a1df54b25405dc430f5775c67b467b41930946697a1bbb210285eabb98430\
+        a3d7bc9f5f7b80df633c732f6f716a0c947b7aec90e1892bd4c845eff3753e84\
+        4e36a562e790626cc01e3202bf6a391392b50934fdaab2b788365b142b47a259\
+        2223992504e1dddd56967684050653a8f9363312d8edd4884bd79c13a1356a68\
+        9e1a6cb4b3687f1736f998743364152e3c69012d969bd69cd1731940d05ef6a1\
+        810d4035d06455c1d0ad6906e8e3a574cc76d8c0c6e479b6f248f47c41f1c901\
+        18b90ba06701cb7bd54582c9afd063f7290a76586eb54bb34752226fa68f9411\
+        39d989b1f72e1d26fb982ff9f575dc76c07b27b909fdd8c481082603cb2287c9\
+        b8c88b2012083428d80087d86071a7792169e6b23f28b9476993fab66c32fb96\
+        07a8d9cd1aca57c04479e7d64ac6006340f1e1c22b030bb2c74efdbb074aedf0\
+        0dce40384a2f0ff7868143c89f85d488ddc1c6a6de409bddd022a56262b2ad2a",
+        u: "885fc29a4601b3e3f7d9a794ec448f8777468847b771b407ec4f7bb576116714",
+        x: "8164a3b0fafcb9c880af010d87fc403220fcdc7896a2a067eca84f6779aafff2",
+        s: "a3b0d17678a42c25ec30c57c678edf74ca2a6d47569c32f03072827780352b70\
+        a74bbf2947d5401177192739f394eec10fafc6f565c346efb777b5f373dbce7e\
+        6794433d477794aabb222ab8655c89865769ee9dd06bc596b421e386fa6abbd6\
+        2be962189a94735ab0f5dda539169d091cdf794b7cfc535a250f951c0b761867\
+        f7e348ee97a7aadcd82ec8b23b4867505742457eee9867181a19b7803800ba84\
+        657220e33c3ea8f9256759b8b5f17d47b199a1951b4e7d3baa599f64cdfd1d05\
+        b126a884b801b67ad8961fc8e32503945666454003d51b323993273fcb377c2f\
+        460e604bbf8ead88b6ed6830c2502e399140749074c0a272fafeeb17893bdc4f\
+        f73aa3cee4345f2859a1fa6775ec949b8352f22ef703b86ddb268d5552bab186\
+        98edf81a344efcefef91eb8bef5c4539cc636da817615f184118680711e7a9f3\
+        2101681b510672fd98646b7b545d9605e5a0af9ffa01cd2a5dc37ef5cd82511b\
+        8f0827f6d58f0fce4e1982af948297705df9f848242f6eeb1e49680774be63a3",
+        hkdf_key: "0a145bd15605245ce3718abf5795d3e3",
+        signature: "Zlyp/QpkkklnfLskpEWenNNpw04o2mnfiFN/tbke37g=",
+    },
+    Vector {
+        name: "pycognito-alice",
+        inputs: Inputs {
+            pool_id: "eu-west-1_AbCdEf",
+            user_id_for_srp: "6f1c2d3e-alice",
+            password: "correct horse battery staple",
+            small_a: "2f5e21b88a83266188f6b8f3b1247883e32906e6e579ea6182ca5f865aac726c\
+            1d04108794451104a22f4dc126b9126c76036bbe6f8db329c0388e781e306456\
+            2f5e21b88a83266188f6b8f3b1247883e32906e6e579ea6182ca5f865aac726c\
+            1d04108794451104a22f4dc126b9126c76036bbe6f8db329c0388e781e306456",
+            salt: "8d3f0c1e9a7b6c5d4e3f2a1b0c9d8e7f6",
+            srp_b: "e46f6658a96aa3bddf07209b89790ee215eba4b04fd7fe6f04adeebf3e16641d\
+            1ad053e7eb027392024117aa4f78368def2d6f4864bea02d3acd8d410eecf3d4\
+            26828ac38909d37076ebde80ff90d6eaa3f99fd4e728be3cf7cb9789c2cf0dd2\
+            c4a639cfe5ea40e820307de6180a131e847981b21f3c13a7acce75706bd9ec4d\
+            a55f02d31fee2275fa9a3d3be5acdc4219d2d8fdd87eefd366f2289dcdac5c55\
+            6e5268b7f939ed7bef99c35f2f27aecc9bed33670baca525da71248e05a6cb73\
+            fbd125cb5a80e9b235ee9fc0685ef5a2b1e8b5bdcff2e0276d91952c29180941\
+            65f97ab834136ac206a7f5f3b2abf86afd3e5f934f3daf202baf0f200ee64912\
+            e774b30c8f3d9f1c2bb8f795b1d77c2b61906a59cfad70997a96ceba553a4aaa\
+            a36985c84b579aa9b1c056256e0fd9eeb9f01037ac958b29887350d3ee9d2e67\
+            90ac112e8efddcbc55e8503ee8380d0817faa30eef45200babdfdeba8ab547b0\
+            1e8e9dac0facc9badbb9ba113b6c23c250cf3129eedb87b2ccb64989e746ddc1",
+            secret_block: "YW5vdGhlciBvcGFxdWUgc2VjcmV0IGJsb2Nr",
+            timestamp: "Mon Jan 5 00:00:09 UTC 2026",
+        },
+        srp_a: "cb990c525d24638a53fc955f5380c6585cbbee9986b164d1bf169af322b776c6\
+        6b370f2ef3c7792afe389cc46caa66d203a56662ccb9a640b4723a7b71b28fb7\
+        b60d6ba35084a1731f13a56552dca7ce8606bd2064581b173e7f5ba5f7a06d92\
+        5f3c809a69f78ea6062aceac39f59bab3001d305d107f1c633f220d7ae624e6d\
+        e20036ea106c76f09bc80e88683608716774b462d8e8746e76ffa608d2b9562c\
+        b0ac031811bcf74e80d2d88c2494fcd17ed489c1dbac63a3397a0a765527efef\
+        e04765c89133cad9706184a37b7e9cbc93197cbefc2e846d05bfcd5267c88fd7\
+        9cfdd74663530b67dd8791e306f3e4c6370574315af99c7b5484ec3e8860c7a4\
+        538a8541a6a13e89333958eafaf22c5716b0c6b26700018521dae70e880bda6d\
+        a9038ff6d5a52f236c7368f3b899d92ffea8401a82a2bfd767dbd05eddc0cc5f\
+        1bf1cd15ab82492f8e915a7a43bc262be43f5ba3d0d01b5f1ed40e5c9d464386\
+        7cb873f96b3a6eae8b7d261e7020d0c2430e08b22fa391d8ed6510cde67051c8",
+        u: "52d63525e5a75a70d1aad08444d4daa079786f3c821bc56aa416bec8a00705ae",
+        x: "b0e92cbdf4497f4c982675f7f93ebe1ada34368ee4df6957be06c802a1fb2501",
+        s: "63af28d8dd599d26c97877291a679a6795a929052685d4a5d602a50933fc492a\
+        77f1806bb9367bce0acdd4a25d5e52187c534734645f3bb8b6200f44fd5aad2e\
+        b07b09a8b4e1442c8fe10b2867413e26176f4077cc6bde80c101f8fa56cc525c\
+        4061ccf940d1e06362959ab62eb6e45d57144d6ef2b622636ae07d5ba6303b6a\
+        f8b9b1876c13cfe7c0d8f8b31862c9d13ce993af16ab4926a9b9d8a06e6588f2\
+        29334a3c0182fbf919569699edde63251e07c99eb30035666fbda9df96ccbc7f\
+        deda35003acdaa498ec52322726e8e4473d61fa9f30fc99a34f9c0d926c9f2e4\
+        c5aaac286d1018ad98ac686f08c164a369507ea7a75209a65f26423c9b25761d\
+        162dbd2eddf00b0a0d2bfc0ee19e60854a7a0f9610cd9d9c229d5e18891a85ab\
+        fb00a557fd8ac5b5d1629d1516e0b4fb22e5eed2eeb1012d43d554d5b308fc91\
+        0d557ab9fda4b5495204adf85fdc40ab73b212948412c8c1c11c89e9946bc764\
+        36da0bb1bd0b67d3d845da82ee4577c0e2b255196be25bf16690d2a25e79c5fb",
+        hkdf_key: "a7923743c2695a41056cc9fd5e99f31c",
+        signature: "ws7hf/rJAaKoN52FMMJWQ/hNIsC/EHs3oDQfK7ztCsI=",
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vectors() {
+        for vector in VECTORS {
+            vector.check().unwrap();
+        }
+        let mut wrong = VECTORS[0];
+        wrong.inputs.password = "not the password";
+        let err = wrong.check().unwrap_err().to_string();
+        assert!(err.starts_with("pycognito-bob: x is "), "{}", err);
+    }
+}
@@ -0,0 +1,868 @@
+use crate::crypto::{constant_time_eq, hkdf_sha256, sha256, sha512};
+use crate::error::{CognitoSrpError, Result};
+use crate::secret::{zeroize, SecretString};
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Deref;
+use num_bigint::BigUint;
+use num_traits::{Num, Zero};
+use once_cell::race::OnceBox;
+use rand::RngCore;
+
+// # https://datatracker.ietf.org/doc/html/rfc5054#appendix-A
+// The 1024- and 2048-bit groups are SRP's own; 3072 and 4096 are the RFC 3526 MODP primes.
+const N_1024: &str = "EEAF0AB9ADB38DD69C33F80AFA8FC5E86072618775FF3C0B9EA2314C9C256576\
+    D674DF7496EA81D3383B4813D692C6E0E0D5D8E250B98BE48E495C1D6089DAD1\
+    5DC7D7B46154D6B6CE8EF4AD69B15D4982559B297BCF1885C529F566660E57EC\
+    68EDBC3C05726CC02FD4CBF4976EAA9AFD5138FE8376435B9FC61D2FC0EB06E3";
+
+const N_2048: &str = "AC6BDB41324A9A9BF166DE5E1389582FAF72B6651987EE07FC3192943DB56050\
+    A37329CBB4A099ED8193E0757767A13DD52312AB4B03310DCD7F48A9DA04FD50\
+    E8083969EDB767B0CF6095179A163AB3661A05FBD5FAAAE82918A9962F0B93B8\
+    55F97993EC975EEAA80D740ADBF4FF747359D041D5C33EA71D281E446B14773B\
+    CA97B43A23FB801676BD207A436C6481F1D2B9078717461A5B9D32E688F87748\
+    544523B524B0D57D5EA77A2775D2ECFA032CFBDBF52FB3786160279004E57AE6\
+    AF874E7303CE53299CCC041C7BC308D82A5698F3A8D0C38271AE35F8E9DBFBB6\
+    94B5C803D89F7AE435DE236D525F54759B65E372FCD68EF20FA7111F9E4AFF73";
+
+// # https://github.com/aws/amazon-cognito-identity-js/blob/master/src/AuthenticationHelper.js#L22
+const N_3072: &str = "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD1\
+    29024E088A67CC74020BBEA63B139B22514A08798E3404DD\
+    EF9519B3CD3A431B302B0A6DF25F14374FE1356D6D51C245\
+    E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+    EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3D\
+    C2007CB8A163BF0598DA48361C55D39A69163FA8FD24CF5F\
+    83655D23DCA3AD961C62F356208552BB9ED529077096966D\
+    670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B\
+    E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9\
+    DE2BCBF6955817183995497CEA956AE515D2261898FA0510\
+    15728E5A8AAAC42DAD33170D04507A33A85521ABDF1CBA64\
+    ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7\
+    ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6B\
+    F12FFA06D98A0864D87602733EC86A64521F2B18177B200C\
+    BBE117577A615D6C770988C0BAD946E208E24FA074E5AB31\
+    43DB5BFCE0FD108E4B82D120A93AD2CAFFFFFFFFFFFFFFFF";
+
+const N_4096: &str = "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74\
+    020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437\
+    4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+    EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05\
+    98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB\
+    9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B\
+    E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718\
+    3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33\
+    A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7\
+    ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864\
+    D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2\
+    08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A92108011A723C12A787E6D7\
+    88719A10BDBA5B2699C327186AF4E23C1A946834B6150BDA2583E9CA2AD44CE8\
+    DBBBC2DB04DE8EF92E8EFC141FBECAA6287C59474E6BC05D99B2964FA090C3A2\
+    233BA186515BE7ED1F612970CEE2D7AFB81BDD762170481CD0069127D5B05AA9\
+    93B4EA988D8FDDC186FFB7DC90A6C08F4DF435C934063199FFFFFFFFFFFFFFFF";
+
+// How integers are laid out as hash input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Padding {
+    // RFC 5054 PAD(): big-endian, left-filled with zeros to the length of N.
+    Modulus,
+    // # https://github.com/aws/amazon-cognito-identity-js/blob/master/src/AuthenticationHelper.js#L270
+    // Big-endian with a leading 0x00 when the top bit is set, so the value would read back as
+    // positive in Java's signed BigInteger. This is what Cognito hashes.
+    Signed,
+}
+
+// The H() of the protocol. SHA-256 and SHA-512 come with the crate; anything else (a RustCrypto
+// Sha384, a hardware engine) implements this.
+pub trait Digest: Send + Sync {
+    fn name(&self) -> &str;
+    fn digest(&self, data: &[u8]) -> Vec<u8>;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Sha256;
+
+impl Digest for Sha256 {
+    fn name(&self) -> &str {
+        "SHA-256"
+    }
+
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        sha256(data).to_vec()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Sha512;
+
+impl Digest for Sha512 {
+    fn name(&self) -> &str {
+        "SHA-512"
+    }
+
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        sha512(data).to_vec()
+    }
+}
+
+// A prime N, generator g and multiplier k = H(PAD(N) | PAD(g)), with the padding convention
+// and digest that go with them. The built-in groups hash with SHA-256.
+#[derive(Clone)]
+pub struct Group {
+    pub(crate) big_n: BigUint,
+    pub(crate) val_g: BigUint,
+    pub(crate) val_k: BigUint,
+    padding: Padding,
+    digest: Arc<dyn Digest>,
+}
+
+impl fmt::Debug for Group {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Group")
+            .field("bits", &self.bits())
+            .field("g", &self.val_g)
+            .field("padding", &self.padding)
+            .field("digest", &self.digest.name())
+            .finish()
+    }
+}
+
+impl Group {
+    fn from_hex(n_hex: &str, g: u32, padding: Padding) -> Self {
+        let big_n = BigUint::from_str_radix(n_hex, 16).expect("group primes are valid hex");
+        Self {
+            big_n,
+            val_g: BigUint::from(g),
+            val_k: BigUint::zero(),
+            padding,
+            digest: Arc::new(Sha256),
+        }
+        .with_k()
+    }
+
+    fn with_k(mut self) -> Self {
+        self.val_k = self.hash(&[&self.pad(&self.big_n), &self.pad(&self.val_g)]);
+        self
+    }
+
+    // The same N and g hashed with another digest, e.g. `Group::rfc5054_4096().with_digest(Sha512)`.
+    // k changes with it, so both sides must agree. The Cognito flavour always uses SHA-256.
+    pub fn with_digest(&self, digest: impl Digest + 'static) -> Group {
+        Group {
+            digest: Arc::new(digest),
+            ..self.clone()
+        }
+        .with_k()
+    }
+
+    pub fn rfc5054_1024() -> &'static Group {
+        static GROUP: OnceBox<Group> = OnceBox::new();
+        GROUP.get_or_init(|| Box::new(Group::from_hex(N_1024, 2, Padding::Modulus)))
+    }
+
+    pub fn rfc5054_2048() -> &'static Group {
+        static GROUP: OnceBox<Group> = OnceBox::new();
+        GROUP.get_or_init(|| Box::new(Group::from_hex(N_2048, 2, Padding::Modulus)))
+    }
+
+    pub fn rfc5054_3072() -> &'static Group {
+        static GROUP: OnceBox<Group> = OnceBox::new();
+        GROUP.get_or_init(|| Box::new(Group::from_hex(N_3072, 5, Padding::Modulus)))
+    }
+
+    pub fn rfc5054_4096() -> &'static Group {
+        static GROUP: OnceBox<Group> = OnceBox::new();
+        GROUP.get_or_init(|| Box::new(Group::from_hex(N_4096, 5, Padding::Modulus)))
+    }
+
+    // The 3072-bit prime with g = 2 and signed padding: what every Cognito user pool uses.
+    pub fn cognito() -> &'static Group {
+        static GROUP: OnceBox<Group> = OnceBox::new();
+        GROUP.get_or_init(|| Box::new(Group::from_hex(N_3072, 2, Padding::Signed)))
+    }
+
+    pub fn bits(&self) -> u64 {
+        self.big_n.bits()
+    }
+
+    pub fn n(&self) -> &BigUint {
+        &self.big_n
+    }
+
+    pub fn g(&self) -> &BigUint {
+        &self.val_g
+    }
+
+    pub fn k(&self) -> &BigUint {
+        &self.val_k
+    }
+
+    pub fn padding(&self) -> Padding {
+        self.padding
+    }
+
+    pub fn digest(&self) -> &dyn Digest {
+        self.digest.as_ref()
+    }
+
+    fn h(&self, data: &[u8]) -> Vec<u8> {
+        self.digest.digest(data)
+    }
+
+    pub fn pad(&self, value: &BigUint) -> Vec<u8> {
+        let bytes = value.to_bytes_be();
+        match self.padding {
+            Padding::Modulus => {
+                let len = self.big_n.bits().div_ceil(8) as usize;
+                let mut padded = vec![0; len.saturating_sub(bytes.len())];
+                padded.extend_from_slice(&bytes);
+                padded
+            }
+            Padding::Signed if bytes[0] & 0x80 != 0 => [&[0], &bytes[..]].concat(),
+            Padding::Signed => bytes,
+        }
+    }
+
+    pub fn hash(&self, parts: &[&[u8]]) -> BigUint {
+        BigUint::from_bytes_be(&self.h(&parts.concat()))
+    }
+
+    // u = H(PAD(A) | PAD(B))
+    pub fn u(&self, big_a: &BigUint, big_b: &BigUint) -> BigUint {
+        self.hash(&[&self.pad(big_a), &self.pad(big_b)])
+    }
+
+    // x = H(salt | H(identity | ":" | password)). The salt is hashed exactly as given.
+    pub fn x(&self, salt: &[u8], identity: &str, password: &str) -> BigUint {
+        let identity_password = SecretString::new(format!("{}:{}", identity, password));
+        let inner = self.h(identity_password.expose_secret().as_bytes());
+        self.hash(&[salt, &inner])
+    }
+
+    // v = g^x, what the server stores instead of the password.
+    pub fn verifier(&self, x: &BigUint) -> BigUint {
+        self.val_g.modpow(x, &self.big_n)
+    }
+
+    // A = g^a
+    pub fn public_a(&self, small_a: &BigUint) -> BigUint {
+        self.val_g.modpow(small_a, &self.big_n)
+    }
+
+    // B = k*v + g^b
+    pub fn public_b(&self, verifier: &BigUint, small_b: &BigUint) -> BigUint {
+        (&self.val_k * verifier + self.val_g.modpow(small_b, &self.big_n)) % &self.big_n
+    }
+
+    // The client's S = (B - k*g^x)^(a + u*x). Aborts when B % N == 0, as RFC 5054 requires:
+    // such a B would let the server fix S without knowing the verifier. Aborts on u == 0 too,
+    // which would leave x, and so the password, out of S.
+    pub fn client_premaster(
+        &self,
+        big_b: &BigUint,
+        x: &BigUint,
+        small_a: &BigUint,
+        u: &BigUint,
+    ) -> Result<BigUint> {
+        self.premaster_with(big_b, self.verifier(x), x, small_a, u)
+    }
+
+    // client_premaster with g^x already computed.
+    fn premaster_with(
+        &self,
+        big_b: &BigUint,
+        g_x: BigUint,
+        x: &BigUint,
+        small_a: &BigUint,
+        u: &BigUint,
+    ) -> Result<BigUint> {
+        let big_n = &self.big_n;
+        let big_b = big_b % big_n;
+        if big_b.is_zero() {
+            return Err(CognitoSrpError::InvalidServerB);
+        }
+        if u.is_zero() {
+            return Err(CognitoSrpError::InvalidU);
+        }
+        // B - k * g^x, kept in [0, N) since BigUint can't go negative.
+        let kgx = (&self.val_k * g_x) % big_n;
+        let base = (big_b + big_n - kgx) % big_n;
+        let mut exponent = small_a + u * x;
+        let premaster = base.modpow(&exponent, big_n);
+        scrub(&mut exponent);
+        Ok(premaster)
+    }
+
+    // The server's S = (A * v^u)^b, refusing A % N == 0 and u == 0 for the same reasons.
+    pub fn server_premaster(
+        &self,
+        big_a: &BigUint,
+        verifier: &BigUint,
+        small_b: &BigUint,
+        u: &BigUint,
+    ) -> Result<BigUint> {
+        let big_n = &self.big_n;
+        if (big_a % big_n).is_zero() {
+            return Err(CognitoSrpError::InvalidClientA);
+        }
+        if u.is_zero() {
+            return Err(CognitoSrpError::InvalidU);
+        }
+        Ok((big_a * verifier.modpow(u, big_n)).modpow(small_b, big_n))
+    }
+
+    // # https://datatracker.ietf.org/doc/html/rfc2945#section-3
+    // M1 = H(H(N) xor H(g) | H(I) | s | A | B | K)
+    fn client_proof(
+        &self,
+        identity: &str,
+        salt: &[u8],
+        big_a: &BigUint,
+        big_b: &BigUint,
+        key: &[u8],
+    ) -> Vec<u8> {
+        let h_n = self.h(&self.big_n.to_bytes_be());
+        let h_g = self.h(&self.val_g.to_bytes_be());
+        let h_ng: Vec<u8> = h_n.iter().zip(&h_g).map(|(n, g)| n ^ g).collect();
+        self.h(&[
+            &h_ng[..],
+            &self.h(identity.as_bytes()),
+            salt,
+            &big_a.to_bytes_be(),
+            &big_b.to_bytes_be(),
+            key,
+        ]
+        .concat())
+    }
+
+    // M2 = H(A | M1 | K)
+    fn server_proof(&self, big_a: &BigUint, m1: &[u8], key: &[u8]) -> Vec<u8> {
+        self.h(&[&big_a.to_bytes_be()[..], m1, key].concat())
+    }
+}
+
+// Toy parameters so that tests of the handshake math can run thousands of exchanges in the time
+// one 3072-bit exponentiation takes. Anyone can take discrete logs at these sizes: they are behind
+// the `insecure-test-params` feature, which is only ever for dev-dependencies.
+#[cfg(feature = "insecure-test-params")]
+impl Group {
+    // The largest 64-bit safe prime, 2^64 - 1469, with g = 2 generating the whole group.
+    pub fn insecure_test() -> &'static Group {
+        static GROUP: OnceBox<Group> = OnceBox::new();
+        GROUP.get_or_init(|| Box::new(Group::from_hex("FFFFFFFFFFFFFA43", 2, Padding::Modulus)))
+    }
+
+    // Any N and g. Only sanity-checked: N odd and 1 < g < N; primality is the caller's business.
+    pub fn insecure_custom(n: BigUint, g: BigUint, padding: Padding) -> Result<Group> {
+        if !n.bit(0) || g <= BigUint::from(1u32) || g >= n {
+            return Err(CognitoSrpError::InvalidArgument(
+                "test group needs an odd N and 1 < g < N".to_owned(),
+            ));
+        }
+        Ok(Group {
+            big_n: n,
+            val_g: g,
+            val_k: BigUint::zero(),
+            padding,
+            digest: Arc::new(Sha256),
+        }
+        .with_k())
+    }
+}
+
+// A group with the powers g^(32^i) mod N precomputed, so that every g^e (A, B, v and the g^x in
+// S) costs some 650 modular multiplications at 3072 bits instead of a full square-and-multiply. Building
+// one takes about as long as a single modpow, so it pays off from the second handshake on: keep
+// one per group for the life of the process, as SrpContext::cognito() does. Like num-bigint's
+// modpow, it is not constant-time.
+//
+// It derefs to its Group; the methods below shadow the Group ones of the same name.
+#[derive(Clone)]
+pub struct SrpContext {
+    group: Group,
+    g_powers: Vec<BigUint>,
+}
+
+impl fmt::Debug for SrpContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SrpContext")
+            .field("group", &self.group)
+            .field("g_powers", &self.g_powers.len())
+            .finish()
+    }
+}
+
+impl SrpContext {
+    // Brickell, Gordon, McCurley and Wilson's fixed-base method, with base-32 digits.
+    const WINDOW: u32 = 32;
+
+    pub fn new(group: &Group) -> Self {
+        let big_n = &group.big_n;
+        // Exponents up to N's length; anything longer falls back to modpow.
+        let digits = group.bits().div_ceil(Self::WINDOW.ilog2().into()) as usize + 1;
+        let mut g_powers = Vec::with_capacity(digits);
+        let mut power = &group.val_g % big_n;
+        for _ in 0..digits {
+            let next = power.modpow(&BigUint::from(Self::WINDOW), big_n);
+            g_powers.push(power);
+            power = next;
+        }
+        Self {
+            group: group.clone(),
+            g_powers,
+        }
+    }
+
+    pub fn cognito() -> &'static SrpContext {
+        static CONTEXT: OnceBox<SrpContext> = OnceBox::new();
+        CONTEXT.get_or_init(|| Box::new(SrpContext::new(Group::cognito())))
+    }
+
+    pub fn group(&self) -> &Group {
+        &self.group
+    }
+
+    // g^exponent mod N.
+    pub fn pow_g(&self, exponent: &BigUint) -> BigUint {
+        let big_n = &self.group.big_n;
+        let mut digits = exponent.to_radix_le(Self::WINDOW);
+        if digits.len() > self.g_powers.len() {
+            zeroize(&mut digits);
+            return self.group.val_g.modpow(exponent, big_n);
+        }
+        // Product over digit values d of (product of g^(32^i) with digit i = d)^d, as a running
+        // product of running products: 31 + one multiplication per non-zero digit.
+        let mut result = BigUint::from(1u32);
+        let mut partial = BigUint::from(1u32);
+        for value in (1..Self::WINDOW as u8).rev() {
+            for (digit, power) in digits.iter().zip(&self.g_powers) {
+                if *digit == value {
+                    partial = partial * power % big_n;
+                }
+            }
+            result = result * &partial % big_n;
+        }
+        zeroize(&mut digits);
+        scrub(&mut partial);
+        result
+    }
+
+    pub fn verifier(&self, x: &BigUint) -> BigUint {
+        self.pow_g(x)
+    }
+
+    pub fn public_a(&self, small_a: &BigUint) -> BigUint {
+        self.pow_g(small_a)
+    }
+
+    pub fn public_b(&self, verifier: &BigUint, small_b: &BigUint) -> BigUint {
+        (&self.group.val_k * verifier + self.pow_g(small_b)) % &self.group.big_n
+    }
+
+    pub fn client_premaster(
+        &self,
+        big_b: &BigUint,
+        x: &BigUint,
+        small_a: &BigUint,
+        u: &BigUint,
+    ) -> Result<BigUint> {
+        self.group
+            .premaster_with(big_b, self.pow_g(x), x, small_a, u)
+    }
+}
+
+impl Deref for SrpContext {
+    type Target = Group;
+
+    fn deref(&self) -> &Group {
+        &self.group
+    }
+}
+
+// Best-effort scrub of a secret BigUint: rewrite its digits in place with zeros. Temporaries
+// inside num-bigint's arithmetic are out of reach.
+pub(crate) fn scrub(value: &mut BigUint) {
+    let digits = value.bits().div_ceil(32) as usize;
+    value.assign_from_slice(&vec![0; digits]);
+}
+
+fn random_exponent(rng: &mut dyn RngCore, group: &Group) -> BigUint {
+    let mut bytes = vec![0; group.bits().div_ceil(8) as usize];
+    rng.fill_bytes(&mut bytes);
+    let exponent = BigUint::from_bytes_be(&bytes) % &group.big_n;
+    zeroize(&mut bytes);
+    exponent
+}
+
+// # https://datatracker.ietf.org/doc/html/rfc5869
+// HKDF-SHA256 of `len` bytes, at most 255 blocks of 32. For stretching K into as many keys
+// (encryption, MAC, ...) as a protocol needs, each under its own `info`.
+pub fn hkdf(ikm: &[u8], salt: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>> {
+    let mut okm = vec![0; len];
+    hkdf_into(ikm, salt, info, &mut okm)?;
+    Ok(okm)
+}
+
+pub(crate) fn hkdf_into(ikm: &[u8], salt: &[u8], info: &[u8], okm: &mut [u8]) -> Result<()> {
+    if okm.len() > 255 * 32 {
+        return Err(CognitoSrpError::InvalidArgument(format!(
+            "HKDF-SHA256 output is at most 8160 bytes, not {}",
+            okm.len()
+        )));
+    }
+    if !hkdf_sha256(ikm, salt, info, okm) {
+        return Err(CognitoSrpError::Hkdf);
+    }
+    Ok(())
+}
+
+// The client's session key K = H(S), the M1 to send and the M2 it expects back.
+pub struct Proof {
+    pub key: Vec<u8>,
+    pub m1: Vec<u8>,
+    m2: Vec<u8>,
+}
+
+impl Proof {
+    // A `len`-byte key derived from K under `info`; the server gets the same from
+    // Accepted::derive_key.
+    pub fn derive_key(&self, info: &[u8], len: usize) -> Result<Vec<u8>> {
+        hkdf(&self.key, &[], info, len)
+    }
+
+    // Checks the server's M2, proving it holds the verifier too.
+    pub fn verify_server(&self, m2: &[u8]) -> Result<()> {
+        if constant_time_eq(&self.m2, m2) {
+            Ok(())
+        } else {
+            Err(CognitoSrpError::InvalidArgument(
+                "server proof M2 does not match".to_owned(),
+            ))
+        }
+    }
+}
+
+impl Drop for Proof {
+    fn drop(&mut self) {
+        zeroize(&mut self.key);
+    }
+}
+
+// What the server ends up with once M1 checks out.
+pub struct Accepted {
+    pub key: Vec<u8>,
+    pub m2: Vec<u8>,
+}
+
+impl Accepted {
+    pub fn derive_key(&self, info: &[u8], len: usize) -> Result<Vec<u8>> {
+        hkdf(&self.key, &[], info, len)
+    }
+}
+
+impl Drop for Accepted {
+    fn drop(&mut self) {
+        zeroize(&mut self.key);
+    }
+}
+
+// The client half of a plain SRP-6a handshake for any identity and group.
+pub struct Client<'g> {
+    group: &'g Group,
+    small_a: BigUint,
+    big_a: BigUint,
+}
+
+impl<'g> Client<'g> {
+    pub fn new(group: &'g Group, rng: &mut dyn RngCore) -> Self {
+        Self::with_small_a(group, random_exponent(rng, group))
+    }
+
+    pub fn with_small_a(group: &'g Group, small_a: BigUint) -> Self {
+        let small_a = small_a % &group.big_n;
+        let big_a = group.public_a(&small_a);
+        Self {
+            group,
+            small_a,
+            big_a,
+        }
+    }
+
+    pub fn public_a(&self) -> &BigUint {
+        &self.big_a
+    }
+
+    // Answers the server's salt and B with M1 and derives K.
+    pub fn process(
+        &self,
+        identity: &str,
+        password: &str,
+        salt: &[u8],
+        big_b: &BigUint,
+    ) -> Result<Proof> {
+        let group = self.group;
+        // Only a broken group gets here with A = 0, but then S would not depend on a.
+        if (&self.big_a % &group.big_n).is_zero() {
+            return Err(CognitoSrpError::InvalidClientA);
+        }
+        let u = group.u(&self.big_a, big_b);
+        let mut x = group.x(salt, identity, password);
+        let premaster = group.client_premaster(big_b, &x, &self.small_a, &u);
+        scrub(&mut x);
+        let mut premaster = premaster?;
+        let key = group.h(&group.pad(&premaster));
+        scrub(&mut premaster);
+        let m1 = group.client_proof(identity, salt, &self.big_a, big_b, &key);
+        let m2 = group.server_proof(&self.big_a, &m1, &key);
+        Ok(Proof { key, m1, m2 })
+    }
+}
+
+impl Drop for Client<'_> {
+    fn drop(&mut self) {
+        scrub(&mut self.small_a);
+    }
+}
+
+// The server half: holds the stored verifier and its ephemeral b for one handshake.
+pub struct Server<'g> {
+    group: &'g Group,
+    verifier: BigUint,
+    small_b: BigUint,
+    big_b: BigUint,
+}
+
+impl<'g> Server<'g> {
+    pub fn new(group: &'g Group, verifier: BigUint, rng: &mut dyn RngCore) -> Self {
+        let small_b = random_exponent(rng, group);
+        Self::with_small_b(group, verifier, small_b)
+    }
+
+    pub fn with_small_b(group: &'g Group, verifier: BigUint, small_b: BigUint) -> Self {
+        let small_b = small_b % &group.big_n;
+        let big_b = group.public_b(&verifier, &small_b);
+        Self {
+            group,
+            verifier,
+            small_b,
+            big_b,
+        }
+    }
+
+    pub fn public_b(&self) -> &BigUint {
+        &self.big_b
+    }
+
+    // Checks the client's M1; on success returns K and the M2 to send back.
+    pub fn verify(
+        &self,
+        identity: &str,
+        salt: &[u8],
+        big_a: &BigUint,
+        m1: &[u8],
+    ) -> Result<Accepted> {
+        let group = self.group;
+        let u = group.u(big_a, &self.big_b);
+        let mut premaster = group.server_premaster(big_a, &self.verifier, &self.small_b, &u)?;
+        let key = group.h(&group.pad(&premaster));
+        scrub(&mut premaster);
+        let expected = group.client_proof(identity, salt, big_a, &self.big_b, &key);
+        if !constant_time_eq(&expected, m1) {
+            return Err(CognitoSrpError::InvalidArgument(
+                "client proof M1 does not match".to_owned(),
+            ));
+        }
+        let m2 = group.server_proof(big_a, m1, &key);
+        Ok(Accepted { key, m2 })
+    }
+}
+
+impl Drop for Server<'_> {
+    fn drop(&mut self) {
+        scrub(&mut self.small_b);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_groups() {
+        for (group, bits) in [
+            (Group::rfc5054_1024(), 1024),
+            (Group::rfc5054_2048(), 2048),
+            (Group::rfc5054_3072(), 3072),
+            (Group::rfc5054_4096(), 4096),
+            (Group::cognito(), 3072),
+        ] {
+            assert_eq!(group.bits(), bits);
+            assert_eq!(
+                group.pad(&BigUint::from(1u32)).len(),
+                match group.padding() {
+                    Padding::Modulus => bits as usize / 8,
+                    Padding::Signed => 1,
+                }
+            );
+        }
+        assert_eq!(Group::rfc5054_3072().n(), Group::cognito().n());
+        assert_eq!(
+            format!("{:x}", Group::rfc5054_2048().k()),
+            "5b9e8ef059c6b32ea59fc1d322d37f04aa30bae5aa9003b8321e21ddb04e300"
+        );
+    }
+
+    #[test]
+    fn test_handshake() {
+        let sha512 = Group::rfc5054_4096().with_digest(Sha512);
+        assert_ne!(sha512.k(), Group::rfc5054_4096().k());
+        for group in [Group::rfc5054_2048(), &sha512] {
+            handshake(group);
+        }
+    }
+
+    fn handshake(group: &Group) {
+        let rng = &mut rand::thread_rng();
+        let salt = b"salt";
+        let verifier = group.verifier(&group.x(salt, "alice", "password123"));
+
+        let client = Client::new(group, rng);
+        let server = Server::new(group, verifier, rng);
+        let proof = client
+            .process("alice", "password123", salt, server.public_b())
+            .unwrap();
+        let server_proof = server
+            .verify("alice", salt, client.public_a(), &proof.m1)
+            .unwrap();
+        assert_eq!(proof.key, server_proof.key);
+        assert_eq!(proof.key.len(), group.digest().digest(b"").len());
+        proof.verify_server(&server_proof.m2).unwrap();
+        let derived = proof.derive_key(b"encryption", 64).unwrap();
+        assert_eq!(derived.len(), 64);
+        assert_eq!(derived, server_proof.derive_key(b"encryption", 64).unwrap());
+        assert_ne!(derived, proof.derive_key(b"mac", 64).unwrap());
+
+        let wrong = client
+            .process("alice", "password124", salt, server.public_b())
+            .unwrap();
+        assert!(server
+            .verify("alice", salt, client.public_a(), &wrong.m1)
+            .is_err());
+        assert!(client
+            .process("alice", "password123", salt, group.n())
+            .is_err());
+    }
+
+    #[test]
+    fn test_hkdf() {
+        // RFC 5869 A.1
+        let okm = hkdf(
+            &[0x0b; 22],
+            &hex::decode("000102030405060708090a0b0c").unwrap(),
+            &hex::decode("f0f1f2f3f4f5f6f7f8f9").unwrap(),
+            42,
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(okm),
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+        );
+        assert_eq!(hkdf(b"ikm", b"", b"", 8160).unwrap().len(), 8160);
+        assert!(matches!(
+            hkdf(b"ikm", b"", b"", 8161),
+            Err(CognitoSrpError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_srp_context() {
+        let rng = &mut rand::thread_rng();
+        let sha512 = Group::rfc5054_2048().with_digest(Sha512);
+        for group in [Group::cognito(), Group::rfc5054_1024(), &sha512] {
+            let context = SrpContext::new(group);
+            let n = group.n();
+            for exponent in [
+                BigUint::zero(),
+                BigUint::from(1u32),
+                BigUint::from(31u32),
+                BigUint::from(32u32),
+                random_exponent(rng, group),
+                n - 1u32,
+                // Longer than the table.
+                n * n + 5u32,
+            ] {
+                assert_eq!(context.pow_g(&exponent), group.g().modpow(&exponent, n));
+            }
+            let small_a = random_exponent(rng, group);
+            let x = group.x(b"salt", "alice", "password123");
+            let big_b = context.public_b(&context.verifier(&x), &small_a);
+            assert_eq!(big_b, group.public_b(&group.verifier(&x), &small_a));
+            assert_eq!(
+                context.client_premaster(&big_b, &x, &small_a, &x).unwrap(),
+                group.client_premaster(&big_b, &x, &small_a, &x).unwrap()
+            );
+        }
+        assert_eq!(SrpContext::cognito().k(), Group::cognito().k());
+    }
+
+    #[test]
+    fn test_degenerate_values() {
+        let group = Group::rfc5054_1024();
+        let one = BigUint::from(1u32);
+        let zero = BigUint::zero();
+        let big_b = group.public_b(&group.verifier(&one), &one);
+        assert!(matches!(
+            group.client_premaster(&(group.n() * 2u32), &one, &one, &one),
+            Err(CognitoSrpError::InvalidServerB)
+        ));
+        assert!(matches!(
+            group.client_premaster(&big_b, &one, &one, &zero),
+            Err(CognitoSrpError::InvalidU)
+        ));
+        assert!(matches!(
+            group.server_premaster(group.n(), &one, &one, &one),
+            Err(CognitoSrpError::InvalidClientA)
+        ));
+        assert!(matches!(
+            group.server_premaster(&big_b, &one, &one, &zero),
+            Err(CognitoSrpError::InvalidU)
+        ));
+    }
+
+    #[cfg(feature = "insecure-test-params")]
+    #[test]
+    fn test_insecure_params() {
+        let group = Group::insecure_test();
+        assert_eq!(group.bits(), 64);
+        for _ in 0..1000 {
+            handshake(group);
+        }
+        let signed =
+            Group::insecure_custom(BigUint::from(1019u32), BigUint::from(2u32), Padding::Signed)
+                .unwrap();
+        let context = SrpContext::new(&signed);
+        for exponent in 0..4096u32 {
+            let exponent = BigUint::from(exponent);
+            assert_eq!(
+                context.pow_g(&exponent),
+                signed.g().modpow(&exponent, signed.n())
+            );
+        }
+
+        // 3^2 = 0 mod 9: not a group at all, but it makes A = 0.
+        let broken = Group::insecure_custom(9u32.into(), 3u32.into(), Padding::Modulus).unwrap();
+        let client = Client::with_small_a(&broken, BigUint::from(2u32));
+        assert!(matches!(
+            client.process("alice", "pw", b"salt", &BigUint::from(4u32)),
+            Err(CognitoSrpError::InvalidClientA)
+        ));
+
+        for (n, g) in [(1018u32, 2u32), (1019, 1), (1019, 1019)] {
+            assert!(Group::insecure_custom(n.into(), g.into(), Padding::Modulus).is_err());
+        }
+    }
+}
@@ -0,0 +1,497 @@
+use crate::client::Client;
+use crate::crypto::sha256;
+#[cfg(feature = "ring")]
+use crate::crypto::{
+    chacha20_poly1305_open, chacha20_poly1305_seal, hkdf_sha256, pbkdf2_hmac_sha256,
+};
+use crate::error::{CognitoSrpError, Result};
+use crate::json::{self, Value};
+#[cfg(feature = "ring")]
+use crate::secret::{SecretBytes, SecretString};
+use crate::tokens::TokenSet;
+#[cfg(feature = "ring")]
+use data_encoding::BASE64;
+use std::env;
+#[cfg(feature = "ring")]
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+#[cfg(feature = "ring")]
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Which tokens: the same user can be signed in to several pools and app clients at once.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TokenKey {
+    pub pool_id: String,
+    pub client_id: String,
+    pub username: String,
+}
+
+impl TokenKey {
+    pub fn new(client: &Client, username: &str) -> Self {
+        Self {
+            pool_id: client.pool_id().to_owned(),
+            client_id: client.client_id().to_owned(),
+            username: username.to_owned(),
+        }
+    }
+
+    // A hash of the key, so usernames never need escaping and don't show up in listings.
+    pub(crate) fn id(&self) -> String {
+        let parts = [&self.pool_id, &self.client_id, &self.username].map(String::as_str);
+        hex::encode(sha256(parts.join("\0").as_bytes()))
+    }
+
+    // The tokens plus the key they belong to, as every backend stores them.
+    pub(crate) fn encode(&self, tokens: &TokenSet) -> String {
+        let mut value = tokens.to_json();
+        value.insert("pool_id", Value::from(self.pool_id.as_str()));
+        value.insert("client_id", Value::from(self.client_id.as_str()));
+        value.insert("username", Value::from(self.username.as_str()));
+        value.to_string()
+    }
+
+    pub(crate) fn decode(&self, contents: &str) -> Result<Option<TokenSet>> {
+        let value = json::parse(contents)?;
+        // A hash collision or a hand-copied entry; either way these are not our tokens.
+        if value.str_field("pool_id").as_ref() != Some(&self.pool_id)
+            || value.str_field("client_id").as_ref() != Some(&self.client_id)
+            || value.str_field("username").as_ref() != Some(&self.username)
+        {
+            return Ok(None);
+        }
+        TokenSet::from_json(&value).map(Some)
+    }
+}
+
+// Somewhere to keep tokens between runs so a restart can refresh instead of running SRP again.
+pub trait TokenStore: Send + Sync {
+    fn load(&self, key: &TokenKey) -> Result<Option<TokenSet>>;
+    fn save(&self, key: &TokenKey, tokens: &TokenSet) -> Result<()>;
+    fn clear(&self, key: &TokenKey) -> Result<()>;
+}
+
+#[cfg(feature = "ring")]
+#[derive(Clone)]
+enum Secret {
+    Passphrase {
+        passphrase: SecretString,
+        iterations: NonZeroU32,
+    },
+    DataKey(SecretBytes<32>),
+}
+
+// What an encrypted FileTokenStore seals its files with: ChaCha20-Poly1305 from ring, under a
+// key derived afresh for each file. Needs the ring backend.
+#[cfg(feature = "ring")]
+#[derive(Clone)]
+pub struct EncryptionKey(Secret);
+
+// The most PBKDF2 rounds a file may ask for. The count is read from the file before anything is
+// authenticated, so without a cap a planted file could pin a CPU for hours.
+#[cfg(feature = "ring")]
+pub const MAX_ITERATIONS: u32 = 10_000_000;
+
+#[cfg(feature = "ring")]
+impl EncryptionKey {
+    // Stretched with PBKDF2-HMAC-SHA256 and a fresh salt per file.
+    pub fn from_passphrase(passphrase: &str) -> Self {
+        EncryptionKey(Secret::Passphrase {
+            passphrase: passphrase.into(),
+            iterations: NonZeroU32::new(600_000).unwrap(),
+        })
+    }
+
+    // PBKDF2 rounds for newly written files, between 1 and MAX_ITERATIONS; existing files record
+    // their own count.
+    pub fn with_iterations(self, iterations: u32) -> Self {
+        match self.0 {
+            Secret::Passphrase { passphrase, .. } => EncryptionKey(Secret::Passphrase {
+                passphrase,
+                iterations: NonZeroU32::new(iterations.clamp(1, MAX_ITERATIONS)).unwrap(),
+            }),
+            secret => EncryptionKey(secret),
+        }
+    }
+
+    // 32 random bytes, e.g. the Plaintext of a KMS GenerateDataKey (AES_256) response.
+    pub fn from_data_key(key: &[u8]) -> Result<Self> {
+        let key = key.try_into().map_err(|_| {
+            CognitoSrpError::InvalidArgument("data key must be 32 bytes".to_owned())
+        })?;
+        Ok(EncryptionKey(Secret::DataKey(SecretBytes(key))))
+    }
+
+    fn kdf(&self) -> (&'static str, u32) {
+        match &self.0 {
+            Secret::Passphrase { iterations, .. } => ("pbkdf2-sha256", iterations.get()),
+            Secret::DataKey(_) => ("hkdf-sha256", 0),
+        }
+    }
+
+    // The cipher key for one file, bound to its salt.
+    fn derive(&self, salt: &[u8], iterations: NonZeroU32) -> SecretBytes<32> {
+        SecretBytes(match &self.0 {
+            Secret::Passphrase { passphrase, .. } => {
+                pbkdf2_hmac_sha256(passphrase.expose_secret().as_bytes(), salt, iterations)
+            }
+            Secret::DataKey(key) => {
+                let mut okm = [0; 32];
+                hkdf_sha256(&**key, salt, b"cogneato token cache", &mut okm);
+                okm
+            }
+        })
+    }
+
+    // Authenticated with the ciphertext, so a file can't be moved to stand in for another
+    // user's.
+    fn aad(key: &TokenKey) -> String {
+        format!("cogneato-v{}\0{}", CACHE_VERSION, key.id())
+    }
+
+    fn seal(&self, key: &TokenKey, plaintext: &str) -> String {
+        let (kdf, iterations) = self.kdf();
+        let salt: [u8; 16] = rand::random();
+        let nonce: [u8; 12] = rand::random();
+        let cipher_key = self.derive(&salt, NonZeroU32::new(iterations.max(1)).unwrap());
+        let mut ciphertext = plaintext.as_bytes().to_vec();
+        chacha20_poly1305_seal(
+            &cipher_key,
+            nonce,
+            Self::aad(key).as_bytes(),
+            &mut ciphertext,
+        );
+        Value::object([
+            ("version", Value::from(CACHE_VERSION as f64)),
+            ("kdf", Value::from(kdf)),
+            ("iterations", Value::from(iterations as f64)),
+            ("salt", Value::from(BASE64.encode(&salt))),
+            ("nonce", Value::from(BASE64.encode(&nonce))),
+            ("ciphertext", Value::from(BASE64.encode(&ciphertext))),
+        ])
+        .to_string()
+    }
+
+    fn open(&self, key: &TokenKey, contents: &str) -> Result<String> {
+        let invalid = |what: &str| CognitoSrpError::Configuration(format!("token cache {}", what));
+        let envelope = json::parse(contents)?;
+        match envelope.get("version") {
+            Some(Value::Number(version)) if *version == CACHE_VERSION as f64 => {}
+            Some(Value::Number(version)) => {
+                return Err(invalid(&format!(
+                    "has unsupported format version {}",
+                    version
+                )))
+            }
+            _ => return Err(invalid("is not encrypted")),
+        }
+        let (kdf, _) = self.kdf();
+        if envelope.str_field("kdf").as_deref() != Some(kdf) {
+            return Err(invalid("was sealed with a different kind of key"));
+        }
+        let iterations = match (&self.0, envelope.get("iterations")) {
+            (Secret::DataKey(_), _) => NonZeroU32::new(1).unwrap(),
+            (_, Some(Value::Number(n))) if *n >= 1.0 && *n <= MAX_ITERATIONS as f64 => {
+                NonZeroU32::new(*n as u32).unwrap()
+            }
+            (_, Some(Value::Number(_))) => return Err(invalid("asks for too many iterations")),
+            _ => return Err(invalid("is missing iterations")),
+        };
+        let field = |name: &str| {
+            envelope
+                .str_field(name)
+                .and_then(|v| BASE64.decode(v.as_bytes()).ok())
+                .ok_or_else(|| invalid(&format!("is missing {}", name)))
+        };
+        let (salt, ciphertext) = (field("salt")?, field("ciphertext")?);
+        let nonce = field("nonce")?
+            .try_into()
+            .map_err(|_| invalid("has a bad nonce"))?;
+
+        let cipher_key = self.derive(&salt, iterations);
+        let plaintext =
+            chacha20_poly1305_open(&cipher_key, nonce, Self::aad(key).as_bytes(), ciphertext)
+                .ok_or_else(|| invalid("could not be decrypted: wrong key or corrupted file"))?;
+        String::from_utf8(plaintext).map_err(|_| invalid("is not UTF-8"))
+    }
+}
+
+// The RustCrypto backend carries SHA-256, HMAC and HKDF but no AEAD, so without ring there is
+// nothing to seal files with. EncryptionKey is still declared there so that asking for one is a
+// compile error carrying this message, rather than a missing item or a store that quietly
+// writes plaintext.
+#[cfg(not(feature = "ring"))]
+#[diagnostic::on_unimplemented(
+    message = "cog-neat-oh: the encrypted FileTokenStore needs the `backend-ring` feature",
+    label = "EncryptionKey can't be made with `backend-rustcrypto` alone",
+    note = "enable `backend-ring`, or keep tokens in a KeyringTokenStore or a TokenStore of your own"
+)]
+pub trait RingBackend: sealed::Sealed {}
+
+#[cfg(not(feature = "ring"))]
+mod sealed {
+    pub trait Sealed {}
+}
+
+#[cfg(not(feature = "ring"))]
+#[derive(Clone, Debug)]
+pub struct EncryptionKey(std::convert::Infallible);
+
+// Nothing implements RingBackend, so none of these can be called.
+#[cfg(not(feature = "ring"))]
+impl EncryptionKey {
+    pub fn from_passphrase<P: RingBackend + ?Sized>(_: &P) -> Self {
+        unreachable!("RingBackend has no implementations")
+    }
+
+    pub fn with_iterations(self, _: u32) -> Self {
+        match self.0 {}
+    }
+
+    pub fn from_data_key<K: RingBackend + ?Sized>(_: &K) -> Result<Self> {
+        unreachable!("RingBackend has no implementations")
+    }
+}
+
+// Never print the key material.
+#[cfg(feature = "ring")]
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("EncryptionKey").field(&self.kdf().0).finish()
+    }
+}
+
+// Bumped whenever the encrypted envelope changes; older versions are rejected, not guessed at.
+#[cfg(feature = "ring")]
+const CACHE_VERSION: u32 = 2;
+
+impl<T: TokenStore + ?Sized> TokenStore for Arc<T> {
+    fn load(&self, key: &TokenKey) -> Result<Option<TokenSet>> {
+        (**self).load(key)
+    }
+
+    fn save(&self, key: &TokenKey, tokens: &TokenSet) -> Result<()> {
+        (**self).save(key, tokens)
+    }
+
+    fn clear(&self, key: &TokenKey) -> Result<()> {
+        (**self).clear(key)
+    }
+}
+
+// One JSON file per key, named by its hash and readable only by the current user. With
+// with_encryption the JSON is sealed, for hosts without a keyring.
+#[derive(Clone, Debug)]
+pub struct FileTokenStore {
+    dir: PathBuf,
+    #[cfg(feature = "ring")]
+    encryption: Option<EncryptionKey>,
+}
+
+impl FileTokenStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            #[cfg(feature = "ring")]
+            encryption: None,
+        }
+    }
+
+    #[cfg(feature = "ring")]
+    pub fn with_encryption(mut self, key: EncryptionKey) -> Self {
+        self.encryption = Some(key);
+        self
+    }
+
+    #[cfg(not(feature = "ring"))]
+    pub fn with_encryption(self, key: EncryptionKey) -> Self {
+        match key.0 {}
+    }
+
+    // $XDG_CACHE_HOME/cogneato, falling back to ~/.cache/cogneato.
+    pub fn in_cache_dir() -> Result<Self> {
+        let var = |name: &str| env::var_os(name).filter(|v| !v.is_empty());
+        let cache = match (var("XDG_CACHE_HOME"), var("HOME")) {
+            (Some(cache), _) => PathBuf::from(cache),
+            (None, Some(home)) => Path::new(&home).join(".cache"),
+            (None, None) => {
+                return Err(CognitoSrpError::Configuration(
+                    "neither XDG_CACHE_HOME nor HOME is set".to_owned(),
+                ))
+            }
+        };
+        Ok(Self::new(cache.join("cogneato")))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, key: &TokenKey) -> PathBuf {
+        self.dir.join(format!("{}.json", key.id()))
+    }
+}
+
+// Writes a sibling readable only by the current user and renames it over `path`, so readers
+// never see half a file.
+pub(crate) fn write_private(path: &Path, contents: &str) -> Result<()> {
+    let temp = path.with_extension(format!("tmp{}", std::process::id()));
+    let mut file =
+        private(OpenOptions::new().write(true).create(true).truncate(true)).open(&temp)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temp, path)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn private(options: &mut OpenOptions) -> &mut OpenOptions {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(0o600)
+}
+
+#[cfg(not(unix))]
+fn private(options: &mut OpenOptions) -> &mut OpenOptions {
+    options
+}
+
+impl TokenStore for FileTokenStore {
+    fn load(&self, key: &TokenKey) -> Result<Option<TokenSet>> {
+        let contents = match fs::read_to_string(self.path(key)) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        #[cfg(feature = "ring")]
+        if let Some(encryption) = &self.encryption {
+            return key.decode(&encryption.open(key, &contents)?);
+        }
+        key.decode(&contents)
+    }
+
+    fn save(&self, key: &TokenKey, tokens: &TokenSet) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        #[cfg(feature = "ring")]
+        if let Some(encryption) = &self.encryption {
+            return write_private(&self.path(key), &encryption.seal(key, &key.encode(tokens)));
+        }
+        write_private(&self.path(key), &key.encode(tokens))
+    }
+
+    fn clear(&self, key: &TokenKey) -> Result<()> {
+        match fs::remove_file(self.path(key)) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_file_store() {
+        let dir = env::temp_dir().join(format!("cogneato-test-{}", rand::random::<u64>()));
+        let store = FileTokenStore::new(&dir);
+        let key = TokenKey {
+            pool_id: "us-east-1_pool".to_owned(),
+            client_id: "client".to_owned(),
+            username: "bob".to_owned(),
+        };
+        let tokens = TokenSet {
+            access_token: "a1".to_owned(),
+            id_token: "i1".to_owned(),
+            refresh_token: Some("r1".into()),
+            token_type: "Bearer".to_owned(),
+            expires_at: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        };
+
+        assert_eq!(store.load(&key).unwrap(), None);
+        store.save(&key, &tokens).unwrap();
+        assert_eq!(store.load(&key).unwrap(), Some(tokens));
+        let other = TokenKey {
+            username: "alice".to_owned(),
+            ..key.clone()
+        };
+        assert_eq!(store.load(&other).unwrap(), None);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(store.path(&key)).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        store.clear(&key).unwrap();
+        store.clear(&key).unwrap();
+        assert_eq!(store.load(&key).unwrap(), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "ring")]
+    fn test_encrypted_file_store() {
+        let dir = env::temp_dir().join(format!("cogneato-test-{}", rand::random::<u64>()));
+        let passphrase = EncryptionKey::from_passphrase("hunter2").with_iterations(10);
+        let store = FileTokenStore::new(&dir).with_encryption(passphrase.clone());
+        let key = TokenKey {
+            pool_id: "us-east-1_pool".to_owned(),
+            client_id: "client".to_owned(),
+            username: "bob".to_owned(),
+        };
+        let tokens = TokenSet {
+            access_token: "a1".to_owned(),
+            id_token: "i1".to_owned(),
+            refresh_token: Some("r1-secret".into()),
+            token_type: "Bearer".to_owned(),
+            expires_at: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        };
+
+        store.save(&key, &tokens).unwrap();
+        let contents = fs::read_to_string(store.path(&key)).unwrap();
+        assert!(!contents.contains("r1-secret"));
+        assert!(contents.contains(r#""version":2"#));
+        assert_eq!(store.load(&key).unwrap(), Some(tokens.clone()));
+
+        let wrong = FileTokenStore::new(&dir)
+            .with_encryption(EncryptionKey::from_passphrase("hunter3").with_iterations(10));
+        assert!(matches!(
+            wrong.load(&key),
+            Err(CognitoSrpError::Configuration(_))
+        ));
+        // A plaintext store doesn't mistake the envelope for tokens.
+        assert_eq!(FileTokenStore::new(&dir).load(&key).unwrap(), None);
+        let moved = TokenKey {
+            username: "mallory".to_owned(),
+            ..key.clone()
+        };
+        fs::write(store.path(&moved), &contents).unwrap();
+        assert!(store.load(&moved).is_err());
+        let greedy = contents.replace(r#""iterations":10"#, r#""iterations":4000000000"#);
+        fs::write(store.path(&key), greedy).unwrap();
+        assert!(store
+            .load(&key)
+            .unwrap_err()
+            .to_string()
+            .contains("too many iterations"));
+        let future = contents.replace(r#""version":2"#, r#""version":3"#);
+        fs::write(store.path(&key), future).unwrap();
+        assert!(store
+            .load(&key)
+            .unwrap_err()
+            .to_string()
+            .contains("version 3"));
+
+        let data_key = EncryptionKey::from_data_key(&[7; 32]).unwrap();
+        let store = FileTokenStore::new(&dir).with_encryption(data_key);
+        store.save(&key, &tokens).unwrap();
+        assert_eq!(store.load(&key).unwrap(), Some(tokens));
+        assert!(EncryptionKey::from_data_key(&[7; 16]).is_err());
+        assert!(!format!("{:?}", passphrase).contains("hunter2"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
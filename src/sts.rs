@@ -0,0 +1,151 @@
+use crate::client::{Client, ServiceError, ServiceErrorKind};
+use crate::credentials::AwsCredentials;
+use crate::error::{CognitoSrpError, Result};
+use crate::time::parse_iso8601;
+use crate::transport::HttpRequest;
+use crate::url;
+use std::time::Duration;
+
+// STS only speaks XML by default. The responses are small and flat, so pulling out the text of
+// a few known elements is enough.
+fn element(xml: &str, name: &str) -> Option<String> {
+    let start = xml.find(&format!("<{}>", name))? + name.len() + 2;
+    let end = start + xml[start..].find(&format!("</{}>", name))?;
+    Some(unescape(&xml[start..end]))
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+impl Client {
+    // # https://docs.aws.amazon.com/STS/latest/APIReference/API_AssumeRoleWithWebIdentity.html
+    // For roles that trust the user pool directly as an OIDC provider, skipping identity pools.
+    // STS checks the token's signature, audience and expiry itself. `duration` defaults to an
+    // hour on the STS side.
+    pub fn assume_role_with_web_identity(
+        &self,
+        role_arn: &str,
+        session_name: &str,
+        duration: Option<Duration>,
+        id_token: &str,
+    ) -> Result<AwsCredentials> {
+        const OPERATION: &str = "AssumeRoleWithWebIdentity";
+        let duration = duration.map(|d| d.as_secs().to_string());
+        let mut form = vec![
+            ("Action", OPERATION),
+            ("Version", "2011-06-15"),
+            ("RoleArn", role_arn),
+            ("RoleSessionName", session_name),
+            ("WebIdentityToken", id_token),
+        ];
+        if let Some(duration) = &duration {
+            form.push(("DurationSeconds", duration));
+        }
+        let request = HttpRequest {
+            method: "POST",
+            url: self.sts_endpoint(),
+            headers: vec![(
+                "Content-Type".to_owned(),
+                "application/x-www-form-urlencoded".to_owned(),
+            )],
+            body: url::form(&form),
+        };
+        let response = self
+            .retry_policy()
+            .run(|| self.transport().send(request.clone()))?;
+        if !(200..300).contains(&response.status) {
+            return Err(ServiceError {
+                operation: OPERATION.to_owned(),
+                status: response.status,
+                kind: match element(&response.body, "Code") {
+                    Some(code) => ServiceErrorKind::parse(&code),
+                    None => ServiceErrorKind::Other(format!("HTTP {}", response.status)),
+                },
+                message: element(&response.body, "Message").unwrap_or_default(),
+            }
+            .into());
+        }
+
+        let field = |name: &str| {
+            element(&response.body, name).ok_or_else(|| {
+                CognitoSrpError::InvalidResponse(format!("{} returned no {}", OPERATION, name))
+            })
+        };
+        let expiration = parse_iso8601(&field("Expiration")?).ok_or_else(|| {
+            CognitoSrpError::InvalidResponse(format!("{} returned a bad Expiration", OPERATION))
+        })?;
+        Ok(AwsCredentials {
+            access_key_id: field("AccessKeyId")?,
+            secret_access_key: field("SecretAccessKey")?,
+            session_token: Some(field("SessionToken")?),
+            expiration: Some(expiration),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_assume_role_with_web_identity() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"<AssumeRoleWithWebIdentityResponse xmlns="https://sts.amazonaws.com/doc/2011-06-15/">
+  <AssumeRoleWithWebIdentityResult>
+    <Credentials>
+      <AccessKeyId>ASIA1</AccessKeyId>
+      <SecretAccessKey>se/cr&amp;et</SecretAccessKey>
+      <SessionToken>token</SessionToken>
+      <Expiration>2015-08-30T12:36:00Z</Expiration>
+    </Credentials>
+  </AssumeRoleWithWebIdentityResult>
+</AssumeRoleWithWebIdentityResponse>"#,
+        );
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let credentials = client
+            .assume_role_with_web_identity(
+                "arn:aws:iam::123456789012:role/app",
+                "bob",
+                Some(Duration::from_secs(900)),
+                "id.token",
+            )
+            .unwrap();
+        assert_eq!(credentials.access_key_id, "ASIA1");
+        assert_eq!(credentials.secret_access_key, "se/cr&et");
+        assert_eq!(
+            credentials.expiration,
+            Some(UNIX_EPOCH + Duration::from_secs(1440938160))
+        );
+        let request = transport.last_request();
+        assert_eq!(request.url, "https://sts.us-east-1.amazonaws.com/");
+        assert_eq!(
+            request.body,
+            "Action=AssumeRoleWithWebIdentity&Version=2011-06-15\
+             &RoleArn=arn%3Aaws%3Aiam%3A%3A123456789012%3Arole%2Fapp&RoleSessionName=bob\
+             &WebIdentityToken=id.token&DurationSeconds=900"
+        );
+
+        transport.respond(
+            400,
+            "<ErrorResponse><Error><Type>Sender</Type><Code>InvalidIdentityToken</Code>\
+             <Message>Incorrect token audience</Message></Error></ErrorResponse>",
+        );
+        let err = client
+            .assume_role_with_web_identity("arn", "bob", None, "id.token")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "AssumeRoleWithWebIdentity failed: InvalidIdentityToken: Incorrect token audience"
+        );
+        assert!(!transport.last_request().body.contains("DurationSeconds"));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::error::{CognitoSrpError, Result};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+struct Signal {
+    stopped: Mutex<bool>,
+    condvar: Condvar,
+}
+
+// Cloneable stop switch, for signalling a task from somewhere that doesn't own its BackgroundTask.
+#[derive(Clone)]
+pub struct ShutdownHandle(Arc<Signal>);
+
+impl ShutdownHandle {
+    pub fn shutdown(&self) {
+        *self.0.stopped.lock().unwrap() = true;
+        self.0.condvar.notify_all();
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        *self.0.stopped.lock().unwrap()
+    }
+
+    // Sleeps for up to `timeout`, returning early (with true) once shutdown is signalled.
+    fn wait(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut stopped = self.0.stopped.lock().unwrap();
+        while !*stopped {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            stopped = self
+                .0
+                .condvar
+                .wait_timeout(stopped, deadline - now)
+                .unwrap()
+                .0;
+        }
+        *stopped
+    }
+}
+
+// Owns a periodic worker thread (TokenManager::spawn_refresh_ahead, RefreshingVerifier::spawn_refresh,
+// ...). Dropping the task stops it and waits for the current tick to finish, so nothing outlives
+// the client that spawned it.
+pub struct BackgroundTask {
+    handle: ShutdownHandle,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl BackgroundTask {
+    pub fn spawn_periodic<F>(name: &str, interval: Duration, mut tick: F) -> Result<Self>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let handle = ShutdownHandle(Arc::default());
+        let signal = handle.clone();
+        let thread = thread::Builder::new()
+            .name(name.to_owned())
+            .spawn(move || {
+                while !signal.wait(interval) {
+                    tick();
+                }
+            })?;
+        Ok(Self {
+            handle,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.handle.clone()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    // Stops the task and reports whether it had died from a panic rather than exiting cleanly.
+    pub fn shutdown(mut self) -> Result<()> {
+        self.stop()
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        self.handle.shutdown();
+        match self.thread.take() {
+            Some(thread) if thread.thread().id() != thread::current().id() => {
+                thread.join().map_err(|_| CognitoSrpError::TaskPanicked)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Drop for BackgroundTask {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_shutdown_stops_ticks() {
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let counter = ticks.clone();
+        let task = BackgroundTask::spawn_periodic("test", Duration::from_millis(1), move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+        while ticks.load(Ordering::SeqCst) < 3 {
+            thread::yield_now();
+        }
+        task.shutdown().unwrap();
+        let after = ticks.load(Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(5));
+        assert_eq!(ticks.load(Ordering::SeqCst), after);
+    }
+
+    #[test]
+    fn test_drop_and_handle() {
+        let task =
+            BackgroundTask::spawn_periodic("test", Duration::from_secs(3600), || {}).unwrap();
+        let handle = task.shutdown_handle();
+        let started = Instant::now();
+        drop(task);
+        assert!(handle.is_shutdown());
+        assert!(started.elapsed() < Duration::from_secs(60));
+
+        let task =
+            BackgroundTask::spawn_periodic("test", Duration::from_secs(3600), || {}).unwrap();
+        task.shutdown_handle().shutdown();
+        while !task.is_finished() {
+            thread::yield_now();
+        }
+        task.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_panicking_task() {
+        let task =
+            BackgroundTask::spawn_periodic("test", Duration::from_millis(1), || panic!("boom"))
+                .unwrap();
+        while !task.is_finished() {
+            thread::yield_now();
+        }
+        assert!(task.shutdown().is_err());
+    }
+}
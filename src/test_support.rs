@@ -0,0 +1,553 @@
+use crate::auth::ChallengeName;
+use crate::client::Client;
+use crate::error::{CognitoSrpError, Result};
+use crate::json::{self, Value};
+use crate::pool_id::PoolId;
+use crate::srp::get_random;
+use crate::srp::server::{PasswordVerifier, ServerSession};
+use crate::transport::{HttpRequest, HttpResponse, Transport};
+use data_encoding::{BASE64, BASE64URL_NOPAD};
+use num_bigint::BigUint;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// An in-process stand-in for cognito-idp, for integration tests that should run without AWS. It
+// speaks the real wire protocol on 127.0.0.1 and covers what this crate drives: USER_SRP_AUTH
+// checked against a stored SRP verifier, SMS_MFA and SOFTWARE_TOKEN_MFA, REFRESH_TOKEN_AUTH and
+// RevokeToken. Tokens are JWT-shaped but unsigned, so `Claims::decode_unverified` reads them and
+// `Verifier` rejects them. SECRET_HASH is accepted without being checked.
+pub struct FakeCognito {
+    address: SocketAddr,
+    pool_id: PoolId,
+    client_id: String,
+    state: Arc<Mutex<State>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct User {
+    verifier: PasswordVerifier,
+    mfa: Option<(ChallengeName, String)>,
+}
+
+// A PASSWORD_VERIFIER or MFA challenge waiting for its answer.
+enum Pending {
+    PasswordVerifier {
+        username: String,
+        small_b: BigUint,
+        srp_a: String,
+    },
+    Mfa {
+        username: String,
+    },
+}
+
+#[derive(Default)]
+struct State {
+    users: BTreeMap<String, User>,
+    // Keyed by SECRET_BLOCK for PASSWORD_VERIFIER, which carries no Session, else by Session.
+    pending: BTreeMap<String, Pending>,
+    refresh_tokens: BTreeMap<String, String>,
+    operations: Vec<String>,
+    issued: u64,
+}
+
+impl FakeCognito {
+    pub fn start(pool_id: &str, client_id: &str) -> Result<Self> {
+        let pool_id = PoolId::parse(pool_id)?;
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        let address = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let server = Server {
+            pool_id: pool_id.clone(),
+            client_id: client_id.to_owned(),
+            state: state.clone(),
+        };
+        let thread = {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        // A client that hangs up mid-request only fails its own call.
+                        let _ = server.handle(stream);
+                    }
+                }
+            })
+        };
+        Ok(Self {
+            address,
+            pool_id,
+            client_id: client_id.to_owned(),
+            state,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn endpoint(&self) -> String {
+        format!("http://{}/", self.address)
+    }
+
+    // A Client for this pool that already points at the fake.
+    pub fn client(&self) -> Result<Client> {
+        Ok(
+            Client::new(self.pool_id.as_str(), &self.client_id, LocalTransport)?
+                .with_endpoint(&self.endpoint()),
+        )
+    }
+
+    // Registers a confirmed user; only the SRP verifier is kept, never the password.
+    pub fn add_user(&self, username: &str, password: &str) -> Result<()> {
+        let verifier = PasswordVerifier::generate(
+            self.pool_id.as_str(),
+            username,
+            password,
+            &mut rand::thread_rng(),
+        )?;
+        self.lock().users.insert(
+            username.to_owned(),
+            User {
+                verifier,
+                mfa: None,
+            },
+        );
+        Ok(())
+    }
+
+    // Makes sign-in for `username` stop at an SMS_MFA, SOFTWARE_TOKEN_MFA or EMAIL_OTP
+    // challenge that only `code` answers.
+    pub fn require_mfa(&self, username: &str, challenge: ChallengeName, code: &str) -> Result<()> {
+        if !matches!(
+            challenge,
+            ChallengeName::SmsMfa | ChallengeName::SoftwareTokenMfa | ChallengeName::EmailOtp
+        ) {
+            return Err(CognitoSrpError::InvalidArgument(format!(
+                "{} is not an MFA challenge",
+                challenge.as_str()
+            )));
+        }
+        let mut state = self.lock();
+        let user = state.users.get_mut(username).ok_or_else(|| {
+            CognitoSrpError::InvalidArgument(format!("no user named {}", username))
+        })?;
+        user.mfa = Some((challenge, code.to_owned()));
+        Ok(())
+    }
+
+    // The operations served so far, in order, e.g. ["InitiateAuth", "RespondToAuthChallenge"].
+    pub fn operations(&self) -> Vec<String> {
+        self.lock().operations.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for FakeCognito {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag.
+        let _ = TcpStream::connect(self.address);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct Server {
+    pool_id: PoolId,
+    client_id: String,
+    state: Arc<Mutex<State>>,
+}
+
+// What goes back in the x-amz-json error body.
+struct Fault(&'static str, &'static str);
+
+const NOT_AUTHORIZED: Fault = Fault("NotAuthorizedException", "Incorrect username or password.");
+
+impl Server {
+    fn handle(&self, mut stream: TcpStream) -> Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut target = String::new();
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                match name.trim().to_ascii_lowercase().as_str() {
+                    "x-amz-target" => target = value.trim().to_owned(),
+                    "content-length" => length = value.trim().parse().unwrap_or(0),
+                    _ => {}
+                }
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        let operation = target.rsplit('.').next().unwrap_or_default().to_owned();
+
+        let result = json::parse(&String::from_utf8_lossy(&body))
+            .map_err(|_| Fault("SerializationException", "Request body is not JSON."))
+            .and_then(|request| self.serve(&operation, &request));
+        let (status, body) = match result {
+            Ok(response) => ("200 OK", response.to_string()),
+            Err(Fault(type_name, message)) => (
+                "400 Bad Request",
+                Value::object([
+                    ("__type", Value::from(type_name)),
+                    ("message", Value::from(message)),
+                ])
+                .to_string(),
+            ),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: application/x-amz-json-1.1\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )?;
+        Ok(())
+    }
+
+    fn serve(&self, operation: &str, request: &Value) -> Result<Value, Fault> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.operations.push(operation.to_owned());
+        if request.str_field("ClientId").as_deref() != Some(self.client_id.as_str()) {
+            return Err(Fault(
+                "ResourceNotFoundException",
+                "User pool client does not exist.",
+            ));
+        }
+        match operation {
+            "InitiateAuth" => {
+                let parameters = request.get("AuthParameters").ok_or(Fault(
+                    "InvalidParameterException",
+                    "Missing required parameter AUTH_PARAMETERS",
+                ))?;
+                match request.str_field("AuthFlow").as_deref() {
+                    Some("USER_SRP_AUTH") => self.start_srp(&mut state, parameters),
+                    Some("REFRESH_TOKEN_AUTH") => {
+                        let token = parameters.str_field("REFRESH_TOKEN").unwrap_or_default();
+                        let username = state
+                            .refresh_tokens
+                            .get(&token)
+                            .cloned()
+                            .ok_or(Fault("NotAuthorizedException", "Invalid Refresh Token"))?;
+                        self.tokens(&mut state, &username, false)
+                    }
+                    _ => Err(Fault("InvalidParameterException", "Unsupported AuthFlow.")),
+                }
+            }
+            "RespondToAuthChallenge" => {
+                let responses = request.get("ChallengeResponses").ok_or(Fault(
+                    "InvalidParameterException",
+                    "Missing required parameter CHALLENGE_RESPONSES",
+                ))?;
+                let field = |name: &str| responses.str_field(name).unwrap_or_default();
+                match request.str_field("ChallengeName").as_deref() {
+                    Some("PASSWORD_VERIFIER") => {
+                        let Some(Pending::PasswordVerifier {
+                            username,
+                            small_b,
+                            srp_a,
+                        }) = state.pending.remove(&field("PASSWORD_CLAIM_SECRET_BLOCK"))
+                        else {
+                            return Err(NOT_AUTHORIZED);
+                        };
+                        let user = state.users.get(&username).ok_or(NOT_AUTHORIZED)?;
+                        let secret_block = field("PASSWORD_CLAIM_SECRET_BLOCK");
+                        ServerSession::with_small_b(&user.verifier, small_b, &secret_block)
+                            .verify(
+                                &srp_a,
+                                &field("TIMESTAMP"),
+                                &field("PASSWORD_CLAIM_SIGNATURE"),
+                            )
+                            .map_err(|_| NOT_AUTHORIZED)?;
+                        match user.mfa.clone() {
+                            Some((challenge, _)) => {
+                                self.mfa_challenge(&mut state, &username, challenge)
+                            }
+                            None => self.tokens(&mut state, &username, true),
+                        }
+                    }
+                    Some(name @ ("SMS_MFA" | "SOFTWARE_TOKEN_MFA" | "EMAIL_OTP")) => {
+                        let session = request.str_field("Session").unwrap_or_default();
+                        let Some(Pending::Mfa { username }) = state.pending.remove(&session) else {
+                            return Err(Fault(
+                                "NotAuthorizedException",
+                                "Invalid session for the user.",
+                            ));
+                        };
+                        let expected = state
+                            .users
+                            .get(&username)
+                            .and_then(|user| user.mfa.clone())
+                            .filter(|(challenge, _)| challenge.as_str() == name)
+                            .map(|(_, code)| code)
+                            .ok_or(Fault("InvalidParameterException", "Unexpected challenge."))?;
+                        // SMS_MFA_CODE, SOFTWARE_TOKEN_MFA_CODE or EMAIL_OTP_CODE.
+                        let code = field(&format!("{}_CODE", name));
+                        if code != expected {
+                            return Err(Fault(
+                                "CodeMismatchException",
+                                "Invalid code or auth state for the user.",
+                            ));
+                        }
+                        self.tokens(&mut state, &username, true)
+                    }
+                    _ => Err(Fault(
+                        "InvalidParameterException",
+                        "Unsupported ChallengeName.",
+                    )),
+                }
+            }
+            "RevokeToken" => {
+                let token = request.str_field("Token").unwrap_or_default();
+                state.refresh_tokens.remove(&token);
+                Ok(Value::object::<String>([]))
+            }
+            _ => Err(Fault(
+                "UnsupportedOperationException",
+                "This operation is not emulated.",
+            )),
+        }
+    }
+
+    fn start_srp(&self, state: &mut State, parameters: &Value) -> Result<Value, Fault> {
+        let username = parameters.str_field("USERNAME").unwrap_or_default();
+        let srp_a = parameters.str_field("SRP_A").ok_or(Fault(
+            "InvalidParameterException",
+            "Missing required parameter SRP_A",
+        ))?;
+        let user = state
+            .users
+            .get(&username)
+            .ok_or(Fault("UserNotFoundException", "User does not exist."))?;
+        let rng = &mut rand::thread_rng();
+        let small_b = BigUint::from_bytes_be(&get_random(rng, 128));
+        let secret_block = BASE64.encode(&get_random(rng, 64));
+        let session = ServerSession::with_small_b(&user.verifier, small_b.clone(), &secret_block);
+        let response = Value::object([
+            ("ChallengeName", Value::from("PASSWORD_VERIFIER")),
+            (
+                "ChallengeParameters",
+                Value::object([
+                    ("SALT", Value::from(session.salt())),
+                    ("SECRET_BLOCK", Value::from(secret_block.as_str())),
+                    ("SRP_B", Value::from(session.srp_b())),
+                    ("USERNAME", Value::from(username.as_str())),
+                    (
+                        "USER_ID_FOR_SRP",
+                        Value::from(user.verifier.user_id_for_srp()),
+                    ),
+                ]),
+            ),
+        ]);
+        state.pending.insert(
+            secret_block,
+            Pending::PasswordVerifier {
+                username,
+                small_b,
+                srp_a,
+            },
+        );
+        Ok(response)
+    }
+
+    fn mfa_challenge(
+        &self,
+        state: &mut State,
+        username: &str,
+        challenge: ChallengeName,
+    ) -> Result<Value, Fault> {
+        let session = hex::encode(get_random(&mut rand::thread_rng(), 32));
+        let mut parameters = Value::object([("USER_ID_FOR_SRP", Value::from(username))]);
+        if challenge == ChallengeName::SmsMfa {
+            parameters.insert("CODE_DELIVERY_DELIVERY_MEDIUM", Value::from("SMS"));
+            parameters.insert("CODE_DELIVERY_DESTINATION", Value::from("+*******0000"));
+        } else if challenge == ChallengeName::EmailOtp {
+            parameters.insert("CODE_DELIVERY_DELIVERY_MEDIUM", Value::from("EMAIL"));
+            parameters.insert("CODE_DELIVERY_DESTINATION", Value::from("b***@e***"));
+        }
+        state.pending.insert(
+            session.clone(),
+            Pending::Mfa {
+                username: username.to_owned(),
+            },
+        );
+        Ok(Value::object([
+            ("ChallengeName", Value::from(challenge.as_str())),
+            ("ChallengeParameters", parameters),
+            ("Session", Value::from(session)),
+        ]))
+    }
+
+    // A fresh access and ID token, plus a refresh token for a sign-in (refreshes keep theirs).
+    fn tokens(&self, state: &mut State, username: &str, sign_in: bool) -> Result<Value, Fault> {
+        state.issued += 1;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let claims = |token_use: &str| {
+            let (client_claim, username_claim) = match token_use {
+                "id" => ("aud", "cognito:username"),
+                _ => ("client_id", "username"),
+            };
+            Value::object([
+                ("sub", Value::from(username)),
+                (
+                    "iss",
+                    Value::from(format!(
+                        "{}/{}",
+                        "https://cognito-idp.local",
+                        self.pool_id.as_str()
+                    )),
+                ),
+                ("token_use", Value::from(token_use)),
+                (client_claim, Value::from(self.client_id.as_str())),
+                (username_claim, Value::from(username)),
+                ("iat", Value::Number(now as f64)),
+                ("exp", Value::Number((now + 3600) as f64)),
+                ("jti", Value::from(state.issued.to_string())),
+            ])
+        };
+        let jwt = |claims: Value| {
+            format!(
+                "{}.{}.fake",
+                BASE64URL_NOPAD.encode(br#"{"alg":"none","kid":"fake"}"#),
+                BASE64URL_NOPAD.encode(claims.to_string().as_bytes())
+            )
+        };
+        let mut result = Value::object([
+            ("AccessToken", Value::from(jwt(claims("access")))),
+            ("IdToken", Value::from(jwt(claims("id")))),
+            ("ExpiresIn", Value::Number(3600.0)),
+            ("TokenType", Value::from("Bearer")),
+        ]);
+        if sign_in {
+            let refresh_token = hex::encode(get_random(&mut rand::thread_rng(), 32));
+            state
+                .refresh_tokens
+                .insert(refresh_token.clone(), username.to_owned());
+            result.insert("RefreshToken", Value::from(refresh_token));
+        }
+        Ok(Value::object([
+            ("AuthenticationResult", result),
+            ("ChallengeParameters", Value::object::<String>([])),
+        ]))
+    }
+}
+
+// Plain-HTTP transport for talking to a FakeCognito; one connection per request.
+pub struct LocalTransport;
+
+impl Transport for LocalTransport {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let rest = request.url.strip_prefix("http://").ok_or_else(|| {
+            CognitoSrpError::Configuration(format!(
+                "LocalTransport only speaks plain http, not {}",
+                request.url
+            ))
+        })?;
+        let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let mut stream = TcpStream::connect(authority)
+            .map_err(|err| CognitoSrpError::Transport(Box::new(err)))?;
+        let mut head = format!(
+            "{} /{} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            request.method,
+            path,
+            authority,
+            request.body.len()
+        );
+        for (name, value) in &request.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(request.body.as_bytes())?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+        let status = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| {
+                CognitoSrpError::InvalidResponse(format!("bad status line: {:?}", head))
+            })?;
+        Ok(HttpResponse {
+            status,
+            body: body.to_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Authentication;
+    use crate::error::ServiceErrorKind;
+    use crate::jwt::Claims;
+
+    #[test]
+    fn test_fake_cognito() {
+        let cognito = FakeCognito::start("us-east-1_Fake", "client").unwrap();
+        cognito.add_user("bob", "hunter2").unwrap();
+        cognito
+            .require_mfa("bob", ChallengeName::SoftwareTokenMfa, "123456")
+            .unwrap();
+        let client = cognito.client().unwrap();
+
+        let err = client.authenticate("bob", "wrong").unwrap_err();
+        assert_eq!(err.service_kind(), Some(&ServiceErrorKind::NotAuthorized));
+
+        let Authentication::Challenge(challenge) = client.authenticate("bob", "hunter2").unwrap()
+        else {
+            panic!("expected an MFA challenge");
+        };
+        assert_eq!(challenge.name, ChallengeName::SoftwareTokenMfa);
+        let Authentication::Tokens(tokens) = client.respond_to_mfa(&challenge, "123456").unwrap()
+        else {
+            panic!("expected tokens");
+        };
+        let claims = Claims::decode_unverified(&tokens.id_token).unwrap();
+        assert_eq!(claims.username.as_deref(), Some("bob"));
+
+        let refresh_token = tokens.refresh_token.unwrap();
+        let refreshed = client
+            .refresh_tokens("bob", refresh_token.expose_secret())
+            .unwrap();
+        assert_ne!(refreshed.access_token, tokens.access_token);
+        client.revoke_token(refresh_token.expose_secret()).unwrap();
+        assert!(client
+            .refresh_tokens("bob", refresh_token.expose_secret())
+            .is_err());
+        assert_eq!(
+            cognito.operations(),
+            [
+                "InitiateAuth",
+                "RespondToAuthChallenge",
+                "InitiateAuth",
+                "RespondToAuthChallenge",
+                "RespondToAuthChallenge",
+                "InitiateAuth",
+                "RevokeToken",
+                "InitiateAuth"
+            ]
+        );
+    }
+}
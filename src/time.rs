@@ -0,0 +1,153 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub(crate) struct UtcDateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+// # http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+pub(crate) fn utc(time: SystemTime) -> UtcDateTime {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    UtcDateTime {
+        year: yoe + era * 400 + i64::from(month <= 2),
+        month,
+        day,
+        hour: (rem / 3600) as u32,
+        minute: (rem % 3600 / 60) as u32,
+        second: (rem % 60) as u32,
+    }
+}
+
+// 20150830T123600Z, as used by SigV4.
+pub(crate) fn amz_date(time: SystemTime) -> String {
+    let t = utc(time);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        t.year, t.month, t.day, t.hour, t.minute, t.second
+    )
+}
+
+// 2015-08-30T12:36:00Z (RFC 3339 in UTC), as the AWS CLI expects in credential_process output.
+pub(crate) fn iso8601(time: SystemTime) -> String {
+    let t = utc(time);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        t.year, t.month, t.day, t.hour, t.minute, t.second
+    )
+}
+
+// # http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+// Reads iso8601's format back, tolerating fractional seconds; None for anything else.
+pub(crate) fn parse_iso8601(value: &str) -> Option<SystemTime> {
+    let value = value.strip_suffix('Z')?;
+    let value = value.split_once('.').map_or(value, |(whole, _)| whole);
+    let (date, time) = value.split_once('T')?;
+    let numbers =
+        |s: &str, sep: char| -> Option<Vec<i64>> { s.split(sep).map(|n| n.parse().ok()).collect() };
+    let (date, time) = (numbers(date, '-')?, numbers(time, ':')?);
+    let (&[year, month, day], &[hour, minute, second]) = (date.as_slice(), time.as_slice()) else {
+        return None;
+    };
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    let secs = days * 86400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + std::time::Duration::from_secs(u64::try_from(secs).ok()?))
+}
+
+// "Tue Oct 7 09:05:03 UTC 2025": the TIMESTAMP Cognito expects in PASSWORD_VERIFIER responses,
+// with the day of month not zero-padded.
+pub(crate) fn cognito_timestamp(time: SystemTime) -> String {
+    const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let days = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86400)
+        .unwrap_or(0);
+    let t = utc(time);
+    format!(
+        "{} {} {} {:02}:{:02}:{:02} UTC {}",
+        // 1970-01-01 was a Thursday.
+        WEEKDAYS[((days + 4) % 7) as usize],
+        MONTHS[t.month as usize - 1],
+        t.day,
+        t.hour,
+        t.minute,
+        t.second,
+        t.year
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_amz_date() {
+        assert_eq!(amz_date(UNIX_EPOCH), "19700101T000000Z");
+        assert_eq!(
+            amz_date(UNIX_EPOCH + Duration::from_secs(1440938160)),
+            "20150830T123600Z"
+        );
+        assert_eq!(
+            amz_date(UNIX_EPOCH + Duration::from_secs(951782400)),
+            "20000229T000000Z"
+        );
+        assert_eq!(
+            iso8601(UNIX_EPOCH + Duration::from_secs(1440938160)),
+            "2015-08-30T12:36:00Z"
+        );
+        for secs in [0, 951782400, 1440938160, 4102444800] {
+            let time = UNIX_EPOCH + Duration::from_secs(secs);
+            assert_eq!(parse_iso8601(&iso8601(time)), Some(time));
+        }
+        assert_eq!(
+            parse_iso8601("2015-08-30T12:36:00.123Z"),
+            Some(UNIX_EPOCH + Duration::from_secs(1440938160))
+        );
+        assert_eq!(parse_iso8601("2015-08-30 12:36:00"), None);
+        assert_eq!(parse_iso8601("2015-13-30T12:36:00Z"), None);
+    }
+
+    #[test]
+    fn test_cognito_timestamp() {
+        assert_eq!(
+            cognito_timestamp(UNIX_EPOCH + Duration::from_secs(1759827903)),
+            "Tue Oct 7 09:05:03 UTC 2025"
+        );
+        assert_eq!(
+            cognito_timestamp(UNIX_EPOCH + Duration::from_secs(1440938160)),
+            "Sun Aug 30 12:36:00 UTC 2015"
+        );
+    }
+}
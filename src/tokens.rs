@@ -0,0 +1,341 @@
+use crate::client::Client;
+use crate::clock::{expires_in, is_expired, time_to_refresh, Clock};
+use crate::error::{CognitoSrpError, Result};
+use crate::json::Value;
+#[cfg(feature = "serde")]
+use crate::jwt::Claims;
+use crate::metrics;
+use crate::secret::SecretString;
+use crate::wire::{AuthResponse, AuthenticationResult, InitiateAuthRequest};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TokenSet {
+    pub access_token: String,
+    pub id_token: String,
+    // Long-lived, so it is scrubbed on drop and kept out of Debug output.
+    pub refresh_token: Option<SecretString>,
+    pub token_type: String,
+    pub expires_at: SystemTime,
+}
+
+impl TokenSet {
+    // Refresh responses omit RefreshToken, so callers pass the previous one through
+    // `refresh_token`.
+    pub(crate) fn from_authentication_result(
+        result: &AuthenticationResult,
+        refresh_token: Option<&str>,
+        now: SystemTime,
+    ) -> Self {
+        Self {
+            access_token: result.access_token.clone(),
+            id_token: result.id_token.clone(),
+            refresh_token: result
+                .refresh_token
+                .as_deref()
+                .or(refresh_token)
+                .map(SecretString::from),
+            token_type: result
+                .token_type
+                .clone()
+                .unwrap_or_else(|| "Bearer".to_owned()),
+            expires_at: now + Duration::from_secs(result.expires_in),
+        }
+    }
+
+    // Whether these came from a refresh with `refresh_token` that handed out a new one instead.
+    pub fn rotated_from(&self, refresh_token: &str) -> bool {
+        self.refresh_token
+            .as_ref()
+            .is_some_and(|new| new.expose_secret() != refresh_token)
+    }
+
+    // Timing helpers for refresh scheduling; pass the Client (or any Clock) so a FixedClock in
+    // tests drives them too. Claims has the same three, from its `exp`.
+    pub fn expires_in(&self, clock: &(impl Clock + ?Sized)) -> Duration {
+        expires_in(self.expires_at, clock)
+    }
+
+    // Expired, or expiring within `leeway`.
+    pub fn is_expired(&self, clock: &(impl Clock + ?Sized), leeway: Duration) -> bool {
+        is_expired(self.expires_at, clock, leeway)
+    }
+
+    // How long until a refresh `lead` ahead of expiry is due; zero once it is.
+    pub fn time_to_refresh(&self, clock: &(impl Clock + ?Sized), lead: Duration) -> Duration {
+        time_to_refresh(self.expires_at, clock, lead)
+    }
+
+    // The cache format: the token strings plus `expires_at` in seconds since the epoch.
+    pub(crate) fn to_json(&self) -> Value {
+        let mut value = Value::object([
+            ("access_token", Value::from(self.access_token.as_str())),
+            ("id_token", Value::from(self.id_token.as_str())),
+            ("token_type", Value::from(self.token_type.as_str())),
+            (
+                "expires_at",
+                Value::from(
+                    self.expires_at
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or(0) as f64,
+                ),
+            ),
+        ]);
+        if let Some(refresh_token) = &self.refresh_token {
+            value.insert("refresh_token", Value::from(refresh_token.expose_secret()));
+        }
+        value
+    }
+
+    // The id token's claims as the caller's own serde type; see Claims::deserialize. The token
+    // is not verified again, as it came from Cognito (or a cache of what did).
+    #[cfg(feature = "serde")]
+    pub fn id_claims<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        Claims::decode_unverified(&self.id_token)?.deserialize()
+    }
+
+    pub(crate) fn from_json(value: &Value) -> Result<Self> {
+        let field = |name: &str| {
+            value.str_field(name).ok_or_else(|| {
+                CognitoSrpError::InvalidResponse(format!("cached tokens are missing {}", name))
+            })
+        };
+        let expires_at = match value.get("expires_at") {
+            Some(Value::Number(secs)) if *secs >= 0.0 => {
+                UNIX_EPOCH + Duration::from_secs(*secs as u64)
+            }
+            _ => {
+                return Err(CognitoSrpError::InvalidResponse(
+                    "cached tokens are missing expires_at".to_owned(),
+                ))
+            }
+        };
+        Ok(Self {
+            access_token: field("access_token")?,
+            id_token: field("id_token")?,
+            refresh_token: value.str_field("refresh_token").map(SecretString::new),
+            token_type: field("token_type")?,
+            expires_at,
+        })
+    }
+}
+
+impl Client {
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_InitiateAuth.html
+    // `username` only feeds SECRET_HASH, so it matters only for app clients with a secret.
+    pub fn refresh_tokens(&self, username: &str, refresh_token: &str) -> Result<TokenSet> {
+        self.measure(metrics::REFRESH, || {
+            self.refresh_tokens_unmeasured(username, refresh_token)
+        })
+    }
+
+    fn refresh_tokens_unmeasured(&self, username: &str, refresh_token: &str) -> Result<TokenSet> {
+        let username = &self.normalize_username(username);
+        let mut request = InitiateAuthRequest::new("REFRESH_TOKEN_AUTH", self.client_id())
+            .with_parameter("REFRESH_TOKEN", refresh_token);
+        if let Some(hash) = self.secret_hash(username) {
+            request = request.with_parameter("SECRET_HASH", &hash);
+        }
+        let response = self.idempotent_call("InitiateAuth", request.to_json())?;
+        let result = AuthResponse::from_json(&response)?
+            .authentication_result
+            .ok_or_else(|| {
+                CognitoSrpError::InvalidResponse(
+                    "InitiateAuth returned no AuthenticationResult".to_owned(),
+                )
+            })?;
+        let tokens = TokenSet::from_authentication_result(&result, Some(refresh_token), self.now());
+        self.hooks().tokens_refreshed(&tokens);
+        if tokens.rotated_from(refresh_token) {
+            self.hooks().refresh_token_rotated(&tokens);
+        }
+        Ok(tokens)
+    }
+
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_RevokeToken.html
+    // Revokes the refresh token and the access tokens issued from it. Unlike the auth flows this
+    // takes the raw ClientSecret rather than a SECRET_HASH.
+    pub fn revoke_token(&self, refresh_token: &str) -> Result<()> {
+        let mut body = Value::object([
+            ("Token", Value::from(refresh_token)),
+            ("ClientId", Value::from(self.client_id())),
+        ]);
+        if let Some(secret) = self.client_secret() {
+            body.insert("ClientSecret", Value::from(secret));
+        }
+        self.idempotent_call("RevokeToken", body)?;
+        self.hooks().sign_out();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_refresh_tokens() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a2","IdToken":"i2","ExpiresIn":3600,"TokenType":"Bearer"},
+                "ChallengeParameters":{}}"#,
+        );
+        let client = Client::new("us-east-1_pool", "client", transport.clone())
+            .unwrap()
+            .with_client_secret("secret");
+
+        let before = SystemTime::now();
+        let tokens = client.refresh_tokens("bob", "r1").unwrap();
+        assert_eq!(tokens.access_token, "a2");
+        assert_eq!(tokens.id_token, "i2");
+        assert_eq!(tokens.refresh_token, Some("r1".into()));
+        assert!(tokens.expires_at >= before + Duration::from_secs(3600));
+
+        let body = transport.last_body();
+        assert_eq!(body.str_field("AuthFlow").unwrap(), "REFRESH_TOKEN_AUTH");
+        let parameters = body.get("AuthParameters").unwrap();
+        assert_eq!(parameters.str_field("REFRESH_TOKEN").unwrap(), "r1");
+        assert_eq!(
+            parameters.str_field("SECRET_HASH").unwrap(),
+            "P1WGx30spzkjVO6gbksCMo0r3Ro1xm4a6xXZTTrvrD0="
+        );
+    }
+
+    #[test]
+    fn test_expiry_helpers() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_759_827_903);
+        let clock = FixedClock::new(now);
+        let tokens = TokenSet {
+            access_token: "a".to_owned(),
+            id_token: "i".to_owned(),
+            refresh_token: None,
+            token_type: "Bearer".to_owned(),
+            expires_at: now + Duration::from_secs(3600),
+        };
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+        assert_eq!(tokens.expires_in(&clock), minutes(60));
+        assert_eq!(tokens.time_to_refresh(&clock, minutes(5)), minutes(55));
+        assert!(!tokens.is_expired(&clock, minutes(5)));
+
+        clock.advance(minutes(56));
+        assert_eq!(tokens.time_to_refresh(&clock, minutes(5)), Duration::ZERO);
+        assert!(tokens.is_expired(&clock, minutes(5)));
+        assert!(!tokens.is_expired(&clock, Duration::ZERO));
+
+        clock.advance(minutes(5));
+        assert_eq!(tokens.expires_in(&clock), Duration::ZERO);
+        assert!(tokens.is_expired(&clock, Duration::ZERO));
+
+        let client = Client::new("us-east-1_pool", "client", crate::api::Offline)
+            .unwrap()
+            .with_clock(FixedClock::new(now));
+        assert_eq!(tokens.expires_in(&client), minutes(60));
+    }
+
+    #[test]
+    fn test_revoke_token() {
+        let transport = MockTransport::new();
+        transport.respond(200, "{}");
+        let client = Client::new("us-east-1_pool", "client", transport.clone())
+            .unwrap()
+            .with_client_secret("secret");
+
+        client.revoke_token("r1").unwrap();
+        assert_eq!(
+            transport.last_request().body,
+            r#"{"ClientId":"client","ClientSecret":"secret","Token":"r1"}"#
+        );
+    }
+
+    #[test]
+    fn test_missing_fields() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a","ExpiresIn":60}}"#,
+        );
+        let client = Client::new("us-east-1_pool", "client", transport).unwrap();
+        assert!(matches!(
+            client.refresh_tokens("bob", "r1"),
+            Err(CognitoSrpError::InvalidResponse(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_id_claims() {
+        #[derive(serde::Deserialize)]
+        struct MyClaims {
+            sub: String,
+            email: String,
+            #[serde(rename = "cognito:groups")]
+            groups: Vec<String>,
+            #[serde(rename = "custom:tenant_id")]
+            tenant_id: Option<String>,
+            exp: u64,
+        }
+
+        let tokens = TokenSet {
+            access_token: crate::jwt::fixtures::token("access_admin"),
+            id_token: crate::jwt::fixtures::token("id_admin"),
+            refresh_token: None,
+            token_type: "Bearer".to_owned(),
+            expires_at: SystemTime::now(),
+        };
+        let claims: MyClaims = tokens.id_claims().unwrap();
+        assert_eq!(claims.sub, "sub-bob");
+        assert_eq!(claims.email, "bob@example.com");
+        assert_eq!(claims.groups, ["admins"]);
+        assert_eq!(claims.tenant_id, None);
+        assert_eq!(claims.exp, 4102444800);
+
+        #[derive(Debug, serde::Deserialize)]
+        struct Strict {
+            #[allow(dead_code)]
+            phone_number: String,
+        }
+        assert!(tokens.id_claims::<Strict>().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        use crate::auth::{Authentication, Challenge, ChallengeName};
+        use crate::jwt::Claims;
+
+        let tokens = TokenSet {
+            access_token: "a".to_owned(),
+            id_token: crate::jwt::fixtures::token("id_admin"),
+            refresh_token: Some("r".into()),
+            token_type: "Bearer".to_owned(),
+            expires_at: UNIX_EPOCH + Duration::from_secs(1_759_827_903),
+        };
+        let json = serde_json::to_string(&tokens).unwrap();
+        assert_eq!(serde_json::from_str::<TokenSet>(&json).unwrap(), tokens);
+
+        let claims = Claims::decode_unverified(&tokens.id_token).unwrap();
+        let json = serde_json::to_value(&claims).unwrap();
+        assert_eq!(json["cognito:username"], "bob");
+        assert_eq!(json["exp"], 4102444800u64);
+        assert_eq!(serde_json::from_value::<Claims>(json).unwrap(), claims);
+        assert!(serde_json::from_str::<Claims>(r#"{"sub":"x"}"#).is_err());
+
+        let challenge = Authentication::Challenge(Challenge {
+            name: ChallengeName::SoftwareTokenMfa,
+            session: Some("s".to_owned()),
+            parameters: Default::default(),
+            username: "bob".to_owned(),
+        });
+        let json = serde_json::to_string(&challenge).unwrap();
+        assert!(json.contains(r#""name":"SOFTWARE_TOKEN_MFA""#));
+        assert_eq!(
+            serde_json::from_str::<Authentication>(&json).unwrap(),
+            challenge
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use crate::error::Result;
+use std::sync::Arc;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: &'static str,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+// The crate does not bundle an HTTP stack; plug in whatever client the application already uses.
+pub trait Transport: Send + Sync {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse>;
+}
+
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        (**self).send(request)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    pub(crate) struct MockTransport {
+        responses: Mutex<VecDeque<HttpResponse>>,
+        pub(crate) requests: Mutex<Vec<HttpRequest>>,
+    }
+
+    impl MockTransport {
+        pub(crate) fn new() -> Arc<Self> {
+            Arc::new(Self::default())
+        }
+
+        pub(crate) fn respond(&self, status: u16, body: &str) {
+            self.responses.lock().unwrap().push_back(HttpResponse {
+                status,
+                body: body.to_owned(),
+            });
+        }
+
+        pub(crate) fn last_request(&self) -> HttpRequest {
+            self.requests.lock().unwrap().last().cloned().unwrap()
+        }
+
+        pub(crate) fn last_body(&self) -> crate::json::Value {
+            crate::json::parse(&self.last_request().body).unwrap()
+        }
+    }
+
+    impl Transport for MockTransport {
+        fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+            self.requests.lock().unwrap().push(request);
+            self.responses.lock().unwrap().pop_front().ok_or_else(|| {
+                crate::error::CognitoSrpError::Transport("no scripted response".into())
+            })
+        }
+    }
+}
@@ -0,0 +1,76 @@
+// # https://datatracker.ietf.org/doc/html/rfc3986#section-2.3
+// Percent-encodes everything except the unreserved characters, which is safe in any part of a
+// URL and in application/x-www-form-urlencoded bodies.
+pub(crate) fn encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for b in value.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(b as char)
+            }
+            b => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+// "a=1&b=2", for query strings and form bodies.
+pub(crate) fn form(pairs: &[(&str, &str)]) -> String {
+    pairs
+        .iter()
+        .map(|(name, value)| format!("{}={}", encode(name), encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+// Undoes `encode`, plus '+' for space as browsers send in query strings; None on bad escapes
+// or invalid UTF-8.
+pub(crate) fn decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut input = value.bytes();
+    while let Some(b) = input.next() {
+        match b {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = [input.next()?, input.next()?];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            }
+            b => bytes.push(b),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+// "a=1&b=2" back into pairs, skipping anything that doesn't decode.
+pub(crate) fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Some((decode(name)?, decode(value)?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("abc-._~XYZ019"), "abc-._~XYZ019");
+        assert_eq!(encode("a b/c+d=é"), "a%20b%2Fc%2Bd%3D%C3%A9");
+        assert_eq!(form(&[("x", "1 2"), ("y&", "")]), "x=1%202&y%26=");
+        assert_eq!(decode("a%20b+c%C3%A9").as_deref(), Some("a b cé"));
+        assert_eq!(decode("%zz"), None);
+        assert_eq!(
+            parse_query("code=a%2Fb&state=x&&flag"),
+            [
+                ("code".to_owned(), "a/b".to_owned()),
+                ("state".to_owned(), "x".to_owned()),
+                ("flag".to_owned(), String::new())
+            ]
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use crate::attributes::UserAttribute;
+use crate::client::Client;
+use crate::error::{CognitoSrpError, Result};
+use crate::json::Value;
+use crate::registration::DeliveryMedium;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MfaType {
+    Sms,
+    SoftwareToken,
+    Email,
+}
+
+impl MfaType {
+    pub(crate) fn parse(name: &str) -> Option<Self> {
+        match name {
+            "SMS_MFA" => Some(MfaType::Sms),
+            "SOFTWARE_TOKEN_MFA" => Some(MfaType::SoftwareToken),
+            "EMAIL_OTP" => Some(MfaType::Email),
+            _ => None,
+        }
+    }
+
+    // The Cognito name, which is also the challenge that follows choosing it.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MfaType::Sms => "SMS_MFA",
+            MfaType::SoftwareToken => "SOFTWARE_TOKEN_MFA",
+            MfaType::Email => "EMAIL_OTP",
+        }
+    }
+}
+
+// Legacy SMS-only MFA settings, still reported alongside UserMFASettingList.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MfaOption {
+    pub delivery_medium: Option<DeliveryMedium>,
+    pub attribute_name: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct User {
+    pub username: String,
+    pub attributes: Vec<UserAttribute>,
+    pub mfa_options: Vec<MfaOption>,
+    pub preferred_mfa: Option<MfaType>,
+    // Every MFA method the user has enabled.
+    pub enabled_mfa: Vec<MfaType>,
+}
+
+impl User {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+            .map(|attribute| attribute.value.as_str())
+    }
+
+    fn from_json(response: &Value) -> Result<Self> {
+        let items = |name: &str| match response.get(name) {
+            Some(Value::Array(items)) => items.as_slice(),
+            _ => &[],
+        };
+        let attributes = items("UserAttributes")
+            .iter()
+            .map(|item| {
+                Ok(UserAttribute {
+                    name: item.str_field("Name").ok_or_else(|| {
+                        CognitoSrpError::InvalidResponse("user attribute has no Name".to_owned())
+                    })?,
+                    value: item.str_field("Value").unwrap_or_default(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        let mfa_options = items("MFAOptions")
+            .iter()
+            .map(|item| {
+                Ok(MfaOption {
+                    delivery_medium: DeliveryMedium::from_field(item)?,
+                    attribute_name: item.str_field("AttributeName"),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            username: response.str_field("Username").ok_or_else(|| {
+                CognitoSrpError::InvalidResponse("GetUser returned no Username".to_owned())
+            })?,
+            attributes,
+            mfa_options,
+            preferred_mfa: response
+                .get("PreferredMfaSetting")
+                .and_then(Value::as_str)
+                .and_then(MfaType::parse),
+            enabled_mfa: items("UserMFASettingList")
+                .iter()
+                .filter_map(|item| item.as_str().and_then(MfaType::parse))
+                .collect(),
+        })
+    }
+}
+
+impl Client {
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_GetUser.html
+    pub fn get_user(&self, access_token: &str) -> Result<User> {
+        let body = Value::object([("AccessToken", Value::from(access_token))]);
+        User::from_json(&self.idempotent_call("GetUser", body)?)
+    }
+
+    // # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_SetUserMFAPreference.html
+    // Makes `enabled` the user's exact set of MFA methods; anything left out is disabled. Email
+    // is the exception: pools without email MFA reject EmailMfaSettings altogether, so it is only
+    // sent when `enabled` includes it. Turn email off with disable_email_mfa.
+    pub fn set_user_mfa_preference(
+        &self,
+        access_token: &str,
+        enabled: &[MfaType],
+        preferred: Option<MfaType>,
+    ) -> Result<()> {
+        if let Some(preferred) = preferred {
+            if !enabled.contains(&preferred) {
+                return Err(CognitoSrpError::InvalidArgument(format!(
+                    "preferred MFA method {:?} is not enabled",
+                    preferred
+                )));
+            }
+        }
+        let settings = |mfa: MfaType| {
+            Value::object([
+                ("Enabled", Value::from(enabled.contains(&mfa))),
+                ("PreferredMfa", Value::from(preferred == Some(mfa))),
+            ])
+        };
+        let mut body = Value::object([
+            ("AccessToken", Value::from(access_token)),
+            ("SMSMfaSettings", settings(MfaType::Sms)),
+            ("SoftwareTokenMfaSettings", settings(MfaType::SoftwareToken)),
+        ]);
+        if enabled.contains(&MfaType::Email) {
+            body.insert("EmailMfaSettings", settings(MfaType::Email));
+        }
+        self.idempotent_call("SetUserMFAPreference", body)?;
+        Ok(())
+    }
+
+    // Turns off email MFA and leaves the other methods as they are. Only for pools with email
+    // MFA; the rest reject it.
+    pub fn disable_email_mfa(&self, access_token: &str) -> Result<()> {
+        let body = Value::object([
+            ("AccessToken", Value::from(access_token)),
+            (
+                "EmailMfaSettings",
+                Value::object([
+                    ("Enabled", Value::from(false)),
+                    ("PreferredMfa", Value::from(false)),
+                ]),
+            ),
+        ]);
+        self.idempotent_call("SetUserMFAPreference", body)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_get_user() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"Username":"bob","UserAttributes":[{"Name":"sub","Value":"sub-bob"},
+                {"Name":"email","Value":"bob@example.com"}],
+                "MFAOptions":[{"DeliveryMedium":"SMS","AttributeName":"phone_number"}],
+                "PreferredMfaSetting":"SOFTWARE_TOKEN_MFA",
+                "UserMFASettingList":["SMS_MFA","SOFTWARE_TOKEN_MFA"]}"#,
+        );
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+
+        let user = client.get_user("access").unwrap();
+        assert_eq!(user.username, "bob");
+        assert_eq!(user.attribute("email"), Some("bob@example.com"));
+        assert_eq!(user.attribute("phone_number"), None);
+        assert_eq!(
+            user.mfa_options,
+            vec![MfaOption {
+                delivery_medium: Some(DeliveryMedium::Sms),
+                attribute_name: Some("phone_number".to_owned()),
+            }]
+        );
+        assert_eq!(user.preferred_mfa, Some(MfaType::SoftwareToken));
+        assert_eq!(user.enabled_mfa, vec![MfaType::Sms, MfaType::SoftwareToken]);
+        assert_eq!(transport.last_request().body, r#"{"AccessToken":"access"}"#);
+    }
+
+    #[test]
+    fn test_set_user_mfa_preference() {
+        let transport = MockTransport::new();
+        transport.respond(200, "{}");
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+
+        assert!(client
+            .set_user_mfa_preference("access", &[MfaType::Sms], Some(MfaType::SoftwareToken))
+            .is_err());
+        client
+            .set_user_mfa_preference(
+                "access",
+                &[MfaType::SoftwareToken],
+                Some(MfaType::SoftwareToken),
+            )
+            .unwrap();
+        let body = transport.last_body();
+        assert_eq!(
+            body.get("SMSMfaSettings").unwrap().to_string(),
+            r#"{"Enabled":false,"PreferredMfa":false}"#
+        );
+        assert_eq!(
+            body.get("SoftwareTokenMfaSettings").unwrap().to_string(),
+            r#"{"Enabled":true,"PreferredMfa":true}"#
+        );
+        assert!(body.get("EmailMfaSettings").is_none());
+
+        transport.respond(200, "{}");
+        client
+            .set_user_mfa_preference(
+                "access",
+                &[MfaType::Email, MfaType::SoftwareToken],
+                Some(MfaType::Email),
+            )
+            .unwrap();
+        assert_eq!(
+            transport
+                .last_body()
+                .get("EmailMfaSettings")
+                .unwrap()
+                .to_string(),
+            r#"{"Enabled":true,"PreferredMfa":true}"#
+        );
+
+        transport.respond(200, "{}");
+        client.disable_email_mfa("access").unwrap();
+        assert_eq!(
+            transport.last_request().body,
+            r#"{"AccessToken":"access","EmailMfaSettings":{"Enabled":false,"PreferredMfa":false}}"#
+        );
+    }
+}
@@ -0,0 +1,343 @@
+// # https://docs.aws.amazon.com/cognito/latest/developerguide/authentication-flows-selection-sdk.html#authentication-flows-selection-choice
+// Choice-based sign-in (USER_AUTH). The client names the first factor it would like in
+// PREFERRED_CHALLENGE; when the user can't use it, Cognito answers SELECT_CHALLENGE with the
+// ones they can, and the client picks one:
+//
+//     let challenge = client.initiate_user_auth("bob", None)?; // SELECT_CHALLENGE
+//     if challenge.available_challenges().contains(&FirstFactor::EmailOtp) {
+//         let code_sent = client.respond_to_select_challenge(&challenge, FirstFactor::EmailOtp)?;
+//         client.respond_to_mfa(&code_sent, &read_code())?;
+//     }
+//
+// Passwords go through SRP wherever the pool offers PASSWORD_SRP, as with authenticate.
+use crate::auth::{Authentication, Challenge, ChallengeName};
+use crate::client::Client;
+use crate::error::{CognitoSrpError, Result, ServiceErrorKind};
+use crate::json::{self, Value};
+use crate::srp::SrpSession;
+use crate::wire::InitiateAuthRequest;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FirstFactor {
+    // The plain password, sent to Cognito over TLS.
+    Password,
+    // The password through SRP, as authenticate does.
+    PasswordSrp,
+    EmailOtp,
+    SmsOtp,
+    // A passkey; see Client::authenticate_with_passkey.
+    WebAuthn,
+    Other(String),
+}
+
+impl FirstFactor {
+    pub(crate) fn parse(name: &str) -> Self {
+        match name {
+            "PASSWORD" => FirstFactor::Password,
+            "PASSWORD_SRP" => FirstFactor::PasswordSrp,
+            "EMAIL_OTP" => FirstFactor::EmailOtp,
+            "SMS_OTP" => FirstFactor::SmsOtp,
+            "WEB_AUTHN" => FirstFactor::WebAuthn,
+            other => FirstFactor::Other(other.to_owned()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            FirstFactor::Password => "PASSWORD",
+            FirstFactor::PasswordSrp => "PASSWORD_SRP",
+            FirstFactor::EmailOtp => "EMAIL_OTP",
+            FirstFactor::SmsOtp => "SMS_OTP",
+            FirstFactor::WebAuthn => "WEB_AUTHN",
+            FirstFactor::Other(name) => name,
+        }
+    }
+
+    fn needs_password(&self) -> bool {
+        matches!(self, FirstFactor::Password | FirstFactor::PasswordSrp)
+    }
+}
+
+impl Challenge {
+    // For SELECT_CHALLENGE, the first factors the user can pick from (Cognito's
+    // AvailableChallenges). Empty for any other challenge.
+    pub fn available_challenges(&self) -> Vec<FirstFactor> {
+        let parsed = self
+            .parameters
+            .get("AVAILABLE_CHALLENGES")
+            .map(|names| json::parse(names));
+        let Some(Ok(Value::Array(names))) = parsed else {
+            return Vec::new();
+        };
+        names
+            .iter()
+            .filter_map(Value::as_str)
+            .map(FirstFactor::parse)
+            .collect()
+    }
+}
+
+impl Client {
+    // InitiateAuth with USER_AUTH. With EMAIL_OTP or SMS_OTP preferred, Cognito sends the code
+    // and asks for it; with no preference, or one the user can't use, the result is
+    // SELECT_CHALLENGE. Password factors need the password up front; use
+    // authenticate_with_user_auth for those.
+    pub fn initiate_user_auth(
+        &self,
+        username: &str,
+        preferred: Option<FirstFactor>,
+    ) -> Result<Authentication> {
+        if let Some(factor) = preferred.as_ref().filter(|factor| factor.needs_password()) {
+            return Err(CognitoSrpError::InvalidArgument(format!(
+                "{} needs the password; use authenticate_with_user_auth",
+                factor.as_str()
+            )));
+        }
+        let username = &self.normalize_username(username);
+        let request = self.user_auth_request(username, preferred.as_ref());
+        let response = self.initiate_auth_call(false, &request)?;
+        self.authentication_from(false, response, username)
+    }
+
+    // USER_AUTH with PASSWORD_SRP preferred, then the usual PASSWORD_VERIFIER answer. App
+    // clients without USER_AUTH enabled reject the flow as an invalid parameter; those fall back
+    // to USER_SRP_AUTH. Challenges after the first factor (MFA, NEW_PASSWORD_REQUIRED, ...) come
+    // back as with authenticate.
+    pub fn authenticate_with_user_auth(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<Authentication> {
+        let username = &self.normalize_username(username);
+        let srp = self.random(|rng| SrpSession::new(self.pool_id(), rng))?;
+        let request = self
+            .user_auth_request(username, Some(&FirstFactor::PasswordSrp))
+            .with_parameter("SRP_A", &srp.srp_a());
+        let response = match self.initiate_auth_call(false, &request) {
+            Err(err) if err.service_kind() == Some(&ServiceErrorKind::InvalidParameter) => {
+                return self.authenticate(username, password);
+            }
+            result => result?,
+        };
+        if response.challenge_name_str() == Some("PASSWORD_VERIFIER") {
+            return self.answer_password_verifier(false, srp, &response, password);
+        }
+        match self.authentication_from(false, response, username)? {
+            Authentication::Challenge(challenge)
+                if challenge.name == ChallengeName::SelectChallenge =>
+            {
+                self.respond_to_select_challenge_with_password(&challenge, password)
+            }
+            authentication => Ok(authentication),
+        }
+    }
+
+    // Picks a first factor that needs no password. EMAIL_OTP and SMS_OTP lead to a challenge
+    // answered with respond_to_mfa, WEB_AUTHN to one answered with respond_to_web_authn.
+    pub fn respond_to_select_challenge(
+        &self,
+        challenge: &Challenge,
+        factor: FirstFactor,
+    ) -> Result<Authentication> {
+        check_first_factor(challenge, &factor)?;
+        if factor.needs_password() {
+            return Err(CognitoSrpError::InvalidArgument(format!(
+                "{} needs the password; use respond_to_select_challenge_with_password",
+                factor.as_str()
+            )));
+        }
+        self.respond_to_challenge(challenge, &[("ANSWER", factor.as_str())])
+    }
+
+    // Picks the password: PASSWORD_SRP when it is on offer, the plain PASSWORD only when it is
+    // the sole password factor.
+    pub fn respond_to_select_challenge_with_password(
+        &self,
+        challenge: &Challenge,
+        password: &str,
+    ) -> Result<Authentication> {
+        let available = challenge.available_challenges();
+        if available.contains(&FirstFactor::Password)
+            && !available.contains(&FirstFactor::PasswordSrp)
+        {
+            check_first_factor(challenge, &FirstFactor::Password)?;
+            return self.respond_to_challenge(
+                challenge,
+                &[("ANSWER", "PASSWORD"), ("PASSWORD", password)],
+            );
+        }
+        check_first_factor(challenge, &FirstFactor::PasswordSrp)?;
+        let srp = self.random(|rng| SrpSession::new(self.pool_id(), rng))?;
+        let response = self.respond_call(
+            false,
+            "SELECT_CHALLENGE",
+            challenge.session.as_deref(),
+            &challenge.username,
+            &[("ANSWER", "PASSWORD_SRP"), ("SRP_A", &srp.srp_a())],
+        )?;
+        response.expect_challenge("PASSWORD_VERIFIER")?;
+        self.answer_password_verifier(false, srp, &response, password)
+    }
+
+    fn user_auth_request(
+        &self,
+        username: &str,
+        preferred: Option<&FirstFactor>,
+    ) -> InitiateAuthRequest {
+        let mut request = InitiateAuthRequest::new("USER_AUTH", self.client_id())
+            .with_parameter("USERNAME", username);
+        if let Some(factor) = preferred {
+            request = request.with_parameter("PREFERRED_CHALLENGE", factor.as_str());
+        }
+        if let Some(hash) = self.secret_hash(username) {
+            request = request.with_parameter("SECRET_HASH", &hash);
+        }
+        request
+    }
+}
+
+// Only factors Cognito listed can be picked; an empty list is taken as not knowing.
+fn check_first_factor(challenge: &Challenge, factor: &FirstFactor) -> Result<()> {
+    if challenge.name != ChallengeName::SelectChallenge {
+        return Err(CognitoSrpError::InvalidArgument(format!(
+            "{} is not SELECT_CHALLENGE",
+            challenge.name.as_str()
+        )));
+    }
+    let available = challenge.available_challenges();
+    if !available.is_empty() && !available.contains(factor) {
+        return Err(CognitoSrpError::InvalidArgument(format!(
+            "{} is not one of the user's first factors",
+            factor.as_str()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    const SELECT_CHALLENGE: &str = r#"{"ChallengeName":"SELECT_CHALLENGE","Session":"s1",
+        "AvailableChallenges":["PASSWORD_SRP","PASSWORD","EMAIL_OTP"],
+        "ChallengeParameters":{"USERNAME":"bob-internal"}}"#;
+    const PASSWORD_VERIFIER: &str = r#"{"ChallengeName":"PASSWORD_VERIFIER","Session":"s2",
+        "ChallengeParameters":{"SALT":"ab12cd34ef56",
+        "SECRET_BLOCK":"b3BhcXVlIHNlY3JldCBibG9jayBmcm9tIGNvZ25pdG8=",
+        "SRP_B":"82f7e185b83288814ad86154a1bfa657f70d27ec43f507a618d89eb5ac9f90e9",
+        "USERNAME":"bob-internal","USER_ID_FOR_SRP":"bob-internal"}}"#;
+    const TOKENS: &str =
+        r#"{"AuthenticationResult":{"AccessToken":"a","IdToken":"i","ExpiresIn":3600}}"#;
+
+    #[test]
+    fn test_select_challenge() {
+        let transport = MockTransport::new();
+        transport.respond(200, SELECT_CHALLENGE);
+        transport.respond(
+            200,
+            r#"{"ChallengeName":"EMAIL_OTP","Session":"s2","ChallengeParameters":{
+                "USERNAME":"bob-internal","CODE_DELIVERY_DELIVERY_MEDIUM":"EMAIL"}}"#,
+        );
+        transport.respond(200, TOKENS);
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+        let Authentication::Challenge(challenge) = client.initiate_user_auth("bob", None).unwrap()
+        else {
+            panic!("expected SELECT_CHALLENGE");
+        };
+        assert_eq!(challenge.name, ChallengeName::SelectChallenge);
+        assert_eq!(
+            challenge.available_challenges(),
+            [
+                FirstFactor::PasswordSrp,
+                FirstFactor::Password,
+                FirstFactor::EmailOtp
+            ]
+        );
+        let initiate = transport.last_body();
+        assert_eq!(initiate.str_field("AuthFlow").unwrap(), "USER_AUTH");
+        let parameters = initiate.get("AuthParameters").unwrap();
+        assert_eq!(parameters.get("PREFERRED_CHALLENGE"), None);
+
+        assert!(matches!(
+            client.respond_to_select_challenge(&challenge, FirstFactor::SmsOtp),
+            Err(CognitoSrpError::InvalidArgument(_))
+        ));
+        assert!(client
+            .respond_to_select_challenge(&challenge, FirstFactor::PasswordSrp)
+            .is_err());
+        let Authentication::Challenge(code_sent) = client
+            .respond_to_select_challenge(&challenge, FirstFactor::EmailOtp)
+            .unwrap()
+        else {
+            panic!("expected EMAIL_OTP");
+        };
+        let answer = transport.last_body();
+        assert_eq!(answer.str_field("Session").unwrap(), "s1");
+        let responses = answer.get("ChallengeResponses").unwrap();
+        assert_eq!(responses.str_field("ANSWER").unwrap(), "EMAIL_OTP");
+
+        let authentication = client.respond_to_mfa(&code_sent, "123456").unwrap();
+        assert!(matches!(authentication, Authentication::Tokens(_)));
+        let responses = transport.last_body();
+        let responses = responses.get("ChallengeResponses").unwrap();
+        assert_eq!(responses.str_field("EMAIL_OTP_CODE").unwrap(), "123456");
+        assert_eq!(transport.requests.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_authenticate_with_user_auth() {
+        let transport = MockTransport::new();
+        transport.respond(200, SELECT_CHALLENGE);
+        transport.respond(200, PASSWORD_VERIFIER);
+        transport.respond(200, TOKENS);
+        let client = Client::new("us-east-1_Pool123", "client", transport.clone()).unwrap();
+        let authentication = client
+            .authenticate_with_user_auth("bob", "P@ssw0rd!")
+            .unwrap();
+        assert!(matches!(authentication, Authentication::Tokens(_)));
+
+        let requests = transport.requests.lock().unwrap();
+        let initiate = json::parse(&requests[0].body).unwrap();
+        let parameters = initiate.get("AuthParameters").unwrap();
+        assert_eq!(
+            parameters.str_field("PREFERRED_CHALLENGE").unwrap(),
+            "PASSWORD_SRP"
+        );
+        assert!(parameters.str_field("SRP_A").unwrap().len() > 700);
+        let select = json::parse(&requests[1].body).unwrap();
+        let responses = select.get("ChallengeResponses").unwrap();
+        assert_eq!(responses.str_field("ANSWER").unwrap(), "PASSWORD_SRP");
+        assert_ne!(responses.str_field("SRP_A"), parameters.str_field("SRP_A"));
+        let verify = json::parse(&requests[2].body).unwrap();
+        assert_eq!(
+            verify.str_field("ChallengeName").unwrap(),
+            "PASSWORD_VERIFIER"
+        );
+        assert_eq!(verify.str_field("Session").unwrap(), "s2");
+    }
+
+    #[test]
+    fn test_falls_back_to_srp() {
+        let transport = MockTransport::new();
+        transport.respond(
+            400,
+            r#"{"__type":"InvalidParameterException","message":"USER_AUTH flow not enabled for this client"}"#,
+        );
+        transport.respond(200, PASSWORD_VERIFIER);
+        transport.respond(200, TOKENS);
+        let client = Client::new("us-east-1_Pool123", "client", transport.clone()).unwrap();
+        let authentication = client
+            .authenticate_with_user_auth("bob", "P@ssw0rd!")
+            .unwrap();
+        assert!(matches!(authentication, Authentication::Tokens(_)));
+        assert!(matches!(
+            client.initiate_user_auth("bob", Some(FirstFactor::Password)),
+            Err(CognitoSrpError::InvalidArgument(_))
+        ));
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        let initiate = json::parse(&requests[1].body).unwrap();
+        assert_eq!(initiate.str_field("AuthFlow").unwrap(), "USER_SRP_AUTH");
+    }
+}
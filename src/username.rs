@@ -0,0 +1,49 @@
+// How usernames typed by people are cleaned up before they reach Cognito; see
+// Client::with_username_normalization. Off by default, since which rewrites are safe depends on
+// the pool: only lowercase for pools created with case-insensitive usernames.
+//
+// Signing in with an email or phone alias needs no option. Cognito answers with the real
+// username in USER_ID_FOR_SRP, and the password claim and SECRET_HASH are computed over that.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UsernameNormalization {
+    // Strip surrounding whitespace, as left by copy and paste.
+    pub trim: bool,
+    pub lowercase: bool,
+}
+
+impl UsernameNormalization {
+    pub fn case_insensitive() -> Self {
+        Self {
+            trim: true,
+            lowercase: true,
+        }
+    }
+
+    pub fn apply(&self, username: &str) -> String {
+        let username = if self.trim { username.trim() } else { username };
+        if self.lowercase {
+            username.to_lowercase()
+        } else {
+            username.to_owned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        assert_eq!(UsernameNormalization::default().apply(" Bob "), " Bob ");
+        let trim = UsernameNormalization {
+            trim: true,
+            lowercase: false,
+        };
+        assert_eq!(trim.apply(" Bob@Example.com\n"), "Bob@Example.com");
+        assert_eq!(
+            UsernameNormalization::case_insensitive().apply(" Bob@Example.com"),
+            "bob@example.com"
+        );
+    }
+}
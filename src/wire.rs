@@ -0,0 +1,382 @@
+// # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_InitiateAuth.html
+// # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_RespondToAuthChallenge.html
+// The cognito-idp auth operations' JSON, as the client sends and reads it. Client::initiate_auth
+// and Client::respond_to_auth_challenge take and return these directly, for flows the typed
+// methods don't drive (CUSTOM_AUTH with your own challenge loop, USER_PASSWORD_AUTH, ...). With
+// the `serde` feature they also (de)serialize to exactly the wire format.
+use crate::auth::ChallengeName;
+use crate::client::Client;
+use crate::error::{CognitoSrpError, Result};
+use crate::json::Value;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct InitiateAuthRequest {
+    // "USER_SRP_AUTH", "REFRESH_TOKEN_AUTH", "CUSTOM_AUTH", ...
+    pub auth_flow: String,
+    pub client_id: String,
+    // USERNAME, SRP_A, SECRET_HASH, ... as the flow needs them.
+    pub auth_parameters: BTreeMap<String, String>,
+}
+
+impl InitiateAuthRequest {
+    pub fn new(auth_flow: &str, client_id: &str) -> Self {
+        Self {
+            auth_flow: auth_flow.to_owned(),
+            client_id: client_id.to_owned(),
+            auth_parameters: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_parameter(mut self, name: &str, value: &str) -> Self {
+        self.auth_parameters
+            .insert(name.to_owned(), value.to_owned());
+        self
+    }
+
+    pub(crate) fn to_json(&self) -> Value {
+        Value::object([
+            ("AuthFlow", Value::from(self.auth_flow.as_str())),
+            ("ClientId", Value::from(self.client_id.as_str())),
+            ("AuthParameters", string_object(&self.auth_parameters)),
+        ])
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct RespondToAuthChallengeRequest {
+    pub client_id: String,
+    pub challenge_name: ChallengeName,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub session: Option<String>,
+    // USERNAME, SECRET_HASH and the answers themselves.
+    pub challenge_responses: BTreeMap<String, String>,
+}
+
+impl RespondToAuthChallengeRequest {
+    pub fn new(client_id: &str, challenge_name: ChallengeName, session: Option<&str>) -> Self {
+        Self {
+            client_id: client_id.to_owned(),
+            challenge_name,
+            session: session.map(str::to_owned),
+            challenge_responses: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_response(mut self, name: &str, value: &str) -> Self {
+        self.challenge_responses
+            .insert(name.to_owned(), value.to_owned());
+        self
+    }
+
+    pub(crate) fn to_json(&self) -> Value {
+        let mut body = Value::object([
+            ("ClientId", Value::from(self.client_id.as_str())),
+            ("ChallengeName", Value::from(self.challenge_name.as_str())),
+            (
+                "ChallengeResponses",
+                string_object(&self.challenge_responses),
+            ),
+        ]);
+        if let Some(session) = &self.session {
+            body.insert("Session", Value::from(session.as_str()));
+        }
+        body
+    }
+}
+
+// What both operations (and their Admin* twins) answer: either tokens or the next challenge.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct AuthResponse {
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub challenge_name: Option<ChallengeName>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub session: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub challenge_parameters: BTreeMap<String, String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub authentication_result: Option<AuthenticationResult>,
+    // USER_AUTH's first factors on offer with SELECT_CHALLENGE, e.g. ["PASSWORD_SRP","EMAIL_OTP"].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub available_challenges: Vec<String>,
+}
+
+impl AuthResponse {
+    // The challenge's name as Cognito spells it, e.g. "PASSWORD_VERIFIER".
+    pub fn challenge_name_str(&self) -> Option<&str> {
+        self.challenge_name.as_ref().map(ChallengeName::as_str)
+    }
+
+    // Checks that Cognito asked for `expected` and nothing else.
+    pub fn expect_challenge(&self, expected: &str) -> Result<()> {
+        match self.challenge_name_str() {
+            Some(name) if name == expected => Ok(()),
+            actual => Err(CognitoSrpError::ChallengeMismatch {
+                expected: expected.to_owned(),
+                actual: actual.map(str::to_owned),
+            }),
+        }
+    }
+
+    pub(crate) fn from_json(response: &Value) -> Result<Self> {
+        Ok(Self {
+            challenge_name: response
+                .get("ChallengeName")
+                .and_then(Value::as_str)
+                .map(ChallengeName::parse),
+            session: response.str_field("Session"),
+            challenge_parameters: string_map(response.get("ChallengeParameters")),
+            authentication_result: response
+                .get("AuthenticationResult")
+                .map(AuthenticationResult::from_json)
+                .transpose()?,
+            available_challenges: match response.get("AvailableChallenges") {
+                Some(Value::Array(names)) => names
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_owned)
+                    .collect(),
+                _ => Vec::new(),
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct AuthenticationResult {
+    pub access_token: String,
+    pub id_token: String,
+    // Absent on refreshes, unless the app client rotates refresh tokens.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub refresh_token: Option<String>,
+    // Seconds.
+    pub expires_in: u64,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub token_type: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub new_device_metadata: Option<NewDeviceMetadata>,
+}
+
+impl AuthenticationResult {
+    pub(crate) fn from_json(result: &Value) -> Result<Self> {
+        let field = |name: &str| {
+            result.str_field(name).ok_or_else(|| {
+                CognitoSrpError::InvalidResponse(format!(
+                    "AuthenticationResult is missing {}",
+                    name
+                ))
+            })
+        };
+        let expires_in = match result.get("ExpiresIn") {
+            Some(Value::Number(secs)) if *secs >= 0.0 => *secs as u64,
+            _ => {
+                return Err(CognitoSrpError::InvalidResponse(
+                    "AuthenticationResult is missing ExpiresIn".to_owned(),
+                ))
+            }
+        };
+        Ok(Self {
+            access_token: field("AccessToken")?,
+            id_token: field("IdToken")?,
+            refresh_token: result.str_field("RefreshToken"),
+            expires_in,
+            token_type: result.str_field("TokenType"),
+            // Incomplete metadata only means the device can't be remembered; the tokens are good.
+            new_device_metadata: result
+                .get("NewDeviceMetadata")
+                .and_then(NewDeviceMetadata::from_json),
+        })
+    }
+}
+
+// # https://docs.aws.amazon.com/cognito-user-identity-pools/latest/APIReference/API_NewDeviceMetadataType.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct NewDeviceMetadata {
+    pub device_key: String,
+    pub device_group_key: String,
+}
+
+impl NewDeviceMetadata {
+    fn from_json(metadata: &Value) -> Option<Self> {
+        Some(Self {
+            device_key: metadata.str_field("DeviceKey")?,
+            device_group_key: metadata.str_field("DeviceGroupKey")?,
+        })
+    }
+}
+
+fn string_map(value: Option<&Value>) -> BTreeMap<String, String> {
+    match value {
+        Some(Value::Object(map)) => map
+            .iter()
+            .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_owned())))
+            .collect(),
+        _ => BTreeMap::new(),
+    }
+}
+
+fn string_object(map: &BTreeMap<String, String>) -> Value {
+    Value::object(
+        map.iter()
+            .map(|(k, v)| (k.as_str(), Value::from(v.as_str()))),
+    )
+}
+
+impl Client {
+    // The bare operations: nothing is filled in beyond what every call gets (ClientMetadata,
+    // UserContextData, ...), so SECRET_HASH is the caller's to add, and no hooks run.
+    pub fn initiate_auth(&self, request: &InitiateAuthRequest) -> Result<AuthResponse> {
+        self.initiate_auth_call(false, request)
+    }
+
+    pub fn respond_to_auth_challenge(
+        &self,
+        request: &RespondToAuthChallengeRequest,
+    ) -> Result<AuthResponse> {
+        self.respond_to_auth_challenge_call(false, request)
+    }
+
+    // AdminInitiateAuth and AdminRespondToAuthChallenge, signed with the client's credentials.
+    pub fn admin_initiate_auth(&self, request: &InitiateAuthRequest) -> Result<AuthResponse> {
+        self.initiate_auth_call(true, request)
+    }
+
+    pub fn admin_respond_to_auth_challenge(
+        &self,
+        request: &RespondToAuthChallengeRequest,
+    ) -> Result<AuthResponse> {
+        self.respond_to_auth_challenge_call(true, request)
+    }
+
+    pub(crate) fn initiate_auth_call(
+        &self,
+        admin: bool,
+        request: &InitiateAuthRequest,
+    ) -> Result<AuthResponse> {
+        let response = self.auth_call(admin, "InitiateAuth", request.to_json())?;
+        AuthResponse::from_json(&response)
+    }
+
+    pub(crate) fn respond_to_auth_challenge_call(
+        &self,
+        admin: bool,
+        request: &RespondToAuthChallengeRequest,
+    ) -> Result<AuthResponse> {
+        let response = self.auth_call(admin, "RespondToAuthChallenge", request.to_json())?;
+        AuthResponse::from_json(&response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_custom_auth_round_trip() {
+        let transport = MockTransport::new();
+        transport.respond(
+            200,
+            r#"{"ChallengeName":"CUSTOM_CHALLENGE","Session":"s1","ChallengeParameters":{"question":"6 x 7"}}"#,
+        );
+        transport.respond(
+            200,
+            r#"{"AuthenticationResult":{"AccessToken":"a","IdToken":"i","RefreshToken":"r","ExpiresIn":3600,
+                "TokenType":"Bearer","NewDeviceMetadata":{"DeviceKey":"k","DeviceGroupKey":"g"}},"ChallengeParameters":{}}"#,
+        );
+        let client = Client::new("us-east-1_pool", "client", transport.clone()).unwrap();
+
+        let request =
+            InitiateAuthRequest::new("CUSTOM_AUTH", "client").with_parameter("USERNAME", "bob");
+        let response = client.initiate_auth(&request).unwrap();
+        assert_eq!(
+            transport.last_request().body,
+            r#"{"AuthFlow":"CUSTOM_AUTH","AuthParameters":{"USERNAME":"bob"},"ClientId":"client"}"#
+        );
+        assert_eq!(
+            response.challenge_name,
+            Some(ChallengeName::CustomChallenge)
+        );
+        assert_eq!(response.challenge_parameters["question"], "6 x 7");
+        assert!(response.expect_challenge("PASSWORD_VERIFIER").is_err());
+
+        let request = RespondToAuthChallengeRequest::new(
+            "client",
+            ChallengeName::CustomChallenge,
+            response.session.as_deref(),
+        )
+        .with_response("USERNAME", "bob")
+        .with_response("ANSWER", "42");
+        let response = client.respond_to_auth_challenge(&request).unwrap();
+        assert_eq!(
+            transport.last_request().body,
+            r#"{"ChallengeName":"CUSTOM_CHALLENGE","ChallengeResponses":{"ANSWER":"42","USERNAME":"bob"},"ClientId":"client","Session":"s1"}"#
+        );
+        let result = response.authentication_result.unwrap();
+        assert_eq!(result.expires_in, 3600);
+        assert_eq!(
+            result.new_device_metadata,
+            Some(NewDeviceMetadata {
+                device_key: "k".to_owned(),
+                device_group_key: "g".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_missing_fields() {
+        let result = crate::json::parse(r#"{"AccessToken":"a","ExpiresIn":60}"#).unwrap();
+        let err = AuthenticationResult::from_json(&result).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid response: AuthenticationResult is missing IdToken"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_wire_format() {
+        let json = r#"{"ChallengeName":"SMS_MFA","Session":"s1","ChallengeParameters":{"CODE_DELIVERY_DESTINATION":"+*******1234"}}"#;
+        let response: AuthResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.challenge_name, Some(ChallengeName::SmsMfa));
+        assert_eq!(
+            response,
+            AuthResponse::from_json(&crate::json::parse(json).unwrap()).unwrap()
+        );
+        assert_eq!(serde_json::to_string(&response).unwrap(), json);
+    }
+}
@@ -0,0 +1,92 @@
+"""Regenerates the JWT fixtures used by the unit tests.
+
+    python3 testdata/make_tokens.py
+
+test_key.pem is a throwaway key that exists only for these fixtures.
+"""
+import base64
+import json
+import os
+
+from cryptography.hazmat.primitives import hashes, serialization
+from cryptography.hazmat.primitives.asymmetric import padding
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+ISSUER = "https://cognito-idp.us-east-1.amazonaws.com/us-east-1_pool"
+KID = "test-key"
+FAR_FUTURE = 4102444800  # 2100-01-01
+PAST = 1600000000
+
+
+def b64url(data):
+    return base64.urlsafe_b64encode(data).rstrip(b"=").decode()
+
+
+def b64url_int(value):
+    return b64url(value.to_bytes((value.bit_length() + 7) // 8, "big"))
+
+
+with open(os.path.join(HERE, "test_key.pem"), "rb") as f:
+    key = serialization.load_pem_private_key(f.read(), password=None)
+
+
+def sign(claims, kid=KID):
+    header = b64url(json.dumps({"alg": "RS256", "kid": kid}, separators=(",", ":")).encode())
+    payload = b64url(json.dumps(claims, separators=(",", ":")).encode())
+    signing_input = f"{header}.{payload}".encode()
+    signature = key.sign(signing_input, padding.PKCS1v15(), hashes.SHA256())
+    return f"{header}.{payload}.{b64url(signature)}"
+
+
+def id_claims(username, groups, exp=FAR_FUTURE, **extra):
+    claims = {
+        "sub": f"sub-{username}",
+        "iss": ISSUER,
+        "aud": "client",
+        "token_use": "id",
+        "cognito:username": username,
+        "cognito:groups": groups,
+        "email": f"{username}@example.com",
+        "auth_time": 1700000000,
+        "iat": 1700000000,
+        "exp": exp,
+    }
+    claims.update(extra)
+    return claims
+
+
+def access_claims(username, groups, exp=FAR_FUTURE):
+    return {
+        "sub": f"sub-{username}",
+        "iss": ISSUER,
+        "client_id": "client",
+        "token_use": "access",
+        "scope": "aws.cognito.signin.user.admin resource/read",
+        "username": username,
+        "cognito:groups": groups,
+        "auth_time": 1700000000,
+        "iat": 1700000000,
+        "exp": exp,
+    }
+
+
+numbers = key.public_key().public_numbers()
+jwks = {"keys": [{"alg": "RS256", "e": b64url_int(numbers.e), "kid": KID, "kty": "RSA",
+                  "n": b64url_int(numbers.n), "use": "sig"}]}
+
+tokens = {
+    "id_admin": sign(id_claims("bob", ["admins"])),
+    "id_guest": sign(id_claims("alice", [])),
+    "id_expired": sign(id_claims("bob", ["admins"], exp=PAST)),
+    "id_other_client": sign(dict(id_claims("bob", ["admins"]), aud="other")),
+    "id_unknown_kid": sign(id_claims("bob", ["admins"]), kid="rotated-away"),
+    "access_admin": sign(access_claims("bob", ["admins"])),
+}
+tokens["id_tampered"] = tokens["id_admin"][:-6] + ("AAAAAA" if not tokens["id_admin"].endswith("AAAAAA") else "BBBBBB")
+
+with open(os.path.join(HERE, "jwks.json"), "w") as f:
+    json.dump(jwks, f, indent=2)
+    f.write("\n")
+with open(os.path.join(HERE, "tokens.json"), "w") as f:
+    json.dump(tokens, f, indent=2)
+    f.write("\n")